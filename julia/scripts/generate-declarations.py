@@ -101,6 +101,7 @@ def parse(file):
 CTYPES_TO_JULIA = {
     "uintptr_t": "UIntptr",
     "uint8_t": "UInt8",
+    "uint16_t": "UInt16",
     "int32_t": "Int32",
     "uint32_t": "UInt32",
     "int64_t": "Int64",
@@ -246,6 +247,7 @@ mts_data_origin_t = UInt64
 
 mts_create_array_callback_t = Ptr{Cvoid}  # TODO: actual type
 mts_realloc_buffer_t = Ptr{Cvoid}         # TODO: actual type
+mts_array_converter_t = Ptr{Cvoid}        # TODO: actual type
 
 # ====== Enf of manual definitions ====== #
 """
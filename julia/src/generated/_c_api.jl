@@ -19,17 +19,21 @@ mts_data_origin_t = UInt64
 
 mts_create_array_callback_t = Ptr{Cvoid}  # TODO: actual type
 mts_realloc_buffer_t = Ptr{Cvoid}         # TODO: actual type
+mts_array_converter_t = Ptr{Cvoid}        # TODO: actual type
 
 # ====== Enf of manual definitions ====== #
 
 
 # ===== Macros definitions
+MTS_ARRAY_OPERATIONS_VERSION = 2
 MTS_SUCCESS = 0
 MTS_INVALID_PARAMETER_ERROR = 1
 MTS_IO_ERROR = 2
 MTS_SERIALIZATION_ERROR = 3
 MTS_BUFFER_SIZE_ERROR = 254
 MTS_INTERNAL_ERROR = 255
+MTS_LABELS_INT32 = 0
+MTS_LABELS_INT64 = 1
 
 
 # ===== Enum definitions
@@ -39,6 +43,9 @@ MTS_INTERNAL_ERROR = 255
 struct mts_block_t
 end
 
+struct mts_tensormap_builder_t
+end
+
 struct mts_tensormap_t
 end
 
@@ -48,6 +55,8 @@ struct mts_labels_t
     values :: Ptr{Int32}
     size :: UIntptr
     count :: UIntptr
+    dtype :: Int32
+    values_i64 :: Ptr{Int64}
 end
 
 struct mts_sample_mapping_t
@@ -68,6 +77,34 @@ struct mts_array_t
     move_samples_from :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{Cvoid}, Ptr{mts_sample_mapping_t}, UIntptr, UIntptr, UIntptr) -> mts_status_t =#
 end
 
+struct mts_array_descriptor_t
+    device_type :: Int32
+    device_id :: Int32
+    dtype_code :: UInt8
+    dtype_bits :: UInt8
+    dtype_lanes :: UInt16
+end
+
+struct mts_index_mapping_t
+    input :: UIntptr
+    output :: UIntptr
+end
+
+struct mts_array_operations_t
+    version :: UInt64
+    add :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{Cvoid}) -> mts_status_t =#
+    mul :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{Cvoid}) -> mts_status_t =#
+    scale :: Ptr{Cvoid} #= (Ptr{Cvoid}, Cdouble) -> mts_status_t =#
+    matmul :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{Cvoid}, Ptr{mts_array_t}) -> mts_status_t =#
+    sum_axis :: Ptr{Cvoid} #= (Ptr{Cvoid}, UIntptr, Ptr{mts_array_t}) -> mts_status_t =#
+    copy_from :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{Cvoid}) -> mts_status_t =#
+    descriptor :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{mts_array_descriptor_t}) -> mts_status_t =#
+    raw_data :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{Ptr{Cvoid}}) -> mts_status_t =#
+    move_along_axis :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{Cvoid}, UIntptr, Ptr{mts_index_mapping_t}, UIntptr, Ptr{UIntptr}) -> mts_status_t =#
+    sparse_csr :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{Ptr{Cdouble}}, Ptr{Ptr{UIntptr}}, Ptr{Ptr{UIntptr}}, Ptr{UIntptr}) -> mts_status_t =#
+    create_sparse_csr :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{UIntptr}, UIntptr, Ptr{Cdouble}, Ptr{UIntptr}, Ptr{UIntptr}, UIntptr, Ptr{mts_array_t}) -> mts_status_t =#
+end
+
 
 
 # ===== Function definitions
@@ -104,6 +141,14 @@ function mts_labels_position(labels::mts_labels_t, values::Ptr{Int32}, values_co
     )
 end
 
+function mts_labels_position_i64(labels::mts_labels_t, values::Ptr{Int64}, values_count::UIntptr, result::Ptr{Int64})
+    ccall((:mts_labels_position_i64, libmetatensor), 
+        mts_status_t,
+        (mts_labels_t, Ptr{Int64}, UIntptr, Ptr{Int64},),
+        labels, values, values_count, result
+    )
+end
+
 function mts_labels_create(labels::Ptr{mts_labels_t})
     ccall((:mts_labels_create, libmetatensor), 
         mts_status_t,
@@ -136,6 +181,14 @@ function mts_labels_clone(labels::mts_labels_t, clone::Ptr{mts_labels_t})
     )
 end
 
+function mts_labels_fingerprint(labels::mts_labels_t, fingerprint::Ptr{UInt64})
+    ccall((:mts_labels_fingerprint, libmetatensor), 
+        mts_status_t,
+        (mts_labels_t, Ptr{UInt64},),
+        labels, fingerprint
+    )
+end
+
 function mts_labels_union(first::mts_labels_t, second::mts_labels_t, result::Ptr{mts_labels_t}, first_mapping::Ptr{Int64}, first_mapping_count::UIntptr, second_mapping::Ptr{Int64}, second_mapping_count::UIntptr)
     ccall((:mts_labels_union, libmetatensor), 
         mts_status_t,
@@ -184,6 +237,102 @@ function mts_get_data_origin(origin::mts_data_origin_t, buffer::Ptr{Cchar}, buff
     )
 end
 
+function mts_register_array_operations(origin::mts_data_origin_t, operations::Ptr{mts_array_operations_t})
+    ccall((:mts_register_array_operations, libmetatensor), 
+        mts_status_t,
+        (mts_data_origin_t, Ptr{mts_array_operations_t},),
+        origin, operations
+    )
+end
+
+function mts_register_array_converter(from::mts_data_origin_t, to::mts_data_origin_t, converter::mts_array_converter_t)
+    ccall((:mts_register_array_converter, libmetatensor), 
+        mts_status_t,
+        (mts_data_origin_t, mts_data_origin_t, mts_array_converter_t,),
+        from, to, converter
+    )
+end
+
+function mts_array_to_origin(array::Ptr{mts_array_t}, origin::mts_data_origin_t, output::Ptr{mts_array_t})
+    ccall((:mts_array_to_origin, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, mts_data_origin_t, Ptr{mts_array_t},),
+        array, origin, output
+    )
+end
+
+function mts_array_descriptor(array::Ptr{mts_array_t}, descriptor::Ptr{mts_array_descriptor_t})
+    ccall((:mts_array_descriptor, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, Ptr{mts_array_descriptor_t},),
+        array, descriptor
+    )
+end
+
+function mts_array_to_dlpack(array::Ptr{mts_array_t}, tensor::Ptr{Ptr{Cvoid}})
+    ccall((:mts_array_to_dlpack, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, Ptr{Ptr{Cvoid}},),
+        array, tensor
+    )
+end
+
+function mts_array_add(array::Ptr{mts_array_t}, other::Ptr{mts_array_t})
+    ccall((:mts_array_add, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, Ptr{mts_array_t},),
+        array, other
+    )
+end
+
+function mts_array_mul(array::Ptr{mts_array_t}, other::Ptr{mts_array_t})
+    ccall((:mts_array_mul, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, Ptr{mts_array_t},),
+        array, other
+    )
+end
+
+function mts_array_scale(array::Ptr{mts_array_t}, factor::Cdouble)
+    ccall((:mts_array_scale, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, Cdouble,),
+        array, factor
+    )
+end
+
+function mts_array_matmul(array::Ptr{mts_array_t}, other::Ptr{mts_array_t}, output::Ptr{mts_array_t})
+    ccall((:mts_array_matmul, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, Ptr{mts_array_t}, Ptr{mts_array_t},),
+        array, other, output
+    )
+end
+
+function mts_array_sum_axis(array::Ptr{mts_array_t}, axis::UIntptr, output::Ptr{mts_array_t})
+    ccall((:mts_array_sum_axis, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, UIntptr, Ptr{mts_array_t},),
+        array, axis, output
+    )
+end
+
+function mts_array_copy_from(array::Ptr{mts_array_t}, other::Ptr{mts_array_t})
+    ccall((:mts_array_copy_from, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, Ptr{mts_array_t},),
+        array, other
+    )
+end
+
+function mts_array_move_along_axis(array::Ptr{mts_array_t}, input::Ptr{mts_array_t}, axis::UIntptr, mapping::Ptr{mts_index_mapping_t}, mapping_count::UIntptr, output_offsets::Ptr{UIntptr})
+    ccall((:mts_array_move_along_axis, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_array_t}, Ptr{mts_array_t}, UIntptr, Ptr{mts_index_mapping_t}, UIntptr, Ptr{UIntptr},),
+        array, input, axis, mapping, mapping_count, output_offsets
+    )
+end
+
 function mts_block(data::mts_array_t, samples::mts_labels_t, components::Ptr{mts_labels_t}, components_count::UIntptr, properties::mts_labels_t)
     ccall((:mts_block, libmetatensor), 
         Ptr{mts_block_t},
@@ -208,6 +357,14 @@ function mts_block_copy(block::Ptr{mts_block_t})
     )
 end
 
+function mts_block_to_origin(block::Ptr{mts_block_t}, origin::mts_data_origin_t)
+    ccall((:mts_block_to_origin, libmetatensor), 
+        Ptr{mts_block_t},
+        (Ptr{mts_block_t}, mts_data_origin_t,),
+        block, origin
+    )
+end
+
 function mts_block_labels(block::Ptr{mts_block_t}, axis::UIntptr, labels::Ptr{mts_labels_t})
     ccall((:mts_block_labels, libmetatensor), 
         mts_status_t,
@@ -240,6 +397,22 @@ function mts_block_add_gradient(block::Ptr{mts_block_t}, parameter::Ptr{Cchar},
     )
 end
 
+function mts_block_take_gradient(block::Ptr{mts_block_t}, parameter::Ptr{Cchar})
+    ccall((:mts_block_take_gradient, libmetatensor), 
+        Ptr{mts_block_t},
+        (Ptr{mts_block_t}, Ptr{Cchar},),
+        block, parameter
+    )
+end
+
+function mts_block_remove_gradient(block::Ptr{mts_block_t}, parameter::Ptr{Cchar})
+    ccall((:mts_block_remove_gradient, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_block_t}, Ptr{Cchar},),
+        block, parameter
+    )
+end
+
 function mts_block_gradients_list(block::Ptr{mts_block_t}, parameters::Ptr{Ptr{Ptr{Cchar}}}, parameters_count::Ptr{UIntptr})
     ccall((:mts_block_gradients_list, libmetatensor), 
         mts_status_t,
@@ -272,6 +445,22 @@ function mts_tensormap_copy(tensor::Ptr{mts_tensormap_t})
     )
 end
 
+function mts_tensormap_to_origin(tensor::Ptr{mts_tensormap_t}, origin::mts_data_origin_t)
+    ccall((:mts_tensormap_to_origin, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{mts_tensormap_t}, mts_data_origin_t,),
+        tensor, origin
+    )
+end
+
+function mts_tensormap_into_blocks(tensor::Ptr{mts_tensormap_t}, blocks::Ptr{Ptr{mts_block_t}}, blocks_count::UIntptr)
+    ccall((:mts_tensormap_into_blocks, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{Ptr{mts_block_t}}, UIntptr,),
+        tensor, blocks, blocks_count
+    )
+end
+
 function mts_tensormap_keys(tensor::Ptr{mts_tensormap_t}, keys::Ptr{mts_labels_t})
     ccall((:mts_tensormap_keys, libmetatensor), 
         mts_status_t,
@@ -288,6 +477,54 @@ function mts_tensormap_block_by_id(tensor::Ptr{mts_tensormap_t}, block::Ptr{Ptr{
     )
 end
 
+function mts_tensormap_insert_block(tensor::Ptr{mts_tensormap_t}, key::Ptr{Int64}, key_count::UIntptr, block::Ptr{mts_block_t})
+    ccall((:mts_tensormap_insert_block, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{Int64}, UIntptr, Ptr{mts_block_t},),
+        tensor, key, key_count, block
+    )
+end
+
+function mts_tensormap_remove_block(tensor::Ptr{mts_tensormap_t}, key::Ptr{Int64}, key_count::UIntptr)
+    ccall((:mts_tensormap_remove_block, libmetatensor), 
+        Ptr{mts_block_t},
+        (Ptr{mts_tensormap_t}, Ptr{Int64}, UIntptr,),
+        tensor, key, key_count
+    )
+end
+
+function mts_tensormap_replace_block(tensor::Ptr{mts_tensormap_t}, index::UIntptr, block::Ptr{mts_block_t})
+    ccall((:mts_tensormap_replace_block, libmetatensor), 
+        Ptr{mts_block_t},
+        (Ptr{mts_tensormap_t}, UIntptr, Ptr{mts_block_t},),
+        tensor, index, block
+    )
+end
+
+function mts_tensormap_drop_blocks(tensor::Ptr{mts_tensormap_t}, selection::mts_labels_t)
+    ccall((:mts_tensormap_drop_blocks, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, mts_labels_t,),
+        tensor, selection
+    )
+end
+
+function mts_tensormap_remove_gradients(tensor::Ptr{mts_tensormap_t}, parameters::Ptr{Ptr{Cchar}}, parameters_count::UIntptr)
+    ccall((:mts_tensormap_remove_gradients, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{Ptr{Cchar}}, UIntptr,),
+        tensor, parameters, parameters_count
+    )
+end
+
+function mts_tensormap_share_gradient_samples(tensor::Ptr{mts_tensormap_t})
+    ccall((:mts_tensormap_share_gradient_samples, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t},),
+        tensor
+    )
+end
+
 function mts_tensormap_blocks_matching(tensor::Ptr{mts_tensormap_t}, block_indexes::Ptr{UIntptr}, count::Ptr{UIntptr}, selection::mts_labels_t)
     ccall((:mts_tensormap_blocks_matching, libmetatensor), 
         mts_status_t,
@@ -296,6 +533,22 @@ function mts_tensormap_blocks_matching(tensor::Ptr{mts_tensormap_t}, block_index
     )
 end
 
+function mts_tensormap_blocks_matching_values(tensor::Ptr{mts_tensormap_t}, dimensions::Ptr{UIntptr}, values::Ptr{Int64}, values_count::UIntptr, block_indexes::Ptr{UIntptr}, count::Ptr{UIntptr})
+    ccall((:mts_tensormap_blocks_matching_values, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{UIntptr}, Ptr{Int64}, UIntptr, Ptr{UIntptr}, Ptr{UIntptr},),
+        tensor, dimensions, values, values_count, block_indexes, count
+    )
+end
+
+function mts_tensormap_blocks_matching_many(tensor::Ptr{mts_tensormap_t}, selection::mts_labels_t, block_indexes::Ptr{UIntptr}, block_indexes_count::UIntptr, offsets::Ptr{UIntptr}, offsets_count::UIntptr)
+    ccall((:mts_tensormap_blocks_matching_many, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, mts_labels_t, Ptr{UIntptr}, UIntptr, Ptr{UIntptr}, UIntptr,),
+        tensor, selection, block_indexes, block_indexes_count, offsets, offsets_count
+    )
+end
+
 function mts_tensormap_keys_to_properties(tensor::Ptr{mts_tensormap_t}, keys_to_move::mts_labels_t, sort_samples::Cbool)
     ccall((:mts_tensormap_keys_to_properties, libmetatensor), 
         Ptr{mts_tensormap_t},
@@ -320,6 +573,38 @@ function mts_tensormap_keys_to_samples(tensor::Ptr{mts_tensormap_t}, keys_to_mov
     )
 end
 
+function mts_tensormap_builder(keys_names::Ptr{Ptr{Cchar}}, keys_names_count::UIntptr, sort_samples::Cbool)
+    ccall((:mts_tensormap_builder, libmetatensor), 
+        Ptr{mts_tensormap_builder_t},
+        (Ptr{Ptr{Cchar}}, UIntptr, Cbool,),
+        keys_names, keys_names_count, sort_samples
+    )
+end
+
+function mts_tensormap_builder_free(builder::Ptr{mts_tensormap_builder_t})
+    ccall((:mts_tensormap_builder_free, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_builder_t},),
+        builder
+    )
+end
+
+function mts_tensormap_builder_add(builder::Ptr{mts_tensormap_builder_t}, key::Ptr{Int64}, key_count::UIntptr, chunk::Ptr{mts_block_t})
+    ccall((:mts_tensormap_builder_add, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_builder_t}, Ptr{Int64}, UIntptr, Ptr{mts_block_t},),
+        builder, key, key_count, chunk
+    )
+end
+
+function mts_tensormap_builder_finish(builder::Ptr{mts_tensormap_builder_t})
+    ccall((:mts_tensormap_builder_finish, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{mts_tensormap_builder_t},),
+        builder
+    )
+end
+
 function mts_labels_load(path::Ptr{Cchar}, labels::Ptr{mts_labels_t})
     ccall((:mts_labels_load, libmetatensor), 
         mts_status_t,
@@ -408,6 +693,14 @@ function mts_tensormap_save(path::Ptr{Cchar}, tensor::Ptr{mts_tensormap_t})
     )
 end
 
+function mts_tensormap_save_shared_gradient_samples(path::Ptr{Cchar}, tensor::Ptr{mts_tensormap_t})
+    ccall((:mts_tensormap_save_shared_gradient_samples, libmetatensor), 
+        mts_status_t,
+        (Ptr{Cchar}, Ptr{mts_tensormap_t},),
+        path, tensor
+    )
+end
+
 function mts_tensormap_save_buffer(buffer::Ptr{Ptr{UInt8}}, buffer_count::Ptr{UIntptr}, realloc_user_data::Ptr{Cvoid}, realloc::mts_realloc_buffer_t, tensor::Ptr{mts_tensormap_t})
     ccall((:mts_tensormap_save_buffer, libmetatensor), 
         mts_status_t,
@@ -415,3 +708,11 @@ function mts_tensormap_save_buffer(buffer::Ptr{Ptr{UInt8}}, buffer_count::Ptr{UI
         buffer, buffer_count, realloc_user_data, realloc, tensor
     )
 end
+
+function mts_tensormap_save_buffer_shared_gradient_samples(buffer::Ptr{Ptr{UInt8}}, buffer_count::Ptr{UIntptr}, realloc_user_data::Ptr{Cvoid}, realloc::mts_realloc_buffer_t, tensor::Ptr{mts_tensormap_t})
+    ccall((:mts_tensormap_save_buffer_shared_gradient_samples, libmetatensor), 
+        mts_status_t,
+        (Ptr{Ptr{UInt8}}, Ptr{UIntptr}, Ptr{Cvoid}, mts_realloc_buffer_t, Ptr{mts_tensormap_t},),
+        buffer, buffer_count, realloc_user_data, realloc, tensor
+    )
+end
@@ -13,6 +13,11 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Version of `mts_array_operations_t` defined by this version of metatensor
+ */
+#define MTS_ARRAY_OPERATIONS_VERSION 2
+
 /**
  * Status code used when a function succeeded
  */
@@ -45,6 +50,18 @@
  */
 #define MTS_INTERNAL_ERROR 255
 
+/**
+ * Value of `mts_labels_t.dtype` for labels storing their values as 32-bit
+ * signed integers in `mts_labels_t.values`
+ */
+#define MTS_LABELS_INT32 0
+
+/**
+ * Value of `mts_labels_t.dtype` for labels storing their values as 64-bit
+ * signed integers in `mts_labels_t.values_i64`
+ */
+#define MTS_LABELS_INT64 1
+
 /**
  * Basic building block for tensor map. A single block contains a n-dimensional
  * `mts_array_t`, and n sets of `mts_labels_t` (one for each dimension).
@@ -55,6 +72,11 @@
  */
 typedef struct mts_block_t mts_block_t;
 
+/**
+ * Opaque type used to build a `mts_tensormap_t` from chunks of data.
+ */
+typedef struct mts_tensormap_builder_t mts_tensormap_builder_t;
+
 /**
  * Opaque type representing a `TensorMap`.
  */
@@ -81,6 +103,10 @@ typedef int32_t mts_status_t;
  * `mts_labels_t` with a non-NULL `internal_ptr_` correspond to a
  * reference-counted Rust data structure, which allow for fast lookup inside
  * the labels with `mts_labels_positions`.
+ *
+ * The values of the labels are stored either as 32-bit signed integers (in
+ * `values`) or as 64-bit signed integers (in `values_i64`), depending on
+ * `dtype`. Labels initialized with all fields set to zero use 32-bit integers.
  */
 typedef struct mts_labels_t {
   /**
@@ -96,7 +122,8 @@ typedef struct mts_labels_t {
    * Pointer to the first element of a 2D row-major array of 32-bit signed
    * integer containing the values taken by the different dimensions in
    * `names`. Each row has `size` elements, and there are `count` rows in
-   * total.
+   * total. This is only used if `dtype` is `MTS_LABELS_INT32`, and should
+   * be NULL otherwise.
    */
   const int32_t *values;
   /**
@@ -107,6 +134,18 @@ typedef struct mts_labels_t {
    * Number entries in the set of labels
    */
   uintptr_t count;
+  /**
+   * Type used to store the values of the labels, either `MTS_LABELS_INT32`
+   * or `MTS_LABELS_INT64`
+   */
+  int32_t dtype;
+  /**
+   * Pointer to the first element of a 2D row-major array of 64-bit signed
+   * integer containing the values taken by the different dimensions in
+   * `names`, with the same layout as `values`. This is only used if `dtype`
+   * is `MTS_LABELS_INT64`, and should be NULL otherwise.
+   */
+  const int64_t *values_i64;
 } mts_labels_t;
 
 /**
@@ -134,7 +173,8 @@ typedef struct mts_sample_mapping_t {
  * map. The array itself if opaque to this library and can come from multiple
  * sources: Rust program, a C/C++ program, a Fortran program, Python with numpy
  * or torch. The data does not have to live on CPU, or even on the same machine
- * where this code is executed.
+ * where this code is executed. The device and data type of the array can be
+ * given to metatensor with `mts_array_operations_t.descriptor`.
  *
  * This struct contains a C-compatible manual implementation of a virtual table
  * (vtable, i.e. trait in Rust, pure virtual class in C++); allowing
@@ -226,6 +266,200 @@ typedef struct mts_array_t {
                                     uintptr_t property_end);
 } mts_array_t;
 
+/**
+ * Description of the device and data type of an array, using the same
+ * conventions as `DLDevice` and `DLDataType` in `DLPack`.
+ *
+ * A `device_type` of 0 indicates that the device and data type of the array
+ * are unknown.
+ */
+typedef struct mts_array_descriptor_t {
+  /**
+   * Type of the device where the data lives, following `DLDeviceType` (1
+   * for CPU, 2 for CUDA, 8 for Metal, ...)
+   */
+  int32_t device_type;
+  /**
+   * Index of the device where the data lives, for systems with multiple
+   * devices of the same type
+   */
+  int32_t device_id;
+  /**
+   * Type code of the data, following `DLDataTypeCode` (0 for signed
+   * integers, 1 for unsigned integers, 2 for floating point, ...)
+   */
+  uint8_t dtype_code;
+  /**
+   * Number of bits used to store a single value
+   */
+  uint8_t dtype_bits;
+  /**
+   * Number of lanes in the data type, used for vector types. This should be
+   * 1 for scalar data types.
+   */
+  uint16_t dtype_lanes;
+} mts_array_descriptor_t;
+
+/**
+ * Representation of a single index moved from an array to another one along
+ * a given axis
+ */
+typedef struct mts_index_mapping_t {
+  /**
+   * index along the axis in the input array
+   */
+  uintptr_t input;
+  /**
+   * index along the axis in the output array
+   */
+  uintptr_t output;
+} mts_index_mapping_t;
+
+/**
+ * Optional operations on arrays, registered for all the arrays with a given
+ * data origin with `mts_register_array_operations`.
+ *
+ * These allow metatensor to manipulate arrays without accessing their data
+ * directly. For all functions, `array` and `other` are the `ptr` of
+ * `mts_array_t` with the same data origin, and the shape of the arrays are
+ * checked by metatensor before calling the functions. Any of the functions can
+ * be set to `NULL`, in which case metatensor will use `mts_array_t.data`
+ * instead.
+ *
+ * New functions might be added at the end of this struct in future versions
+ * of metatensor, together with an increase of `MTS_ARRAY_OPERATIONS_VERSION`.
+ * Metatensor will only use the functions available in the `version` of the
+ * struct that was registered.
+ */
+typedef struct mts_array_operations_t {
+  /**
+   * Version of this struct, this should be set to
+   * `MTS_ARRAY_OPERATIONS_VERSION`.
+   */
+  uint64_t version;
+  /**
+   * Add the values in the `other` array to the values in `array`,
+   * element-wise. Both arrays have the same shape.
+   */
+  mts_status_t (*add)(void *array, const void *other);
+  /**
+   * Multiply the values in `array` by the values in the `other` array,
+   * element-wise. Both arrays have the same shape.
+   */
+  mts_status_t (*mul)(void *array, const void *other);
+  /**
+   * Multiply all the values in `array` by `factor`.
+   */
+  mts_status_t (*scale)(void *array, double factor);
+  /**
+   * Compute the matrix product of `array` and `other`, contracting the last
+   * axis of `array` with the first axis of `other`, and store the result in
+   * a new array in `output`. `other` is always a 2-dimensional array, and
+   * `output` should have the same shape as `array` except for the last axis,
+   * which has the size of the second axis of `other`.
+   */
+  mts_status_t (*matmul)(const void *array, const void *other, struct mts_array_t *output);
+  /**
+   * Sum the values in `array` along the given `axis`, and store the result
+   * in a new array in `output`. `output` should have the same shape as
+   * `array`, except for `axis` which should have a size of 1.
+   */
+  mts_status_t (*sum_axis)(const void *array, uintptr_t axis, struct mts_array_t *output);
+  /**
+   * Set all the values in `array` to the values in the `other` array. Both
+   * arrays have the same shape.
+   */
+  mts_status_t (*copy_from)(void *array, const void *other);
+  /**
+   * Get the device and data type of `array` in `descriptor`. This is used
+   * to check that all the arrays in a block or a tensor map live on the
+   * same device and use the same data type.
+   *
+   * Added in version 2 of this struct.
+   */
+  mts_status_t (*descriptor)(const void *array, struct mts_array_descriptor_t *descriptor);
+  /**
+   * Get a pointer to the underlying data storage of `array` in `data`. The
+   * data should be stored as a C-contiguous array, on the device and with
+   * the data type given by `descriptor`.
+   *
+   * This is used to share the data with other libraries through `DLPack`,
+   * and requires `descriptor` to be set as well.
+   *
+   * Added in version 2 of this struct.
+   */
+  mts_status_t (*raw_data)(void *array, void **data);
+  /**
+   * Copy values from the `input` array to `array` along `axis`. For each
+   * entry in `mapping` (containing `mapping_count` entries), this should
+   * copy `input[..., mapping.input, ...]` to `array[..., mapping.output,
+   * ...]`, where the index is on `axis`. On all the other axes `i`, the
+   * whole input is copied to `output_offsets[i]..output_offsets[i] +
+   * input_shape[i]`. `output_offsets` contains one entry for each
+   * dimension, and the entry for `axis` should be ignored.
+   *
+   * The arrays have the same number of dimensions, and all indexes are
+   * checked to be in bounds by metatensor before calling this function.
+   *
+   * Added in version 2 of this struct.
+   */
+  mts_status_t (*move_along_axis)(void *array,
+                                  const void *input,
+                                  uintptr_t axis,
+                                  const struct mts_index_mapping_t *mapping,
+                                  uintptr_t mapping_count,
+                                  const uintptr_t *output_offsets);
+  /**
+   * Get the data of `array` in compressed sparse row (CSR) format, if the
+   * array stores its data in this format. The rows correspond to the first
+   * dimension of the array, and the columns to all the other dimensions
+   * flattened together.
+   *
+   * `values` and `indices` should be set to arrays with `nnz` entries,
+   * containing the non-zero values and the corresponding columns, and
+   * `indptr` to an array with `shape[0] + 1` entries, containing the start
+   * of each row in `values`. The columns in each row must be sorted and
+   * unique. The pointers must stay valid until `array` is modified. For
+   * arrays storing dense data, `values` should be set to `NULL`.
+   *
+   * Added in version 2 of this struct.
+   */
+  mts_status_t (*sparse_csr)(const void *array,
+                             const double **values,
+                             const uintptr_t **indices,
+                             const uintptr_t **indptr,
+                             uintptr_t *nnz);
+  /**
+   * Create a new array with the same origin as `array` and the given
+   * `shape`, storing data in compressed sparse row (CSR) format, and store
+   * it in `output`. The data uses the same conventions as `sparse_csr`,
+   * and should be copied in the new array.
+   *
+   * This is used when loading sparse data from files.
+   *
+   * Added in version 2 of this struct.
+   */
+  mts_status_t (*create_sparse_csr)(const void *array,
+                                    const uintptr_t *shape,
+                                    uintptr_t shape_count,
+                                    const double *values,
+                                    const uintptr_t *indices,
+                                    const uintptr_t *indptr,
+                                    uintptr_t nnz,
+                                    struct mts_array_t *output);
+} mts_array_operations_t;
+
+/**
+ * Function pointer to convert an array to a different data origin.
+ *
+ * This function gets an `array` with the origin this converter was
+ * registered for, and should fill `output` with a new `mts_array_t` with the
+ * target data origin, the same shape and a copy of the same values; or
+ * return non-zero `mts_status_t`.
+ */
+typedef mts_status_t (*mts_array_converter_t)(const struct mts_array_t *array,
+                                              struct mts_array_t *output);
+
 /**
  * Function pointer to grow in-memory buffers for `mts_tensormap_save_buffer`
  * and `mts_labels_save_buffer`.
@@ -303,6 +537,27 @@ mts_status_t mts_labels_position(struct mts_labels_t labels,
                                  uintptr_t values_count,
                                  int64_t *result);
 
+/**
+ * Get the position of the entry defined by the 64-bit `values` array in the
+ * given set of `labels`. This is the same as `mts_labels_position`, but
+ * allows looking up values which do not fit in 32-bit integers in labels
+ * using `MTS_LABELS_INT64`.
+ *
+ * @param labels set of labels with an associated Rust data structure
+ * @param values array containing the label to lookup
+ * @param values_count size of the values array
+ * @param result position of the values in the labels or -1 if the values
+ *               were not found
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_labels_position_i64(struct mts_labels_t labels,
+                                     const int64_t *values,
+                                     uintptr_t values_count,
+                                     int64_t *result);
+
 /**
  * Finish the creation of `mts_labels_t` by associating it to Rust-owned
  * labels.
@@ -375,6 +630,24 @@ mts_status_t mts_labels_user_data(struct mts_labels_t labels, void **user_data);
  */
 mts_status_t mts_labels_clone(struct mts_labels_t labels, struct mts_labels_t *clone);
 
+/**
+ * Get a 64-bit fingerprint of the content (names and values) of `labels`.
+ *
+ * Labels that are equal always have the same fingerprint, and labels with
+ * different fingerprints are always different. This can be used for fast
+ * comparison of labels. The fingerprint is computed the first time this
+ * function is called and then cached, and it is not guaranteed to be stable
+ * across different versions of metatensor.
+ *
+ * @param labels set of labels with an associated Rust data structure
+ * @param fingerprint pointer to a `uint64_t`, that will be set to the
+ *                    fingerprint of the labels
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_labels_fingerprint(struct mts_labels_t labels, uint64_t *fingerprint);
+
 /**
  * Take the union of two `mts_labels_t`.
  *
@@ -511,6 +784,250 @@ mts_status_t mts_register_data_origin(const char *name, mts_data_origin_t *origi
  */
 mts_status_t mts_get_data_origin(mts_data_origin_t origin, char *buffer, uintptr_t buffer_size);
 
+/**
+ * Register optional `operations` for all arrays with the given data `origin`.
+ *
+ * The functions in `operations` are used by `mts_array_add`, `mts_array_mul`,
+ * `mts_array_scale`, `mts_array_matmul`, `mts_array_sum_axis` and
+ * `mts_array_copy_from` instead of accessing the data of the arrays directly.
+ * Calling this function multiple times with the same `origin` replaces the
+ * previously registered operations.
+ *
+ * @param origin pre-registered data origin
+ * @param operations pointer to the operations for this data origin. The
+ *                   `version` of the operations should be set to
+ *                   `MTS_ARRAY_OPERATIONS_VERSION`, or to the version of
+ *                   metatensor the code was written for. Only the functions
+ *                   available in this version will be used.
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_register_array_operations(mts_data_origin_t origin,
+                                           const struct mts_array_operations_t *operations);
+
+/**
+ * Register a `converter` to convert arrays with the data origin `from` to
+ * arrays with the data origin `to`.
+ *
+ * Converters are used by `mts_array_to_origin`, `mts_block_to_origin` and
+ * `mts_tensormap_to_origin`. They are also used by the operations taking two
+ * arrays (`mts_array_add`, `mts_array_mul`, `mts_array_matmul`,
+ * `mts_array_copy_from` and `mts_array_move_along_axis`) to convert the
+ * second array to the origin of the first one when their origins differ,
+ * and when saving arrays which do not give access to their data (for
+ * example because it is stored on a GPU). Calling this function multiple
+ * times with the same origins replaces the previously registered converter.
+ *
+ * @param from pre-registered data origin of the arrays to convert
+ * @param to pre-registered data origin of the converted arrays
+ * @param converter function converting arrays from `from` to `to`
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_register_array_converter(mts_data_origin_t from,
+                                          mts_data_origin_t to,
+                                          mts_array_converter_t converter);
+
+/**
+ * Convert `array` to the given data `origin`, using the converter registered
+ * with `mts_register_array_converter`. If `array` already has this origin,
+ * `output` is set to a copy of `array`.
+ *
+ * @param array array to convert
+ * @param origin pre-registered data origin to convert to
+ * @param output pointer to an empty `mts_array_t` that will be set to the
+ *               converted array. The caller is responsible for calling
+ *               `output->destroy` when it is no longer needed.
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_to_origin(const struct mts_array_t *array,
+                                 mts_data_origin_t origin,
+                                 struct mts_array_t *output);
+
+/**
+ * Get the device and data type of the given `array` in `descriptor`, using
+ * `mts_array_operations_t.descriptor`. If this function was not registered
+ * for the data origin of the array, `descriptor->device_type` is set to 0.
+ *
+ * @param array array to describe
+ * @param descriptor pointer to an `mts_array_descriptor_t` where the
+ *                   descriptor will be stored
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_descriptor(const struct mts_array_t *array,
+                                  struct mts_array_descriptor_t *descriptor);
+
+/**
+ * Export the given `array` as a `DLPack` `DLManagedTensor`, without copying
+ * the data.
+ *
+ * This uses `mts_array_operations_t.raw_data` and
+ * `mts_array_operations_t.descriptor` if they were registered for the data
+ * origin of the array, and `mts_array_t.data` otherwise (in which case the
+ * data is 64-bit floating point values on CPU, or a copy converted to 32-bit
+ * floating point values if `mts_array_operations_t.descriptor` describes the
+ * array as such). The tensor does not own the data, and must not be used
+ * after `array` has been destroyed.
+ *
+ * @param array array to export
+ * @param tensor pointer to a `DLManagedTensor*` (declared as `void*` to avoid
+ *               requiring `dlpack.h`) where the exported tensor will be
+ *               stored. The caller is responsible for calling the `deleter`
+ *               of this tensor when it is no longer needed.
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_to_dlpack(const struct mts_array_t *array, void **tensor);
+
+/**
+ * Add the values in the `other` array to the values in `array`, element-wise.
+ *
+ * This uses `mts_array_operations_t.add` if it was registered for the data
+ * origin of both arrays, and `mts_array_t.data` otherwise.
+ *
+ * @param array array to modify
+ * @param other array to add to `array`, with the same shape
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_add(struct mts_array_t *array, const struct mts_array_t *other);
+
+/**
+ * Multiply the values in `array` by the values in the `other` array,
+ * element-wise.
+ *
+ * This uses `mts_array_operations_t.mul` if it was registered for the data
+ * origin of both arrays, and `mts_array_t.data` otherwise.
+ *
+ * @param array array to modify
+ * @param other array to multiply `array` with, with the same shape
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_mul(struct mts_array_t *array, const struct mts_array_t *other);
+
+/**
+ * Multiply all the values in `array` by `factor`.
+ *
+ * This uses `mts_array_operations_t.scale` if it was registered for the data
+ * origin of the array, and `mts_array_t.data` otherwise.
+ *
+ * @param array array to modify
+ * @param factor scaling factor
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_scale(struct mts_array_t *array, double factor);
+
+/**
+ * Compute the matrix product of `array` and the 2-dimensional `other` array,
+ * contracting the last axis of `array` with the first axis of `other`.
+ *
+ * This uses `mts_array_operations_t.matmul` if it was registered for the
+ * data origin of both arrays, and `mts_array_t.create` and
+ * `mts_array_t.data` otherwise.
+ *
+ * @param array first array in the product
+ * @param other second array in the product
+ * @param output pointer to an empty `mts_array_t` that will be set to the
+ *               result of the product. The caller is responsible for calling
+ *               `output->destroy` when it is no longer needed.
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_matmul(const struct mts_array_t *array,
+                              const struct mts_array_t *other,
+                              struct mts_array_t *output);
+
+/**
+ * Sum the values in `array` along the given `axis`. The result has the same
+ * shape as `array`, except for `axis` which has a size of 1.
+ *
+ * This uses `mts_array_operations_t.sum_axis` if it was registered for the
+ * data origin of the array, and `mts_array_t.create` and `mts_array_t.data`
+ * otherwise.
+ *
+ * @param array array to sum
+ * @param axis axis along which the sum is performed
+ * @param output pointer to an empty `mts_array_t` that will be set to the
+ *               result of the sum. The caller is responsible for calling
+ *               `output->destroy` when it is no longer needed.
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_sum_axis(const struct mts_array_t *array,
+                                uintptr_t axis,
+                                struct mts_array_t *output);
+
+/**
+ * Set all the values in `array` to the values in the `other` array.
+ *
+ * This uses `mts_array_operations_t.copy_from` if it was registered for the
+ * data origin of both arrays, and `mts_array_t.data` otherwise.
+ *
+ * @param array array to modify
+ * @param other array to copy the values from, with the same shape
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_copy_from(struct mts_array_t *array, const struct mts_array_t *other);
+
+/**
+ * Copy values from the `input` array to `array` along `axis`.
+ *
+ * For each entry in `mapping`, this copies `input[..., mapping.input, ...]`
+ * to `array[..., mapping.output, ...]`, where the index is on `axis`. On all
+ * the other axes `i`, the whole input is copied to `output_offsets[i]` up to
+ * `output_offsets[i] + input_shape[i]` in the output.
+ *
+ * This uses `mts_array_operations_t.move_along_axis` if it was registered for
+ * the data origin of both arrays, and `mts_array_t.data` otherwise.
+ *
+ * @param array array to modify
+ * @param input array to copy the values from, with the same number of
+ *              dimensions as `array`
+ * @param axis axis along which the indexes in `mapping` are defined
+ * @param mapping indexes to copy along `axis`
+ * @param mapping_count number of entries in `mapping`
+ * @param output_offsets offset of the input in the output for each
+ *                       dimension, the entry for `axis` is ignored. This can
+ *                       be `NULL` to use an offset of 0 on all axes.
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_array_move_along_axis(struct mts_array_t *array,
+                                       const struct mts_array_t *input,
+                                       uintptr_t axis,
+                                       const struct mts_index_mapping_t *mapping,
+                                       uintptr_t mapping_count,
+                                       const uintptr_t *output_offsets);
+
 /**
  * Create a new `mts_block_t` with the given `data` and `samples`, `components`
  * and `properties` labels.
@@ -565,6 +1082,23 @@ mts_status_t mts_block_free(struct mts_block_t *block);
  */
 struct mts_block_t *mts_block_copy(const struct mts_block_t *block);
 
+/**
+ * Make a copy of an `mts_block_t`, with the values and gradients of all the
+ * arrays converted to the given data `origin` using the converters registered
+ * with `mts_register_array_converter`.
+ *
+ * The memory allocated by this function should be released using
+ * `mts_block_free`, or moved into a tensor map using `mts_tensormap`.
+ *
+ * @param block existing block to convert
+ * @param origin pre-registered data origin to convert to
+ *
+ * @returns A pointer to the newly allocated block, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_block_t *mts_block_to_origin(const struct mts_block_t *block, mts_data_origin_t origin);
+
 /**
  * Get the set of labels from this `block`.
  *
@@ -646,6 +1180,37 @@ mts_status_t mts_block_add_gradient(struct mts_block_t *block,
                                     const char *parameter,
                                     struct mts_block_t *gradient);
 
+/**
+ * Remove the gradient with respect to `parameter` from this `block`, and
+ * return it.
+ *
+ * The memory allocated for the returned gradient should be released using
+ * `mts_block_free`, or moved into another block with `mts_block_add_gradient`.
+ *
+ * @param block pointer to an existing block
+ * @param parameter name of the gradient to remove, as a NULL-terminated UTF-8
+ *                  string
+ *
+ * @returns A pointer to the removed gradient, or a `NULL` pointer in case of
+ *          error. In case of error, you can use `mts_last_error()` to get
+ *          the error message.
+ */
+struct mts_block_t *mts_block_take_gradient(struct mts_block_t *block, const char *parameter);
+
+/**
+ * Remove the gradient with respect to `parameter` from this `block`, and
+ * release the corresponding memory.
+ *
+ * @param block pointer to an existing block
+ * @param parameter name of the gradient to remove, as a NULL-terminated UTF-8
+ *                  string
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_block_remove_gradient(struct mts_block_t *block, const char *parameter);
+
 /**
  * Get a list of all gradients defined in this `block` in the `parameters` array.
  *
@@ -713,6 +1278,50 @@ mts_status_t mts_tensormap_free(struct mts_tensormap_t *tensor);
  */
 struct mts_tensormap_t *mts_tensormap_copy(const struct mts_tensormap_t *tensor);
 
+/**
+ * Make a copy of an `mts_tensormap_t`, with the values and gradients of all the
+ * arrays converted to the given data `origin` using the converters registered
+ * with `mts_register_array_converter`.
+ *
+ * The memory allocated by this function should be released using
+ * `mts_tensormap_free`.
+ *
+ * @param tensor existing tensor map to convert
+ * @param origin pre-registered data origin to convert to
+ *
+ * @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_to_origin(const struct mts_tensormap_t *tensor,
+                                                mts_data_origin_t origin);
+
+/**
+ * Move all the blocks out of this `tensor` map, and release the memory
+ * associated with the tensor map itself.
+ *
+ * `blocks_count` must be set to the number of blocks in the tensor map (i.e.
+ * the number of entries in its keys), and `blocks` must have space for this
+ * many pointers. On success, each entry in `blocks` is set to a block which
+ * should be released using `mts_block_free`, or moved into a tensor map; and
+ * `tensor` is no longer valid. The data of the blocks is not copied. On
+ * error, `tensor` is left unchanged.
+ *
+ * Use `mts_tensormap_keys` before calling this function to keep the keys
+ * associated with the blocks.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param blocks array to be filled with pointers to the blocks
+ * @param blocks_count number of entries in `blocks`
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_into_blocks(struct mts_tensormap_t *tensor,
+                                       struct mts_block_t **blocks,
+                                       uintptr_t blocks_count);
+
 /**
  * Get the keys for the given `tensor` map.
  *
@@ -733,8 +1342,9 @@ mts_status_t mts_tensormap_keys(const struct mts_tensormap_t *tensor, struct mts
  *
  * The block memory is still managed by the tensor map, this block should not
  * be freed. The block is invalidated when the tensor map is freed with
- * `mts_tensormap_free` or the set of keys is modified by calling one
- * of the `mts_tensormap_keys_to_XXX` function.
+ * `mts_tensormap_free` or when the blocks are modified with
+ * `mts_tensormap_insert_block`, `mts_tensormap_remove_block`,
+ * `mts_tensormap_replace_block` or `mts_tensormap_drop_blocks`.
  *
  * @param tensor pointer to an existing tensor map
  * @param block pointer to be filled with a block
@@ -748,6 +1358,130 @@ mts_status_t mts_tensormap_block_by_id(struct mts_tensormap_t *tensor,
                                        struct mts_block_t **block,
                                        uintptr_t index);
 
+/**
+ * Add a new `block` to this `tensor` map, associated with the given `key`.
+ *
+ * The block is added after all the existing blocks. The `key` must not be
+ * already present in the keys of the tensor map, and the block must contain
+ * the same kind of data as the existing blocks (same labels names, same
+ * gradients, same data origin, device and dtype), as checked by
+ * `mts_tensormap`.
+ *
+ * The tensor map takes ownership of the block, which should not be released
+ * separately, even if this function fails.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param key values of the new key, as 64-bit integers
+ * @param key_count number of values in `key`, this should match the number
+ *                  of dimensions of the keys
+ * @param block block to add to the tensor map
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_insert_block(struct mts_tensormap_t *tensor,
+                                        const int64_t *key,
+                                        uintptr_t key_count,
+                                        struct mts_block_t *block);
+
+/**
+ * Remove the block associated with the given `key` from this `tensor` map,
+ * and return it.
+ *
+ * The memory allocated for the returned block should be released using
+ * `mts_block_free`, or moved into a tensor map.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param key values of the key to remove, as 64-bit integers
+ * @param key_count number of values in `key`, this should match the number
+ *                  of dimensions of the keys
+ *
+ * @returns A pointer to the removed block, or a `NULL` pointer in case of
+ *          error. In case of error, you can use `mts_last_error()` to get
+ *          the error message.
+ */
+struct mts_block_t *mts_tensormap_remove_block(struct mts_tensormap_t *tensor,
+                                               const int64_t *key,
+                                               uintptr_t key_count);
+
+/**
+ * Replace the `index`-th block in this `tensor` map with `block`, and return
+ * the previous block.
+ *
+ * The new block must contain the same kind of data as the other blocks in
+ * the tensor map, as checked by `mts_tensormap`. The tensor map takes
+ * ownership of the new block, which should not be released separately, even
+ * if this function fails. The memory allocated for the returned block should
+ * be released using `mts_block_free`, or moved into a tensor map.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param index index of the block to replace
+ * @param block new block to store in the tensor map
+ *
+ * @returns A pointer to the previous block, or a `NULL` pointer in case of
+ *          error. In case of error, you can use `mts_last_error()` to get
+ *          the error message.
+ */
+struct mts_block_t *mts_tensormap_replace_block(struct mts_tensormap_t *tensor,
+                                                uintptr_t index,
+                                                struct mts_block_t *block);
+
+/**
+ * Remove all the blocks in this `tensor` map with keys matching one of the
+ * entries in `selection`, and release the corresponding memory.
+ *
+ * The `selection` should have a subset of the names/dimensions of the keys
+ * for this tensor map, and can contain multiple entries. Entries in the
+ * `selection` without a corresponding block are ignored.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param selection labels describing which blocks should be removed
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_drop_blocks(struct mts_tensormap_t *tensor,
+                                       struct mts_labels_t selection);
+
+/**
+ * Remove the gradients with respect to all the given `parameters` from all
+ * the blocks in this `tensor` map, and release the corresponding memory.
+ *
+ * `parameters` must be an array of `parameters_count` NULL-terminated
+ * strings, encoded as UTF-8. Parameters without corresponding gradients in
+ * the blocks are ignored.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param parameters names of the gradients to remove
+ * @param parameters_count number of entries in the `parameters` array
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_remove_gradients(struct mts_tensormap_t *tensor,
+                                            const char *const *parameters,
+                                            uintptr_t parameters_count);
+
+/**
+ * Use a single set of `Labels` for all identical gradient samples in this
+ * `tensor` map, across all blocks and gradient parameters (including
+ * gradients of gradients).
+ *
+ * This reduces the memory used by the gradient samples, and samples used by
+ * more than one gradient are then stored only once when saving this tensor
+ * map.
+ *
+ * @param tensor pointer to an existing tensor map
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_share_gradient_samples(struct mts_tensormap_t *tensor);
+
 /**
  * Get indices of the blocks in this `tensor` corresponding to the given
  * `selection`. The `selection` should have a subset of the names/dimensions of
@@ -774,6 +1508,71 @@ mts_status_t mts_tensormap_blocks_matching(const struct mts_tensormap_t *tensor,
                                            uintptr_t *count,
                                            struct mts_labels_t selection);
 
+/**
+ * Get indices of the blocks in this `tensor` where the keys dimensions with
+ * index `dimensions[i]` take the value `values[i]`, for all `i` in
+ * `0..values_count`. This is equivalent to `mts_tensormap_blocks_matching`
+ * with a selection containing a single entry, without having to create the
+ * corresponding `mts_labels_t`.
+ *
+ * When calling this function, `*count` should contain the number of entries in
+ * `block_indexes`. When the function returns successfully, `*count` will
+ * contain the number of blocks matching the selection, i.e. how many values
+ * were written to `block_indexes`.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param dimensions index of the keys dimensions used in the selection
+ * @param values values taken by the corresponding keys dimensions, as 64-bit
+ *               integers
+ * @param values_count number of entries in both `dimensions` and `values`
+ * @param block_indexes array to be filled with indexes of blocks in the tensor
+ *                      map matching the selection
+ * @param count number of entries in `block_indexes`
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_blocks_matching_values(const struct mts_tensormap_t *tensor,
+                                                  const uintptr_t *dimensions,
+                                                  const int64_t *values,
+                                                  uintptr_t values_count,
+                                                  uintptr_t *block_indexes,
+                                                  uintptr_t *count);
+
+/**
+ * Get indices of the blocks in this `tensor` corresponding to each one of the
+ * entries in `selection`. The `selection` should have a subset of the
+ * names/dimensions of the keys for this tensor map.
+ *
+ * The results are written in compressed form: the blocks matching the `i`-th
+ * entry in `selection` are `block_indexes[offsets[i]]`, ...,
+ * `block_indexes[offsets[i + 1] - 1]`. Since each block matches at most one
+ * entry in the selection, there are at most as many matching blocks as there
+ * are keys in the tensor map.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param selection labels describing which blocks are requested
+ * @param block_indexes array to be filled with indexes of blocks in the tensor
+ *                      map matching the `selection`
+ * @param block_indexes_count number of entries in `block_indexes`, this must
+ *                            be the number of keys in `tensor`
+ * @param offsets array to be filled with the position in `block_indexes` of
+ *                the blocks matching each entry in `selection`
+ * @param offsets_count number of entries in `offsets`, this must be the
+ *                      number of entries in `selection` plus one
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_blocks_matching_many(const struct mts_tensormap_t *tensor,
+                                                struct mts_labels_t selection,
+                                                uintptr_t *block_indexes,
+                                                uintptr_t block_indexes_count,
+                                                uintptr_t *offsets,
+                                                uintptr_t offsets_count);
+
 /**
  * Merge blocks with the same value for selected keys dimensions along the
  * property axis.
@@ -870,6 +1669,85 @@ struct mts_tensormap_t *mts_tensormap_keys_to_samples(const struct mts_tensormap
                                                       struct mts_labels_t keys_to_move,
                                                       bool sort_samples);
 
+/**
+ * Create a new `mts_tensormap_builder_t`, used to build a tensor map from
+ * chunks of data given in any order.
+ *
+ * Each chunk is a block associated with a key. Chunks with the same key are
+ * merged together along the samples axis when calling
+ * `mts_tensormap_builder_finish`, and must have the same components,
+ * properties and gradients.
+ *
+ * The memory allocated by this function should be released using
+ * `mts_tensormap_builder_free` or `mts_tensormap_builder_finish`.
+ *
+ * @param keys_names names of the keys dimensions, as an array of
+ *                   NULL-terminated UTF-8 strings
+ * @param keys_names_count number of entries in the `keys_names` array
+ * @param sort_samples whether the samples of the final blocks should be
+ *                     sorted, or kept in the order in which they were added
+ *
+ * @returns A pointer to the newly allocated builder, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_builder_t *mts_tensormap_builder(const char *const *keys_names,
+                                                      uintptr_t keys_names_count,
+                                                      bool sort_samples);
+
+/**
+ * Free the memory associated with a `builder` previously created with
+ * `mts_tensormap_builder`, and all the chunks it contains.
+ *
+ * If `builder` is `NULL`, this function does nothing.
+ *
+ * @param builder pointer to an existing builder, or `NULL`
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_builder_free(struct mts_tensormap_builder_t *builder);
+
+/**
+ * Add a new `chunk` of data associated with the given `key` to this
+ * `builder`.
+ *
+ * The builder takes ownership of the chunk, which should not be released
+ * separately, even if this function fails. The samples of the chunk must not
+ * already be present in previous chunks for the same key.
+ *
+ * @param builder pointer to an existing builder
+ * @param key values of the key for this chunk, as 64-bit integers
+ * @param key_count number of values in `key`, this should match the number
+ *                  of keys dimensions given to `mts_tensormap_builder`
+ * @param chunk block containing a chunk of data for the given key
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_builder_add(struct mts_tensormap_builder_t *builder,
+                                       const int64_t *key,
+                                       uintptr_t key_count,
+                                       struct mts_block_t *chunk);
+
+/**
+ * Merge all the chunks in this `builder` and create the corresponding tensor
+ * map.
+ *
+ * This function consumes the builder, which should not be used or released
+ * afterward, even if this function fails. The memory allocated for the
+ * tensor map should be released using `mts_tensormap_free`.
+ *
+ * @param builder pointer to an existing builder
+ *
+ * @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_builder_finish(struct mts_tensormap_builder_t *builder);
+
 /**
  * Load labels from the file at the given path.
  *
@@ -1085,6 +1963,10 @@ mts_status_t mts_block_save_buffer(uint8_t **buffer,
  *                                                     /   data.npy
  * ```
  *
+ * Gradients of gradients are stored in the same way, in a `gradients`
+ * directory inside the directory of the corresponding gradient, e.g.
+ * `blocks/<block_id>/gradients/<parameter>/gradients/<other>/samples.npy`.
+ *
  * @param path path to the file as a NULL-terminated UTF-8 string
  * @param create_array callback function that will be used to create data
  *                     arrays inside each block
@@ -1122,7 +2004,10 @@ struct mts_tensormap_t *mts_tensormap_load_buffer(const uint8_t *buffer,
 /**
  * Save a tensor map to the file at the given path.
  *
- * If the file already exists, it is overwritten.
+ * If the file already exists, it is overwritten. Gradient samples are always
+ * stored separately for each gradient, use
+ * `mts_tensormap_save_shared_gradient_samples` to store gradient samples
+ * shared between multiple gradients only once.
  *
  * @param path path to the file as a NULL-terminated UTF-8 string
  * @param tensor tensor map to save to the file
@@ -1133,6 +2018,24 @@ struct mts_tensormap_t *mts_tensormap_load_buffer(const uint8_t *buffer,
  */
 mts_status_t mts_tensormap_save(const char *path, const struct mts_tensormap_t *tensor);
 
+/**
+ * Save a tensor map to the file at the given path, storing gradient samples
+ * shared between multiple gradients (see
+ * `mts_tensormap_share_gradient_samples`) only once.
+ *
+ * If the file already exists, it is overwritten. Files saved with this
+ * function can only be loaded by metatensor-core 0.2.0 or later.
+ *
+ * @param path path to the file as a NULL-terminated UTF-8 string
+ * @param tensor tensor map to save to the file
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_save_shared_gradient_samples(const char *path,
+                                                        const struct mts_tensormap_t *tensor);
+
 /**
  * Save a tensor map to an in-memory buffer.
  *
@@ -1146,6 +2049,10 @@ mts_status_t mts_tensormap_save(const char *path, const struct mts_tensormap_t *
  * Users of this function are responsible for freeing the `*buffer` when they
  * are done with it, using the function matching the `realloc` callback.
  *
+ * Gradient samples are always stored separately for each gradient, use
+ * `mts_tensormap_save_buffer_shared_gradient_samples` to store gradient
+ * samples shared between multiple gradients only once.
+ *
  * @param buffer pointer to the buffer the tensor will be stored to, which can
  *        change due to reallocations.
  * @param buffer_count pointer to the buffer size on input, number of written
@@ -1165,6 +2072,34 @@ mts_status_t mts_tensormap_save_buffer(uint8_t **buffer,
                                        mts_realloc_buffer_t realloc,
                                        const struct mts_tensormap_t *tensor);
 
+/**
+ * Save a tensor map to an in-memory buffer, storing gradient samples shared
+ * between multiple gradients (see `mts_tensormap_share_gradient_samples`)
+ * only once.
+ *
+ * This function behaves like `mts_tensormap_save_buffer` otherwise. Data
+ * saved with this function can only be loaded by metatensor-core 0.2.0 or
+ * later.
+ *
+ * @param buffer pointer to the buffer the tensor will be stored to, which can
+ *        change due to reallocations.
+ * @param buffer_count pointer to the buffer size on input, number of written
+ *        bytes on output
+ * @param realloc_user_data custom data for the `realloc` callback. This will
+ *        be passed as the first argument to `realloc` as-is.
+ * @param realloc function that allows to grow the buffer allocation
+ * @param tensor tensor map that will saved to the buffer
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full error
+ *          message.
+ */
+mts_status_t mts_tensormap_save_buffer_shared_gradient_samples(uint8_t **buffer,
+                                                               uintptr_t *buffer_count,
+                                                               void *realloc_user_data,
+                                                               mts_realloc_buffer_t realloc,
+                                                               const struct mts_tensormap_t *tensor);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
@@ -1686,6 +1686,10 @@ private:
         labels_(labels)
     {
         assert(labels_.internal_ptr_ != nullptr);
+        if (labels_.dtype != MTS_LABELS_INT32) {
+            mts_labels_free(&labels_);
+            throw Error("Labels using 64-bit values are not yet supported in C++");
+        }
 
         for (size_t i=0; i<labels_.size; i++) {
             names_.push_back(labels_.names[i]);
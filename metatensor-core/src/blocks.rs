@@ -4,7 +4,7 @@ use std::collections::{HashMap, BTreeSet};
 
 use crate::utils::ConstCString;
 use crate::{Labels, LabelsBuilder};
-use crate::{mts_array_t, get_data_origin};
+use crate::{mts_array_t, mts_data_origin_t, get_data_origin};
 use crate::Error;
 
 /// A `Vec` which can not be modified
@@ -167,6 +167,27 @@ impl TensorBlock {
         })
     }
 
+    /// Create a copy of this block with all the values and gradients
+    /// converted to the given data `origin`.
+    pub fn to_origin(&self, origin: mts_data_origin_t) -> Result<TensorBlock, Error> {
+        let values = self.values.convert_to(origin)?;
+
+        let mut gradients = HashMap::new();
+        for (gradient_parameter, gradient_block) in &self.gradients {
+            gradients.insert(gradient_parameter.clone(), gradient_block.to_origin(origin)?);
+        }
+        let gradient_parameters = self.gradient_parameters.clone();
+
+        Ok(TensorBlock {
+            values,
+            samples: Arc::clone(&self.samples),
+            components: self.components.clone(),
+            properties: Arc::clone(&self.properties),
+            gradients,
+            gradient_parameters
+        })
+    }
+
     /// Get all gradients defined in this block
     pub fn gradients(&self) -> &HashMap<String, TensorBlock> {
         &self.gradients
@@ -197,6 +218,10 @@ impl TensorBlock {
     ///
     /// The components labels must contain at least the same entries as the
     /// value components labels, and can prepend other components labels.
+    ///
+    /// The same gradient samples `Arc<Labels>` can be shared between multiple
+    /// gradients and blocks to save memory, see also
+    /// `TensorMap::share_gradient_samples`.
     pub fn add_gradient(
         &mut self,
         parameter: &str,
@@ -216,6 +241,16 @@ impl TensorBlock {
             )));
         }
 
+        if let (Some(values), Some(gradient)) = (self.values.descriptor()?, gradient.values.descriptor()?) {
+            if values != gradient {
+                return Err(Error::InvalidParameter(format!(
+                    "the gradient data is on a different device or has a \
+                    different dtype ({}) than the value data ({})",
+                    gradient, values,
+                )));
+            }
+        }
+
         if gradient.samples.size() == 0 {
             return Err(Error::InvalidParameter(
                 "gradients samples must have at least one dimension, named 'sample', we got none".into()
@@ -229,13 +264,21 @@ impl TensorBlock {
             )));
         }
 
-        let max_sample = self.samples.count();
-        for sample in &*gradient.samples {
-            if sample[0].isize() < 0 || sample[0].usize() >= max_sample {
-                return Err(Error::InvalidParameter(format!(
-                    "invalid value for the 'sample' in gradient samples: we got \
-                    {}, but the values contain {} samples", sample[0], max_sample
-                )));
+        // the samples were already checked if they are shared with another
+        // gradient of this block
+        let already_checked = self.gradients.values().any(|existing| {
+            Arc::ptr_eq(&existing.samples, &gradient.samples)
+        });
+
+        if !already_checked {
+            let max_sample = self.samples.count();
+            for sample in &*gradient.samples {
+                if sample.get(0).isize() < 0 || sample.get(0).usize() >= max_sample {
+                    return Err(Error::InvalidParameter(format!(
+                        "invalid value for the 'sample' in gradient samples: we got \
+                        {}, but the values contain {} samples", sample.get(0), max_sample
+                    )));
+                }
             }
         }
 
@@ -277,6 +320,24 @@ impl TensorBlock {
         return Ok(());
     }
 
+    /// Remove the gradient with respect to `parameter` from this block, and
+    /// return it.
+    pub fn take_gradient(&mut self, parameter: &str) -> Result<TensorBlock, Error> {
+        let gradient = self.gradients.remove(parameter).ok_or_else(|| Error::InvalidParameter(format!(
+            "can not find gradients with respect to '{}' in this block", parameter
+        )))?;
+
+        self.gradient_parameters.retain(|p| p.as_str() != parameter);
+
+        return Ok(gradient);
+    }
+
+    /// Remove the gradient with respect to `parameter` from this block.
+    pub fn remove_gradient(&mut self, parameter: &str) -> Result<(), Error> {
+        self.take_gradient(parameter)?;
+        return Ok(());
+    }
+
     /// Move components to properties for this block and all gradients in this
     /// block
     pub(crate) fn components_to_properties(&mut self, dimensions: &[&str]) -> Result<(), Error> {
@@ -305,11 +366,12 @@ impl TensorBlock {
             .copied()
             .collect();
 
-        let mut new_properties_builder = LabelsBuilder::new(new_property_names)?;
+        let dtype = std::cmp::max(moved_component.dtype(), old_properties.dtype());
+        let mut new_properties_builder = LabelsBuilder::new_with_dtype(new_property_names, dtype)?;
         for new_property in &*moved_component {
             for old_property in old_properties.iter() {
                 let mut property = new_property.to_vec();
-                property.extend_from_slice(old_property);
+                property.extend(old_property);
                 new_properties_builder.add(&property)?;
             }
         }
@@ -520,5 +582,46 @@ mod tests {
             let result = block.add_gradient("components", gradient);
             assert!(result.is_ok());
         }
+
+        #[test]
+        fn remove_gradients() {
+            let properties = example_labels("properties", 7);
+            let mut block = TensorBlock::new(
+                TestArray::new(vec![4, 7]),
+                example_labels("samples", 4),
+                vec![],
+                properties.clone(),
+            ).unwrap();
+
+            for parameter in ["foo", "bar"] {
+                let gradient = TensorBlock::new(
+                    TestArray::new(vec![3, 7]),
+                    example_labels("sample", 3),
+                    vec![],
+                    properties.clone(),
+                ).unwrap();
+                block.add_gradient(parameter, gradient).unwrap();
+            }
+
+            let gradient = block.take_gradient("foo").unwrap();
+            assert_eq!(gradient.samples.names(), ["sample"]);
+            assert!(block.gradient("foo").is_none());
+
+            let parameters = block.gradient_parameters_c().iter().map(|p| p.as_str()).collect::<Vec<_>>();
+            assert_eq!(parameters, ["bar"]);
+
+            // the gradient can be added back after removal
+            block.add_gradient("foo", gradient).unwrap();
+            block.remove_gradient("bar").unwrap();
+
+            let parameters = block.gradient_parameters_c().iter().map(|p| p.as_str()).collect::<Vec<_>>();
+            assert_eq!(parameters, ["foo"]);
+
+            let error = block.remove_gradient("bar").unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "invalid parameter: can not find gradients with respect to 'bar' in this block"
+            );
+        }
     }
 }
@@ -2,7 +2,7 @@ use std::sync::Arc;
 use std::os::raw::c_char;
 use std::ffi::CStr;
 
-use crate::{TensorBlock, Error, mts_array_t};
+use crate::{TensorBlock, Error, mts_array_t, mts_data_origin_t};
 
 use super::labels::{mts_labels_t, rust_to_mts_labels, mts_labels_to_rust};
 
@@ -166,6 +166,45 @@ pub unsafe extern fn mts_block_copy(
     return result;
 }
 
+/// Make a copy of an `mts_block_t`, with the values and gradients of all the
+/// arrays converted to the given data `origin` using the converters registered
+/// with `mts_register_array_converter`.
+///
+/// The memory allocated by this function should be released using
+/// `mts_block_free`, or moved into a tensor map using `mts_tensormap`.
+///
+/// @param block existing block to convert
+/// @param origin pre-registered data origin to convert to
+///
+/// @returns A pointer to the newly allocated block, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_to_origin(
+    block: *const mts_block_t,
+    origin: mts_data_origin_t,
+) -> *mut mts_block_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(block);
+        let new_block = (*block).to_origin(origin)?;
+        let boxed = Box::new(mts_block_t(new_block));
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = Box::into_raw(boxed);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
 
 /// Get the set of labels from this `block`.
 ///
@@ -341,6 +380,70 @@ pub unsafe extern fn mts_block_add_gradient(
     })
 }
 
+/// Remove the gradient with respect to `parameter` from this `block`, and
+/// return it.
+///
+/// The memory allocated for the returned gradient should be released using
+/// `mts_block_free`, or moved into another block with `mts_block_add_gradient`.
+///
+/// @param block pointer to an existing block
+/// @param parameter name of the gradient to remove, as a NULL-terminated UTF-8
+///                  string
+///
+/// @returns A pointer to the removed gradient, or a `NULL` pointer in case of
+///          error. In case of error, you can use `mts_last_error()` to get
+///          the error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_take_gradient(
+    block: *mut mts_block_t,
+    parameter: *const c_char,
+) -> *mut mts_block_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(block, parameter);
+        let parameter = CStr::from_ptr(parameter).to_str().unwrap();
+
+        let gradient = (*block).take_gradient(parameter)?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = mts_block_t::into_boxed_raw(gradient);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+/// Remove the gradient with respect to `parameter` from this `block`, and
+/// release the corresponding memory.
+///
+/// @param block pointer to an existing block
+/// @param parameter name of the gradient to remove, as a NULL-terminated UTF-8
+///                  string
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_remove_gradient(
+    block: *mut mts_block_t,
+    parameter: *const c_char,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block, parameter);
+        let parameter = CStr::from_ptr(parameter).to_str().unwrap();
+
+        (*block).remove_gradient(parameter)?;
+        Ok(())
+    })
+}
+
 /// Get a list of all gradients defined in this `block` in the `parameters` array.
 ///
 /// @param block pointer to an existing block
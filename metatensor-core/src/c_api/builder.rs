@@ -0,0 +1,168 @@
+use std::os::raw::c_char;
+use std::ffi::CStr;
+
+use crate::TensorMapBuilder;
+
+use super::blocks::mts_block_t;
+use super::tensor::{mts_tensormap_t, key_to_rust};
+use super::status::{mts_status_t, catch_unwind};
+
+/// Opaque type used to build a `mts_tensormap_t` from chunks of data.
+#[allow(non_camel_case_types)]
+pub struct mts_tensormap_builder_t(TensorMapBuilder);
+
+
+/// Create a new `mts_tensormap_builder_t`, used to build a tensor map from
+/// chunks of data given in any order.
+///
+/// Each chunk is a block associated with a key. Chunks with the same key are
+/// merged together along the samples axis when calling
+/// `mts_tensormap_builder_finish`, and must have the same components,
+/// properties and gradients.
+///
+/// The memory allocated by this function should be released using
+/// `mts_tensormap_builder_free` or `mts_tensormap_builder_finish`.
+///
+/// @param keys_names names of the keys dimensions, as an array of
+///                   NULL-terminated UTF-8 strings
+/// @param keys_names_count number of entries in the `keys_names` array
+/// @param sort_samples whether the samples of the final blocks should be
+///                     sorted, or kept in the order in which they were added
+///
+/// @returns A pointer to the newly allocated builder, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_builder(
+    keys_names: *const *const c_char,
+    keys_names_count: usize,
+    sort_samples: bool,
+) -> *mut mts_tensormap_builder_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+
+    let status = catch_unwind(move || {
+        let mut names = Vec::new();
+        if keys_names_count != 0 {
+            check_pointers_non_null!(keys_names);
+            for &name in std::slice::from_raw_parts(keys_names, keys_names_count) {
+                check_pointers_non_null!(name);
+                names.push(CStr::from_ptr(name).to_str().expect("invalid utf8"));
+            }
+        }
+
+        let builder = TensorMapBuilder::new(names, sort_samples)?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = Box::into_raw(Box::new(mts_tensormap_builder_t(builder)));
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+
+/// Free the memory associated with a `builder` previously created with
+/// `mts_tensormap_builder`, and all the chunks it contains.
+///
+/// If `builder` is `NULL`, this function does nothing.
+///
+/// @param builder pointer to an existing builder, or `NULL`
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_builder_free(builder: *mut mts_tensormap_builder_t) -> mts_status_t {
+    catch_unwind(|| {
+        if !builder.is_null() {
+            std::mem::drop(Box::from_raw(builder));
+        }
+
+        Ok(())
+    })
+}
+
+
+/// Add a new `chunk` of data associated with the given `key` to this
+/// `builder`.
+///
+/// The builder takes ownership of the chunk, which should not be released
+/// separately, even if this function fails. The samples of the chunk must not
+/// already be present in previous chunks for the same key.
+///
+/// @param builder pointer to an existing builder
+/// @param key values of the key for this chunk, as 64-bit integers
+/// @param key_count number of values in `key`, this should match the number
+///                  of keys dimensions given to `mts_tensormap_builder`
+/// @param chunk block containing a chunk of data for the given key
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_builder_add(
+    builder: *mut mts_tensormap_builder_t,
+    key: *const i64,
+    key_count: usize,
+    chunk: *mut mts_block_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(chunk);
+
+        // move the chunk out of the pointer
+        let chunk = mts_block_t::from_boxed_raw(chunk);
+
+        check_pointers_non_null!(builder);
+        let key = key_to_rust(key, key_count)?;
+        (*builder).0.add(&key, chunk)?;
+
+        Ok(())
+    })
+}
+
+
+/// Merge all the chunks in this `builder` and create the corresponding tensor
+/// map.
+///
+/// This function consumes the builder, which should not be used or released
+/// afterward, even if this function fails. The memory allocated for the
+/// tensor map should be released using `mts_tensormap_free`.
+///
+/// @param builder pointer to an existing builder
+///
+/// @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_builder_finish(
+    builder: *mut mts_tensormap_builder_t,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(builder);
+
+        let builder = Box::from_raw(builder).0;
+        let tensor = builder.finish()?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = mts_tensormap_t::into_boxed_raw(tensor);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
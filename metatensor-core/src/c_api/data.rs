@@ -1,7 +1,9 @@
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
 use std::ffi::CStr;
 
-use crate::mts_data_origin_t;
+use crate::{mts_data_origin_t, mts_array_t, mts_array_operations_t, mts_array_descriptor_t, Error};
+use crate::mts_index_mapping_t;
+use crate::mts_array_converter_t;
 
 use super::{mts_status_t, catch_unwind};
 use super::utils::copy_str_to_c;
@@ -55,3 +57,370 @@ pub unsafe extern fn mts_get_data_origin(
         return copy_str_to_c(&origin, buffer, buffer_size);
     })
 }
+
+
+/// Register optional `operations` for all arrays with the given data `origin`.
+///
+/// The functions in `operations` are used by `mts_array_add`, `mts_array_mul`,
+/// `mts_array_scale`, `mts_array_matmul`, `mts_array_sum_axis` and
+/// `mts_array_copy_from` instead of accessing the data of the arrays directly.
+/// Calling this function multiple times with the same `origin` replaces the
+/// previously registered operations.
+///
+/// @param origin pre-registered data origin
+/// @param operations pointer to the operations for this data origin. The
+///                   `version` of the operations should be set to
+///                   `MTS_ARRAY_OPERATIONS_VERSION`, or to the version of
+///                   metatensor the code was written for. Only the functions
+///                   available in this version will be used.
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_register_array_operations(
+    origin: mts_data_origin_t,
+    operations: *const mts_array_operations_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(operations);
+        crate::register_array_operations(origin, operations)
+    })
+}
+
+/// Register a `converter` to convert arrays with the data origin `from` to
+/// arrays with the data origin `to`.
+///
+/// Converters are used by `mts_array_to_origin`, `mts_block_to_origin` and
+/// `mts_tensormap_to_origin`. They are also used by the operations taking two
+/// arrays (`mts_array_add`, `mts_array_mul`, `mts_array_matmul`,
+/// `mts_array_copy_from` and `mts_array_move_along_axis`) to convert the
+/// second array to the origin of the first one when their origins differ,
+/// and when saving arrays which do not give access to their data (for
+/// example because it is stored on a GPU). Calling this function multiple
+/// times with the same origins replaces the previously registered converter.
+///
+/// @param from pre-registered data origin of the arrays to convert
+/// @param to pre-registered data origin of the converted arrays
+/// @param converter function converting arrays from `from` to `to`
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_register_array_converter(
+    from: mts_data_origin_t,
+    to: mts_data_origin_t,
+    converter: mts_array_converter_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(converter);
+        let converter = converter.expect("converter should not be NULL");
+        crate::register_array_converter(from, to, converter)
+    })
+}
+
+/// Convert `array` to the given data `origin`, using the converter registered
+/// with `mts_register_array_converter`. If `array` already has this origin,
+/// `output` is set to a copy of `array`.
+///
+/// @param array array to convert
+/// @param origin pre-registered data origin to convert to
+/// @param output pointer to an empty `mts_array_t` that will be set to the
+///               converted array. The caller is responsible for calling
+///               `output->destroy` when it is no longer needed.
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_to_origin(
+    array: *const mts_array_t,
+    origin: mts_data_origin_t,
+    output: *mut mts_array_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array, output);
+        let result = (*array).convert_to(origin)?;
+        std::ptr::write(output, result);
+        Ok(())
+    })
+}
+
+/// Get the device and data type of the given `array` in `descriptor`, using
+/// `mts_array_operations_t.descriptor`. If this function was not registered
+/// for the data origin of the array, `descriptor->device_type` is set to 0.
+///
+/// @param array array to describe
+/// @param descriptor pointer to an `mts_array_descriptor_t` where the
+///                   descriptor will be stored
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_descriptor(
+    array: *const mts_array_t,
+    descriptor: *mut mts_array_descriptor_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array, descriptor);
+        *descriptor = (*array).descriptor()?.unwrap_or(mts_array_descriptor_t {
+            device_type: 0,
+            device_id: 0,
+            dtype_code: 0,
+            dtype_bits: 0,
+            dtype_lanes: 0,
+        });
+        Ok(())
+    })
+}
+
+/// Export the given `array` as a `DLPack` `DLManagedTensor`, without copying
+/// the data.
+///
+/// This uses `mts_array_operations_t.raw_data` and
+/// `mts_array_operations_t.descriptor` if they were registered for the data
+/// origin of the array, and `mts_array_t.data` otherwise (in which case the
+/// data is 64-bit floating point values on CPU, or a copy converted to 32-bit
+/// floating point values if `mts_array_operations_t.descriptor` describes the
+/// array as such). The tensor does not own the data, and must not be used
+/// after `array` has been destroyed.
+///
+/// @param array array to export
+/// @param tensor pointer to a `DLManagedTensor*` (declared as `void*` to avoid
+///               requiring `dlpack.h`) where the exported tensor will be
+///               stored. The caller is responsible for calling the `deleter`
+///               of this tensor when it is no longer needed.
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_to_dlpack(
+    array: *const mts_array_t,
+    tensor: *mut *mut c_void,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array, tensor);
+        *tensor = (*array).to_dlpack()?.cast();
+        Ok(())
+    })
+}
+
+/// Check that `array` and `other` are not the same array, since one of them
+/// is going to be modified
+unsafe fn check_different_arrays(array: *const mts_array_t, other: *const mts_array_t) -> Result<(), Error> {
+    if std::ptr::eq(array, other) || (*array).ptr == (*other).ptr {
+        return Err(Error::InvalidParameter(
+            "`array` and `other` must be different arrays".into()
+        ));
+    }
+    return Ok(());
+}
+
+/// Add the values in the `other` array to the values in `array`, element-wise.
+///
+/// This uses `mts_array_operations_t.add` if it was registered for the data
+/// origin of both arrays, and `mts_array_t.data` otherwise.
+///
+/// @param array array to modify
+/// @param other array to add to `array`, with the same shape
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_add(
+    array: *mut mts_array_t,
+    other: *const mts_array_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array, other);
+        check_different_arrays(array, other)?;
+        (*array).add(&*other)
+    })
+}
+
+/// Multiply the values in `array` by the values in the `other` array,
+/// element-wise.
+///
+/// This uses `mts_array_operations_t.mul` if it was registered for the data
+/// origin of both arrays, and `mts_array_t.data` otherwise.
+///
+/// @param array array to modify
+/// @param other array to multiply `array` with, with the same shape
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_mul(
+    array: *mut mts_array_t,
+    other: *const mts_array_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array, other);
+        check_different_arrays(array, other)?;
+        (*array).mul(&*other)
+    })
+}
+
+/// Multiply all the values in `array` by `factor`.
+///
+/// This uses `mts_array_operations_t.scale` if it was registered for the data
+/// origin of the array, and `mts_array_t.data` otherwise.
+///
+/// @param array array to modify
+/// @param factor scaling factor
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_scale(
+    array: *mut mts_array_t,
+    factor: f64,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array);
+        (*array).scale(factor)
+    })
+}
+
+/// Compute the matrix product of `array` and the 2-dimensional `other` array,
+/// contracting the last axis of `array` with the first axis of `other`.
+///
+/// This uses `mts_array_operations_t.matmul` if it was registered for the
+/// data origin of both arrays, and `mts_array_t.create` and
+/// `mts_array_t.data` otherwise.
+///
+/// @param array first array in the product
+/// @param other second array in the product
+/// @param output pointer to an empty `mts_array_t` that will be set to the
+///               result of the product. The caller is responsible for calling
+///               `output->destroy` when it is no longer needed.
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_matmul(
+    array: *const mts_array_t,
+    other: *const mts_array_t,
+    output: *mut mts_array_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array, other, output);
+        let result = (*array).matmul(&*other)?;
+        std::ptr::write(output, result);
+        Ok(())
+    })
+}
+
+/// Sum the values in `array` along the given `axis`. The result has the same
+/// shape as `array`, except for `axis` which has a size of 1.
+///
+/// This uses `mts_array_operations_t.sum_axis` if it was registered for the
+/// data origin of the array, and `mts_array_t.create` and `mts_array_t.data`
+/// otherwise.
+///
+/// @param array array to sum
+/// @param axis axis along which the sum is performed
+/// @param output pointer to an empty `mts_array_t` that will be set to the
+///               result of the sum. The caller is responsible for calling
+///               `output->destroy` when it is no longer needed.
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_sum_axis(
+    array: *const mts_array_t,
+    axis: usize,
+    output: *mut mts_array_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array, output);
+        let result = (*array).sum_axis(axis)?;
+        std::ptr::write(output, result);
+        Ok(())
+    })
+}
+
+/// Set all the values in `array` to the values in the `other` array.
+///
+/// This uses `mts_array_operations_t.copy_from` if it was registered for the
+/// data origin of both arrays, and `mts_array_t.data` otherwise.
+///
+/// @param array array to modify
+/// @param other array to copy the values from, with the same shape
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_copy_from(
+    array: *mut mts_array_t,
+    other: *const mts_array_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array, other);
+        check_different_arrays(array, other)?;
+        (*array).copy_from(&*other)
+    })
+}
+
+/// Copy values from the `input` array to `array` along `axis`.
+///
+/// For each entry in `mapping`, this copies `input[..., mapping.input, ...]`
+/// to `array[..., mapping.output, ...]`, where the index is on `axis`. On all
+/// the other axes `i`, the whole input is copied to `output_offsets[i]` up to
+/// `output_offsets[i] + input_shape[i]` in the output.
+///
+/// This uses `mts_array_operations_t.move_along_axis` if it was registered for
+/// the data origin of both arrays, and `mts_array_t.data` otherwise.
+///
+/// @param array array to modify
+/// @param input array to copy the values from, with the same number of
+///              dimensions as `array`
+/// @param axis axis along which the indexes in `mapping` are defined
+/// @param mapping indexes to copy along `axis`
+/// @param mapping_count number of entries in `mapping`
+/// @param output_offsets offset of the input in the output for each
+///                       dimension, the entry for `axis` is ignored. This can
+///                       be `NULL` to use an offset of 0 on all axes.
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_array_move_along_axis(
+    array: *mut mts_array_t,
+    input: *const mts_array_t,
+    axis: usize,
+    mapping: *const mts_index_mapping_t,
+    mapping_count: usize,
+    output_offsets: *const usize,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(array, input);
+        check_different_arrays(array, input)?;
+
+        let mapping = if mapping_count == 0 {
+            &[]
+        } else {
+            check_pointers_non_null!(mapping);
+            std::slice::from_raw_parts(mapping, mapping_count)
+        };
+
+        let ndim = (*input).shape()?.len();
+        let output_offsets = if output_offsets.is_null() {
+            vec![0; ndim]
+        } else {
+            std::slice::from_raw_parts(output_offsets, ndim).to_vec()
+        };
+
+        (*array).move_along_axis(&*input, axis, mapping, &output_offsets)
+    })
+}
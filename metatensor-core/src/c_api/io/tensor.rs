@@ -53,6 +53,10 @@ use super::mts_create_array_callback_t;
 ///                                                     /   data.npy
 /// ```
 ///
+/// Gradients of gradients are stored in the same way, in a `gradients`
+/// directory inside the directory of the corresponding gradient, e.g.
+/// `blocks/<block_id>/gradients/<parameter>/gradients/<other>/samples.npy`.
+///
 /// @param path path to the file as a NULL-terminated UTF-8 string
 /// @param create_array callback function that will be used to create data
 ///                     arrays inside each block
@@ -203,7 +207,10 @@ fn wrap_create_array(create_array: &mts_create_array_callback_t) -> impl Fn(Vec<
 
 /// Save a tensor map to the file at the given path.
 ///
-/// If the file already exists, it is overwritten.
+/// If the file already exists, it is overwritten. Gradient samples are always
+/// stored separately for each gradient, use
+/// `mts_tensormap_save_shared_gradient_samples` to store gradient samples
+/// shared between multiple gradients only once.
 ///
 /// @param path path to the file as a NULL-terminated UTF-8 string
 /// @param tensor tensor map to save to the file
@@ -228,6 +235,36 @@ pub unsafe extern fn mts_tensormap_save(
 }
 
 
+/// Save a tensor map to the file at the given path, storing gradient samples
+/// shared between multiple gradients (see
+/// `mts_tensormap_share_gradient_samples`) only once.
+///
+/// If the file already exists, it is overwritten. Files saved with this
+/// function can only be loaded by metatensor-core 0.2.0 or later.
+///
+/// @param path path to the file as a NULL-terminated UTF-8 string
+/// @param tensor tensor map to save to the file
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_save_shared_gradient_samples(
+    path: *const c_char,
+    tensor: *const mts_tensormap_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(path, tensor);
+
+        let path = CStr::from_ptr(path).to_str().expect("use UTF-8 for path");
+        let file = BufWriter::new(File::create(path)?);
+        crate::io::save_shared_gradient_samples(file, &*tensor)?;
+
+        Ok(())
+    })
+}
+
+
 /// Save a tensor map to an in-memory buffer.
 ///
 /// On input, `*buffer` should contain the address of a starting buffer (which
@@ -240,6 +277,10 @@ pub unsafe extern fn mts_tensormap_save(
 /// Users of this function are responsible for freeing the `*buffer` when they
 /// are done with it, using the function matching the `realloc` callback.
 ///
+/// Gradient samples are always stored separately for each gradient, use
+/// `mts_tensormap_save_buffer_shared_gradient_samples` to store gradient
+/// samples shared between multiple gradients only once.
+///
 /// @param buffer pointer to the buffer the tensor will be stored to, which can
 ///        change due to reallocations.
 /// @param buffer_count pointer to the buffer size on input, number of written
@@ -253,7 +294,6 @@ pub unsafe extern fn mts_tensormap_save(
 ///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full error
 ///          message.
 #[no_mangle]
-#[allow(clippy::cast_possible_truncation)]
 pub unsafe extern fn mts_tensormap_save_buffer(
     buffer: *mut *mut u8,
     buffer_count: *mut usize,
@@ -262,30 +302,99 @@ pub unsafe extern fn mts_tensormap_save_buffer(
     tensor: *const mts_tensormap_t,
 ) -> mts_status_t {
     catch_unwind(|| {
-        check_pointers_non_null!(tensor, buffer_count, buffer);
-
-        if realloc.is_none() {
-            return Err(Error::InvalidParameter(
-                "realloc callback can not be NULL in mts_tensormap_save_buffer".into()
-            ));
-        }
+        save_buffer(
+            buffer,
+            buffer_count,
+            realloc_user_data,
+            realloc,
+            tensor,
+            "mts_tensormap_save_buffer",
+            false,
+        )
+    })
+}
 
-        if (*buffer).is_null() {
-            assert_eq!(*buffer_count, 0);
-        }
 
-        let mut external_buffer = ExternalBuffer {
-            data: buffer,
-            len: *buffer_count,
+/// Save a tensor map to an in-memory buffer, storing gradient samples shared
+/// between multiple gradients (see `mts_tensormap_share_gradient_samples`)
+/// only once.
+///
+/// This function behaves like `mts_tensormap_save_buffer` otherwise. Data
+/// saved with this function can only be loaded by metatensor-core 0.2.0 or
+/// later.
+///
+/// @param buffer pointer to the buffer the tensor will be stored to, which can
+///        change due to reallocations.
+/// @param buffer_count pointer to the buffer size on input, number of written
+///        bytes on output
+/// @param realloc_user_data custom data for the `realloc` callback. This will
+///        be passed as the first argument to `realloc` as-is.
+/// @param realloc function that allows to grow the buffer allocation
+/// @param tensor tensor map that will saved to the buffer
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full error
+///          message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_save_buffer_shared_gradient_samples(
+    buffer: *mut *mut u8,
+    buffer_count: *mut usize,
+    realloc_user_data: *mut c_void,
+    realloc: mts_realloc_buffer_t,
+    tensor: *const mts_tensormap_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        save_buffer(
+            buffer,
+            buffer_count,
             realloc_user_data,
-            realloc: realloc.expect("we checked"),
-            current: 0,
-        };
+            realloc,
+            tensor,
+            "mts_tensormap_save_buffer_shared_gradient_samples",
+            true,
+        )
+    })
+}
+
+/// Implementation of `mts_tensormap_save_buffer` and
+/// `mts_tensormap_save_buffer_shared_gradient_samples`
+#[allow(clippy::cast_possible_truncation)]
+unsafe fn save_buffer(
+    buffer: *mut *mut u8,
+    buffer_count: *mut usize,
+    realloc_user_data: *mut c_void,
+    realloc: mts_realloc_buffer_t,
+    tensor: *const mts_tensormap_t,
+    function: &str,
+    share_gradient_samples: bool,
+) -> Result<(), Error> {
+    check_pointers_non_null!(tensor, buffer_count, buffer);
+
+    if realloc.is_none() {
+        return Err(Error::InvalidParameter(format!(
+            "realloc callback can not be NULL in {}", function
+        )));
+    }
+
+    if (*buffer).is_null() {
+        assert_eq!(*buffer_count, 0);
+    }
 
+    let mut external_buffer = ExternalBuffer {
+        data: buffer,
+        len: *buffer_count,
+        realloc_user_data,
+        realloc: realloc.expect("we checked"),
+        current: 0,
+    };
+
+    if share_gradient_samples {
+        crate::io::save_shared_gradient_samples(&mut external_buffer, &*tensor)?;
+    } else {
         crate::io::save(&mut external_buffer, &*tensor)?;
+    }
 
-        *buffer_count = external_buffer.current as usize;
+    *buffer_count = external_buffer.current as usize;
 
-        Ok(())
-    })
+    return Ok(());
 }
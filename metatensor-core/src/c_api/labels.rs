@@ -3,8 +3,16 @@ use std::ffi::CStr;
 use std::sync::Arc;
 
 use crate::{LabelValue, Labels, LabelsBuilder, Error};
+use crate::labels::LabelsDType;
 use super::status::{mts_status_t, catch_unwind};
 
+/// Value of `mts_labels_t.dtype` for labels storing their values as 32-bit
+/// signed integers in `mts_labels_t.values`
+pub const MTS_LABELS_INT32: i32 = 0;
+/// Value of `mts_labels_t.dtype` for labels storing their values as 64-bit
+/// signed integers in `mts_labels_t.values_i64`
+pub const MTS_LABELS_INT64: i32 = 1;
+
 /// A set of labels used to carry metadata associated with a tensor map.
 ///
 /// This is similar to a list of `count` named tuples, but stored as a 2D array
@@ -15,7 +23,11 @@ use super::status::{mts_status_t, catch_unwind};
 /// `mts_labels_t` with a non-NULL `internal_ptr_` correspond to a
 /// reference-counted Rust data structure, which allow for fast lookup inside
 /// the labels with `mts_labels_positions`.
-
+///
+/// The values of the labels are stored either as 32-bit signed integers (in
+/// `values`) or as 64-bit signed integers (in `values_i64`), depending on
+/// `dtype`. Labels initialized with all fields set to zero use 32-bit integers.
+//
 // An `mts_labels_t` can either correspond to a Rust `Arc<Labels>` (`labels_ptr`
 // is non-NULL, and corresponds to the pointer `Arc::into_raw` gives); or to a
 // set of Labels created from C, and containing pointer to C-allocated data.
@@ -31,12 +43,21 @@ pub struct mts_labels_t {
     /// Pointer to the first element of a 2D row-major array of 32-bit signed
     /// integer containing the values taken by the different dimensions in
     /// `names`. Each row has `size` elements, and there are `count` rows in
-    /// total.
+    /// total. This is only used if `dtype` is `MTS_LABELS_INT32`, and should
+    /// be NULL otherwise.
     pub values: *const i32,
     /// Number of dimensions/size of a single entry in the set of labels
     pub size: usize,
     /// Number entries in the set of labels
     pub count: usize,
+    /// Type used to store the values of the labels, either `MTS_LABELS_INT32`
+    /// or `MTS_LABELS_INT64`
+    pub dtype: i32,
+    /// Pointer to the first element of a 2D row-major array of 64-bit signed
+    /// integer containing the values taken by the different dimensions in
+    /// `names`, with the same layout as `values`. This is only used if `dtype`
+    /// is `MTS_LABELS_INT64`, and should be NULL otherwise.
+    pub values_i64: *const i64,
 }
 
 impl mts_labels_t {
@@ -52,10 +73,21 @@ pub unsafe fn rust_to_mts_labels(labels: Arc<Labels>) -> mts_labels_t {
     let size = labels.size();
     let count = labels.count();
 
-    let values = if labels.count() == 0 || labels.size() == 0 {
-        std::ptr::null()
-    } else {
-        (&labels[0][0] as *const LabelValue).cast()
+    let mut values = std::ptr::null();
+    let mut values_i64 = std::ptr::null();
+    let dtype = match labels.dtype() {
+        LabelsDType::Int32 => {
+            if labels.count() != 0 && labels.size() != 0 {
+                values = labels.values_i32().expect("wrong dtype").as_ptr();
+            }
+            MTS_LABELS_INT32
+        },
+        LabelsDType::Int64 => {
+            if labels.count() != 0 && labels.size() != 0 {
+                values_i64 = labels.values_i64().expect("wrong dtype").as_ptr();
+            }
+            MTS_LABELS_INT64
+        },
     };
 
     let names = if labels.size() == 0 {
@@ -71,7 +103,9 @@ pub unsafe fn rust_to_mts_labels(labels: Arc<Labels>) -> mts_labels_t {
         names,
         values,
         size,
-        count
+        count,
+        dtype,
+        values_i64,
     }
 }
 
@@ -112,9 +146,25 @@ unsafe fn create_rust_labels(labels: &mts_labels_t) -> Result<Arc<Labels>, Error
         return Err(Error::InvalidParameter("labels.names can not be NULL in mts_labels_t".into()))
     }
 
-    if labels.values.is_null() && labels.count > 0 {
-        return Err(Error::InvalidParameter("labels.values is NULL but labels.count is >0 in mts_labels_t".into()))
-    }
+    let dtype = match labels.dtype {
+        MTS_LABELS_INT32 => {
+            if labels.values.is_null() && labels.count > 0 {
+                return Err(Error::InvalidParameter("labels.values is NULL but labels.count is >0 in mts_labels_t".into()))
+            }
+            LabelsDType::Int32
+        },
+        MTS_LABELS_INT64 => {
+            if labels.values_i64.is_null() && labels.count > 0 {
+                return Err(Error::InvalidParameter("labels.values_i64 is NULL but labels.count is >0 in mts_labels_t".into()))
+            }
+            LabelsDType::Int64
+        },
+        other => {
+            return Err(Error::InvalidParameter(format!(
+                "invalid dtype in mts_labels_t: expected MTS_LABELS_INT32 or MTS_LABELS_INT64, got {}", other
+            )))
+        }
+    };
 
     let mut names = Vec::new();
     for i in 0..labels.size {
@@ -128,14 +178,25 @@ unsafe fn create_rust_labels(labels: &mts_labels_t) -> Result<Arc<Labels>, Error
         names.push(name);
     }
 
-    let mut builder = LabelsBuilder::new(names)?;
+    let mut builder = LabelsBuilder::new_with_dtype(names, dtype)?;
     builder.reserve(labels.count);
 
     if labels.count != 0 && labels.size != 0 {
-        assert!(!labels.values.is_null());
-        let slice = std::slice::from_raw_parts(labels.values.cast::<LabelValue>(), labels.count * labels.size);
-        for chunk in slice.chunks_exact(labels.size) {
-            builder.add(chunk)?;
+        match dtype {
+            LabelsDType::Int32 => {
+                assert!(!labels.values.is_null());
+                let slice = std::slice::from_raw_parts(labels.values, labels.count * labels.size);
+                for chunk in slice.chunks_exact(labels.size) {
+                    builder.add(chunk)?;
+                }
+            }
+            LabelsDType::Int64 => {
+                assert!(!labels.values_i64.is_null());
+                let slice = std::slice::from_raw_parts(labels.values_i64, labels.count * labels.size);
+                for chunk in slice.chunks_exact(labels.size) {
+                    builder.add(chunk)?;
+                }
+            }
         }
     }
 
@@ -182,8 +243,61 @@ pub unsafe extern fn mts_labels_position(
         }
 
         assert!(values_count != 0);
-        let label = std::slice::from_raw_parts(values.cast(), values_count);
-        *result = labels.position(label).map_or(-1, |p| p as i64);
+        let label = std::slice::from_raw_parts(values, values_count)
+            .iter()
+            .map(|&v| LabelValue::from(v))
+            .collect::<Vec<_>>();
+        *result = labels.position(&label).map_or(-1, |p| p as i64);
+
+        Ok(())
+    })
+}
+
+/// Get the position of the entry defined by the 64-bit `values` array in the
+/// given set of `labels`. This is the same as `mts_labels_position`, but
+/// allows looking up values which do not fit in 32-bit integers in labels
+/// using `MTS_LABELS_INT64`.
+///
+/// @param labels set of labels with an associated Rust data structure
+/// @param values array containing the label to lookup
+/// @param values_count size of the values array
+/// @param result position of the values in the labels or -1 if the values
+///               were not found
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+#[allow(clippy::cast_possible_wrap)]
+pub unsafe extern fn mts_labels_position_i64(
+    labels: mts_labels_t,
+    values: *const i64,
+    values_count: usize,
+    result: *mut i64
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(values, result);
+        if !labels.is_rust() {
+            return Err(Error::InvalidParameter(
+                "these labels do not support calling mts_labels_position_i64, \
+                call mts_labels_create first".into()
+            ));
+        }
+
+        let labels = &(*labels.internal_ptr_.cast::<Labels>());
+        if values_count != labels.size() {
+            return Err(Error::InvalidParameter(format!(
+                "expected label of size {} in mts_labels_position_i64, got size {}",
+                (*labels).size(), values_count
+            )));
+        }
+
+        assert!(values_count != 0);
+        let label = std::slice::from_raw_parts(values, values_count)
+            .iter()
+            .map(|&v| LabelValue::from(v))
+            .collect::<Vec<_>>();
+        *result = labels.position(&label).map_or(-1, |p| p as i64);
 
         Ok(())
     })
@@ -341,6 +455,42 @@ pub unsafe extern fn mts_labels_clone(
     })
 }
 
+/// Get a 64-bit fingerprint of the content (names and values) of `labels`.
+///
+/// Labels that are equal always have the same fingerprint, and labels with
+/// different fingerprints are always different. This can be used for fast
+/// comparison of labels. The fingerprint is computed the first time this
+/// function is called and then cached, and it is not guaranteed to be stable
+/// across different versions of metatensor.
+///
+/// @param labels set of labels with an associated Rust data structure
+/// @param fingerprint pointer to a `uint64_t`, that will be set to the
+///                    fingerprint of the labels
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_labels_fingerprint(
+    labels: mts_labels_t,
+    fingerprint: *mut u64,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(fingerprint);
+
+        if !labels.is_rust() {
+            return Err(Error::InvalidParameter(
+                "these labels do not support calling mts_labels_fingerprint, \
+                call mts_labels_create first".into()
+            ));
+        }
+
+        let rust_labels = &*labels.internal_ptr_.cast::<Labels>();
+        *fingerprint = rust_labels.fingerprint();
+
+        Ok(())
+    })
+}
+
 /// common checks and transformations for the set operations
 unsafe fn labels_set_common<'a>(
     operation: &str,
@@ -6,7 +6,7 @@ use once_cell::sync::Lazy;
 
 #[macro_use]
 mod status;
-pub use self::status::{catch_unwind, mts_status_t};
+pub use self::status::{catch_unwind, mts_status_t, Nullable};
 
 #[cfg(test)]
 pub use self::status::MTS_SUCCESS;
@@ -15,6 +15,7 @@ mod labels;
 mod data;
 mod blocks;
 mod tensor;
+mod builder;
 mod io;
 
 mod utils;
@@ -79,12 +79,36 @@ pub fn catch_unwind<F>(function: F) -> mts_status_t where F: FnOnce() -> Result<
     }
 }
 
+/// Values which can be NULL when given to C API functions: raw pointers and
+/// function pointers (represented as `Option<fn>`).
+pub trait Nullable {
+    fn is_null(&self) -> bool;
+}
+
+impl<T: ?Sized> Nullable for *const T {
+    fn is_null(&self) -> bool {
+        return <*const T>::is_null(*self);
+    }
+}
+
+impl<T: ?Sized> Nullable for *mut T {
+    fn is_null(&self) -> bool {
+        return <*mut T>::is_null(*self);
+    }
+}
+
+impl<T> Nullable for Option<T> {
+    fn is_null(&self) -> bool {
+        return self.is_none();
+    }
+}
+
 /// Check that pointers (used as C API function parameters) are not null.
 #[macro_export]
 #[doc(hidden)]
 macro_rules! check_pointers_non_null {
     ($pointer: ident) => {
-        if $pointer.is_null() {
+        if $crate::c_api::Nullable::is_null(&$pointer) {
             return Err($crate::Error::InvalidParameter(
                 format!(
                     "got invalid NULL pointer for {} at {}:{}",
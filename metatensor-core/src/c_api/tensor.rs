@@ -3,7 +3,7 @@ use std::sync::Arc;
 use std::ffi::CStr;
 use std::collections::BTreeSet;
 
-use crate::{TensorMap, TensorBlock, Error};
+use crate::{TensorMap, TensorBlock, LabelValue, Error, mts_data_origin_t};
 
 use super::labels::{mts_labels_t, rust_to_mts_labels, mts_labels_to_rust};
 use super::blocks::mts_block_t;
@@ -163,6 +163,95 @@ pub unsafe extern fn mts_tensormap_copy(
     return result;
 }
 
+/// Make a copy of an `mts_tensormap_t`, with the values and gradients of all the
+/// arrays converted to the given data `origin` using the converters registered
+/// with `mts_register_array_converter`.
+///
+/// The memory allocated by this function should be released using
+/// `mts_tensormap_free`.
+///
+/// @param tensor existing tensor map to convert
+/// @param origin pre-registered data origin to convert to
+///
+/// @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_to_origin(
+    tensor: *const mts_tensormap_t,
+    origin: mts_data_origin_t,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(tensor);
+        let new_tensor = (*tensor).to_origin(origin)?;
+        let boxed = Box::new(mts_tensormap_t(new_tensor));
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = Box::into_raw(boxed);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+
+/// Move all the blocks out of this `tensor` map, and release the memory
+/// associated with the tensor map itself.
+///
+/// `blocks_count` must be set to the number of blocks in the tensor map (i.e.
+/// the number of entries in its keys), and `blocks` must have space for this
+/// many pointers. On success, each entry in `blocks` is set to a block which
+/// should be released using `mts_block_free`, or moved into a tensor map; and
+/// `tensor` is no longer valid. The data of the blocks is not copied. On
+/// error, `tensor` is left unchanged.
+///
+/// Use `mts_tensormap_keys` before calling this function to keep the keys
+/// associated with the blocks.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param blocks array to be filled with pointers to the blocks
+/// @param blocks_count number of entries in `blocks`
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_into_blocks(
+    tensor: *mut mts_tensormap_t,
+    blocks: *mut *mut mts_block_t,
+    blocks_count: usize,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor);
+
+        if blocks_count != (*tensor).blocks().len() {
+            return Err(Error::InvalidParameter(format!(
+                "expected space for {} blocks in mts_tensormap_into_blocks, got space for {}",
+                (*tensor).blocks().len(), blocks_count
+            )));
+        }
+
+        if blocks_count != 0 {
+            check_pointers_non_null!(blocks);
+        }
+
+        let (_, rust_blocks) = mts_tensormap_t::from_boxed_raw(tensor).into_parts();
+        for (i, block) in rust_blocks.into_iter().enumerate() {
+            *blocks.add(i) = mts_block_t::into_boxed_raw(block);
+        }
+
+        Ok(())
+    })
+}
+
 
 /// Get the keys for the given `tensor` map.
 ///
@@ -199,8 +288,9 @@ pub unsafe extern fn mts_tensormap_keys(
 ///
 /// The block memory is still managed by the tensor map, this block should not
 /// be freed. The block is invalidated when the tensor map is freed with
-/// `mts_tensormap_free` or the set of keys is modified by calling one
-/// of the `mts_tensormap_keys_to_XXX` function.
+/// `mts_tensormap_free` or when the blocks are modified with
+/// `mts_tensormap_insert_block`, `mts_tensormap_remove_block`,
+/// `mts_tensormap_replace_block` or `mts_tensormap_drop_blocks`.
 ///
 /// @param tensor pointer to an existing tensor map
 /// @param block pointer to be filled with a block
@@ -236,6 +326,247 @@ pub unsafe extern fn mts_tensormap_block_by_id(
 }
 
 
+/// Add a new `block` to this `tensor` map, associated with the given `key`.
+///
+/// The block is added after all the existing blocks. The `key` must not be
+/// already present in the keys of the tensor map, and the block must contain
+/// the same kind of data as the existing blocks (same labels names, same
+/// gradients, same data origin, device and dtype), as checked by
+/// `mts_tensormap`.
+///
+/// The tensor map takes ownership of the block, which should not be released
+/// separately, even if this function fails.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param key values of the new key, as 64-bit integers
+/// @param key_count number of values in `key`, this should match the number
+///                  of dimensions of the keys
+/// @param block block to add to the tensor map
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_insert_block(
+    tensor: *mut mts_tensormap_t,
+    key: *const i64,
+    key_count: usize,
+    block: *mut mts_block_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block);
+
+        // move the block out of the pointer
+        let block = Box::from_raw(block).into_block();
+
+        check_pointers_non_null!(tensor);
+        let key = key_to_rust(key, key_count)?;
+        (*tensor).insert_block(&key, block)?;
+
+        Ok(())
+    })
+}
+
+
+/// Remove the block associated with the given `key` from this `tensor` map,
+/// and return it.
+///
+/// The memory allocated for the returned block should be released using
+/// `mts_block_free`, or moved into a tensor map.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param key values of the key to remove, as 64-bit integers
+/// @param key_count number of values in `key`, this should match the number
+///                  of dimensions of the keys
+///
+/// @returns A pointer to the removed block, or a `NULL` pointer in case of
+///          error. In case of error, you can use `mts_last_error()` to get
+///          the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_remove_block(
+    tensor: *mut mts_tensormap_t,
+    key: *const i64,
+    key_count: usize,
+) -> *mut mts_block_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(tensor);
+        let key = key_to_rust(key, key_count)?;
+        let block = (*tensor).remove_block(&key)?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = mts_block_t::into_boxed_raw(block);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+
+/// Replace the `index`-th block in this `tensor` map with `block`, and return
+/// the previous block.
+///
+/// The new block must contain the same kind of data as the other blocks in
+/// the tensor map, as checked by `mts_tensormap`. The tensor map takes
+/// ownership of the new block, which should not be released separately, even
+/// if this function fails. The memory allocated for the returned block should
+/// be released using `mts_block_free`, or moved into a tensor map.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param index index of the block to replace
+/// @param block new block to store in the tensor map
+///
+/// @returns A pointer to the previous block, or a `NULL` pointer in case of
+///          error. In case of error, you can use `mts_last_error()` to get
+///          the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_replace_block(
+    tensor: *mut mts_tensormap_t,
+    index: usize,
+    block: *mut mts_block_t,
+) -> *mut mts_block_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(block);
+
+        // move the block out of the pointer
+        let block = Box::from_raw(block).into_block();
+
+        check_pointers_non_null!(tensor);
+        let previous = (*tensor).replace_block(index, block)?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = mts_block_t::into_boxed_raw(previous);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+
+/// Remove all the blocks in this `tensor` map with keys matching one of the
+/// entries in `selection`, and release the corresponding memory.
+///
+/// The `selection` should have a subset of the names/dimensions of the keys
+/// for this tensor map, and can contain multiple entries. Entries in the
+/// `selection` without a corresponding block are ignored.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param selection labels describing which blocks should be removed
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_drop_blocks(
+    tensor: *mut mts_tensormap_t,
+    selection: mts_labels_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor);
+
+        let selection = mts_labels_to_rust(&selection)?;
+        (*tensor).drop_blocks(&selection)?;
+
+        Ok(())
+    })
+}
+
+/// Remove the gradients with respect to all the given `parameters` from all
+/// the blocks in this `tensor` map, and release the corresponding memory.
+///
+/// `parameters` must be an array of `parameters_count` NULL-terminated
+/// strings, encoded as UTF-8. Parameters without corresponding gradients in
+/// the blocks are ignored.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param parameters names of the gradients to remove
+/// @param parameters_count number of entries in the `parameters` array
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_remove_gradients(
+    tensor: *mut mts_tensormap_t,
+    parameters: *const *const c_char,
+    parameters_count: usize,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor);
+
+        let mut rust_parameters = Vec::new();
+        if parameters_count != 0 {
+            check_pointers_non_null!(parameters);
+            for &parameter in std::slice::from_raw_parts(parameters, parameters_count) {
+                check_pointers_non_null!(parameter);
+                let parameter = CStr::from_ptr(parameter).to_str().expect("invalid utf8");
+                rust_parameters.push(parameter);
+            }
+        }
+
+        (*tensor).remove_gradients(&rust_parameters);
+
+        Ok(())
+    })
+}
+
+/// Use a single set of `Labels` for all identical gradient samples in this
+/// `tensor` map, across all blocks and gradient parameters (including
+/// gradients of gradients).
+///
+/// This reduces the memory used by the gradient samples, and samples used by
+/// more than one gradient are then stored only once when saving this tensor
+/// map.
+///
+/// @param tensor pointer to an existing tensor map
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_share_gradient_samples(
+    tensor: *mut mts_tensormap_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor);
+        (*tensor).share_gradient_samples();
+
+        Ok(())
+    })
+}
+
+/// Convert a key given as `key_count` 64-bit integers to a vector of
+/// `LabelValue`
+pub(super) unsafe fn key_to_rust(key: *const i64, key_count: usize) -> Result<Vec<LabelValue>, Error> {
+    if key_count == 0 {
+        return Ok(Vec::new());
+    }
+
+    check_pointers_non_null!(key);
+    let key = std::slice::from_raw_parts(key, key_count)
+        .iter()
+        .map(|&v| LabelValue::from(v))
+        .collect();
+
+    return Ok(key);
+}
+
+
 /// Get indices of the blocks in this `tensor` corresponding to the given
 /// `selection`. The `selection` should have a subset of the names/dimensions of
 /// the keys for this tensor map, and only one entry, describing the requested
@@ -291,6 +622,149 @@ pub unsafe extern fn mts_tensormap_blocks_matching(
 }
 
 
+/// Get indices of the blocks in this `tensor` where the keys dimensions with
+/// index `dimensions[i]` take the value `values[i]`, for all `i` in
+/// `0..values_count`. This is equivalent to `mts_tensormap_blocks_matching`
+/// with a selection containing a single entry, without having to create the
+/// corresponding `mts_labels_t`.
+///
+/// When calling this function, `*count` should contain the number of entries in
+/// `block_indexes`. When the function returns successfully, `*count` will
+/// contain the number of blocks matching the selection, i.e. how many values
+/// were written to `block_indexes`.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param dimensions index of the keys dimensions used in the selection
+/// @param values values taken by the corresponding keys dimensions, as 64-bit
+///               integers
+/// @param values_count number of entries in both `dimensions` and `values`
+/// @param block_indexes array to be filled with indexes of blocks in the tensor
+///                      map matching the selection
+/// @param count number of entries in `block_indexes`
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_blocks_matching_values(
+    tensor: *const mts_tensormap_t,
+    dimensions: *const usize,
+    values: *const i64,
+    values_count: usize,
+    block_indexes: *mut usize,
+    count: *mut usize,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor, count);
+
+        if *count != (*tensor).keys().count() {
+            return Err(Error::InvalidParameter(format!(
+                "expected space for {} indices as input to mts_tensormap_blocks_matching_values, got space for {}",
+                (*tensor).keys().count(), *count
+            )));
+        }
+
+        let (dimensions, values) = if values_count == 0 {
+            (&[][..], &[][..])
+        } else {
+            check_pointers_non_null!(dimensions, values);
+            (
+                std::slice::from_raw_parts(dimensions, values_count),
+                std::slice::from_raw_parts(values, values_count),
+            )
+        };
+
+        let rust_blocks = (*tensor).blocks_matching_values(dimensions, values)?;
+        *count = rust_blocks.len();
+
+        if rust_blocks.is_empty() {
+            return Ok(());
+        }
+
+        check_pointers_non_null!(block_indexes);
+        let block_indexes = std::slice::from_raw_parts_mut(block_indexes, rust_blocks.len());
+        block_indexes.copy_from_slice(&rust_blocks);
+
+        Ok(())
+    })
+}
+
+
+/// Get indices of the blocks in this `tensor` corresponding to each one of the
+/// entries in `selection`. The `selection` should have a subset of the
+/// names/dimensions of the keys for this tensor map.
+///
+/// The results are written in compressed form: the blocks matching the `i`-th
+/// entry in `selection` are `block_indexes[offsets[i]]`, ...,
+/// `block_indexes[offsets[i + 1] - 1]`. Since each block matches at most one
+/// entry in the selection, there are at most as many matching blocks as there
+/// are keys in the tensor map.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param selection labels describing which blocks are requested
+/// @param block_indexes array to be filled with indexes of blocks in the tensor
+///                      map matching the `selection`
+/// @param block_indexes_count number of entries in `block_indexes`, this must
+///                            be the number of keys in `tensor`
+/// @param offsets array to be filled with the position in `block_indexes` of
+///                the blocks matching each entry in `selection`
+/// @param offsets_count number of entries in `offsets`, this must be the
+///                      number of entries in `selection` plus one
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_blocks_matching_many(
+    tensor: *const mts_tensormap_t,
+    selection: mts_labels_t,
+    block_indexes: *mut usize,
+    block_indexes_count: usize,
+    offsets: *mut usize,
+    offsets_count: usize,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor, offsets);
+
+        if block_indexes_count != (*tensor).keys().count() {
+            return Err(Error::InvalidParameter(format!(
+                "expected space for {} indices as input to mts_tensormap_blocks_matching_many, got space for {}",
+                (*tensor).keys().count(), block_indexes_count
+            )));
+        }
+
+        let selection = mts_labels_to_rust(&selection)?;
+        if offsets_count != selection.count() + 1 {
+            return Err(Error::InvalidParameter(format!(
+                "expected space for {} offsets as input to mts_tensormap_blocks_matching_many, got space for {}",
+                selection.count() + 1, offsets_count
+            )));
+        }
+
+        let rust_blocks = (*tensor).blocks_matching_many(&selection)?;
+
+        let offsets = std::slice::from_raw_parts_mut(offsets, offsets_count);
+        let block_indexes = if block_indexes_count == 0 {
+            &mut []
+        } else {
+            check_pointers_non_null!(block_indexes);
+            std::slice::from_raw_parts_mut(block_indexes, block_indexes_count)
+        };
+
+        let mut position = 0;
+        offsets[0] = 0;
+        for (i, matching) in rust_blocks.iter().enumerate() {
+            for &block in matching {
+                block_indexes[position] = block;
+                position += 1;
+            }
+            offsets[i + 1] = position;
+        }
+
+        Ok(())
+    })
+}
+
 /// Merge blocks with the same value for selected keys dimensions along the
 /// property axis.
 ///
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::ops::Range;
 use std::os::raw::c_void;
 use std::sync::Mutex;
@@ -58,7 +59,8 @@ unsafe impl Send for mts_array_t {}
 /// map. The array itself if opaque to this library and can come from multiple
 /// sources: Rust program, a C/C++ program, a Fortran program, Python with numpy
 /// or torch. The data does not have to live on CPU, or even on the same machine
-/// where this code is executed.
+/// where this code is executed. The device and data type of the array can be
+/// given to metatensor with `mts_array_operations_t.descriptor`.
 ///
 /// This struct contains a C-compatible manual implementation of a virtual table
 /// (vtable, i.e. trait in Rust, pure virtual class in C++); allowing
@@ -176,6 +178,362 @@ pub struct mts_sample_mapping_t {
     pub output: usize,
 }
 
+/// Representation of a single index moved from an array to another one along
+/// a given axis
+#[derive(Debug, Clone)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct mts_index_mapping_t {
+    /// index along the axis in the input array
+    pub input: usize,
+    /// index along the axis in the output array
+    pub output: usize,
+}
+
+/// Description of the device and data type of an array, using the same
+/// conventions as `DLDevice` and `DLDataType` in `DLPack`.
+///
+/// A `device_type` of 0 indicates that the device and data type of the array
+/// are unknown.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct mts_array_descriptor_t {
+    /// Type of the device where the data lives, following `DLDeviceType` (1
+    /// for CPU, 2 for CUDA, 8 for Metal, ...)
+    pub device_type: i32,
+    /// Index of the device where the data lives, for systems with multiple
+    /// devices of the same type
+    pub device_id: i32,
+    /// Type code of the data, following `DLDataTypeCode` (0 for signed
+    /// integers, 1 for unsigned integers, 2 for floating point, ...)
+    pub dtype_code: u8,
+    /// Number of bits used to store a single value
+    pub dtype_bits: u8,
+    /// Number of lanes in the data type, used for vector types. This should be
+    /// 1 for scalar data types.
+    pub dtype_lanes: u16,
+}
+
+impl std::fmt::Display for mts_array_descriptor_t {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let device = match self.device_type {
+            1 => Some("cpu"),
+            2 => Some("cuda"),
+            3 => Some("cuda_host"),
+            4 => Some("opencl"),
+            7 => Some("vulkan"),
+            8 => Some("metal"),
+            10 => Some("rocm"),
+            14 => Some("oneapi"),
+            15 => Some("webgpu"),
+            _ => None,
+        };
+
+        if let Some(device) = device {
+            write!(f, "{}:{}", device, self.device_id)?;
+        } else {
+            write!(f, "device({}):{}", self.device_type, self.device_id)?;
+        }
+
+        let dtype = match self.dtype_code {
+            0 => Some("int"),
+            1 => Some("uint"),
+            2 => Some("float"),
+            4 => Some("bfloat"),
+            5 => Some("complex"),
+            6 => Some("bool"),
+            _ => None,
+        };
+
+        if let Some(dtype) = dtype {
+            write!(f, " {}{}", dtype, self.dtype_bits)?;
+        } else {
+            write!(f, " dtype({}):{}", self.dtype_code, self.dtype_bits)?;
+        }
+
+        if self.dtype_lanes > 1 {
+            write!(f, "x{}", self.dtype_lanes)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Version of `mts_array_operations_t` defined by this version of metatensor
+pub const MTS_ARRAY_OPERATIONS_VERSION: u64 = 2;
+
+/// Optional operations on arrays, registered for all the arrays with a given
+/// data origin with `mts_register_array_operations`.
+///
+/// These allow metatensor to manipulate arrays without accessing their data
+/// directly. For all functions, `array` and `other` are the `ptr` of
+/// `mts_array_t` with the same data origin, and the shape of the arrays are
+/// checked by metatensor before calling the functions. Any of the functions can
+/// be set to `NULL`, in which case metatensor will use `mts_array_t.data`
+/// instead.
+///
+/// New functions might be added at the end of this struct in future versions
+/// of metatensor, together with an increase of `MTS_ARRAY_OPERATIONS_VERSION`.
+/// Metatensor will only use the functions available in the `version` of the
+/// struct that was registered.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy)]
+pub struct mts_array_operations_t {
+    /// Version of this struct, this should be set to
+    /// `MTS_ARRAY_OPERATIONS_VERSION`.
+    pub version: u64,
+
+    /// Add the values in the `other` array to the values in `array`,
+    /// element-wise. Both arrays have the same shape.
+    pub add: Option<unsafe extern fn(
+        array: *mut c_void,
+        other: *const c_void,
+    ) -> mts_status_t>,
+
+    /// Multiply the values in `array` by the values in the `other` array,
+    /// element-wise. Both arrays have the same shape.
+    pub mul: Option<unsafe extern fn(
+        array: *mut c_void,
+        other: *const c_void,
+    ) -> mts_status_t>,
+
+    /// Multiply all the values in `array` by `factor`.
+    pub scale: Option<unsafe extern fn(
+        array: *mut c_void,
+        factor: f64,
+    ) -> mts_status_t>,
+
+    /// Compute the matrix product of `array` and `other`, contracting the last
+    /// axis of `array` with the first axis of `other`, and store the result in
+    /// a new array in `output`. `other` is always a 2-dimensional array, and
+    /// `output` should have the same shape as `array` except for the last axis,
+    /// which has the size of the second axis of `other`.
+    pub matmul: Option<unsafe extern fn(
+        array: *const c_void,
+        other: *const c_void,
+        output: *mut mts_array_t,
+    ) -> mts_status_t>,
+
+    /// Sum the values in `array` along the given `axis`, and store the result
+    /// in a new array in `output`. `output` should have the same shape as
+    /// `array`, except for `axis` which should have a size of 1.
+    pub sum_axis: Option<unsafe extern fn(
+        array: *const c_void,
+        axis: usize,
+        output: *mut mts_array_t,
+    ) -> mts_status_t>,
+
+    /// Set all the values in `array` to the values in the `other` array. Both
+    /// arrays have the same shape.
+    pub copy_from: Option<unsafe extern fn(
+        array: *mut c_void,
+        other: *const c_void,
+    ) -> mts_status_t>,
+
+    /// Get the device and data type of `array` in `descriptor`. This is used
+    /// to check that all the arrays in a block or a tensor map live on the
+    /// same device and use the same data type.
+    ///
+    /// Added in version 2 of this struct.
+    pub descriptor: Option<unsafe extern fn(
+        array: *const c_void,
+        descriptor: *mut mts_array_descriptor_t,
+    ) -> mts_status_t>,
+
+    /// Get a pointer to the underlying data storage of `array` in `data`. The
+    /// data should be stored as a C-contiguous array, on the device and with
+    /// the data type given by `descriptor`.
+    ///
+    /// This is used to share the data with other libraries through `DLPack`,
+    /// and requires `descriptor` to be set as well.
+    ///
+    /// Added in version 2 of this struct.
+    pub raw_data: Option<unsafe extern fn(
+        array: *mut c_void,
+        data: *mut *mut c_void,
+    ) -> mts_status_t>,
+
+    /// Copy values from the `input` array to `array` along `axis`. For each
+    /// entry in `mapping` (containing `mapping_count` entries), this should
+    /// copy `input[..., mapping.input, ...]` to `array[..., mapping.output,
+    /// ...]`, where the index is on `axis`. On all the other axes `i`, the
+    /// whole input is copied to `output_offsets[i]..output_offsets[i] +
+    /// input_shape[i]`. `output_offsets` contains one entry for each
+    /// dimension, and the entry for `axis` should be ignored.
+    ///
+    /// The arrays have the same number of dimensions, and all indexes are
+    /// checked to be in bounds by metatensor before calling this function.
+    ///
+    /// Added in version 2 of this struct.
+    pub move_along_axis: Option<unsafe extern fn(
+        array: *mut c_void,
+        input: *const c_void,
+        axis: usize,
+        mapping: *const mts_index_mapping_t,
+        mapping_count: usize,
+        output_offsets: *const usize,
+    ) -> mts_status_t>,
+
+    /// Get the data of `array` in compressed sparse row (CSR) format, if the
+    /// array stores its data in this format. The rows correspond to the first
+    /// dimension of the array, and the columns to all the other dimensions
+    /// flattened together.
+    ///
+    /// `values` and `indices` should be set to arrays with `nnz` entries,
+    /// containing the non-zero values and the corresponding columns, and
+    /// `indptr` to an array with `shape[0] + 1` entries, containing the start
+    /// of each row in `values`. The columns in each row must be sorted and
+    /// unique. The pointers must stay valid until `array` is modified. For
+    /// arrays storing dense data, `values` should be set to `NULL`.
+    ///
+    /// Added in version 2 of this struct.
+    pub sparse_csr: Option<unsafe extern fn(
+        array: *const c_void,
+        values: *mut *const f64,
+        indices: *mut *const usize,
+        indptr: *mut *const usize,
+        nnz: *mut usize,
+    ) -> mts_status_t>,
+
+    /// Create a new array with the same origin as `array` and the given
+    /// `shape`, storing data in compressed sparse row (CSR) format, and store
+    /// it in `output`. The data uses the same conventions as `sparse_csr`,
+    /// and should be copied in the new array.
+    ///
+    /// This is used when loading sparse data from files.
+    ///
+    /// Added in version 2 of this struct.
+    pub create_sparse_csr: Option<unsafe extern fn(
+        array: *const c_void,
+        shape: *const usize,
+        shape_count: usize,
+        values: *const f64,
+        indices: *const usize,
+        indptr: *const usize,
+        nnz: usize,
+        output: *mut mts_array_t,
+    ) -> mts_status_t>,
+}
+
+static REGISTERED_ARRAY_OPERATIONS: Lazy<Mutex<HashMap<u64, mts_array_operations_t>>> = Lazy::new(|| {
+    Mutex::new(HashMap::new())
+});
+
+/// Register the given `operations` for all arrays with the given data
+/// `origin`, replacing any previously registered operations.
+///
+/// Only the fields available in the `version` of `operations` are read, the
+/// other functions are set to `None`.
+pub unsafe fn register_array_operations(
+    origin: mts_data_origin_t,
+    operations: *const mts_array_operations_t,
+) -> Result<(), Error> {
+    let version = std::ptr::addr_of!((*operations).version).read();
+    if version == 0 || version > MTS_ARRAY_OPERATIONS_VERSION {
+        return Err(Error::InvalidParameter(format!(
+            "unsupported version of mts_array_operations_t: got {}, expected at most {}",
+            version, MTS_ARRAY_OPERATIONS_VERSION
+        )));
+    }
+
+    let registered_origins = REGISTERED_DATA_ORIGIN.lock().expect("mutex got poisoned");
+    if origin.0 == 0 || origin.0 >= registered_origins.len() as u64 {
+        return Err(Error::InvalidParameter(format!(
+            "data origin {} is not registered", origin.0
+        )));
+    }
+
+    // fields available in version 1
+    let mut operations_copy = mts_array_operations_t {
+        version,
+        add: std::ptr::addr_of!((*operations).add).read(),
+        mul: std::ptr::addr_of!((*operations).mul).read(),
+        scale: std::ptr::addr_of!((*operations).scale).read(),
+        matmul: std::ptr::addr_of!((*operations).matmul).read(),
+        sum_axis: std::ptr::addr_of!((*operations).sum_axis).read(),
+        copy_from: std::ptr::addr_of!((*operations).copy_from).read(),
+        descriptor: None,
+        raw_data: None,
+        move_along_axis: None,
+        sparse_csr: None,
+        create_sparse_csr: None,
+    };
+
+    // fields added in version 2
+    if version >= 2 {
+        operations_copy.descriptor = std::ptr::addr_of!((*operations).descriptor).read();
+        operations_copy.raw_data = std::ptr::addr_of!((*operations).raw_data).read();
+        operations_copy.move_along_axis = std::ptr::addr_of!((*operations).move_along_axis).read();
+        operations_copy.sparse_csr = std::ptr::addr_of!((*operations).sparse_csr).read();
+        operations_copy.create_sparse_csr = std::ptr::addr_of!((*operations).create_sparse_csr).read();
+    }
+
+    let mut registered = REGISTERED_ARRAY_OPERATIONS.lock().expect("mutex got poisoned");
+    registered.insert(origin.0, operations_copy);
+
+    return Ok(());
+}
+
+/// Function pointer to convert an array to a different data origin.
+///
+/// This function gets an `array` with the origin this converter was
+/// registered for, and should fill `output` with a new `mts_array_t` with the
+/// target data origin, the same shape and a copy of the same values; or
+/// return non-zero `mts_status_t`.
+#[allow(non_camel_case_types)]
+pub type mts_array_converter_t = Option<unsafe extern fn(
+    array: *const mts_array_t,
+    output: *mut mts_array_t,
+) -> mts_status_t>;
+
+/// Non-NULL version of `mts_array_converter_t`
+pub(crate) type ArrayConverter = unsafe extern fn(
+    array: *const mts_array_t,
+    output: *mut mts_array_t,
+) -> mts_status_t;
+
+static REGISTERED_ARRAY_CONVERTERS: Lazy<Mutex<HashMap<(u64, u64), ArrayConverter>>> = Lazy::new(|| {
+    Mutex::new(HashMap::new())
+});
+
+/// Register the given `converter` to convert arrays with the data origin
+/// `from` to arrays with the data origin `to`, replacing any previously
+/// registered converter for the same pair of origins.
+#[allow(clippy::cast_possible_truncation)]
+pub fn register_array_converter(
+    from: mts_data_origin_t,
+    to: mts_data_origin_t,
+    converter: ArrayConverter,
+) -> Result<(), Error> {
+    let registered_origins = REGISTERED_DATA_ORIGIN.lock().expect("mutex got poisoned");
+    for origin in [from, to] {
+        if origin.0 == 0 || origin.0 >= registered_origins.len() as u64 {
+            return Err(Error::InvalidParameter(format!(
+                "data origin {} is not registered", origin.0
+            )));
+        }
+    }
+
+    if from == to {
+        return Err(Error::InvalidParameter(format!(
+            "can not register a converter from '{}' to itself", registered_origins[from.0 as usize]
+        )));
+    }
+
+    let mut registered = REGISTERED_ARRAY_CONVERTERS.lock().expect("mutex got poisoned");
+    registered.insert((from.0, to.0), converter);
+
+    return Ok(());
+}
+
+/// Get the converter registered from the data origin `from` to `to`, if any
+fn array_converter(from: mts_data_origin_t, to: mts_data_origin_t) -> Option<ArrayConverter> {
+    let registered = REGISTERED_ARRAY_CONVERTERS.lock().expect("mutex got poisoned");
+    return registered.get(&(from.0, to.0)).copied();
+}
+
 impl std::fmt::Debug for mts_array_t {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut origin = None;
@@ -486,6 +844,625 @@ impl mts_array_t {
     }
 }
 
+
+/// Check that `array` and `other` have the same shape before applying the
+/// element-wise `operation` to them
+fn check_same_shape(array: &mts_array_t, other: &mts_array_t, operation: &str) -> Result<(), Error> {
+    let shape = array.shape()?;
+    let other_shape = other.shape()?;
+    if shape != other_shape {
+        return Err(Error::InvalidParameter(format!(
+            "can not {} arrays with different shapes: {:?} and {:?}",
+            operation, shape, other_shape
+        )));
+    }
+    return Ok(());
+}
+
+/// Check the status returned by one of the functions in `mts_array_operations_t`
+fn check_operation_status(status: mts_status_t, function: &str) -> Result<(), Error> {
+    if !status.is_success() {
+        return Err(Error::External {
+            status, context: format!("calling mts_array_operations_t.{} failed", function)
+        });
+    }
+    return Ok(());
+}
+
+impl mts_array_t {
+    /// Get the function selected by `get` in the operations registered for
+    /// the origin of this array. If `other` is given, the function is only
+    /// returned if `other` has the same origin as this array.
+    pub(crate) fn operation<F>(
+        &self,
+        other: Option<&mts_array_t>,
+        get: impl FnOnce(&mts_array_operations_t) -> Option<F>,
+    ) -> Result<Option<F>, Error> {
+        let origin = self.origin()?;
+        if let Some(other) = other {
+            if other.origin()? != origin {
+                return Ok(None);
+            }
+        }
+
+        let registered = REGISTERED_ARRAY_OPERATIONS.lock().expect("mutex got poisoned");
+        return Ok(registered.get(&origin.0).and_then(get));
+    }
+
+    /// Convert this array to the given data `origin`, using the converter
+    /// registered with `register_array_converter`. If the array already has
+    /// this origin, this returns a copy of the array.
+    pub fn convert_to(&self, origin: mts_data_origin_t) -> Result<mts_array_t, Error> {
+        let current = self.origin()?;
+        if current == origin {
+            return self.try_clone();
+        }
+
+        let converter = match array_converter(current, origin) {
+            Some(converter) => converter,
+            None => {
+                return Err(Error::InvalidParameter(format!(
+                    "there is no registered converter from '{}' to '{}'",
+                    get_data_origin(current), get_data_origin(origin)
+                )));
+            }
+        };
+
+        let mut output = mts_array_t::null();
+        let status = unsafe { converter(self, &mut output) };
+        if !status.is_success() {
+            return Err(Error::External {
+                status, context: format!(
+                    "failed to convert an array from '{}' to '{}'",
+                    get_data_origin(current), get_data_origin(origin)
+                )
+            });
+        }
+
+        if output.origin.is_none() || output.shape.is_none() {
+            return Err(Error::InvalidParameter(format!(
+                "the converter from '{}' to '{}' did not set the output array",
+                get_data_origin(current), get_data_origin(origin)
+            )));
+        }
+
+        let output_origin = output.origin()?;
+        if output_origin != origin {
+            return Err(Error::InvalidParameter(format!(
+                "the converter from '{}' to '{}' returned an array with origin '{}'",
+                get_data_origin(current), get_data_origin(origin), get_data_origin(output_origin)
+            )));
+        }
+
+        let shape = self.shape()?;
+        let output_shape = output.shape()?;
+        if shape != output_shape {
+            return Err(Error::InvalidParameter(format!(
+                "the converter from '{}' to '{}' returned an array with shape {:?}, expected {:?}",
+                get_data_origin(current), get_data_origin(origin), output_shape, shape
+            )));
+        }
+
+        return Ok(output);
+    }
+
+    /// Convert this array with the converters registered for its origin,
+    /// until one of the converted arrays gives access to its data. This is
+    /// used to save arrays which can not give access to their data directly,
+    /// for example because it is stored on a GPU. This returns `None` if
+    /// none of the converted arrays gives access to its data.
+    pub fn convert_for_data(&self) -> Result<Option<mts_array_t>, Error> {
+        let origin = self.origin()?;
+        let mut targets = {
+            let registered = REGISTERED_ARRAY_CONVERTERS.lock().expect("mutex got poisoned");
+            registered.keys().filter(|(from, _)| *from == origin.0).map(|&(_, to)| to).collect::<Vec<_>>()
+        };
+        // use the converters in the order of registration of the origins
+        targets.sort_unstable();
+
+        for target in targets {
+            let converted = self.convert_to(mts_data_origin_t(target))?;
+            if converted.data().is_ok() {
+                return Ok(Some(converted));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    /// Convert `other` to the origin of this array if they have different
+    /// origins and a converter is registered between them, so that binary
+    /// operations can use the operations registered for this origin. This
+    /// returns `None` if no conversion is needed or possible.
+    fn convert_other(&self, other: &mts_array_t) -> Result<Option<mts_array_t>, Error> {
+        let origin = self.origin()?;
+        let other_origin = other.origin()?;
+        if origin == other_origin || array_converter(other_origin, origin).is_none() {
+            return Ok(None);
+        }
+
+        return other.convert_to(origin).map(Some);
+    }
+
+    /// Get the device and data type of this array, or `None` if they are
+    /// unknown.
+    pub fn descriptor(&self) -> Result<Option<mts_array_descriptor_t>, Error> {
+        if let Some(function) = self.operation(None, |operations| operations.descriptor)? {
+            let mut descriptor = mts_array_descriptor_t {
+                device_type: 0,
+                device_id: 0,
+                dtype_code: 0,
+                dtype_bits: 0,
+                dtype_lanes: 0,
+            };
+            let status = unsafe { function(self.ptr, &mut descriptor) };
+            check_operation_status(status, "descriptor")?;
+
+            if descriptor.device_type != 0 {
+                return Ok(Some(descriptor));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    /// Add the values in `other` to the values in this array, element-wise.
+    pub fn add(&mut self, other: &mts_array_t) -> Result<(), Error> {
+        check_same_shape(self, other, "add")?;
+
+        let converted = self.convert_other(other)?;
+        let other = converted.as_ref().unwrap_or(other);
+
+        if let Some(function) = self.operation(Some(other), |operations| operations.add)? {
+            let status = unsafe { function(self.ptr, other.ptr) };
+            return check_operation_status(status, "add");
+        }
+
+        let other = other.data()?;
+        for (value, other) in self.data_mut()?.iter_mut().zip(other) {
+            *value += other;
+        }
+
+        return Ok(());
+    }
+
+    /// Multiply the values in this array by the values in `other`,
+    /// element-wise.
+    pub fn mul(&mut self, other: &mts_array_t) -> Result<(), Error> {
+        check_same_shape(self, other, "multiply")?;
+
+        let converted = self.convert_other(other)?;
+        let other = converted.as_ref().unwrap_or(other);
+
+        if let Some(function) = self.operation(Some(other), |operations| operations.mul)? {
+            let status = unsafe { function(self.ptr, other.ptr) };
+            return check_operation_status(status, "mul");
+        }
+
+        let other = other.data()?;
+        for (value, other) in self.data_mut()?.iter_mut().zip(other) {
+            *value *= other;
+        }
+
+        return Ok(());
+    }
+
+    /// Multiply all the values in this array by `factor`.
+    pub fn scale(&mut self, factor: f64) -> Result<(), Error> {
+        if let Some(function) = self.operation(None, |operations| operations.scale)? {
+            let status = unsafe { function(self.ptr, factor) };
+            return check_operation_status(status, "scale");
+        }
+
+        for value in self.data_mut()? {
+            *value *= factor;
+        }
+
+        return Ok(());
+    }
+
+    /// Compute the matrix product of this array with the 2-dimensional
+    /// `other` array, contracting the last axis of this array with the first
+    /// axis of `other`.
+    pub fn matmul(&self, other: &mts_array_t) -> Result<mts_array_t, Error> {
+        let shape = self.shape()?;
+        let other_shape = other.shape()?;
+        if other_shape.len() != 2 || shape[shape.len() - 1] != other_shape[0] {
+            return Err(Error::InvalidParameter(format!(
+                "can not multiply arrays with shapes {:?} and {:?}: the second \
+                array must be 2-dimensional, and its first dimension must \
+                match the last dimension of the first array",
+                shape, other_shape
+            )));
+        }
+
+        let mut output_shape = shape.to_vec();
+        *output_shape.last_mut().expect("shape is not empty") = other_shape[1];
+
+        let converted = self.convert_other(other)?;
+        let other = converted.as_ref().unwrap_or(other);
+
+        let output = if let Some(function) = self.operation(Some(other), |operations| operations.matmul)? {
+            let mut output = mts_array_t::null();
+            let status = unsafe { function(self.ptr, other.ptr, &mut output) };
+            check_operation_status(status, "matmul")?;
+            output
+        } else {
+            let mut output = self.create(&output_shape)?;
+            let (size, other_size) = (other_shape[0], other_shape[1]);
+            if size != 0 && other_size != 0 {
+                let other = other.data()?;
+                let rows = self.data()?.chunks_exact(size);
+                for (row, output_row) in rows.zip(output.data_mut()?.chunks_exact_mut(other_size)) {
+                    for (value, other_row) in row.iter().zip(other.chunks_exact(other_size)) {
+                        for (output, other) in output_row.iter_mut().zip(other_row) {
+                            *output += value * other;
+                        }
+                    }
+                }
+            }
+            output
+        };
+
+        check_output_shape(&output, &output_shape, "matmul")?;
+        return Ok(output);
+    }
+
+    /// Sum the values in this array along the given `axis`. The returned
+    /// array has the same shape as this one, except for `axis` which has a
+    /// size of 1.
+    pub fn sum_axis(&self, axis: usize) -> Result<mts_array_t, Error> {
+        let shape = self.shape()?;
+        if axis >= shape.len() {
+            return Err(Error::InvalidParameter(format!(
+                "axis {} is out of bounds for array with {} dimensions",
+                axis, shape.len()
+            )));
+        }
+
+        let mut output_shape = shape.to_vec();
+        output_shape[axis] = 1;
+
+        let output = if let Some(function) = self.operation(None, |operations| operations.sum_axis)? {
+            let mut output = mts_array_t::null();
+            let status = unsafe { function(self.ptr, axis, &mut output) };
+            check_operation_status(status, "sum_axis")?;
+            output
+        } else {
+            let mut output = self.create(&output_shape)?;
+            let size = shape[axis];
+            let inner_size = shape[axis + 1..].iter().product::<usize>();
+            if size != 0 && inner_size != 0 {
+                let outer = self.data()?.chunks_exact(size * inner_size);
+                for (values, output) in outer.zip(output.data_mut()?.chunks_exact_mut(inner_size)) {
+                    for values in values.chunks_exact(inner_size) {
+                        for (output, value) in output.iter_mut().zip(values) {
+                            *output += value;
+                        }
+                    }
+                }
+            }
+            output
+        };
+
+        check_output_shape(&output, &output_shape, "sum_axis")?;
+        return Ok(output);
+    }
+
+    /// Set all the values in this array to the values in `other`.
+    pub fn copy_from(&mut self, other: &mts_array_t) -> Result<(), Error> {
+        check_same_shape(self, other, "copy")?;
+
+        let converted = self.convert_other(other)?;
+        let other = converted.as_ref().unwrap_or(other);
+
+        if let Some(function) = self.operation(Some(other), |operations| operations.copy_from)? {
+            let status = unsafe { function(self.ptr, other.ptr) };
+            return check_operation_status(status, "copy_from");
+        }
+
+        let other = other.data()?;
+        self.data_mut()?.copy_from_slice(other);
+
+        return Ok(());
+    }
+
+    /// Copy values from `input` to this array along `axis`. For each entry in
+    /// `mapping`, `input[..., mapping.input, ...]` is copied to `self[...,
+    /// mapping.output, ...]`, where the index is on `axis`. On all the other
+    /// axes `i`, the whole input is copied to `output_offsets[i]..
+    /// output_offsets[i] + input_shape[i]`. The entry of `output_offsets`
+    /// for `axis` is ignored.
+    pub fn move_along_axis(
+        &mut self,
+        input: &mts_array_t,
+        axis: usize,
+        mapping: &[mts_index_mapping_t],
+        output_offsets: &[usize],
+    ) -> Result<(), Error> {
+        let output_shape = self.shape()?.to_vec();
+        let input_shape = input.shape()?.to_vec();
+        check_move_along_axis(&output_shape, &input_shape, axis, mapping, output_offsets)?;
+
+        let converted = self.convert_other(input)?;
+        let input = converted.as_ref().unwrap_or(input);
+
+        if let Some(function) = self.operation(Some(input), |operations| operations.move_along_axis)? {
+            let status = unsafe {
+                function(
+                    self.ptr,
+                    input.ptr,
+                    axis,
+                    mapping.as_ptr(),
+                    mapping.len(),
+                    output_offsets.as_ptr(),
+                )
+            };
+            return check_operation_status(status, "move_along_axis");
+        }
+
+        if input_shape.iter().enumerate().any(|(i, &size)| i != axis && size == 0) {
+            return Ok(());
+        }
+
+        let input = input.data()?;
+        let output = self.data_mut()?;
+
+        let input_strides = c_strides(&input_shape);
+        let output_strides = c_strides(&output_shape);
+
+        // copy contiguous runs along the last axis, iterating over all the
+        // other axes except `axis`
+        let last = input_shape.len() - 1;
+        let run = if axis == last { 1 } else { input_shape[last] };
+        let mut index = vec![0; last];
+        for entry in mapping {
+            loop {
+                let mut input_start = 0;
+                let mut output_start = 0;
+                for (dim, &i) in index.iter().chain(std::iter::once(&0)).enumerate() {
+                    let (input_i, output_i) = if dim == axis {
+                        (entry.input, entry.output)
+                    } else {
+                        (i, i + output_offsets[dim])
+                    };
+                    input_start += input_i * input_strides[dim];
+                    output_start += output_i * output_strides[dim];
+                }
+
+                output[output_start..output_start + run].copy_from_slice(&input[input_start..input_start + run]);
+
+                // move to the next run, skipping `axis`
+                let mut done = true;
+                for dim in (0..last).rev() {
+                    if dim == axis {
+                        continue;
+                    }
+                    index[dim] += 1;
+                    if index[dim] < input_shape[dim] {
+                        done = false;
+                        break;
+                    }
+                    index[dim] = 0;
+                }
+
+                if done {
+                    break;
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/// Data of an array stored in compressed sparse row (CSR) format, see
+/// `mts_array_operations_t.sparse_csr`
+#[derive(Debug, Clone, Copy)]
+pub struct SparseCsr<'a> {
+    /// non-zero values in the array
+    pub values: &'a [f64],
+    /// column of each non-zero value
+    pub indices: &'a [usize],
+    /// start of each row in `values`, with one more entry than the number of
+    /// rows
+    pub indptr: &'a [usize],
+}
+
+impl SparseCsr<'_> {
+    /// Check that this data is a valid CSR matrix for an array with the given
+    /// `shape`
+    pub fn check(&self, shape: &[usize]) -> Result<(), Error> {
+        let rows = shape.first().copied().unwrap_or(1);
+        let columns = shape.iter().skip(1).product::<usize>();
+
+        if self.indices.len() != self.values.len() {
+            return Err(Error::InvalidParameter(format!(
+                "invalid sparse data: got {} values but {} indices",
+                self.values.len(), self.indices.len()
+            )));
+        }
+
+        if self.indptr.len() != rows + 1 || self.indptr[0] != 0 || self.indptr[rows] != self.values.len() {
+            return Err(Error::InvalidParameter(format!(
+                "invalid sparse data: indptr must contain {} entries, starting at 0 and ending at {}",
+                rows + 1, self.values.len()
+            )));
+        }
+
+        for range in self.indptr.windows(2) {
+            if range[0] > range[1] {
+                return Err(Error::InvalidParameter(
+                    "invalid sparse data: indptr must be sorted".into()
+                ));
+            }
+
+            let row = &self.indices[range[0]..range[1]];
+            if row.windows(2).any(|w| w[0] >= w[1]) || row.last().is_some_and(|&i| i >= columns) {
+                return Err(Error::InvalidParameter(format!(
+                    "invalid sparse data: column indexes must be sorted, \
+                    unique and smaller than {} in each row",
+                    columns
+                )));
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+impl mts_array_t {
+    /// Get the data of this array in compressed sparse row format, or `None`
+    /// if the array is not stored in this format.
+    pub fn sparse_csr(&self) -> Result<Option<SparseCsr<'_>>, Error> {
+        let function = if let Some(function) = self.operation(None, |operations| operations.sparse_csr)? {
+            function
+        } else {
+            return Ok(None);
+        };
+
+        let mut values = std::ptr::null();
+        let mut indices = std::ptr::null();
+        let mut indptr = std::ptr::null();
+        let mut nnz = 0;
+        let status = unsafe { function(self.ptr, &mut values, &mut indices, &mut indptr, &mut nnz) };
+        check_operation_status(status, "sparse_csr")?;
+
+        if values.is_null() {
+            return Ok(None);
+        }
+
+        let shape = self.shape()?;
+        let rows = shape.first().copied().unwrap_or(1);
+        if indices.is_null() || indptr.is_null() {
+            return Err(Error::InvalidParameter(
+                "mts_array_operations_t.sparse_csr returned NULL pointers".into()
+            ));
+        }
+
+        let csr = unsafe {
+            SparseCsr {
+                values: std::slice::from_raw_parts(values, nnz),
+                indices: std::slice::from_raw_parts(indices, nnz),
+                indptr: std::slice::from_raw_parts(indptr, rows + 1),
+            }
+        };
+        csr.check(shape)?;
+
+        return Ok(Some(csr));
+    }
+
+    /// Create a new array with the same origin as this one, containing the
+    /// given sparse data. This returns `None` if the origin of this array does
+    /// not support sparse data.
+    pub fn create_sparse_csr(&self, shape: &[usize], csr: SparseCsr<'_>) -> Result<Option<mts_array_t>, Error> {
+        csr.check(shape)?;
+
+        let function = if let Some(function) = self.operation(None, |operations| operations.create_sparse_csr)? {
+            function
+        } else {
+            return Ok(None);
+        };
+
+        let mut output = mts_array_t::null();
+        let status = unsafe {
+            function(
+                self.ptr,
+                shape.as_ptr(),
+                shape.len(),
+                csr.values.as_ptr(),
+                csr.indices.as_ptr(),
+                csr.indptr.as_ptr(),
+                csr.values.len(),
+                &mut output,
+            )
+        };
+        check_operation_status(status, "create_sparse_csr")?;
+        check_output_shape(&output, shape, "create_sparse_csr")?;
+
+        return Ok(Some(output));
+    }
+}
+
+/// Check the parameters of `mts_array_t::move_along_axis`
+fn check_move_along_axis(
+    output_shape: &[usize],
+    input_shape: &[usize],
+    axis: usize,
+    mapping: &[mts_index_mapping_t],
+    output_offsets: &[usize],
+) -> Result<(), Error> {
+    if input_shape.len() != output_shape.len() {
+        return Err(Error::InvalidParameter(format!(
+            "can not move data between arrays with different number of dimensions: {:?} and {:?}",
+            input_shape, output_shape
+        )));
+    }
+
+    if axis >= input_shape.len() {
+        return Err(Error::InvalidParameter(format!(
+            "axis {} is out of bounds for array with {} dimensions",
+            axis, input_shape.len()
+        )));
+    }
+
+    if output_offsets.len() != input_shape.len() {
+        return Err(Error::InvalidParameter(format!(
+            "expected {} output offsets, got {}",
+            input_shape.len(), output_offsets.len()
+        )));
+    }
+
+    for entry in mapping {
+        if entry.input >= input_shape[axis] || entry.output >= output_shape[axis] {
+            return Err(Error::InvalidParameter(format!(
+                "index mapping {} -> {} is out of bounds for axis {} with \
+                size {} in the input and {} in the output",
+                entry.input, entry.output, axis, input_shape[axis], output_shape[axis]
+            )));
+        }
+    }
+
+    for (dim, (&size, &offset)) in input_shape.iter().zip(output_offsets).enumerate() {
+        if dim != axis && offset + size > output_shape[dim] {
+            return Err(Error::InvalidParameter(format!(
+                "can not move data from an array with shape {:?} to an array \
+                with shape {:?} with offset {} on axis {}",
+                input_shape, output_shape, offset, dim
+            )));
+        }
+    }
+
+    return Ok(());
+}
+
+/// Get the strides of a C-contiguous array with the given `shape`
+fn c_strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for i in (0..shape.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape[i + 1];
+    }
+    return strides;
+}
+
+/// Check that the `output` of `function` has the expected shape
+fn check_output_shape(output: &mts_array_t, expected: &[usize], function: &str) -> Result<(), Error> {
+    if output.shape.is_none() {
+        return Err(Error::InvalidParameter(format!(
+            "mts_array_operations_t.{} did not set the output array", function
+        )));
+    }
+
+    let shape = output.shape()?;
+    if shape != expected {
+        return Err(Error::InvalidParameter(format!(
+            "mts_array_operations_t.{} returned an array with shape {:?}, expected {:?}",
+            function, shape, expected
+        )));
+    }
+    return Ok(());
+}
+
 #[cfg(test)]
 pub(crate) use self::tests::TestArray;
 
@@ -497,27 +1474,50 @@ mod tests {
 
     pub struct TestArray {
         shape: Vec<usize>,
+        data: Vec<f64>,
     }
 
     impl TestArray {
         #[allow(clippy::new_ret_no_self)]
         pub fn new(shape: Vec<usize>) -> mts_array_t {
-            let array = Box::new(TestArray {shape});
+            let data = vec![0.0; shape.iter().product()];
+            let array = Box::new(TestArray {shape, data});
 
             return mts_array_t {
                 ptr: Box::into_raw(array).cast(),
                 origin: Some(TestArray::origin),
-                data: None,
+                data: Some(TestArray::data),
                 shape: Some(TestArray::shape),
                 reshape: Some(TestArray::reshape),
                 swap_axes: Some(TestArray::swap_axes),
-                create: None,
+                create: Some(TestArray::create),
                 copy: None,
                 destroy: Some(TestArray::destroy),
                 move_samples_from: None,
             }
         }
 
+        /// Create a new `TestArray` with the given `shape` and `data`
+        pub fn with_data(shape: Vec<usize>, data: &[f64]) -> mts_array_t {
+            let mut array = TestArray::new(shape);
+            array.data_mut().unwrap().copy_from_slice(data);
+            return array;
+        }
+
+        unsafe extern fn data(ptr: *mut c_void, data: *mut *mut f64) -> mts_status_t {
+            let ptr = ptr.cast::<TestArray>();
+            *data = (*ptr).data.as_mut_ptr();
+
+            return mts_status_t(MTS_SUCCESS);
+        }
+
+        unsafe extern fn create(_: *const c_void, shape_ptr: *const usize, shape_count: usize, new_array: *mut mts_array_t) -> mts_status_t {
+            let shape = std::slice::from_raw_parts(shape_ptr, shape_count);
+            *new_array = TestArray::new(shape.to_vec());
+
+            return mts_status_t(MTS_SUCCESS);
+        }
+
         unsafe extern fn origin(_: *const c_void, origin: *mut mts_data_origin_t) -> mts_status_t {
             *origin = register_data_origin("rust.TestArray".into());
 
@@ -571,6 +1571,295 @@ mod tests {
         assert_eq!(get_data_origin(origin), "test origin");
     }
 
+    #[test]
+    fn operations_fallback() {
+        let mut array = TestArray::with_data(vec![2, 3], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let other = TestArray::with_data(vec![2, 3], &[1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+
+        array.add(&other).unwrap();
+        assert_eq!(array.data().unwrap(), [2.0, 3.0, 4.0, 6.0, 7.0, 8.0]);
+
+        array.mul(&other).unwrap();
+        assert_eq!(array.data().unwrap(), [2.0, 3.0, 4.0, 12.0, 14.0, 16.0]);
+
+        array.scale(0.5).unwrap();
+        assert_eq!(array.data().unwrap(), [1.0, 1.5, 2.0, 6.0, 7.0, 8.0]);
+
+        let sum = array.sum_axis(0).unwrap();
+        assert_eq!(sum.shape().unwrap(), [1, 3]);
+        assert_eq!(sum.data().unwrap(), [7.0, 8.5, 10.0]);
+
+        let sum = array.sum_axis(1).unwrap();
+        assert_eq!(sum.shape().unwrap(), [2, 1]);
+        assert_eq!(sum.data().unwrap(), [4.5, 21.0]);
+
+        let matrix = TestArray::with_data(vec![3, 2], &[1.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        let product = array.matmul(&matrix).unwrap();
+        assert_eq!(product.shape().unwrap(), [2, 2]);
+        assert_eq!(product.data().unwrap(), [3.0, 3.5, 14.0, 15.0]);
+
+        array.copy_from(&other).unwrap();
+        assert_eq!(array.data().unwrap(), [1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+
+        let wrong_shape = TestArray::new(vec![3, 2]);
+        let error = array.add(&wrong_shape).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: can not add arrays with different shapes: [2, 3] and [3, 2]");
+
+        let error = array.matmul(&other).unwrap_err();
+        assert_eq!(error.to_string(),
+            "invalid parameter: can not multiply arrays with shapes [2, 3] and [2, 3]: \
+            the second array must be 2-dimensional, and its first dimension \
+            must match the last dimension of the first array"
+        );
+
+        let error = array.sum_axis(2).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: axis 2 is out of bounds for array with 2 dimensions");
+    }
+
+    #[test]
+    fn move_along_axis() {
+        let input = TestArray::with_data(vec![2, 3], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        // gather along the first axis, into a range of the second axis
+        let mut output = TestArray::new(vec![3, 4]);
+        let mapping = [
+            mts_index_mapping_t { input: 1, output: 0 },
+            mts_index_mapping_t { input: 0, output: 2 },
+        ];
+        output.move_along_axis(&input, 0, &mapping, &[0, 1]).unwrap();
+        assert_eq!(output.data().unwrap(), [
+            0.0, 4.0, 5.0, 6.0,
+            0.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 2.0, 3.0,
+        ]);
+
+        // gather along the last axis
+        let mut output = TestArray::new(vec![2, 2]);
+        let mapping = [
+            mts_index_mapping_t { input: 2, output: 0 },
+            mts_index_mapping_t { input: 0, output: 1 },
+        ];
+        output.move_along_axis(&input, 1, &mapping, &[0, 0]).unwrap();
+        assert_eq!(output.data().unwrap(), [3.0, 1.0, 6.0, 4.0]);
+
+        // gather along a middle axis
+        let input = TestArray::with_data(vec![2, 2, 2], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        let mut output = TestArray::new(vec![2, 1, 3]);
+        let mapping = [mts_index_mapping_t { input: 1, output: 0 }];
+        output.move_along_axis(&input, 1, &mapping, &[0, 0, 1]).unwrap();
+        assert_eq!(output.data().unwrap(), [0.0, 3.0, 4.0, 0.0, 7.0, 8.0]);
+
+        let error = output.move_along_axis(&input, 1, &mapping, &[0, 0, 2]).unwrap_err();
+        assert_eq!(error.to_string(),
+            "invalid parameter: can not move data from an array with shape [2, 2, 2] \
+            to an array with shape [2, 1, 3] with offset 2 on axis 2"
+        );
+
+        let mapping = [mts_index_mapping_t { input: 2, output: 0 }];
+        let error = output.move_along_axis(&input, 1, &mapping, &[0, 0, 0]).unwrap_err();
+        assert_eq!(error.to_string(),
+            "invalid parameter: index mapping 2 -> 0 is out of bounds for axis 1 \
+            with size 2 in the input and 1 in the output"
+        );
+
+        let error = output.move_along_axis(&input, 3, &[], &[0, 0, 0]).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: axis 3 is out of bounds for array with 3 dimensions");
+    }
+
+    #[test]
+    fn register_operations() {
+        unsafe extern fn descriptor(_: *const c_void, _: *mut mts_array_descriptor_t) -> mts_status_t {
+            return mts_status_t(MTS_SUCCESS);
+        }
+
+        let origin = register_data_origin("test operations".into());
+        let mut operations = mts_array_operations_t {
+            version: MTS_ARRAY_OPERATIONS_VERSION,
+            add: None,
+            mul: None,
+            scale: None,
+            matmul: None,
+            sum_axis: None,
+            copy_from: None,
+            descriptor: None,
+            raw_data: None,
+            move_along_axis: None,
+            sparse_csr: None,
+            create_sparse_csr: None,
+        };
+
+        unsafe {
+            register_array_operations(origin, &operations).unwrap();
+
+            let error = register_array_operations(mts_data_origin_t(100000), &operations).unwrap_err();
+            assert_eq!(error.to_string(), "invalid parameter: data origin 100000 is not registered");
+
+            // the functions added in version 2 are ignored for version 1
+            operations.version = 1;
+            operations.descriptor = Some(descriptor);
+            register_array_operations(origin, &operations).unwrap();
+            let registered = REGISTERED_ARRAY_OPERATIONS.lock().expect("mutex got poisoned")[&origin.0];
+            assert_eq!(registered.version, 1);
+            assert!(registered.descriptor.is_none());
+
+            operations.version = MTS_ARRAY_OPERATIONS_VERSION + 1;
+            let error = register_array_operations(origin, &operations).unwrap_err();
+            assert_eq!(error.to_string(), "invalid parameter: unsupported version of mts_array_operations_t: got 3, expected at most 2");
+        }
+    }
+
+    #[test]
+    fn dlpack_f32_without_raw_data() {
+        unsafe extern fn f32_origin(_: *const c_void, origin: *mut mts_data_origin_t) -> mts_status_t {
+            *origin = register_data_origin("rust.F32TestArray".into());
+            return mts_status_t(MTS_SUCCESS);
+        }
+
+        unsafe extern fn f32_descriptor(_: *const c_void, descriptor: *mut mts_array_descriptor_t) -> mts_status_t {
+            *descriptor = mts_array_descriptor_t {
+                device_type: 1,
+                device_id: 0,
+                dtype_code: 2,
+                dtype_bits: 32,
+                dtype_lanes: 1,
+            };
+            return mts_status_t(MTS_SUCCESS);
+        }
+
+        let origin = register_data_origin("rust.F32TestArray".into());
+        let operations = mts_array_operations_t {
+            version: MTS_ARRAY_OPERATIONS_VERSION,
+            add: None,
+            mul: None,
+            scale: None,
+            matmul: None,
+            sum_axis: None,
+            copy_from: None,
+            descriptor: Some(f32_descriptor),
+            raw_data: None,
+            move_along_axis: None,
+            sparse_csr: None,
+            create_sparse_csr: None,
+        };
+        unsafe { register_array_operations(origin, &operations).unwrap() };
+
+        let mut array = TestArray::with_data(vec![2, 2], &[1.0, 2.5, 3.0, -4.0]);
+        array.origin = Some(f32_origin);
+
+        let tensor = array.to_dlpack().unwrap();
+        unsafe {
+            let dl_tensor = &(*tensor).dl_tensor;
+            assert_eq!((dl_tensor.dtype.code, dl_tensor.dtype.bits, dl_tensor.dtype.lanes), (2, 32, 1));
+            assert_eq!(std::slice::from_raw_parts(dl_tensor.data.cast::<f32>(), 4), [1.0, 2.5, 3.0, -4.0]);
+
+            let deleter = (*tensor).deleter.unwrap();
+            deleter(tensor);
+        }
+    }
+
+    #[test]
+    fn check_sparse_csr() {
+        let csr = SparseCsr {
+            values: &[1.0, 2.0, 3.0],
+            indices: &[0, 2, 1],
+            indptr: &[0, 2, 3],
+        };
+        csr.check(&[2, 3]).unwrap();
+
+        let error = csr.check(&[2, 2]).unwrap_err();
+        assert_eq!(error.to_string(),
+            "invalid parameter: invalid sparse data: column indexes must be \
+            sorted, unique and smaller than 2 in each row"
+        );
+
+        // unsorted columns
+        let csr = SparseCsr { indices: &[2, 0, 1], ..csr };
+        let error = csr.check(&[2, 3]).unwrap_err();
+        assert_eq!(error.to_string(),
+            "invalid parameter: invalid sparse data: column indexes must be \
+            sorted, unique and smaller than 3 in each row"
+        );
+
+        // duplicated columns
+        let csr = SparseCsr { indices: &[1, 1, 1], ..csr };
+        assert!(csr.check(&[2, 3]).is_err());
+
+        let csr = SparseCsr { indices: &[0, 2, 1], indptr: &[0, 2, 1, 3], ..csr };
+        let error = csr.check(&[3, 3]).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: invalid sparse data: indptr must be sorted");
+    }
+
+    #[test]
+    fn converters() {
+        static CONVERSIONS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+        unsafe extern fn other_origin(_: *const c_void, origin: *mut mts_data_origin_t) -> mts_status_t {
+            *origin = register_data_origin("rust.OtherTestArray".into());
+
+            return mts_status_t(MTS_SUCCESS);
+        }
+
+        unsafe extern fn convert(array: *const mts_array_t, output: *mut mts_array_t) -> mts_status_t {
+            CONVERSIONS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let shape = (*array).shape().unwrap().to_vec();
+            std::ptr::write(output, TestArray::with_data(shape, (*array).data().unwrap()));
+
+            return mts_status_t(MTS_SUCCESS);
+        }
+
+        let test_origin = register_data_origin("rust.TestArray".into());
+        let other_test_origin = register_data_origin("rust.OtherTestArray".into());
+
+        let mut other = TestArray::with_data(vec![2, 2], &[1.0, 2.0, 3.0, 4.0]);
+        other.origin = Some(other_origin);
+
+        let error = other.convert_to(test_origin).unwrap_err();
+        assert_eq!(error.to_string(),
+            "invalid parameter: there is no registered converter from \
+            'rust.OtherTestArray' to 'rust.TestArray'"
+        );
+
+        register_array_converter(other_test_origin, test_origin, convert).unwrap();
+        let converted = other.convert_to(test_origin).unwrap();
+        assert_eq!(converted.origin().unwrap(), test_origin);
+        assert_eq!(converted.data().unwrap(), [1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(CONVERSIONS.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // binary operations convert the second array to the origin of the first
+        let mut array = TestArray::with_data(vec![2, 2], &[1.0, 1.0, 1.0, 1.0]);
+        array.add(&other).unwrap();
+        assert_eq!(array.data().unwrap(), [2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(CONVERSIONS.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let error = register_array_converter(test_origin, test_origin, convert).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: can not register a converter from 'rust.TestArray' to itself");
+
+        let error = register_array_converter(mts_data_origin_t(100000), test_origin, convert).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: data origin 100000 is not registered");
+    }
+
+    #[test]
+    fn descriptor_display() {
+        let mut descriptor = mts_array_descriptor_t {
+            device_type: 1,
+            device_id: 0,
+            dtype_code: 2,
+            dtype_bits: 64,
+            dtype_lanes: 1,
+        };
+        assert_eq!(descriptor.to_string(), "cpu:0 float64");
+
+        descriptor.device_type = 2;
+        descriptor.device_id = 1;
+        descriptor.dtype_bits = 32;
+        assert_eq!(descriptor.to_string(), "cuda:1 float32");
+
+        descriptor.device_type = 42;
+        descriptor.dtype_code = 12;
+        descriptor.dtype_lanes = 4;
+        assert_eq!(descriptor.to_string(), "device(42):1 dtype(12):32x4");
+    }
+
     #[test]
     fn debug() {
         let data: mts_array_t = TestArray::new(vec![3, 4, 5]);
@@ -0,0 +1,174 @@
+//! Definitions of the `DLPack` data structures, following version 0.8 of the
+//! `dlpack.h` header. These are not part of the metatensor C API, which uses
+//! `void*` for pointers to `DLManagedTensor` to avoid conflicts with other
+//! definitions of the same structs.
+
+use std::os::raw::c_void;
+
+use crate::{mts_array_t, Error};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DLDevice {
+    pub device_type: i32,
+    pub device_id: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DLDataType {
+    pub code: u8,
+    pub bits: u8,
+    pub lanes: u16,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DLTensor {
+    pub data: *mut c_void,
+    pub device: DLDevice,
+    pub ndim: i32,
+    pub dtype: DLDataType,
+    pub shape: *mut i64,
+    pub strides: *mut i64,
+    pub byte_offset: u64,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DLManagedTensor {
+    pub dl_tensor: DLTensor,
+    pub manager_ctx: *mut c_void,
+    pub deleter: Option<unsafe extern fn(*mut DLManagedTensor)>,
+}
+
+/// Memory owned by a `DLManagedTensor` created by metatensor
+struct ManagerContext {
+    shape: Vec<i64>,
+    /// values converted to 32-bit floats, if the tensor does not borrow the
+    /// data from the array. This is only kept alive for the tensor, and never
+    /// read directly.
+    _data_f32: Vec<f32>,
+}
+
+/// Implementation of `DLManagedTensor.deleter` for tensors created by
+/// metatensor
+unsafe extern fn delete_managed_tensor(tensor: *mut DLManagedTensor) {
+    if tensor.is_null() {
+        return;
+    }
+
+    let tensor = Box::from_raw(tensor);
+    std::mem::drop(Box::from_raw(tensor.manager_ctx.cast::<ManagerContext>()));
+}
+
+impl mts_array_t {
+    /// Export this array as a `DLPack` `DLManagedTensor`, without copying the
+    /// data. The returned tensor borrows the data from this array, and must
+    /// not be used after this array has been destroyed.
+    ///
+    /// This uses `mts_array_operations_t.raw_data` and
+    /// `mts_array_operations_t.descriptor` if they are registered, and
+    /// `mts_array_t.data` for floating point data on CPU otherwise. In the
+    /// latter case, arrays described as containing 32-bit floats are exported
+    /// as a copy of the data converted to 32-bit floats, owned by the tensor.
+    pub fn to_dlpack(&self) -> Result<*mut DLManagedTensor, Error> {
+        let shape = self.shape()?;
+        let mut data_f32 = Vec::new();
+
+        let (data, descriptor) = if let Some(function) = self.operation(None, |operations| operations.raw_data)? {
+            let descriptor = self.descriptor()?.ok_or_else(|| Error::InvalidParameter(
+                "can not export an array with unknown device and dtype to DLPack".into()
+            ))?;
+
+            let mut data = std::ptr::null_mut();
+            let status = unsafe { function(self.ptr, &mut data) };
+            if !status.is_success() {
+                return Err(Error::External {
+                    status, context: "calling mts_array_operations_t.raw_data failed".into()
+                });
+            }
+
+            (data, descriptor)
+        } else {
+            let mut descriptor = crate::mts_array_descriptor_t {
+                // kDLCPU
+                device_type: 1,
+                device_id: 0,
+                // kDLFloat
+                dtype_code: 2,
+                dtype_bits: 64,
+                dtype_lanes: 1,
+            };
+
+            // `mts_array_t.data` always gives 64-bit floats on CPU, which are
+            // converted back to 32-bit floats if the array uses them
+            let is_cpu_f32 = self.descriptor()?.is_some_and(|d| {
+                d.device_type == 1 && d.dtype_code == 2 && d.dtype_bits == 32 && d.dtype_lanes == 1
+            });
+            let data = if is_cpu_f32 {
+                descriptor.dtype_bits = 32;
+                #[allow(clippy::cast_possible_truncation)]
+                data_f32.extend(self.data()?.iter().map(|&v| v as f32));
+                data_f32.as_mut_ptr().cast()
+            } else {
+                self.data()?.as_ptr().cast_mut().cast()
+            };
+
+            (data, descriptor)
+        };
+
+        let mut context = Box::new(ManagerContext {
+            shape: shape.iter().map(|&s| i64::try_from(s).expect("shape is too large")).collect(),
+            _data_f32: data_f32,
+        });
+
+        let tensor = Box::new(DLManagedTensor {
+            dl_tensor: DLTensor {
+                data,
+                device: DLDevice {
+                    device_type: descriptor.device_type,
+                    device_id: descriptor.device_id,
+                },
+                ndim: i32::try_from(shape.len()).expect("too many dimensions"),
+                dtype: DLDataType {
+                    code: descriptor.dtype_code,
+                    bits: descriptor.dtype_bits,
+                    lanes: descriptor.dtype_lanes,
+                },
+                shape: context.shape.as_mut_ptr(),
+                // NULL strides indicate C-contiguous data
+                strides: std::ptr::null_mut(),
+                byte_offset: 0,
+            },
+            manager_ctx: Box::into_raw(context).cast(),
+            deleter: Some(delete_managed_tensor),
+        });
+
+        return Ok(Box::into_raw(tensor));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::TestArray;
+
+    #[test]
+    fn export() {
+        let array = TestArray::with_data(vec![2, 3], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let tensor = array.to_dlpack().unwrap();
+
+        unsafe {
+            let dl_tensor = &(*tensor).dl_tensor;
+            assert_eq!(dl_tensor.data.cast_const().cast::<f64>(), array.data().unwrap().as_ptr());
+            assert_eq!(dl_tensor.ndim, 2);
+            assert_eq!(std::slice::from_raw_parts(dl_tensor.shape, 2), [2, 3]);
+            assert!(dl_tensor.strides.is_null());
+            assert_eq!(dl_tensor.device.device_type, 1);
+            assert_eq!((dl_tensor.dtype.code, dl_tensor.dtype.bits, dl_tensor.dtype.lanes), (2, 64, 1));
+
+            let deleter = (*tensor).deleter.unwrap();
+            deleter(tensor);
+        }
+    }
+}
@@ -1,15 +1,18 @@
 use std::io::BufReader;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use byteorder::{LittleEndian, ReadBytesExt, BigEndian, WriteBytesExt, NativeEndian};
+use indexmap::IndexSet;
 use zip::{ZipArchive, ZipWriter};
+use zip::result::ZipError;
 
 use super::npy_header::{Header, DataType};
 use super::{check_for_extra_bytes, PathOrBuffer};
 use super::labels::{load_labels, save_labels};
 
-use crate::{TensorBlock, Labels, Error, mts_array_t};
+use crate::{TensorBlock, TensorMap, Labels, Error, mts_array_t};
+use crate::data::SparseCsr;
 
 
 /// Check if the file/buffer in `data` looks like it could contain serialized
@@ -28,7 +31,7 @@ pub fn looks_like_block_data(mut data: PathOrBuffer) -> bool {
         PathOrBuffer::Buffer(ref mut buffer) => {
             match ZipArchive::new(buffer) {
                 Ok(mut archive) => {
-                    return archive.by_name("values.npy").is_ok()
+                    return archive.by_name("values.npy").is_ok() || archive.by_name("values_csr/data.npy").is_ok();
                 }
                 Err(_) => { return false; }
             }
@@ -50,7 +53,9 @@ pub fn load_block<R, F>(reader: R, create_array: F) -> Result<TensorBlock, Error
 {
     let mut archive = ZipArchive::new(reader).map_err(|e| ("<root>".into(), e))?;
 
-    return read_single_block(&mut archive, "", None, &create_array);
+    let mut cache = LabelsCache::default();
+    let gradients = GradientsIndex::new(&archive);
+    return read_single_block(&mut archive, "", None, &mut cache, &gradients, &create_array);
 }
 
 /// Save the given block to a file (or any other writer).
@@ -59,7 +64,8 @@ pub fn load_block<R, F>(reader: R, create_array: F) -> Result<TensorBlock, Error
 /// numpy's NPZ format (i.e. zip archive containing NPY files).
 pub fn save_block<W: std::io::Write + std::io::Seek>(writer: W, block: &TensorBlock) -> Result<(), Error> {
     let mut archive = ZipWriter::new(writer);
-    write_single_block(&mut archive, "", true, block)?;
+
+    write_single_block(&mut archive, "", true, block, &SharedLabels::default())?;
     archive.finish().map_err(|e| ("<root>".into(), e))?;
 
     return Ok(());
@@ -68,29 +74,247 @@ pub fn save_block<W: std::io::Write + std::io::Seek>(writer: W, block: &TensorBl
 
 /******************************************************************************/
 
+/// Cache of the `Labels` already loaded from a file. This is used to share a
+/// single `Arc<Labels>` between all the blocks (and gradients) using identical
+/// labels, making later comparisons of these labels cheaper.
+#[derive(Default)]
+pub(super) struct LabelsCache {
+    labels: HashMap<u64, Vec<Arc<Labels>>>,
+    /// labels stored in `gradient_samples/<index>.npy`, by index
+    shared: HashMap<usize, Arc<Labels>>,
+    /// did we already check `gradient_samples/version.npy`?
+    shared_version_checked: bool,
+}
+
+impl LabelsCache {
+    /// Load `Labels` from the given `reader`, re-using existing labels with
+    /// the same content if possible.
+    fn load<R: std::io::Read>(&mut self, reader: R) -> Result<Arc<Labels>, Error> {
+        let labels = load_labels(reader)?;
+
+        let candidates = self.labels.entry(labels.fingerprint()).or_default();
+        for candidate in &*candidates {
+            if candidate.dtype() == labels.dtype() && **candidate == labels {
+                return Ok(Arc::clone(candidate));
+            }
+        }
+
+        let labels = Arc::new(labels);
+        candidates.push(Arc::clone(&labels));
+        return Ok(labels);
+    }
+
+    /// Load the shared gradient samples with the given `index` from the
+    /// `archive`, or get them from the cache if they were already loaded
+    fn load_shared<R>(&mut self, archive: &mut ZipArchive<R>, index: usize) -> Result<Arc<Labels>, Error>
+        where R: std::io::Read + std::io::Seek
+    {
+        if let Some(labels) = self.shared.get(&index) {
+            return Ok(Arc::clone(labels));
+        }
+
+        if !self.shared_version_checked {
+            let path = String::from("gradient_samples/version.npy");
+            let version = read_indices(archive.by_name(&path).map_err(|e| (path.clone(), e))?, &path)?;
+            if version != [SHARED_LABELS_VERSION] {
+                return Err(Error::Serialization(format!(
+                    "unsupported version for the shared gradient samples: \
+                    expected [{}], got {:?}. This file might have been created \
+                    by a newer version of metatensor",
+                    SHARED_LABELS_VERSION, version
+                )));
+            }
+            self.shared_version_checked = true;
+        }
+
+        let path = format!("gradient_samples/{}.npy", index);
+        let file = archive.by_name(&path).map_err(|e| (path, e))?;
+        let labels = self.load(file)?;
+
+        self.shared.insert(index, Arc::clone(&labels));
+        return Ok(labels);
+    }
+}
+
+/// Parameters of all the gradients stored in an archive, found with a single
+/// pass over the file names in the archive.
+pub(super) struct GradientsIndex {
+    /// gradient parameters, indexed by the prefix of the parent block. We use
+    /// an `IndexSet` to keep the gradients in the same order as in the file.
+    parameters: HashMap<String, IndexSet<String>>,
+}
+
+impl GradientsIndex {
+    pub(super) fn new<R>(archive: &ZipArchive<R>) -> GradientsIndex
+        where R: std::io::Read + std::io::Seek
+    {
+        let mut parameters = HashMap::<String, IndexSet<String>>::new();
+        for name in archive.file_names() {
+            let directory = name.strip_suffix("/samples.npy").or_else(|| name.strip_suffix("/samples_ref.npy"));
+            let Some((gradients, parameter)) = directory.and_then(|directory| directory.rsplit_once('/')) else {
+                continue;
+            };
+
+            // gradients are stored in `<prefix>gradients/<parameter>/`
+            if let Some(prefix) = gradients.strip_suffix("gradients") {
+                if prefix.is_empty() || prefix.ends_with('/') {
+                    parameters.entry(prefix.to_string()).or_default().insert(parameter.to_string());
+                }
+            }
+        }
+
+        return GradientsIndex { parameters };
+    }
+
+    /// Get the parameters of the gradients of the block stored at `prefix`
+    fn parameters(&self, prefix: &str) -> impl Iterator<Item = &String> {
+        return self.parameters.get(prefix).into_iter().flatten();
+    }
+}
+
+/// Version of the layout used to store shared gradient samples, written to
+/// `gradient_samples/version.npy`
+const SHARED_LABELS_VERSION: usize = 1;
+
+/// Gradient samples `Labels` shared between multiple gradients, i.e. the same
+/// `Arc<Labels>` used by more than one gradient (see
+/// `TensorMap::share_gradient_samples`). These are written only once to
+/// `gradient_samples/<index>.npy`, and the gradients using them contain a
+/// `samples_ref.npy` file with the corresponding index instead of
+/// `samples.npy`.
+#[derive(Default)]
+pub(super) struct SharedLabels {
+    /// index of the shared labels, using the address of the labels as key
+    indexes: HashMap<*const Labels, usize>,
+    labels: Vec<Arc<Labels>>,
+}
+
+impl SharedLabels {
+    /// Find the gradient samples of `tensor` which are used by more than one
+    /// gradient
+    pub(super) fn new(tensor: &TensorMap) -> SharedLabels {
+        /// Count how many gradients use each gradient samples, keeping the
+        /// samples in the order they are first used
+        fn count_gradient_samples(
+            block: &TensorBlock,
+            all_samples: &mut Vec<Arc<Labels>>,
+            counts: &mut HashMap<*const Labels, usize>,
+        ) {
+            // iterate over the parameters in the order they were added to
+            // get the same indexes every time
+            for parameter in block.gradient_parameters_c() {
+                let gradient = block.gradient(parameter.as_str()).expect("missing gradient");
+
+                let count = counts.entry(Arc::as_ptr(&gradient.samples)).or_insert(0);
+                if *count == 0 {
+                    all_samples.push(Arc::clone(&gradient.samples));
+                }
+                *count += 1;
+
+                count_gradient_samples(gradient, all_samples, counts);
+            }
+        }
+
+        let mut all_samples = Vec::new();
+        let mut counts = HashMap::new();
+        for block in tensor.blocks() {
+            count_gradient_samples(block, &mut all_samples, &mut counts);
+        }
+
+        let mut shared = SharedLabels::default();
+        for labels in all_samples {
+            if counts[&Arc::as_ptr(&labels)] > 1 {
+                shared.indexes.insert(Arc::as_ptr(&labels), shared.labels.len());
+                shared.labels.push(labels);
+            }
+        }
+
+        return shared;
+    }
+
+    /// Get the index of the given `labels` if they are shared
+    fn index(&self, labels: &Arc<Labels>) -> Option<usize> {
+        return self.indexes.get(&Arc::as_ptr(labels)).copied();
+    }
+
+    /// Write all the shared labels to the `archive`
+    pub(super) fn write<W: std::io::Write + std::io::Seek>(&self, archive: &mut ZipWriter<W>) -> Result<(), Error> {
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .large_file(true)
+            .last_modified_time(zip::DateTime::from_date_and_time(2000, 1, 1, 0, 0, 0).expect("invalid datetime"));
+
+        if self.labels.is_empty() {
+            return Ok(());
+        }
+
+        let path = String::from("gradient_samples/version.npy");
+        archive.start_file(&path, options).map_err(|e| (path, e))?;
+        write_indices(archive, &[SHARED_LABELS_VERSION])?;
+
+        for (index, labels) in self.labels.iter().enumerate() {
+            let path = format!("gradient_samples/{}.npy", index);
+            archive.start_file(&path, options).map_err(|e| (path, e))?;
+            save_labels(archive, labels)?;
+        }
+
+        return Ok(());
+    }
+}
+
 #[allow(clippy::needless_pass_by_value)]
 pub(super) fn read_single_block<R, F>(
     archive: &mut ZipArchive<R>,
     prefix: &str,
     properties: Option<Arc<Labels>>,
+    cache: &mut LabelsCache,
+    gradients: &GradientsIndex,
     create_array: &F,
 ) -> Result<TensorBlock, Error>
     where R: std::io::Read + std::io::Seek,
           F: Fn(Vec<usize>) -> Result<mts_array_t, Error>
 {
     let path = format!("{}values.npy", prefix);
-    let data_file = archive.by_name(&path).map_err(|e| (path, e))?;
-    let (data, shape) = read_data(data_file, &create_array)?;
+    let data = match archive.by_name(&path) {
+        Ok(data_file) => Some(read_data(data_file, &create_array)?),
+        Err(ZipError::FileNotFound) => None,
+        Err(e) => return Err((path, e).into()),
+    };
+    let (data, shape) = match data {
+        Some(data) => data,
+        None => read_sparse_data(archive, prefix, &create_array)?,
+    };
 
     let path = format!("{}samples.npy", prefix);
-    let samples_file = archive.by_name(&path).map_err(|e| (path, e))?;
-    let samples = Arc::new(load_labels(samples_file)?);
+    let samples = match archive.by_name(&path) {
+        // gradient samples are only shared when they are stored in
+        // `gradient_samples/`, so that saving the data again uses the same
+        // layout as the initial file
+        Ok(samples_file) if properties.is_some() => Some(Arc::new(load_labels(samples_file)?)),
+        Ok(samples_file) => Some(cache.load(samples_file)?),
+        Err(ZipError::FileNotFound) => None,
+        Err(e) => return Err((path, e).into()),
+    };
+    let samples = if let Some(samples) = samples {
+        samples
+    } else {
+        // gradient samples shared with other gradients
+        let path = format!("{}samples_ref.npy", prefix);
+        let reference_file = archive.by_name(&path).map_err(|e| (path.clone(), e))?;
+        let index = read_indices(reference_file, &path)?;
+        if index.len() != 1 {
+            return Err(Error::Serialization(format!(
+                "expected a single index in '{}', got {}", path, index.len()
+            )));
+        }
+        cache.load_shared(archive, index[0])?
+    };
 
     let mut components = Vec::new();
     for i in 0..(shape.len() - 2) {
         let path = format!("{}components/{}.npy", prefix, i);
         let component_file = archive.by_name(&path).map_err(|e| (path, e))?;
-        components.push(Arc::new(load_labels(component_file)?));
+        components.push(cache.load(component_file)?);
     }
 
     let properties = if let Some(ref properties) = properties {
@@ -98,26 +322,18 @@ pub(super) fn read_single_block<R, F>(
     } else {
         let path = format!("{}properties.npy", prefix);
         let properties_file = archive.by_name(&path).map_err(|e| (path, e))?;
-        Arc::new(load_labels(properties_file)?)
+        cache.load(properties_file)?
     };
 
     let mut block = TensorBlock::new(data, samples, components, properties.clone())?;
 
-    let mut parameters = HashSet::new();
-    let gradient_prefix = format!("{}gradients/", prefix);
-    for name in archive.file_names() {
-        if name.starts_with(&gradient_prefix) && name.ends_with("/samples.npy") {
-            let (_, parameter) = name.split_at(gradient_prefix.len());
-            let parameter = parameter.split('/').next().expect("could not find gradient parameter");
-            parameters.insert(parameter.to_string());
-        }
-    }
-
-    for parameter in &parameters {
+    for parameter in gradients.parameters(prefix) {
         let gradient = read_single_block(
             archive,
             &format!("{}gradients/{}/", prefix, parameter),
             Some(properties.clone()),
+            cache,
+            gradients,
             create_array
         )?;
 
@@ -159,24 +375,147 @@ fn read_data<R, F>(mut reader: R, create_array: &F) -> Result<(mts_array_t, Vec<
     return Ok((array, shape));
 }
 
+// Read sparse data stored in CSR format in the `values_csr` directory, with
+// one NPY file for each of `data`, `indices`, `indptr` and `shape`
+fn read_sparse_data<R, F>(
+    archive: &mut ZipArchive<R>,
+    prefix: &str,
+    create_array: &F,
+) -> Result<(mts_array_t, Vec<usize>), Error>
+    where R: std::io::Read + std::io::Seek,
+          F: Fn(Vec<usize>) -> Result<mts_array_t, Error>
+{
+    let mut read_indices = |name: &str| {
+        let path = format!("{}values_csr/{}.npy", prefix, name);
+        let file = archive.by_name(&path).map_err(|e| (path.clone(), e))?;
+        return read_indices(file, &path);
+    };
+
+    let shape = read_indices("shape")?;
+    let indices = read_indices("indices")?;
+    let indptr = read_indices("indptr")?;
+
+    let path = format!("{}values_csr/data.npy", prefix);
+    let mut values_file = archive.by_name(&path).map_err(|e| (path, e))?;
+    let header = Header::from_reader(&mut values_file)?;
+    if header.fortran_order || header.shape.len() != 1 {
+        return Err(Error::Serialization("sparse data must be stored as 1-D arrays".into()));
+    }
+
+    let mut values = vec![0.0; header.shape[0]];
+    match header.type_descriptor {
+        DataType::Scalar(s) if s == "<f8" => {
+            values_file.read_f64_into::<LittleEndian>(&mut values)?;
+        }
+        DataType::Scalar(s) if s == ">f8" => {
+            values_file.read_f64_into::<BigEndian>(&mut values)?;
+        }
+        _ => {
+            return Err(Error::Serialization(format!(
+                "unknown type for sparse data array, expected 64-bit floating points, got {}",
+                header.type_descriptor
+            )));
+        }
+    }
+    check_for_extra_bytes(&mut values_file)?;
+
+    if shape.is_empty() {
+        return Err(Error::Serialization("sparse data must have at least one dimension".into()));
+    }
+
+    let csr = SparseCsr {
+        values: &values,
+        indices: &indices,
+        indptr: &indptr,
+    };
+    csr.check(&shape)?;
+
+    // use an empty array to find the origin of the arrays created by
+    // `create_array`, and create a sparse array with the same origin
+    let mut empty_shape = shape.clone();
+    empty_shape[0] = 0;
+    let empty = create_array(empty_shape)?;
+    if let Some(array) = empty.create_sparse_csr(&shape, csr)? {
+        return Ok((array, shape));
+    }
+
+    // the origin does not support sparse data, create a dense array instead
+    let mut array = create_array(shape.clone())?;
+    let columns = shape[1..].iter().product::<usize>();
+    let data = array.data_mut()?;
+    for (row, range) in indptr.windows(2).enumerate() {
+        for (&column, &value) in indices[range[0]..range[1]].iter().zip(&values[range[0]..range[1]]) {
+            data[row * columns + column] = value;
+        }
+    }
+
+    return Ok((array, shape));
+}
+
+// Read a 1-D array of 64-bit integers used as indexes, using numpy's NPY
+// format. `path` is the path of the file in the archive, used in error messages.
+fn read_indices<R: std::io::Read>(mut reader: R, path: &str) -> Result<Vec<usize>, Error> {
+    let header = Header::from_reader(&mut reader)?;
+    if header.fortran_order || header.shape.len() != 1 {
+        return Err(Error::Serialization(format!(
+            "indexes in '{}' must be stored as a 1-D array", path
+        )));
+    }
+
+    let mut data = vec![0; header.shape[0]];
+    match header.type_descriptor {
+        DataType::Scalar(s) if s == "<i8" => {
+            reader.read_i64_into::<LittleEndian>(&mut data)?;
+        }
+        DataType::Scalar(s) if s == ">i8" => {
+            reader.read_i64_into::<BigEndian>(&mut data)?;
+        }
+        _ => {
+            return Err(Error::Serialization(format!(
+                "unknown type for indexes in '{}', expected 64-bit integers, got {}",
+                path, header.type_descriptor
+            )));
+        }
+    }
+
+    check_for_extra_bytes(&mut reader)?;
+
+    return data.into_iter().map(|value| usize::try_from(value).map_err(|_| Error::Serialization(
+        format!("invalid negative index in '{}': {}", path, value)
+    ))).collect();
+}
+
 pub(super) fn write_single_block<W: std::io::Write + std::io::Seek>(
     archive: &mut ZipWriter<W>,
     prefix: &str,
     values: bool,
     block: &TensorBlock,
+    shared: &SharedLabels,
 ) -> Result<(), Error> {
     let options = zip::write::FileOptions::default()
         .compression_method(zip::CompressionMethod::Stored)
         .large_file(true)
         .last_modified_time(zip::DateTime::from_date_and_time(2000, 1, 1, 0, 0, 0).expect("invalid datetime"));
 
-    let path = format!("{}values.npy", prefix);
-    archive.start_file(&path, options).map_err(|e| (path, e))?;
-    write_data(archive, &block.values)?;
+    if let Some(csr) = block.values.sparse_csr()? {
+        write_sparse_data(archive, prefix, options, block.values.shape()?, csr)?;
+    } else {
+        let path = format!("{}values.npy", prefix);
+        archive.start_file(&path, options).map_err(|e| (path, e))?;
+        write_data(archive, &block.values)?;
+    }
 
-    let path = format!("{}samples.npy", prefix);
-    archive.start_file(&path, options).map_err(|e| (path, e))?;
-    save_labels(archive, &block.samples)?;
+    // only the samples of gradients can be shared
+    let shared_index = if values { None } else { shared.index(&block.samples) };
+    if let Some(index) = shared_index {
+        let path = format!("{}samples_ref.npy", prefix);
+        archive.start_file(&path, options).map_err(|e| (path, e))?;
+        write_indices(archive, &[index])?;
+    } else {
+        let path = format!("{}samples.npy", prefix);
+        archive.start_file(&path, options).map_err(|e| (path, e))?;
+        save_labels(archive, &block.samples)?;
+    }
 
     for (i, component) in block.components.iter().enumerate() {
         let path = format!("{}components/{}.npy", prefix, i);
@@ -192,14 +531,85 @@ pub(super) fn write_single_block<W: std::io::Write + std::io::Seek>(
 
     for (parameter, gradient) in block.gradients() {
         let prefix = format!("{}gradients/{}/", prefix, parameter);
-        write_single_block(archive, &prefix, false, gradient)?;
+        write_single_block(archive, &prefix, false, gradient, shared)?;
     }
 
     Ok(())
 }
 
+// Write sparse data in CSR format to the `values_csr` directory, with one NPY
+// file for each of `data`, `indices`, `indptr` and `shape`
+fn write_sparse_data<W: std::io::Write + std::io::Seek>(
+    archive: &mut ZipWriter<W>,
+    prefix: &str,
+    options: zip::write::FileOptions,
+    shape: &[usize],
+    csr: SparseCsr<'_>,
+) -> Result<(), Error> {
+    let path = format!("{}values_csr/data.npy", prefix);
+    archive.start_file(&path, options).map_err(|e| (path, e))?;
+
+    let type_descriptor = if cfg!(target_endian = "little") {
+        "<f8"
+    } else {
+        ">f8"
+    };
+    let header = Header {
+        type_descriptor: DataType::Scalar(type_descriptor.into()),
+        fortran_order: false,
+        shape: vec![csr.values.len()],
+    };
+    header.write(&mut *archive)?;
+    for &value in csr.values {
+        archive.write_f64::<NativeEndian>(value)?;
+    }
+
+    for (name, indices) in [("indices", csr.indices), ("indptr", csr.indptr), ("shape", shape)] {
+        let path = format!("{}values_csr/{}.npy", prefix, name);
+        archive.start_file(&path, options).map_err(|e| (path, e))?;
+        write_indices(archive, indices)?;
+    }
+
+    return Ok(());
+}
+
+// Write a 1-D array of indexes as 64-bit integers, using numpy's NPY format
+fn write_indices<W: std::io::Write>(writer: &mut W, indices: &[usize]) -> Result<(), Error> {
+    let type_descriptor = if cfg!(target_endian = "little") {
+        "<i8"
+    } else {
+        ">i8"
+    };
+
+    let header = Header {
+        type_descriptor: DataType::Scalar(type_descriptor.into()),
+        fortran_order: false,
+        shape: vec![indices.len()],
+    };
+
+    header.write(&mut *writer)?;
+
+    for &index in indices {
+        let index = i64::try_from(index).expect("index is too large to be saved");
+        writer.write_i64::<NativeEndian>(index)?;
+    }
+
+    return Ok(());
+}
+
 // Write an array to the given writer, using numpy's NPY format
 fn write_data<W: std::io::Write>(writer: &mut W, array: &mts_array_t) -> Result<(), Error> {
+    // arrays which do not give access to their data (e.g. data on a GPU) are
+    // converted with the registered converters before saving
+    let converted;
+    let data = match array.data() {
+        Ok(data) => data,
+        Err(error) => {
+            converted = array.convert_for_data()?.ok_or(error)?;
+            converted.data()?
+        }
+    };
+
     let type_descriptor = if cfg!(target_endian = "little") {
         "<f8"
     } else {
@@ -214,7 +624,7 @@ fn write_data<W: std::io::Write>(writer: &mut W, array: &mts_array_t) -> Result<
 
     header.write(&mut *writer)?;
 
-    for &value in array.data()? {
+    for &value in data {
         writer.write_f64::<NativeEndian>(value)?;
     }
 
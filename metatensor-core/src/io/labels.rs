@@ -4,7 +4,8 @@ use byteorder::{LittleEndian, ReadBytesExt, BigEndian, WriteBytesExt, NativeEndi
 
 use super::npy_header::{Header, DataType};
 use super::{check_for_extra_bytes, PathOrBuffer};
-use crate::{Error, Labels, LabelsBuilder, LabelValue};
+use crate::Error;
+use crate::labels::{Labels, LabelsDType};
 
 
 /// Check if the file/buffer in `data` looks like it could contain serialized
@@ -43,6 +44,9 @@ pub fn looks_like_labels_data(mut data: PathOrBuffer) -> bool {
 /// associating name and either "<i4" for little endian file or ">i4" for big
 /// endian file. Data is stored in exactly the same way as inside a `Labels`,
 /// i.e. a big blob of 32-bit integers.
+///
+/// Labels using 64-bit integers are stored in the same way, using "<i8" or
+/// ">i8" for all the dimensions.
 pub fn load_labels<R: std::io::Read>(mut reader: R) -> Result<Labels, Error> {
     let header = Header::from_reader(&mut reader)?;
     if header.fortran_order {
@@ -50,22 +54,38 @@ pub fn load_labels<R: std::io::Read>(mut reader: R) -> Result<Labels, Error> {
     } else if header.shape.len() != 1 {
         return Err(Error::Serialization("Expected a 1-D array when loading Labels".into()));
     }
-    let (names, endianness) = check_type_descriptor(&header.type_descriptor)?;
-
-    let mut data = vec![0; header.shape[0] * names.len()];
-    match endianness {
-        Endianness::LittleEndian => reader.read_i32_into::<LittleEndian>(&mut data)?,
-        Endianness::BigEndian => reader.read_i32_into::<BigEndian>(&mut data)?,
-    }
+    let (names, endianness, dtype) = check_type_descriptor(&header.type_descriptor)?;
+    let names = names.iter().map(|s| &**s).collect::<Vec<_>>();
+
+    // files could have been modified after they were written, so we check
+    // that the entries are unique. This is done by sorting the entries, to
+    // avoid building the positions map when loading the labels.
+    match dtype {
+        LabelsDType::Int32 => {
+            let mut data = vec![0; header.shape[0] * names.len()];
+            match endianness {
+                Endianness::LittleEndian => reader.read_i32_into::<LittleEndian>(&mut data)?,
+                Endianness::BigEndian => reader.read_i32_into::<BigEndian>(&mut data)?,
+            }
 
-    check_for_extra_bytes(&mut reader)?;
+            check_for_extra_bytes(&mut reader)?;
+            let labels = Labels::new_assume_unique(names, data)?;
+            labels.check_unique()?;
+            return Ok(labels);
+        }
+        LabelsDType::Int64 => {
+            let mut data = vec![0; header.shape[0] * names.len()];
+            match endianness {
+                Endianness::LittleEndian => reader.read_i64_into::<LittleEndian>(&mut data)?,
+                Endianness::BigEndian => reader.read_i64_into::<BigEndian>(&mut data)?,
+            }
 
-    let mut builder = LabelsBuilder::new(names.iter().map(|s| &**s).collect())?;
-    for chunk in data.chunks_exact(names.len()) {
-        builder.add(&chunk.iter().map(|&i| LabelValue::new(i)).collect::<Vec<_>>())?;
+            check_for_extra_bytes(&mut reader)?;
+            let labels = Labels::new_assume_unique_i64(names, data)?;
+            labels.check_unique()?;
+            return Ok(labels);
+        }
     }
-
-    return Ok(builder.finish());
 }
 
 /// Write `Labels` to the writer using numpy's NPY format.
@@ -73,13 +93,18 @@ pub fn load_labels<R: std::io::Read>(mut reader: R) -> Result<Labels, Error> {
 /// See [`read_npy_labels`] for more information on how `Labels` are stored to
 /// files.
 pub fn save_labels<W: std::io::Write>(writer: &mut W, labels: &Labels) -> Result<(), Error> {
+    let typ = match labels.dtype() {
+        LabelsDType::Int32 => "i4",
+        LabelsDType::Int64 => "i8",
+    };
+
     let mut type_descriptor = Vec::new();
     for name in labels.names() {
         if cfg!(target_endian = "little") {
-            type_descriptor.push((name.into(), "<i4".into()));
+            type_descriptor.push((name.into(), format!("<{}", typ)));
         } else {
             assert!(cfg!(target_endian = "big"));
-            type_descriptor.push((name.into(), ">i4".into()));
+            type_descriptor.push((name.into(), format!(">{}", typ)));
         }
     }
 
@@ -90,9 +115,14 @@ pub fn save_labels<W: std::io::Write>(writer: &mut W, labels: &Labels) -> Result
     };
     header.write(&mut *writer)?;
 
-    for entry in labels {
-        for value in entry {
-            writer.write_i32::<NativeEndian>(value.i32())?;
+    if let Some(values) = labels.values_i32() {
+        for &value in values {
+            writer.write_i32::<NativeEndian>(value)?;
+        }
+    } else {
+        let values = labels.values_i64().expect("unknown Labels dtype");
+        for &value in values {
+            writer.write_i64::<NativeEndian>(value)?;
         }
     }
 
@@ -106,33 +136,28 @@ enum Endianness {
 }
 
 /// Check that the given type descriptor matches the expected one for Labels and
-/// return the corresponding set of names & endianness.
-fn check_type_descriptor(desc: &DataType) -> Result<(Vec<String>, Endianness), Error> {
+/// return the corresponding set of names, endianness & dtype.
+fn check_type_descriptor(desc: &DataType) -> Result<(Vec<String>, Endianness, LabelsDType), Error> {
     let mut names = Vec::new();
 
     let error = Error::Serialization("invalid dtype for labels".into());
 
-    let mut endianness = None;
+    let mut format = None;
     match desc {
         DataType::Compound(list) => {
             for (name, typ) in list {
-                if endianness.is_none() {
-                    if typ == "<i4" {
-                        endianness = Some(Endianness::LittleEndian);
-                    } else if typ == ">i4" {
-                        endianness = Some(Endianness::BigEndian);
-                    }
-                }
-
-                if typ == "<i4" {
-                    if endianness != Some(Endianness::LittleEndian) {
-                        return Err(error);
-                    }
-                } else if typ == ">i4" {
-                    if endianness != Some(Endianness::BigEndian) {
-                        return Err(error);
-                    }
-                } else {
+                let current = match &**typ {
+                    "<i4" => (Endianness::LittleEndian, LabelsDType::Int32),
+                    ">i4" => (Endianness::BigEndian, LabelsDType::Int32),
+                    "<i8" => (Endianness::LittleEndian, LabelsDType::Int64),
+                    ">i8" => (Endianness::BigEndian, LabelsDType::Int64),
+                    _ => return Err(error),
+                };
+
+                // all dimensions must use the same format
+                if format.is_none() {
+                    format = Some(current);
+                } else if format != Some(current) {
                     return Err(error);
                 }
 
@@ -144,5 +169,6 @@ fn check_type_descriptor(desc: &DataType) -> Result<(Vec<String>, Endianness), E
         }
     }
 
-    return Ok((names, endianness.expect("failed to find endianness")));
+    let (endianness, dtype) = format.expect("failed to find endianness");
+    return Ok((names, endianness, dtype));
 }
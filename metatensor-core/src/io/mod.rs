@@ -13,6 +13,7 @@ pub use self::block::looks_like_block_data;
 mod tensor;
 pub use self::tensor::load;
 pub use self::tensor::save;
+pub use self::tensor::save_shared_gradient_samples;
 pub use self::tensor::looks_like_tensormap_data;
 
 
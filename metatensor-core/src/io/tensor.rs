@@ -7,7 +7,7 @@ use crate::{TensorMap, Error, mts_array_t};
 
 use super::PathOrBuffer;
 use super::labels::{load_labels, save_labels};
-use super::block::{read_single_block, write_single_block};
+use super::block::{read_single_block, write_single_block, GradientsIndex, LabelsCache, SharedLabels};
 
 
 /// Check if the file/buffer in `data` looks like it could contain a serialized
@@ -74,6 +74,29 @@ pub fn looks_like_tensormap_data(mut data: PathOrBuffer) -> bool {
 ///                                                                     / <n_components>.npy
 ///                                                     /   values.npy
 /// ```
+///
+/// Gradients of gradients are stored in the same way, in a `gradients`
+/// directory inside the directory of the corresponding gradient, e.g.
+/// `blocks/<block_id>/gradients/<parameter>/gradients/<other>/samples.npy`.
+///
+/// When saving with [`save_shared_gradient_samples`], gradient samples shared
+/// by multiple gradients (i.e. gradients using the same `Arc<Labels>`, see
+/// `TensorMap::share_gradient_samples`) are only stored once, in
+/// `/gradient_samples/<index>.npy`. The corresponding gradients then contain a
+/// `samples_ref.npy` file instead of `samples.npy`, storing `<index>` as a 1-D
+/// array with a single 64-bit integer. The version of this layout is stored in
+/// `/gradient_samples/version.npy` as a 1-D array with a single 64-bit
+/// integer, currently 1. Both layouts can be loaded by this function.
+///
+/// Arrays storing their data in compressed sparse row format (see
+/// `mts_array_operations_t.sparse_csr`) are saved in a `values_csr` directory
+/// instead of `values.npy`. This directory contains `data.npy` with the
+/// non-zero values as 64-bit floats, `indices.npy` and `indptr.npy` with the
+/// column indexes and row pointers, and `shape.npy` with the full shape of
+/// the array, all as 64-bit integers. When loading such data, the sparse
+/// array is created with `mts_array_operations_t.create_sparse_csr` if it is
+/// available for the arrays created by `create_array`, and the data is stored
+/// in a dense array otherwise.
 pub fn load<R, F>(reader: R, create_array: F) -> Result<TensorMap, Error>
     where R: std::io::Read + std::io::Seek,
           F: Fn(Vec<usize>) -> Result<mts_array_t, Error>
@@ -93,11 +116,15 @@ pub fn load<R, F>(reader: R, create_array: F) -> Result<TensorMap, Error>
     }
 
     let mut blocks = Vec::new();
+    let mut cache = LabelsCache::default();
+    let gradients = GradientsIndex::new(&archive);
     for block_i in 0..keys.count() {
         blocks.push(read_single_block(
             &mut archive,
             &format!("blocks/{}/", block_i),
             None,
+            &mut cache,
+            &gradients,
             &create_array,
         )?,);
     }
@@ -109,8 +136,24 @@ pub fn load<R, F>(reader: R, create_array: F) -> Result<TensorMap, Error>
 /// Save the given tensor to a file (or any other writer).
 ///
 /// The format used is documented in the [`load`] function, and is based on
-/// numpy's NPZ format (i.e. zip archive containing NPY files).
+/// numpy's NPZ format (i.e. zip archive containing NPY files). All gradient
+/// samples are stored in the corresponding `samples.npy`, even if they are
+/// shared between multiple gradients.
 pub fn save<W: std::io::Write + std::io::Seek>(writer: W, tensor: &TensorMap) -> Result<(), Error> {
+    return save_impl(writer, tensor, false);
+}
+
+/// Save the given tensor to a file (or any other writer), storing gradient
+/// samples shared between multiple gradients only once.
+///
+/// The corresponding layout is documented in the [`load`] function. Files
+/// saved with this function can not be loaded by versions of metatensor-core
+/// older than 0.2.0.
+pub fn save_shared_gradient_samples<W: std::io::Write + std::io::Seek>(writer: W, tensor: &TensorMap) -> Result<(), Error> {
+    return save_impl(writer, tensor, true);
+}
+
+fn save_impl<W: std::io::Write + std::io::Seek>(writer: W, tensor: &TensorMap, share_gradient_samples: bool) -> Result<(), Error> {
     let mut archive = ZipWriter::new(writer);
 
     let options = zip::write::FileOptions::default()
@@ -122,8 +165,15 @@ pub fn save<W: std::io::Write + std::io::Seek>(writer: W, tensor: &TensorMap) ->
     archive.start_file(&path, options).map_err(|e| (path, e))?;
     save_labels(&mut archive, tensor.keys())?;
 
+    let shared = if share_gradient_samples {
+        SharedLabels::new(tensor)
+    } else {
+        SharedLabels::default()
+    };
+    shared.write(&mut archive)?;
+
     for (block_i, block) in tensor.blocks().iter().enumerate() {
-        write_single_block(&mut archive, &format!("blocks/{}/", block_i), true, block)?;
+        write_single_block(&mut archive, &format!("blocks/{}/", block_i), true, block, &shared)?;
     }
 
     archive.finish().map_err(|e| ("<root>".into(), e))?;
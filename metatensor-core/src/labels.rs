@@ -3,30 +3,33 @@ use std::sync::RwLock;
 use std::ffi::CString;
 use std::collections::BTreeSet;
 use std::os::raw::c_void;
+use std::hash::Hasher;
 
 use hashbrown::HashMap;
 use hashbrown::hash_map::RawEntryMut;
 
+use once_cell::sync::OnceCell;
 use smallvec::SmallVec;
 
 use crate::Error;
 use crate::utils::ConstCString;
 
-/// A single value inside a label. This is represented as a 32-bit signed
-/// integer, with a couple of helper function to get its value as usize/isize.
+/// A single value inside a label. This is represented as a 64-bit signed
+/// integer (which can hold the values of both 32-bit and 64-bit `Labels`),
+/// with a couple of helper function to get its value as usize/isize.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
-pub struct LabelValue(i32);
+pub struct LabelValue(i64);
 
 impl PartialEq<i32> for LabelValue {
     fn eq(&self, other: &i32) -> bool {
-        self.0 == *other
+        self.0 == i64::from(*other)
     }
 }
 
 impl PartialEq<LabelValue> for i32 {
     fn eq(&self, other: &LabelValue) -> bool {
-        *self == other.0
+        i64::from(*self) == other.0
     }
 }
 
@@ -42,73 +45,148 @@ impl std::fmt::Display for LabelValue {
     }
 }
 
-#[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
 impl From<u32> for LabelValue {
     fn from(value: u32) -> LabelValue {
-        assert!(value < i32::MAX as u32);
-        LabelValue(value as i32)
+        LabelValue(i64::from(value))
     }
 }
 
 impl From<i32> for LabelValue {
     fn from(value: i32) -> LabelValue {
+        LabelValue(i64::from(value))
+    }
+}
+
+impl From<i64> for LabelValue {
+    fn from(value: i64) -> LabelValue {
         LabelValue(value)
     }
 }
 
-#[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
 impl From<usize> for LabelValue {
     fn from(value: usize) -> LabelValue {
-        assert!(value < i32::MAX as usize);
-        LabelValue(value as i32)
+        LabelValue(i64::try_from(value).expect("value is too large for a LabelValue"))
     }
 }
 
-#[allow(clippy::cast_possible_truncation)]
 impl From<isize> for LabelValue {
     fn from(value: isize) -> LabelValue {
-        assert!(value < i32::MAX as isize && value > i32::MIN as isize);
-        LabelValue(value as i32)
+        LabelValue(value as i64)
     }
 }
 
 impl LabelValue {
     /// Create a `LabelValue` with the given `value`
     pub fn new(value: i32) -> LabelValue {
-        LabelValue(value)
+        LabelValue(i64::from(value))
     }
 
     /// Get the integer value of this `LabelValue` as a usize
-    #[allow(clippy::cast_sign_loss)]
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
     pub fn usize(self) -> usize {
         debug_assert!(self.0 >= 0);
         self.0 as usize
     }
 
     /// Get the integer value of this `LabelValue` as an isize
+    #[allow(clippy::cast_possible_truncation)]
     pub fn isize(self) -> isize {
         self.0 as isize
     }
 
     /// Get the integer value of this `LabelValue` as an i32
+    #[allow(clippy::cast_possible_truncation)]
     pub fn i32(self) -> i32 {
+        debug_assert!(self.try_i32().is_some());
+        self.0 as i32
+    }
+
+    /// Get the integer value of this `LabelValue` as an i32, if it fits in
+    /// 32 bits.
+    pub fn try_i32(self) -> Option<i32> {
+        i32::try_from(self.0).ok()
+    }
+
+    /// Get the integer value of this `LabelValue` as an i64
+    pub fn i64(self) -> i64 {
         self.0
     }
 }
 
+/// Integer type used to store the values of a set of `Labels`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LabelsDType {
+    /// 32-bit signed integers, this is the default
+    Int32,
+    /// 64-bit signed integers
+    Int64,
+}
+
+impl std::fmt::Display for LabelsDType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelsDType::Int32 => write!(f, "int32"),
+            LabelsDType::Int64 => write!(f, "int64"),
+        }
+    }
+}
+
+/// Actual storage for the values of `Labels`, as a linearized 2D array in
+/// row-major order
+#[derive(Clone, PartialEq, Eq)]
+enum LabelsValues {
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+}
+
+impl LabelsValues {
+    fn len(&self) -> usize {
+        match self {
+            LabelsValues::Int32(values) => values.len(),
+            LabelsValues::Int64(values) => values.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn empty(dtype: LabelsDType) -> LabelsValues {
+        match dtype {
+            LabelsDType::Int32 => LabelsValues::Int32(Vec::new()),
+            LabelsDType::Int64 => LabelsValues::Int64(Vec::new()),
+        }
+    }
+
+    fn dtype(&self) -> LabelsDType {
+        match self {
+            LabelsValues::Int32(_) => LabelsDType::Int32,
+            LabelsValues::Int64(_) => LabelsDType::Int64,
+        }
+    }
+}
+
 type DefaultHasher = std::hash::BuildHasherDefault<ahash::AHasher>;
 
 /// Builder for `Labels`, this should be used to construct `Labels`.
 pub struct LabelsBuilder {
     // cf `Labels` for the documentation of the fields
     names: Vec<ConstCString>,
+    dtype: LabelsDType,
     values: Vec<LabelValue>,
     positions: HashMap<SmallVec<[LabelValue; 4]>, usize, DefaultHasher>,
 }
 
 impl LabelsBuilder {
-    /// Create a new empty `LabelsBuilder` with the given `names`
+    /// Create a new empty `LabelsBuilder` with the given `names`, storing
+    /// values as 32-bit integers.
     pub fn new(names: Vec<&str>) -> Result<LabelsBuilder, Error> {
+        LabelsBuilder::new_with_dtype(names, LabelsDType::Int32)
+    }
+
+    /// Create a new empty `LabelsBuilder` with the given `names`, storing
+    /// values with the given `dtype`.
+    pub fn new_with_dtype(names: Vec<&str>, dtype: LabelsDType) -> Result<LabelsBuilder, Error> {
         for name in &names {
             if !is_valid_label_name(name) {
                 return Err(Error::InvalidParameter(format!(
@@ -132,6 +210,7 @@ impl LabelsBuilder {
 
         Ok(LabelsBuilder {
             names: names,
+            dtype: dtype,
             values: Vec::new(),
             positions: Default::default(),
         })
@@ -160,11 +239,21 @@ impl LabelsBuilder {
     /// Add a single `entry` to this set of labels.
     ///
     /// This function will return an `Error` when attempting to add the same
-    /// `label` more than once.
+    /// `label` more than once, or when adding a value that does not fit in
+    /// 32-bit integers to a builder with `LabelsDType::Int32`.
     pub fn add<T>(&mut self, entry: &[T]) -> Result<(), Error>
         where T: Copy + Into<LabelValue>
     {
-        let entry = entry.iter().copied().map(Into::into).collect::<SmallVec<_>>();
+        let entry = entry.iter().copied().map(Into::into).collect::<SmallVec<[LabelValue; 4]>>();
+        if self.dtype == LabelsDType::Int32 {
+            if let Some(value) = entry.iter().find(|v| v.try_i32().is_none()) {
+                return Err(Error::InvalidParameter(format!(
+                    "label value {} does not fit in a 32-bit integer, \
+                    use 64-bit Labels instead", value
+                )));
+            }
+        }
+
         match self.add_or_get_position(entry) {
             Ok(_) => return Ok(()),
             Err((existing, entry)) => {
@@ -200,21 +289,32 @@ impl LabelsBuilder {
     }
 
     /// Finish building the `Labels`
+    #[allow(clippy::cast_possible_truncation)]
     pub fn finish(self) -> Labels {
         if self.names.is_empty() {
             assert!(self.values.is_empty());
             return Labels {
                 names: Vec::new(),
-                values: Vec::new(),
-                positions: Default::default(),
+                values: LabelsValues::empty(self.dtype),
+                positions: OnceCell::new(),
+                fingerprint: OnceCell::new(),
                 user_data: RwLock::new(UserData::null()),
             }
         }
 
+        let values = match self.dtype {
+            // all values have been checked to fit in 32-bit in `add`
+            LabelsDType::Int32 => LabelsValues::Int32(self.values.iter().map(|v| v.0 as i32).collect()),
+            LabelsDType::Int64 => LabelsValues::Int64(self.values.iter().map(|v| v.0).collect()),
+        };
+
+        // we already had to compute the positions to check for uniqueness,
+        // so we can keep them around
         return Labels {
             names: self.names,
-            values: self.values,
-            positions: self.positions,
+            values: values,
+            positions: OnceCell::with_value(self.positions),
+            fingerprint: OnceCell::new(),
             user_data: RwLock::new(UserData::null()),
         };
     }
@@ -282,13 +382,21 @@ pub struct Labels {
     /// Names of the labels, stored as const C strings for easier integration
     /// with the C API
     names: Vec<ConstCString>,
-    /// Values of the labels, as a linearized 2D array in row-major order
-    values: Vec<LabelValue>,
+    /// Values of the labels, as a linearized 2D array in row-major order. The
+    /// values are stored either as 32-bit or 64-bit integers.
+    values: LabelsValues,
     /// Store the position of all the known labels, for faster access later.
-    /// This uses `XxHash64` instead of the default hasher in std since
-    /// `XxHash64` is much faster and we don't need the cryptographic strength
+    /// This uses `AHasher` instead of the default hasher in std since
+    /// `AHasher` is much faster and we don't need the cryptographic strength
     /// hash from std.
-    positions: HashMap<SmallVec<[LabelValue; 4]>, usize, DefaultHasher>,
+    ///
+    /// This map is initialized lazily on the first call to a function
+    /// requiring it (`position`, `contains`, `select`, ...), since a lot of
+    /// `Labels` are only ever iterated over.
+    positions: OnceCell<HashMap<SmallVec<[LabelValue; 4]>, usize, DefaultHasher>>,
+    /// Hash of the names and values of these labels, computed lazily by
+    /// `Labels::fingerprint`
+    fingerprint: OnceCell<u64>,
     /// Some data provided by the user that we should keep around (this is
     /// used to store a pointer to the on-GPU tensor in metatensor-torch).
     user_data: RwLock<UserData>,
@@ -296,7 +404,26 @@ pub struct Labels {
 
 impl PartialEq for Labels {
     fn eq(&self, other: &Self) -> bool {
-        self.names == other.names && self.values == other.values
+        if std::ptr::eq(self, other) {
+            return true;
+        }
+
+        if self.fingerprint() != other.fingerprint() {
+            return false;
+        }
+
+        if self.names != other.names {
+            return false;
+        }
+
+        match (&self.values, &other.values) {
+            (LabelsValues::Int32(first), LabelsValues::Int32(second)) => first == second,
+            (LabelsValues::Int64(first), LabelsValues::Int64(second)) => first == second,
+            (LabelsValues::Int32(first), LabelsValues::Int64(second)) |
+            (LabelsValues::Int64(second), LabelsValues::Int32(first)) => {
+                first.len() == second.len() && first.iter().zip(second).all(|(&a, &b)| i64::from(a) == b)
+            }
+        }
     }
 }
 
@@ -323,11 +450,180 @@ impl std::fmt::Debug for Labels {
 }
 
 impl Labels {
+    /// Create a new set of `Labels` with the given `names` and 32-bit
+    /// `values`, without checking that all the entries in `values` are unique.
+    ///
+    /// `values` should contain the entries of the labels as a linearized 2D
+    /// array in row-major order. This is intended for `Labels` coming from a
+    /// trusted source (e.g. files written by `save_labels`), and skips the
+    /// construction of the positions map until it is actually needed.
+    pub fn new_assume_unique(names: Vec<&str>, values: Vec<i32>) -> Result<Labels, Error> {
+        return Labels::new_assume_unique_impl(names, LabelsValues::Int32(values));
+    }
+
+    /// Same as `Labels::new_assume_unique`, but for 64-bit `values`.
+    pub fn new_assume_unique_i64(names: Vec<&str>, values: Vec<i64>) -> Result<Labels, Error> {
+        return Labels::new_assume_unique_impl(names, LabelsValues::Int64(values));
+    }
+
+    fn new_assume_unique_impl(names: Vec<&str>, values: LabelsValues) -> Result<Labels, Error> {
+        let builder = LabelsBuilder::new_with_dtype(names, values.dtype())?;
+
+        if builder.size() == 0 {
+            if !values.is_empty() {
+                return Err(Error::InvalidParameter(
+                    "can not have values in Labels without any dimension".into()
+                ));
+            }
+            return Ok(builder.finish());
+        }
+
+        if values.len() % builder.size() != 0 {
+            return Err(Error::InvalidParameter(format!(
+                "the number of values ({}) is not a multiple of the number of \
+                dimensions ({}) in Labels", values.len(), builder.size()
+            )));
+        }
+
+        return Ok(Labels {
+            names: builder.names,
+            values: values,
+            positions: OnceCell::new(),
+            fingerprint: OnceCell::new(),
+            user_data: RwLock::new(UserData::null()),
+        });
+    }
+
+    /// Check that all the entries in these `Labels` are unique, returning an
+    /// error otherwise.
+    ///
+    /// This sorts the entries instead of building the positions map, and is
+    /// intended to validate `Labels` created with `Labels::new_assume_unique`
+    /// from an untrusted source.
+    pub fn check_unique(&self) -> Result<(), Error> {
+        fn duplicated<T: Ord>(values: &[T], size: usize) -> Option<(usize, usize)> {
+            let entry = |i: usize| &values[(i * size)..((i + 1) * size)];
+
+            let mut order = (0..(values.len() / size)).collect::<Vec<_>>();
+            order.sort_unstable_by(|&a, &b| entry(a).cmp(entry(b)).then(a.cmp(&b)));
+
+            return order.windows(2)
+                .find(|pair| entry(pair[0]) == entry(pair[1]))
+                .map(|pair| (pair[0], pair[1]));
+        }
+
+        if self.size() == 0 {
+            return Ok(());
+        }
+
+        let duplicated = match &self.values {
+            LabelsValues::Int32(values) => duplicated(values, self.size()),
+            LabelsValues::Int64(values) => duplicated(values, self.size()),
+        };
+
+        if let Some((existing, duplicate)) = duplicated {
+            let values_display = self.entry(duplicate).iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+            return Err(Error::InvalidParameter(format!(
+                "can not have the same label value multiple time: [{}] is already present at position {}",
+                values_display, existing
+            )));
+        }
+
+        return Ok(());
+    }
+
+    /// Get a 64-bit fingerprint of the content (names and values) of these
+    /// `Labels`.
+    ///
+    /// Labels that compare equal have the same fingerprint, regardless of the
+    /// dtype used to store their values; while different fingerprints mean the
+    /// labels are different. The fingerprint is computed on the first call to
+    /// this function and then cached. It is not guaranteed to be stable across
+    /// different versions of metatensor.
+    pub fn fingerprint(&self) -> u64 {
+        *self.fingerprint.get_or_init(|| {
+            let mut hasher = ahash::AHasher::default();
+            hasher.write_usize(self.names.len());
+            for name in &self.names {
+                hasher.write(name.as_str().as_bytes());
+                // separator, to make sure ["ab", "c"] and ["a", "bc"] have
+                // different hashes
+                hasher.write_u8(0xff);
+            }
+
+            match &self.values {
+                LabelsValues::Int32(values) => {
+                    for &value in values {
+                        hasher.write_i64(i64::from(value));
+                    }
+                }
+                LabelsValues::Int64(values) => {
+                    for &value in values {
+                        hasher.write_i64(value);
+                    }
+                }
+            }
+
+            return hasher.finish();
+        })
+    }
+
+    /// Get the map from entries to positions, computing it if needed
+    fn positions(&self) -> &HashMap<SmallVec<[LabelValue; 4]>, usize, DefaultHasher> {
+        self.positions.get_or_init(|| {
+            let mut positions = HashMap::default();
+            if self.size() == 0 {
+                return positions;
+            }
+
+            positions.reserve(self.count());
+            for (i, entry) in self.iter().enumerate() {
+                // if there are duplicated entries, keep the first one
+                positions.entry(entry.to_vec()).or_insert(i);
+            }
+
+            return positions;
+        })
+    }
+
     /// Get the number of entries/named values in a single label
     pub fn size(&self) -> usize {
         self.names.len()
     }
 
+    /// Get the type used to store the values of these labels
+    pub fn dtype(&self) -> LabelsDType {
+        self.values.dtype()
+    }
+
+    /// Get the values of these labels as a linearized 2D array of 32-bit
+    /// integers, or `None` if the labels use another dtype.
+    pub fn values_i32(&self) -> Option<&[i32]> {
+        match &self.values {
+            LabelsValues::Int32(values) => Some(values),
+            LabelsValues::Int64(_) => None,
+        }
+    }
+
+    /// Get the values of these labels as a linearized 2D array of 64-bit
+    /// integers, or `None` if the labels use another dtype.
+    pub fn values_i64(&self) -> Option<&[i64]> {
+        match &self.values {
+            LabelsValues::Int32(_) => None,
+            LabelsValues::Int64(values) => Some(values),
+        }
+    }
+
+    /// Get the entry at the given `index` in these labels
+    pub fn entry(&self, index: usize) -> LabelsEntry<'_> {
+        let start = index * self.size();
+        let stop = (index + 1) * self.size();
+        match &self.values {
+            LabelsValues::Int32(values) => LabelsEntry::Int32(&values[start..stop]),
+            LabelsValues::Int64(values) => LabelsEntry::Int64(&values[start..stop]),
+        }
+    }
+
     /// Get the names of the entries/columns in this set of labels
     pub fn names(&self) -> Vec<&str> {
         self.names.iter().map(|s| s.as_str()).collect()
@@ -381,7 +677,7 @@ impl Labels {
 
     /// Check whether the given `label` is part of this set of labels
     pub fn contains(&self, label: &[LabelValue]) -> bool {
-        self.positions.contains_key(label)
+        self.positions().contains_key(label)
     }
 
     /// Get the position (i.e. row index) of the given label in the full labels
@@ -389,15 +685,90 @@ impl Labels {
     pub fn position(&self, value: &[LabelValue]) -> Option<usize> {
         assert!(value.len() == self.size(), "invalid size of index in Labels::position");
 
-        self.positions.get(value).copied()
+        self.positions().get(value).copied()
     }
 
     /// Iterate over the entries in this set of labels
     pub fn iter(&self) -> Iter {
-        debug_assert!(self.values.len() % self.names.len() == 0);
-        return Iter {
-            chunks: self.values.chunks_exact(self.names.len())
-        };
+        // `chunks_exact` does not accept a size of 0, but there are no values
+        // in labels without dimensions
+        let size = usize::max(self.size(), 1);
+        match &self.values {
+            LabelsValues::Int32(values) => Iter::Int32(values.chunks_exact(size)),
+            LabelsValues::Int64(values) => Iter::Int64(values.chunks_exact(size)),
+        }
+    }
+
+    /// Add a new `entry` at the end of these labels, returning an error if
+    /// the entry is already present or if it does not fit in the dtype of
+    /// these labels.
+    ///
+    /// Contrary to creating new labels with `LabelsBuilder`, this only costs
+    /// O(1) once the positions of the existing entries are known.
+    pub fn push(&mut self, entry: &[LabelValue]) -> Result<(), Error> {
+        assert_eq!(
+            self.size(), entry.len(),
+            "wrong size for added label: got {}, but expected {}",
+            entry.len(), self.size()
+        );
+
+        if let Some(existing) = self.position(entry) {
+            let values_display = entry.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+            return Err(Error::InvalidParameter(format!(
+                "can not have the same label value multiple time: [{}] is already present at position {}",
+                values_display, existing
+            )));
+        }
+
+        let new_position = self.count();
+        match self.values {
+            LabelsValues::Int32(ref mut values) => {
+                if let Some(value) = entry.iter().find(|v| v.try_i32().is_none()) {
+                    return Err(Error::InvalidParameter(format!(
+                        "label value {} does not fit in a 32-bit integer, \
+                        use 64-bit Labels instead", value
+                    )));
+                }
+                values.extend(entry.iter().map(|v| v.i32()));
+            }
+            LabelsValues::Int64(ref mut values) => {
+                values.extend(entry.iter().map(|v| v.0));
+            }
+        }
+
+        self.positions.get_mut().expect("positions should be initialized").insert(entry.into(), new_position);
+        self.modified();
+
+        return Ok(());
+    }
+
+    /// Remove the entry at the given `index` from these labels
+    pub fn remove(&mut self, index: usize) {
+        assert!(index < self.count(), "index out of bounds in Labels::remove");
+
+        let entry = self.entry(index).to_vec();
+        let range = (index * self.size())..((index + 1) * self.size());
+        match self.values {
+            LabelsValues::Int32(ref mut values) => { values.drain(range); }
+            LabelsValues::Int64(ref mut values) => { values.drain(range); }
+        }
+
+        if let Some(positions) = self.positions.get_mut() {
+            positions.remove(&entry);
+            for position in positions.values_mut() {
+                if *position > index {
+                    *position -= 1;
+                }
+            }
+        }
+        self.modified();
+    }
+
+    /// Reset the data computed from the values of these labels after they
+    /// have been modified
+    fn modified(&mut self) {
+        self.fingerprint = OnceCell::new();
+        *self.user_data.get_mut().expect("lock got poisoned") = UserData::null();
     }
 
     /// Compute the union of two labels, and optionally the mapping from the
@@ -412,10 +783,12 @@ impl Labels {
             ));
         }
 
+        // the union of 32-bit and 64-bit labels uses 64-bit values
         let mut builder = LabelsBuilder {
             names: self.names.clone(),
-            values: self.values.clone(),
-            positions: self.positions.clone(),
+            dtype: std::cmp::max(self.dtype(), other.dtype()),
+            values: self.iter().flatten().collect(),
+            positions: self.positions().clone(),
         };
 
         if !first_mapping.is_empty() {
@@ -427,8 +800,7 @@ impl Labels {
         }
 
         for (i, entry) in other.iter().enumerate() {
-            let entry = entry.iter().copied().map(Into::into).collect::<SmallVec<_>>();
-            let position = builder.add_or_get_position(entry);
+            let position = builder.add_or_get_position(entry.to_vec());
 
             if !second_mapping.is_empty() {
                 let index = match position {
@@ -473,12 +845,14 @@ impl Labels {
             second_indexes.fill(-1);
         }
 
-        let mut builder = LabelsBuilder::new(self.names()).expect("should be valid names");
+        let dtype = std::cmp::max(self.dtype(), other.dtype());
+        let mut builder = LabelsBuilder::new_with_dtype(self.names(), dtype).expect("should be valid names");
         for (i, entry) in first.iter().enumerate() {
-            if let Some(position) = second.position(entry) {
+            let entry = entry.to_vec();
+            if let Some(position) = second.position(&entry) {
                 #[allow(clippy::cast_possible_wrap)]
                 let new_position = builder.count() as i64;
-                builder.add(entry).expect("should not already exist");
+                builder.add(&entry).expect("should not already exist");
 
                 if !first_indexes.is_empty() {
                     first_indexes[i] = new_position;
@@ -514,7 +888,7 @@ impl Labels {
         if selection.names == self.names {
             for entry in selection {
                 #[allow(clippy::cast_possible_wrap)]
-                if let Some(position) = self.position(entry) {
+                if let Some(position) = self.position(&entry.to_vec()) {
                     selected[n_selected] = position as i64;
                     n_selected += 1;
                 }
@@ -536,7 +910,7 @@ impl Labels {
             let mut candidate = vec![LabelValue::new(0); dimensions_to_match.len()];
             for (entry_i, entry) in self.iter().enumerate() {
                 for (i, &d) in dimensions_to_match.iter().enumerate() {
-                    candidate[i] = entry[d];
+                    candidate[i] = entry.get(d);
                 }
 
                 #[allow(clippy::cast_possible_wrap)]
@@ -551,42 +925,154 @@ impl Labels {
     }
 }
 
+/// A single entry in a set of `Labels`, borrowing the values of the labels
+/// without copying them. Depending on the dtype of the labels, the values are
+/// stored as 32-bit or 64-bit integers, and they can be accessed as
+/// `LabelValue` with `LabelsEntry::get` or `LabelsEntry::iter`.
+#[derive(Clone, Copy)]
+pub enum LabelsEntry<'a> {
+    /// Entry in labels using `LabelsDType::Int32`
+    Int32(&'a [i32]),
+    /// Entry in labels using `LabelsDType::Int64`
+    Int64(&'a [i64]),
+}
+
+impl<'a> LabelsEntry<'a> {
+    /// Get the number of values in this entry
+    pub fn len(&self) -> usize {
+        match self {
+            LabelsEntry::Int32(values) => values.len(),
+            LabelsEntry::Int64(values) => values.len(),
+        }
+    }
+
+    /// Check if this entry is empty (contains no values)
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the value at the given `index` in this entry.
+    ///
+    /// This function panics if the `index` is out of bounds.
+    pub fn get(&self, index: usize) -> LabelValue {
+        match self {
+            LabelsEntry::Int32(values) => LabelValue::from(values[index]),
+            LabelsEntry::Int64(values) => LabelValue::from(values[index]),
+        }
+    }
+
+    /// Iterate over the values in this entry
+    pub fn iter(&self) -> LabelsEntryIter<'a> {
+        match *self {
+            LabelsEntry::Int32(values) => LabelsEntryIter::Int32(values.iter()),
+            LabelsEntry::Int64(values) => LabelsEntryIter::Int64(values.iter()),
+        }
+    }
+
+    /// Copy the values of this entry
+    pub fn to_vec(self) -> SmallVec<[LabelValue; 4]> {
+        self.iter().collect()
+    }
+}
+
+impl PartialEq for LabelsEntry<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (LabelsEntry::Int32(first), LabelsEntry::Int32(second)) => first == second,
+            (LabelsEntry::Int64(first), LabelsEntry::Int64(second)) => first == second,
+            _ => self.len() == other.len() && self.iter().eq(other.iter()),
+        }
+    }
+}
+
+impl Eq for LabelsEntry<'_> {}
+
+impl PartialEq<[LabelValue]> for LabelsEntry<'_> {
+    fn eq(&self, other: &[LabelValue]) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter().copied())
+    }
+}
+
+impl std::fmt::Debug for LabelsEntry<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for LabelsEntry<'a> {
+    type IntoIter = LabelsEntryIter<'a>;
+    type Item = LabelValue;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the values in a single `LabelsEntry`
+#[derive(Clone)]
+pub enum LabelsEntryIter<'a> {
+    #[doc(hidden)]
+    Int32(std::slice::Iter<'a, i32>),
+    #[doc(hidden)]
+    Int64(std::slice::Iter<'a, i64>),
+}
+
+impl Iterator for LabelsEntryIter<'_> {
+    type Item = LabelValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            LabelsEntryIter::Int32(values) => values.next().map(|&v| LabelValue::from(v)),
+            LabelsEntryIter::Int64(values) => values.next().map(|&v| LabelValue::from(v)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            LabelsEntryIter::Int32(values) => values.size_hint(),
+            LabelsEntryIter::Int64(values) => values.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for LabelsEntryIter<'_> {}
+
 /// iterator over `Labels` entries
-pub struct Iter<'a> {
-    chunks: std::slice::ChunksExact<'a, LabelValue>,
+#[derive(Clone)]
+pub enum Iter<'a> {
+    #[doc(hidden)]
+    Int32(std::slice::ChunksExact<'a, i32>),
+    #[doc(hidden)]
+    Int64(std::slice::ChunksExact<'a, i64>),
 }
 
 impl<'a> Iterator for Iter<'a> {
-    type Item = &'a [LabelValue];
+    type Item = LabelsEntry<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.chunks.next()
+        match self {
+            Iter::Int32(chunks) => chunks.next().map(LabelsEntry::Int32),
+            Iter::Int64(chunks) => chunks.next().map(LabelsEntry::Int64),
+        }
     }
-}
 
-impl<'a> ExactSizeIterator for Iter<'a> {
-    fn len(&self) -> usize {
-        self.chunks.len()
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Iter::Int32(chunks) => chunks.size_hint(),
+            Iter::Int64(chunks) => chunks.size_hint(),
+        }
     }
 }
 
+impl ExactSizeIterator for Iter<'_> {}
+
 impl<'a> IntoIterator for &'a Labels {
     type IntoIter = Iter<'a>;
-    type Item = &'a [LabelValue];
+    type Item = LabelsEntry<'a>;
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
     }
 }
 
-impl std::ops::Index<usize> for Labels {
-    type Output = [LabelValue];
-    fn index(&self, i: usize) -> &[LabelValue] {
-        let start = i * self.size();
-        let stop = (i + 1) * self.size();
-        &self.values[start..stop]
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -600,6 +1086,36 @@ mod tests {
         assert_eq!(e.to_string(), "invalid parameter: labels names must be unique, got 'not' multiple times");
     }
 
+    #[test]
+    fn push_remove() {
+        let mut builder = LabelsBuilder::new(vec!["aa", "bb"]).unwrap();
+        builder.add(&[0, 1]).unwrap();
+        builder.add(&[1, 2]).unwrap();
+        builder.add(&[2, 3]).unwrap();
+        let mut labels = builder.finish();
+        let fingerprint = labels.fingerprint();
+
+        labels.remove(0);
+        assert_eq!(labels.values_i32().unwrap(), &[1, 2, 2, 3]);
+        assert_eq!(labels.position(&[LabelValue::new(2), LabelValue::new(3)]), Some(1));
+        assert_eq!(labels.position(&[LabelValue::new(0), LabelValue::new(1)]), None);
+
+        labels.push(&[LabelValue::new(0), LabelValue::new(1)]).unwrap();
+        assert_eq!(labels.values_i32().unwrap(), &[1, 2, 2, 3, 0, 1]);
+        assert_eq!(labels.position(&[LabelValue::new(0), LabelValue::new(1)]), Some(2));
+        assert_ne!(labels.fingerprint(), fingerprint);
+
+        let error = labels.push(&[LabelValue::new(2), LabelValue::new(3)]).unwrap_err();
+        assert_eq!(error.to_string(),
+            "invalid parameter: can not have the same label value multiple time: [2, 3] is already present at position 1"
+        );
+
+        let error = labels.push(&[LabelValue::from(i64::MAX), LabelValue::new(3)]).unwrap_err();
+        assert_eq!(error.to_string(), format!(
+            "invalid parameter: label value {} does not fit in a 32-bit integer, use 64-bit Labels instead", i64::MAX
+        ));
+    }
+
     #[test]
     fn union() {
         let mut builder = LabelsBuilder::new(vec!["aa", "bb"]).unwrap();
@@ -618,7 +1134,7 @@ mod tests {
 
         let union = first.union(&second, first_mapping, second_mapping).unwrap();
         assert_eq!(union.names(), ["aa", "bb"]);
-        assert_eq!(union.values, &[0, 1, 1, 2, 2, 3, 4, 5]);
+        assert_eq!(union.values_i32().unwrap(), &[0, 1, 1, 2, 2, 3, 4, 5]);
         assert_eq!(first_mapping, &[0, 1]);
         assert_eq!(second_mapping, &[2, 1, 3]);
 
@@ -627,7 +1143,7 @@ mod tests {
 
         let union = second.union(&first, first_mapping, second_mapping).unwrap();
         assert_eq!(union.names(), ["aa", "bb"]);
-        assert_eq!(union.values, &[2, 3, 1, 2, 4, 5, 0, 1]);
+        assert_eq!(union.values_i32().unwrap(), &[2, 3, 1, 2, 4, 5, 0, 1]);
         assert_eq!(first_mapping, &[0, 1, 2]);
         assert_eq!(second_mapping, &[3, 1]);
 
@@ -645,7 +1161,7 @@ mod tests {
 
         let union = first.union(&empty, first_mapping, second_mapping).unwrap();
         assert_eq!(union.names(), ["aa", "bb"]);
-        assert_eq!(union.values, &[0, 1, 1, 2]);
+        assert_eq!(union.values_i32().unwrap(), &[0, 1, 1, 2]);
         assert_eq!(first_mapping, &[0, 1]);
         assert_eq!(second_mapping, &[]);
     }
@@ -668,7 +1184,7 @@ mod tests {
 
         let intersection = first.intersection(&second, first_mapping, second_mapping).unwrap();
         assert_eq!(intersection.names(), ["aa", "bb"]);
-        assert_eq!(intersection.values, &[1, 2]);
+        assert_eq!(intersection.values_i32().unwrap(), &[1, 2]);
         assert_eq!(first_mapping, &[-1, 0]);
         assert_eq!(second_mapping, &[-1, 0, -1]);
 
@@ -677,7 +1193,7 @@ mod tests {
 
         let intersection = second.intersection(&first, first_mapping, second_mapping).unwrap();
         assert_eq!(intersection.names(), ["aa", "bb"]);
-        assert_eq!(intersection.values, &[1, 2]);
+        assert_eq!(intersection.values_i32().unwrap(), &[1, 2]);
         assert_eq!(first_mapping, &[-1, 0, -1]);
         assert_eq!(second_mapping, &[-1, 0]);
 
@@ -700,6 +1216,116 @@ mod tests {
         assert_eq!(second_mapping, &[]);
     }
 
+    #[test]
+    fn lazy_positions() {
+        let labels = Labels::new_assume_unique(
+            vec!["aa", "bb"],
+            vec![0, 1, 1, 2],
+        ).unwrap();
+        assert!(labels.positions.get().is_none());
+
+        assert_eq!(labels.count(), 2);
+        assert_eq!(labels.entry(1).to_vec().as_slice(), [1, 2]);
+        assert!(labels.positions.get().is_none());
+
+        assert_eq!(labels.position(&[LabelValue::new(1), LabelValue::new(2)]), Some(1));
+        assert!(labels.contains(&[LabelValue::new(0), LabelValue::new(1)]));
+        assert!(!labels.contains(&[LabelValue::new(0), LabelValue::new(2)]));
+        assert!(labels.positions.get().is_some());
+
+        // labels created with the builder already have their positions
+        let mut builder = LabelsBuilder::new(vec!["aa"]).unwrap();
+        builder.add(&[3]).unwrap();
+        let labels = builder.finish();
+        assert!(labels.positions.get().is_some());
+
+        let labels = Labels::new_assume_unique(vec!["aa", "bb"], vec![0, 1, 1, 2, 3, 2, 1, 2]).unwrap();
+        let err = labels.check_unique().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid parameter: can not have the same label value multiple time: \
+            [1, 2] is already present at position 1"
+        );
+
+        let labels = Labels::new_assume_unique_i64(vec!["aa"], vec![4, 1, 2]).unwrap();
+        labels.check_unique().unwrap();
+
+        let err = Labels::new_assume_unique(vec!["aa", "bb"], vec![0]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid parameter: the number of values (1) is not a multiple of \
+            the number of dimensions (2) in Labels"
+        );
+    }
+
+    #[test]
+    fn int64_labels() {
+        let large = i64::from(i32::MAX) + 10;
+
+        let mut builder = LabelsBuilder::new(vec!["aa"]).unwrap();
+        let err = builder.add(&[large]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid parameter: label value 2147483657 does not fit in a \
+            32-bit integer, use 64-bit Labels instead"
+        );
+
+        let mut builder = LabelsBuilder::new_with_dtype(vec!["aa"], LabelsDType::Int64).unwrap();
+        builder.add(&[large]).unwrap();
+        builder.add(&[3]).unwrap();
+        let first = builder.finish();
+        assert_eq!(first.dtype(), LabelsDType::Int64);
+        assert_eq!(first.values_i64().unwrap(), [large, 3]);
+        assert!(first.values_i32().is_none());
+        assert_eq!(first.position(&[LabelValue::from(large)]), Some(0));
+
+        let mut builder = LabelsBuilder::new(vec!["aa"]).unwrap();
+        builder.add(&[3]).unwrap();
+        builder.add(&[4]).unwrap();
+        let second = builder.finish();
+        assert_eq!(second.dtype(), LabelsDType::Int32);
+
+        // mixing 32-bit and 64-bit labels gives 64-bit labels
+        let union = second.union(&first, &mut [], &mut []).unwrap();
+        assert_eq!(union.dtype(), LabelsDType::Int64);
+        assert_eq!(union.values_i64().unwrap(), [3, 4, large]);
+
+        let intersection = second.intersection(&first, &mut [], &mut []).unwrap();
+        assert_eq!(intersection.dtype(), LabelsDType::Int64);
+        assert_eq!(intersection.values_i64().unwrap(), [3]);
+
+        // equality only depends on the values, not the dtype
+        let converted = Labels::new_assume_unique_i64(vec!["aa"], vec![3, 4]).unwrap();
+        assert_eq!(converted, second);
+    }
+
+    #[test]
+    fn fingerprint() {
+        let mut builder = LabelsBuilder::new(vec!["aa", "bb"]).unwrap();
+        builder.add(&[0, 1]).unwrap();
+        builder.add(&[1, 2]).unwrap();
+        let first = builder.finish();
+
+        let second = Labels::new_assume_unique(vec!["aa", "bb"], vec![0, 1, 1, 2]).unwrap();
+        assert_eq!(first.fingerprint(), second.fingerprint());
+        assert_eq!(first, second);
+
+        // the dtype does not change the fingerprint
+        let second = Labels::new_assume_unique_i64(vec!["aa", "bb"], vec![0, 1, 1, 2]).unwrap();
+        assert_eq!(first.fingerprint(), second.fingerprint());
+
+        let second = Labels::new_assume_unique(vec!["aa", "bb"], vec![0, 1, 1, 3]).unwrap();
+        assert_ne!(first.fingerprint(), second.fingerprint());
+        assert_ne!(first, second);
+
+        let second = Labels::new_assume_unique(vec!["aa", "cc"], vec![0, 1, 1, 2]).unwrap();
+        assert_ne!(first.fingerprint(), second.fingerprint());
+
+        let first = Labels::new_assume_unique(vec!["ab", "c"], vec![]).unwrap();
+        let second = Labels::new_assume_unique(vec!["a", "bc"], vec![]).unwrap();
+        assert_ne!(first.fingerprint(), second.fingerprint());
+    }
+
     #[test]
     fn marker_traits() {
         // ensure Arc<Labels> is Send and Sync, assuming the user data is
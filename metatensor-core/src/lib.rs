@@ -12,17 +12,22 @@
 mod utils;
 
 mod labels;
-use self::labels::{LabelsBuilder, LabelValue, Labels};
+use self::labels::{LabelsBuilder, LabelsEntry, LabelValue, Labels};
 
 mod data;
 use self::data::{mts_array_t, mts_sample_mapping_t, mts_data_origin_t};
 use self::data::{register_data_origin, get_data_origin};
+use self::data::{mts_array_operations_t, mts_array_descriptor_t, register_array_operations};
+use self::data::mts_index_mapping_t;
+use self::data::{mts_array_converter_t, register_array_converter};
+
+mod dlpack;
 
 mod blocks;
 use self::blocks::TensorBlock;
 
 mod tensor;
-use self::tensor::TensorMap;
+use self::tensor::{TensorMap, TensorMapBuilder};
 
 #[doc(hidden)]
 mod c_api;
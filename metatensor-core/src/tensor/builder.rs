@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+
+use crate::labels::{LabelsBuilder, LabelsDType, LabelValue};
+use crate::{Error, TensorBlock};
+
+use super::TensorMap;
+use super::utils::KeyAndBlock;
+use super::keys_to_samples::merge_blocks_along_samples;
+
+/// Chunks of data added to a `TensorMapBuilder` for a single key
+struct PartialBlock {
+    chunks: Vec<TensorBlock>,
+    /// all the samples in `chunks`, used to check for duplicates
+    samples: HashSet<Vec<LabelValue>>,
+}
+
+/// Builder creating a `TensorMap` from chunks of data given in any order.
+///
+/// Each chunk is a `TensorBlock` associated with a key. Chunks with the same
+/// key are merged together along the samples axis when calling `finish`, and
+/// must have the same metadata (except for the samples) and gradients. The
+/// blocks are stored in the final `TensorMap` in the order in which their key
+/// was first seen.
+pub struct TensorMapBuilder {
+    keys_names: Vec<String>,
+    sort_samples: bool,
+    blocks: IndexMap<Vec<LabelValue>, PartialBlock>,
+}
+
+fn key_to_string(key: &[LabelValue]) -> String {
+    key.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+impl TensorMapBuilder {
+    /// Create a new empty `TensorMapBuilder`, for keys with the given `names`.
+    ///
+    /// If `sort_samples` is true, the samples of the blocks will be
+    /// lexicographically sorted in the final `TensorMap`. Otherwise they are
+    /// kept in the order in which they were added, like for
+    /// `TensorMap::keys_to_samples`.
+    pub fn new(keys_names: Vec<&str>, sort_samples: bool) -> Result<TensorMapBuilder, Error> {
+        if keys_names.is_empty() {
+            return Err(Error::InvalidParameter(
+                "can not create a TensorMapBuilder without keys dimensions".into()
+            ));
+        }
+
+        // validate the names
+        LabelsBuilder::new(keys_names.clone())?;
+
+        return Ok(TensorMapBuilder {
+            keys_names: keys_names.into_iter().map(String::from).collect(),
+            sort_samples,
+            blocks: IndexMap::new(),
+        });
+    }
+
+    /// Add a new `chunk` of data associated with the given `key`.
+    ///
+    /// The samples of the chunk must not already be present in previous
+    /// chunks for the same key. The chunk is consumed by this function, and
+    /// dropped if it returns an error.
+    pub fn add(&mut self, key: &[LabelValue], chunk: TensorBlock) -> Result<(), Error> {
+        if key.len() != self.keys_names.len() {
+            return Err(Error::InvalidParameter(format!(
+                "expected a key with {} values to add a chunk, got {}",
+                self.keys_names.len(), key.len()
+            )));
+        }
+
+        let partial = self.blocks.entry(key.to_vec()).or_insert_with(|| PartialBlock {
+            chunks: Vec::new(),
+            samples: HashSet::new(),
+        });
+
+        if let Some(first) = partial.chunks.first() {
+            if let Some(mismatch) = metadata_mismatch(first, &chunk)? {
+                return Err(Error::InvalidParameter(format!(
+                    "the chunk for key [{}] has different {} than the previous chunks for this key",
+                    key_to_string(key), mismatch
+                )));
+            }
+        }
+
+        for sample in &*chunk.samples {
+            let sample = sample.to_vec();
+            if partial.samples.contains(&sample[..]) {
+                return Err(Error::InvalidParameter(format!(
+                    "sample [{}] is present in multiple chunks for key [{}]",
+                    key_to_string(&sample), key_to_string(key)
+                )));
+            }
+        }
+        partial.samples.extend(chunk.samples.iter().map(|s| s.iter().collect()));
+        partial.chunks.push(chunk);
+
+        return Ok(());
+    }
+
+    /// Merge all the chunks for each key and create the corresponding
+    /// `TensorMap`.
+    pub fn finish(self) -> Result<TensorMap, Error> {
+        let dtype = if self.blocks.keys().flatten().all(|v| v.try_i32().is_some()) {
+            LabelsDType::Int32
+        } else {
+            LabelsDType::Int64
+        };
+
+        let names = self.keys_names.iter().map(|n| &**n).collect();
+        let mut keys = LabelsBuilder::new_with_dtype(names, dtype)?;
+        keys.reserve(self.blocks.len());
+
+        let mut blocks = Vec::new();
+        for (key, mut partial) in self.blocks {
+            keys.add(&key)?;
+
+            if partial.chunks.len() == 1 && !self.sort_samples {
+                blocks.push(partial.chunks.remove(0));
+                continue;
+            }
+
+            let chunks = partial.chunks.iter()
+                .map(|block| KeyAndBlock { key: Vec::new(), block })
+                .collect::<Vec<_>>();
+
+            blocks.push(merge_blocks_along_samples(&chunks, &[], dtype, self.sort_samples)?);
+        }
+
+        return TensorMap::new(Arc::new(keys.finish()), blocks);
+    }
+}
+
+/// Check that `chunk` has the same metadata as `first`, except for the
+/// samples, returning a description of the first mismatch if any
+fn metadata_mismatch(first: &TensorBlock, chunk: &TensorBlock) -> Result<Option<&'static str>, Error> {
+    if first.samples.names() != chunk.samples.names() {
+        return Ok(Some("samples names"));
+    }
+
+    if first.components != chunk.components {
+        return Ok(Some("components"));
+    }
+
+    if first.properties != chunk.properties {
+        return Ok(Some("properties"));
+    }
+
+    if first.values.origin()? != chunk.values.origin()? {
+        return Ok(Some("data origin"));
+    }
+
+    // arrays with an unknown descriptor are checked when creating the
+    // TensorMap, in the same way as `TensorMap::new` does
+    if let (Some(first), Some(descriptor)) = (first.values.descriptor()?, chunk.values.descriptor()?) {
+        if first != descriptor {
+            return Ok(Some("device or dtype"));
+        }
+    }
+
+    return Ok(gradients_mismatch(first, chunk));
+}
+
+/// Check that `chunk` has the same gradients as `first`, recursing into
+/// gradients of gradients
+fn gradients_mismatch(first: &TensorBlock, chunk: &TensorBlock) -> Option<&'static str> {
+    if first.gradients().len() != chunk.gradients().len() {
+        return Some("gradients");
+    }
+
+    for (parameter, first_gradient) in first.gradients() {
+        let gradient = match chunk.gradient(parameter) {
+            Some(gradient) => gradient,
+            None => return Some("gradients"),
+        };
+
+        if first_gradient.samples.names() != gradient.samples.names() {
+            return Some("gradients samples names");
+        }
+
+        if first_gradient.components != gradient.components {
+            return Some("gradients components");
+        }
+
+        if let Some(mismatch) = gradients_mismatch(first_gradient, gradient) {
+            return Some(mismatch);
+        }
+    }
+
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::TestArray;
+    use crate::{LabelValue, TensorBlock};
+
+    use super::TensorMapBuilder;
+    use super::super::utils::example_labels;
+
+    fn chunk(samples: Vec<[i32; 1]>, n_properties: i32) -> TensorBlock {
+        let properties = (0..n_properties).map(|p| [p]).collect::<Vec<_>>();
+        return TensorBlock::new(
+            TestArray::new(vec![samples.len(), properties.len()]),
+            example_labels(vec!["s"], samples),
+            vec![],
+            example_labels(vec!["p"], properties),
+        ).unwrap();
+    }
+
+    #[test]
+    fn builder() {
+        let key = |v: i32| [LabelValue::new(v)];
+
+        let mut builder = TensorMapBuilder::new(vec!["key"], false).unwrap();
+        builder.add(&key(4), chunk(vec![[0], [1]], 3)).unwrap();
+        builder.add(&key(-2), chunk(vec![[0]], 2)).unwrap();
+
+        let error = builder.add(&key(4), chunk(vec![[2], [0]], 3)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: sample [0] is present in multiple chunks for key [4]"
+        );
+
+        let error = builder.add(&key(4), chunk(vec![[2]], 2)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: the chunk for key [4] has different properties \
+            than the previous chunks for this key"
+        );
+
+        let error = builder.add(&[], chunk(vec![[2]], 2)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: expected a key with 1 values to add a chunk, got 0"
+        );
+
+        let tensor = builder.finish().unwrap();
+        assert_eq!(**tensor.keys(), *example_labels(vec!["key"], vec![[4], [-2]]));
+        assert_eq!(*tensor.blocks()[0].samples, *example_labels(vec!["s"], vec![[0], [1]]));
+
+        let error = TensorMapBuilder::new(vec![], false).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not create a TensorMapBuilder without keys dimensions"
+        );
+
+        let tensor = TensorMapBuilder::new(vec!["key"], true).unwrap().finish().unwrap();
+        assert_eq!(tensor.keys().count(), 0);
+        assert_eq!(tensor.keys().names(), ["key"]);
+    }
+}
@@ -2,13 +2,11 @@ use std::sync::Arc;
 
 use indexmap::IndexSet;
 
-use crate::labels::{Labels, LabelsBuilder};
+use crate::labels::{Labels, LabelsBuilder, LabelsDType};
 use crate::{Error, TensorBlock};
 
-use crate::data::mts_sample_mapping_t;
-
 use super::TensorMap;
-use super::utils::{KeyAndBlock, remove_dimensions_from_keys, merge_samples, merge_gradient_samples};
+use super::utils::{KeyAndBlock, remove_dimensions_from_keys, merge_samples, merge_gradients};
 
 
 impl TensorMap {
@@ -62,7 +60,7 @@ impl TensorMap {
                 .map(|(key, block)| {
                     let mut moved_key = Vec::new();
                     for &i in &splitted_keys.dimensions_positions {
-                        moved_key.push(key[i]);
+                        moved_key.push(key.get(i));
                     }
 
                     KeyAndBlock {
@@ -76,22 +74,20 @@ impl TensorMap {
                 &blocks_to_merge,
                 keys_to_move,
                 &names_to_move,
+                self.keys.dtype(),
                 sort_samples,
             )?;
             new_blocks.push(block);
         } else {
-            for entry in &splitted_keys.new_keys {
-                let mut selection = LabelsBuilder::new(splitted_keys.new_keys.names())?;
-                selection.add(entry)?;
-
-                let matching = self.blocks_matching(&selection.finish())?;
+            let all_matching = self.blocks_matching_many(&splitted_keys.new_keys)?;
+            for matching in all_matching {
                 let blocks_to_merge = matching.iter()
                     .map(|&i| {
                         let block = &self.blocks[i];
-                        let key = &self.keys[i];
+                        let key = self.keys.entry(i);
                         let mut moved_key = Vec::new();
                         for &i in &splitted_keys.dimensions_positions {
-                            moved_key.push(key[i]);
+                            moved_key.push(key.get(i));
                         }
 
                         KeyAndBlock {
@@ -105,6 +101,7 @@ impl TensorMap {
                     &blocks_to_merge,
                     keys_to_move,
                     &names_to_move,
+                    self.keys.dtype(),
                     sort_samples,
                 )?;
                 new_blocks.push(block);
@@ -121,19 +118,12 @@ fn merge_blocks_along_properties(
     blocks_to_merge: &[KeyAndBlock],
     keys_to_move: Option<&Labels>,
     extracted_names: &[&str],
+    keys_dtype: LabelsDType,
     sort_samples: bool,
 ) -> Result<TensorBlock, Error> {
     assert!(!blocks_to_merge.is_empty());
 
     let first_block = blocks_to_merge[0].block;
-    for gradient in first_block.gradients().values() {
-        if !gradient.gradients().is_empty() {
-            return Err(Error::InvalidParameter(
-                "gradient of gradients are not supported yet in keys_to_properties".into()
-            ));
-        }
-    }
-
     let first_components_label = &first_block.components;
     let first_property_labels = &first_block.properties;
     for KeyAndBlock{block, ..} in blocks_to_merge {
@@ -159,17 +149,25 @@ fn merge_blocks_along_properties(
     let (merged_samples, samples_mappings) = merge_samples(
         blocks_to_merge,
         first_block.samples.names(),
+        keys_dtype,
         sort_samples,
     );
 
+    // the new properties contain both the moved keys and the old properties,
+    // so they need 64-bit storage if any of these use it
+    let mut new_properties_dtype = blocks_to_merge.iter()
+        .map(|KeyAndBlock{block, ..}| block.properties.dtype())
+        .fold(keys_dtype, std::cmp::max);
+
     let mut new_properties = IndexSet::new();
     if let Some(keys_to_move) = keys_to_move {
+        new_properties_dtype = std::cmp::max(new_properties_dtype, keys_to_move.dtype());
         // use the user-provided new values
         for new_property in keys_to_move {
             for KeyAndBlock{block, ..} in blocks_to_merge {
                 for old_property in &*block.properties {
-                    let mut property = new_property.to_vec();
-                    property.extend_from_slice(old_property);
+                    let mut property = new_property.iter().collect::<Vec<_>>();
+                    property.extend(old_property);
                     new_properties.insert(property);
                 }
             }
@@ -181,7 +179,7 @@ fn merge_blocks_along_properties(
         for KeyAndBlock{key, block} in blocks_to_merge {
             for old_property in &*block.properties {
                 let mut property = key.clone();
-                property.extend_from_slice(old_property);
+                property.extend(old_property);
                 new_properties.insert(property);
             }
         }
@@ -191,7 +189,7 @@ fn merge_blocks_along_properties(
         .chain(first_block.properties.names().iter())
         .copied()
         .collect();
-    let mut new_properties_builder = LabelsBuilder::new(new_property_names)?;
+    let mut new_properties_builder = LabelsBuilder::new_with_dtype(new_property_names, new_properties_dtype)?;
     for property in new_properties {
         new_properties_builder.add(&property)?;
     }
@@ -218,7 +216,7 @@ fn merge_blocks_along_properties(
         }
 
         let mut first = key.clone();
-        first.extend_from_slice(&block.properties[0]);
+        first.extend(block.properties.entry(0));
 
         // we can lookup only the `first` new property here, since the new
         // properties match exactly the old ones, just with an added "channel"
@@ -256,60 +254,8 @@ fn merge_blocks_along_properties(
     ).expect("constructed an invalid block");
 
     // now collect & merge the different gradients
-    for (parameter, first_gradient) in first_block.gradients() {
-        let new_gradient_samples = merge_gradient_samples(
-            blocks_to_merge, parameter, &samples_mappings
-        )?;
-
-        let mut new_shape = first_gradient.values.shape()?.to_vec();
-        new_shape[0] = new_gradient_samples.count();
-        let property_axis = new_shape.len() - 1;
-        new_shape[property_axis] = new_properties_count;
-
-        let mut new_gradient = first_block.values.create(&new_shape)?;
-        let new_components = first_gradient.components.to_vec();
-
-        for ((KeyAndBlock{block, ..}, samples_mapping), property_range) in blocks_to_merge.iter().zip(&samples_mappings).zip(&property_ranges) {
-            if property_range.is_none() {
-                continue;
-            }
-            let property_range = property_range.as_ref().unwrap();
-
-            let gradient = block.gradient(parameter).expect("missing gradient");
-            debug_assert!(*gradient.components == *new_components);
-
-            let mut samples_to_move = Vec::new();
-            for (sample_i, grad_sample) in gradient.samples.iter().enumerate() {
-                // translate from the old sample id in gradients to the new ones
-                let mut grad_sample = grad_sample.to_vec();
-                let old_sample_i = grad_sample[0].usize();
-
-                let mapping = &samples_mapping[old_sample_i];
-                debug_assert_eq!(mapping.input, old_sample_i);
-                grad_sample[0] = mapping.output.into();
-
-                let new_sample_i = new_gradient_samples.position(&grad_sample).expect("missing entry in merged samples");
-                samples_to_move.push(mts_sample_mapping_t {
-                    input: sample_i,
-                    output: new_sample_i,
-                });
-            }
-            new_gradient.move_samples_from(
-                &gradient.values,
-                &samples_to_move,
-                property_range.clone(),
-            )?;
-        }
-
-        let new_gradient = TensorBlock::new(
-            new_gradient,
-            new_gradient_samples,
-            new_components,
-            new_block.properties.clone()
-        ).expect("created invalid gradient");
-
-        new_block.add_gradient(parameter, new_gradient).expect("could not add gradient");
-    }
+    let blocks = blocks_to_merge.iter().map(|b| b.block).collect::<Vec<_>>();
+    merge_gradients(&mut new_block, &blocks, &samples_mappings, &property_ranges)?;
 
     return Ok(new_block);
 }
@@ -1,12 +1,10 @@
 use std::sync::Arc;
 
-use crate::labels::{Labels, LabelsBuilder};
+use crate::labels::{Labels, LabelsDType};
 use crate::{Error, TensorBlock};
 
-use crate::data::mts_sample_mapping_t;
-
 use super::TensorMap;
-use super::utils::{KeyAndBlock, remove_dimensions_from_keys, merge_samples, merge_gradient_samples};
+use super::utils::{KeyAndBlock, remove_dimensions_from_keys, merge_samples, merge_gradients};
 
 impl TensorMap {
     /// Merge blocks with the same value for selected keys dimensions along the
@@ -53,7 +51,7 @@ impl TensorMap {
                 .map(|(key, block)| {
                     let mut moved_key = Vec::new();
                     for &i in &splitted_keys.dimensions_positions {
-                        moved_key.push(key[i]);
+                        moved_key.push(key.get(i));
                     }
 
                     KeyAndBlock {
@@ -66,22 +64,20 @@ impl TensorMap {
             let block = merge_blocks_along_samples(
                 &blocks_to_merge,
                 &names_to_move,
+                self.keys.dtype(),
                 sort_samples,
             )?;
             new_blocks.push(block);
         } else {
-            for entry in &splitted_keys.new_keys {
-                let mut selection = LabelsBuilder::new(splitted_keys.new_keys.names())?;
-                selection.add(entry)?;
-
-                let matching = self.blocks_matching(&selection.finish())?;
+            let all_matching = self.blocks_matching_many(&splitted_keys.new_keys)?;
+            for matching in all_matching {
                 let blocks_to_merge = matching.iter()
                     .map(|&i| {
                         let block = &self.blocks[i];
-                        let key = &self.keys[i];
+                        let key = self.keys.entry(i);
                         let mut moved_key = Vec::new();
                         for &i in &splitted_keys.dimensions_positions {
-                            moved_key.push(key[i]);
+                            moved_key.push(key.get(i));
                         }
 
                         KeyAndBlock {
@@ -94,6 +90,7 @@ impl TensorMap {
                 new_blocks.push(merge_blocks_along_samples(
                     &blocks_to_merge,
                     &names_to_move,
+                    self.keys.dtype(),
                     sort_samples,
                 )?);
             }
@@ -104,22 +101,15 @@ impl TensorMap {
 }
 
 /// Merge the given `blocks` along the sample axis.
-fn merge_blocks_along_samples(
+pub(super) fn merge_blocks_along_samples(
     blocks_to_merge: &[KeyAndBlock],
     extracted_names: &[&str],
+    keys_dtype: LabelsDType,
     sort_samples: bool,
 ) -> Result<TensorBlock, Error> {
     assert!(!blocks_to_merge.is_empty());
 
     let first_block = blocks_to_merge[0].block;
-    for gradient in first_block.gradients().values() {
-        if !gradient.gradients().is_empty() {
-            return Err(Error::InvalidParameter(
-                "gradient of gradients are not supported yet in keys_to_samples".into()
-            ));
-        }
-    }
-
     let first_components_label = &first_block.components;
     let first_properties_label = &first_block.properties;
 
@@ -147,6 +137,7 @@ fn merge_blocks_along_samples(
     let (merged_samples, samples_mappings) = merge_samples(
         blocks_to_merge,
         new_sample_names,
+        keys_dtype,
         sort_samples,
     );
 
@@ -176,52 +167,9 @@ fn merge_blocks_along_samples(
     ).expect("invalid block");
 
     // now collect & merge the different gradients
-    for (parameter, first_gradient) in first_block.gradients() {
-        let new_gradient_samples = merge_gradient_samples(
-            blocks_to_merge, parameter, &samples_mappings
-        )?;
-
-        let mut new_shape = first_gradient.values.shape()?.to_vec();
-        new_shape[0] = new_gradient_samples.count();
-        let mut new_gradient = first_block.values.create(&new_shape)?;
-        let new_components = first_gradient.components.to_vec();
-
-        for (KeyAndBlock{block, ..}, samples_mapping) in blocks_to_merge.iter().zip(&samples_mappings) {
-            let gradient = block.gradient(parameter).expect("missing gradient");
-            debug_assert!(*gradient.components == *new_components);
-
-            let mut samples_to_move = Vec::new();
-            for (sample_i, grad_sample) in gradient.samples.iter().enumerate() {
-                // translate from the old sample id in gradients to the new ones
-                let mut grad_sample = grad_sample.to_vec();
-                let old_sample_i = grad_sample[0].usize();
-
-                let mapping = &samples_mapping[old_sample_i];
-                debug_assert_eq!(mapping.input, old_sample_i);
-                grad_sample[0] = mapping.output.into();
-
-                let new_sample_i = new_gradient_samples.position(&grad_sample).expect("missing entry in merged samples");
-                samples_to_move.push(mts_sample_mapping_t {
-                    input: sample_i,
-                    output: new_sample_i,
-                });
-            }
-            new_gradient.move_samples_from(
-                &gradient.values,
-                &samples_to_move,
-                property_range.clone(),
-            )?;
-        }
-
-        let new_gradient = TensorBlock::new(
-            new_gradient,
-            new_gradient_samples,
-            new_components,
-            new_block.properties.clone()
-        ).expect("created invalid gradient");
-
-        new_block.add_gradient(parameter, new_gradient).expect("could not add gradient");
-    }
+    let blocks = blocks_to_merge.iter().map(|b| b.block).collect::<Vec<_>>();
+    let property_ranges = vec![Some(property_range); blocks.len()];
+    merge_gradients(&mut new_block, &blocks, &samples_mappings, &property_ranges)?;
 
     return Ok(new_block);
 }
@@ -1,15 +1,18 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use crate::TensorBlock;
-use crate::{Labels, Error};
-use crate::get_data_origin;
+use crate::{Labels, LabelsBuilder, LabelsEntry, LabelValue, Error};
+use crate::{mts_data_origin_t, get_data_origin};
 
 mod utils;
 
 mod keys_to_samples;
 mod keys_to_properties;
 
+mod builder;
+pub use self::builder::TensorMapBuilder;
+
 
 /// A tensor map is the main user-facing struct of this library, and can store
 /// any kind of data used in atomistic machine learning.
@@ -24,9 +27,17 @@ mod keys_to_properties;
 pub struct TensorMap {
     keys: Arc<Labels>,
     blocks: Vec<TensorBlock>,
+    /// Lazily-built indexes used by `blocks_matching` for selections with a
+    /// subset of the keys dimensions, keyed by the (sorted) positions of
+    /// these dimensions in the keys
+    partial_indexes: Mutex<HashMap<Vec<usize>, Arc<PartialKeysIndex>>>,
     // TODO: arbitrary tensor-level metadata? e.g. using `HashMap<String, String>`
 }
 
+/// Index from the values taken by a subset of the keys dimensions to the
+/// blocks with these values
+type PartialKeysIndex = HashMap<Vec<LabelValue>, Vec<usize>>;
+
 fn check_labels_names(
     block: &TensorBlock,
     sample_names: &[&str],
@@ -66,13 +77,14 @@ fn check_labels_names(
     Ok(())
 }
 
-fn check_origin(blocks: &[TensorBlock]) -> Result<(), Error> {
-    if blocks.is_empty() {
-        return Ok(());
-    }
+fn check_origin<'a>(blocks: impl IntoIterator<Item = &'a TensorBlock>) -> Result<(), Error> {
+    let mut blocks = blocks.into_iter();
+    let first_origin = match blocks.next() {
+        Some(block) => block.values.origin()?,
+        None => return Ok(()),
+    };
 
-    let first_origin = blocks[0].values.origin()?;
-    for block in blocks.iter().skip(1) {
+    for block in blocks {
         let block_origin = block.values.origin()?;
         if first_origin != block_origin {
             return Err(Error::InvalidParameter(format!(
@@ -86,6 +98,34 @@ fn check_origin(blocks: &[TensorBlock]) -> Result<(), Error> {
     Ok(())
 }
 
+/// Check that all the blocks with a known descriptor live on the same device
+/// and use the same data type
+fn check_descriptor<'a>(blocks: impl IntoIterator<Item = &'a TensorBlock>) -> Result<(), Error> {
+    let mut first = None;
+    for (block_i, block) in blocks.into_iter().enumerate() {
+        let descriptor = match block.values.descriptor()? {
+            Some(descriptor) => descriptor,
+            None => continue,
+        };
+
+        match first {
+            None => first = Some((block_i, descriptor)),
+            Some((first_i, first_descriptor)) => {
+                if descriptor != first_descriptor {
+                    return Err(Error::InvalidParameter(format!(
+                        "tried to build a TensorMap from blocks on different \
+                        devices or with different dtypes: block {} is {} and \
+                        block {} is {}",
+                        first_i, first_descriptor, block_i, descriptor,
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Clone, PartialEq)]
 struct GradientMetadata<'a> {
     sample_names: Vec<&'a str>,
@@ -116,6 +156,69 @@ impl GradientMap<'_> {
     }
 }
 
+/// Names of the metadata and gradients of a block, which must be the same for
+/// all the blocks in a `TensorMap`
+struct BlockMetadata<'a> {
+    sample_names: Vec<&'a str>,
+    component_names: Vec<Vec<&'a str>>,
+    property_names: Vec<&'a str>,
+    gradient_map: GradientMap<'a>,
+}
+
+impl BlockMetadata<'_> {
+    fn new(block: &TensorBlock) -> BlockMetadata<'_> {
+        BlockMetadata {
+            sample_names: block.samples.names(),
+            component_names: block.components.iter()
+                .map(|c| c.names())
+                .collect::<Vec<_>>(),
+            property_names: block.properties.names(),
+            gradient_map: GradientMap::new(block),
+        }
+    }
+
+    /// Check that `block` has the same metadata names and gradients as the
+    /// block used to create this `BlockMetadata`
+    fn check(&self, block: &TensorBlock) -> Result<(), Error> {
+        // check samples and components are the same as those of the first block
+        check_labels_names(block, &self.sample_names, &self.component_names, "")?;
+
+        // check properties are the same as those of the first block
+        if block.properties.names() != self.property_names {
+            return Err(Error::InvalidParameter(format!(
+                "all blocks must have the same property names, got [{}] and [{}]",
+                block.properties.names().join(", "),
+                self.property_names.join(", "),
+            )));
+        }
+
+        // check gradients are the same as those of the first block
+        if GradientMap::new(block) != self.gradient_map {
+            return Err(Error::InvalidParameter(
+                "all blocks must have the same set of gradients, with \
+                the same sample, property and component names, \
+                and the same must be true for gradients of gradients".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Build new keys containing the entries of `keys` for which `keep` returns
+/// `true`.
+fn rebuild_keys(keys: &Labels, keep: impl Fn(usize) -> bool) -> Result<Labels, Error> {
+    let mut builder = LabelsBuilder::new_with_dtype(keys.names(), keys.dtype())?;
+    builder.reserve(keys.count());
+    for i in 0..keys.count() {
+        if keep(i) {
+            builder.add(&keys.entry(i).to_vec())?;
+        }
+    }
+
+    return Ok(builder.finish());
+}
+
 impl TensorMap {
     /// Create a new `TensorMap` with the given keys and blocks.
     ///
@@ -133,45 +236,209 @@ impl TensorMap {
         }
 
         check_origin(&blocks)?;
+        check_descriptor(&blocks)?;
 
         if !blocks.is_empty() {
             // extract metadata from the first block
-            let sample_names = blocks[0].samples.names();
-            let component_names = blocks[0].components.iter()
-                .map(|c| c.names())
-                .collect::<Vec<_>>();
-            let property_names = blocks[0].properties.names();
-            let gradient_map = GradientMap::new(&blocks[0]);
-
+            let metadata = BlockMetadata::new(&blocks[0]);
             for block in &blocks {
-                // check samples and components are the same as those of the first block
-                check_labels_names(block, &sample_names, &component_names, "")?;
+                metadata.check(block)?;
+            }
+        }
 
-                // check properties are the same as those of the first block
-                if block.properties.names() != property_names {
-                    return Err(Error::InvalidParameter(format!(
-                        "all blocks must have the same property names, got [{}] and [{}]",
-                        block.properties.names().join(", "),
-                        property_names.join(", "),
-                    )));
+        Ok(TensorMap {
+            keys: keys,
+            blocks,
+            partial_indexes: Mutex::default(),
+        })
+    }
+
+    /// Check that `block` can be added to this `TensorMap`, comparing it with
+    /// all the blocks except the one at index `skip` (if any).
+    fn check_new_block(&self, block: &TensorBlock, skip: Option<usize>) -> Result<(), Error> {
+        let others = self.blocks.iter().enumerate()
+            .filter(|&(i, _)| Some(i) != skip)
+            .map(|(_, block)| block);
+
+        let reference = match others.clone().next() {
+            Some(reference) => reference,
+            None => return Ok(()),
+        };
+
+        BlockMetadata::new(reference).check(block)?;
+        check_origin([reference, block])?;
+
+        if let Some(descriptor) = block.values.descriptor()? {
+            for other in others {
+                if let Some(other_descriptor) = other.values.descriptor()? {
+                    if descriptor != other_descriptor {
+                        return Err(Error::InvalidParameter(format!(
+                            "can not add a block on a different device or with \
+                            a different dtype to this TensorMap: the new block is \
+                            {} and the existing blocks are {}",
+                            descriptor, other_descriptor,
+                        )));
+                    }
+                    break;
                 }
+            }
+        }
 
-                // check gradients are the same as those of the first block
-                if GradientMap::new(block) != gradient_map {
-                    return Err(Error::InvalidParameter(
-                        "all blocks must have the same set of gradients, with \
-                        the same sample, property and component names, \
-                        and the same must be true for gradients of gradients".into(),
-                    ));
+        return Ok(());
+    }
+
+    /// Add a new `block` to this `TensorMap`, associated with the given `key`.
+    ///
+    /// The block is added after all the existing blocks. The `key` must not
+    /// already be present in the keys, and the block must contain the same
+    /// kind of data as the existing blocks, as checked by `TensorMap::new`.
+    pub fn insert_block(&mut self, key: &[LabelValue], block: TensorBlock) -> Result<(), Error> {
+        if self.keys.size() == 0 {
+            return Err(Error::InvalidParameter(
+                "can not insert a block in a TensorMap without keys dimensions".into()
+            ));
+        }
+
+        if key.len() != self.keys.size() {
+            return Err(Error::InvalidParameter(format!(
+                "expected a key with {} values to insert a block, got {}",
+                self.keys.size(), key.len()
+            )));
+        }
+
+        self.check_new_block(&block, None)?;
+
+        self.keys_mut()?.push(key)?;
+        self.blocks.push(block);
+
+        return Ok(());
+    }
+
+    /// Remove the block associated with the given `key` from this
+    /// `TensorMap`, and return it.
+    pub fn remove_block(&mut self, key: &[LabelValue]) -> Result<TensorBlock, Error> {
+        if key.len() != self.keys.size() {
+            return Err(Error::InvalidParameter(format!(
+                "expected a key with {} values to remove a block, got {}",
+                self.keys.size(), key.len()
+            )));
+        }
+
+        let position = self.keys.position(key).ok_or_else(|| {
+            let key = key.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+            Error::InvalidParameter(format!(
+                "there is no block with key [{}] in this TensorMap", key
+            ))
+        })?;
+
+        self.keys_mut()?.remove(position);
+
+        return Ok(self.blocks.remove(position));
+    }
+
+    /// Replace the block at the given `index` with `block`, and return the
+    /// previous block. The new block must contain the same kind of data as
+    /// the other blocks, as checked by `TensorMap::new`.
+    pub fn replace_block(&mut self, index: usize, block: TensorBlock) -> Result<TensorBlock, Error> {
+        if index >= self.blocks.len() {
+            return Err(Error::InvalidParameter(format!(
+                "block index out of bounds: we have {} blocks but the index is {}",
+                self.blocks.len(), index
+            )));
+        }
+
+        self.check_new_block(&block, Some(index))?;
+
+        return Ok(std::mem::replace(&mut self.blocks[index], block));
+    }
+
+    /// Remove all the blocks with keys matching one of the entries in
+    /// `selection` from this `TensorMap`. The `selection` can contain a
+    /// subset of the names of the keys, following the same rules as
+    /// `Labels::select`.
+    pub fn drop_blocks(&mut self, selection: &Labels) -> Result<(), Error> {
+        let mut selected = vec![-1; self.keys.count()];
+        let n_selected = self.keys.select(selection, &mut selected)?;
+        if n_selected == 0 {
+            return Ok(());
+        }
+
+        let mut dropped = vec![false; self.keys.count()];
+        for &i in &selected[..n_selected] {
+            let i = usize::try_from(i).expect("selected index should be positive");
+            dropped[i] = true;
+        }
+
+        let keys = rebuild_keys(&self.keys, |i| !dropped[i])?;
+        self.set_keys(keys);
+
+        let mut block_i = 0;
+        self.blocks.retain(|_| {
+            block_i += 1;
+            !dropped[block_i - 1]
+        });
+
+        return Ok(());
+    }
+
+    /// Remove the gradients with respect to all the given `parameters` from
+    /// all the blocks in this `TensorMap`. Parameters without corresponding
+    /// gradients are ignored.
+    pub fn remove_gradients(&mut self, parameters: &[&str]) {
+        for block in &mut self.blocks {
+            for parameter in parameters {
+                if block.gradient(parameter).is_some() {
+                    block.remove_gradient(parameter).expect("the gradient should exist");
                 }
+            }
+        }
+    }
 
+    /// Use a single `Arc<Labels>` for all the identical gradient samples in
+    /// this `TensorMap`, across blocks and gradient parameters (including
+    /// gradients of gradients).
+    ///
+    /// Samples used by more than one gradient are then stored only once when
+    /// saving this `TensorMap`.
+    pub fn share_gradient_samples(&mut self) {
+        /// Unique gradient samples, indexed by their fingerprint
+        #[derive(Default)]
+        struct UniqueSamples {
+            samples: Vec<Arc<Labels>>,
+            by_fingerprint: HashMap<u64, Vec<usize>>,
+        }
+
+        fn share_samples(block: &mut TensorBlock, unique: &mut UniqueSamples) {
+            // iterate over the parameters in the order they were added to
+            // get the same shared samples every time
+            let parameters = block.gradient_parameters_c().iter()
+                .map(|parameter| parameter.as_str().to_owned())
+                .collect::<Vec<_>>();
+
+            for parameter in parameters {
+                let gradient = block.gradient_mut(&parameter).expect("missing gradient");
+
+                let candidates = unique.by_fingerprint.entry(gradient.samples.fingerprint()).or_default();
+                let existing = candidates.iter().copied().find(|&i| {
+                    let candidate = &unique.samples[i];
+                    candidate.dtype() == gradient.samples.dtype() && **candidate == *gradient.samples
+                });
+
+                if let Some(i) = existing {
+                    gradient.samples = Arc::clone(&unique.samples[i]);
+                } else {
+                    candidates.push(unique.samples.len());
+                    unique.samples.push(Arc::clone(&gradient.samples));
+                }
+
+                share_samples(gradient, unique);
             }
         }
 
-        Ok(TensorMap {
-            keys: keys,
-            blocks,
-        })
+        let mut unique = UniqueSamples::default();
+        for block in &mut self.blocks {
+            share_samples(block, &mut unique);
+        }
     }
 
     /// Try to copy this `TensorMap`. This can fail if we are unable to copy the
@@ -184,10 +451,32 @@ impl TensorMap {
 
         return Ok(TensorMap {
             keys: Arc::clone(&self.keys),
-            blocks
+            blocks,
+            partial_indexes: Mutex::default(),
+        });
+    }
+
+    /// Create a copy of this `TensorMap` with the values and gradients in all
+    /// blocks converted to the given data `origin`.
+    pub fn to_origin(&self, origin: mts_data_origin_t) -> Result<TensorMap, Error> {
+        let mut blocks = Vec::new();
+        for block in &self.blocks {
+            blocks.push(block.to_origin(origin)?);
+        }
+
+        return Ok(TensorMap {
+            keys: Arc::clone(&self.keys),
+            blocks,
+            partial_indexes: Mutex::default(),
         });
     }
 
+    /// Split this `TensorMap` into its keys and blocks, without copying any
+    /// data
+    pub fn into_parts(self) -> (Arc<Labels>, Vec<TensorBlock>) {
+        return (self.keys, self.blocks);
+    }
+
     /// Get the list of blocks in this `TensorMap`
     pub fn blocks(&self) -> &[TensorBlock] {
         &self.blocks
@@ -203,6 +492,25 @@ impl TensorMap {
         &self.keys
     }
 
+    /// Update the keys of this `TensorMap`, invalidating the indexes used by
+    /// `blocks_matching`
+    fn set_keys(&mut self, keys: Labels) {
+        self.keys = Arc::new(keys);
+        self.partial_indexes.get_mut().expect("mutex got poisoned").clear();
+    }
+
+    /// Get mutable access to the keys of this `TensorMap`, to modify them in
+    /// place. If the keys are shared with other tensor maps, they are copied
+    /// first.
+    fn keys_mut(&mut self) -> Result<&mut Labels, Error> {
+        self.partial_indexes.get_mut().expect("mutex got poisoned").clear();
+        if Arc::get_mut(&mut self.keys).is_none() {
+            self.keys = Arc::new(rebuild_keys(&self.keys, |_| true)?);
+        }
+
+        return Ok(Arc::get_mut(&mut self.keys).expect("keys should not be shared"));
+    }
+
     /// Get the index of blocks matching the given selection.
     ///
     /// The selection must contains a single entry, defining the requested key
@@ -220,6 +528,23 @@ impl TensorMap {
             )));
         }
 
+        let mut matching = self.blocks_matching_many(selection)?;
+        return Ok(matching.pop().expect("missing selection entry"));
+    }
+
+    /// Get the index of blocks matching each one of the entries in
+    /// `selection`.
+    ///
+    /// This is equivalent to calling `blocks_matching` separately for each
+    /// entry in `selection`, but re-uses the lookup structures between entries.
+    /// Selections containing all the keys dimensions use the keys' hash index
+    /// directly, while selections with a subset of the dimensions use an index
+    /// built the first time this set of dimensions is requested.
+    pub fn blocks_matching_many(&self, selection: &Labels) -> Result<Vec<Vec<usize>>, Error> {
+        if selection.size() == 0 {
+            return Ok(vec![(0..self.blocks().len()).collect(); selection.count()]);
+        }
+
         let mut dimensions = Vec::new();
         'outer: for requested in selection.names() {
             for (i, &name) in self.keys.names().iter().enumerate() {
@@ -235,24 +560,101 @@ impl TensorMap {
             )));
         }
 
-        let mut matching = Vec::new();
-        let selection = selection.iter().next().expect("empty selection");
+        return Ok(self.blocks_matching_entries(&dimensions, selection.iter()));
+    }
 
-        for (block_i, labels) in self.keys.iter().enumerate() {
-            let mut selected = true;
-            for (&requested_i, &value) in dimensions.iter().zip(selection) {
-                if labels[requested_i] != value {
-                    selected = false;
-                    break;
-                }
+    /// Get the index of blocks matching the given key `values`, where
+    /// `values[i]` is the value taken by the keys dimension `dimensions[i]`.
+    ///
+    /// This is equivalent to `blocks_matching` with a selection containing a
+    /// single entry, without having to create the corresponding `Labels`.
+    pub fn blocks_matching_values(&self, dimensions: &[usize], values: &[i64]) -> Result<Vec<usize>, Error> {
+        if dimensions.len() != values.len() {
+            return Err(Error::InvalidParameter(format!(
+                "expected the same number of dimensions and values, got {} and {}",
+                dimensions.len(), values.len()
+            )));
+        }
+
+        for (i, &dimension) in dimensions.iter().enumerate() {
+            if dimension >= self.keys.size() {
+                return Err(Error::InvalidParameter(format!(
+                    "dimension {} is out of bounds for keys with {} dimensions",
+                    dimension, self.keys.size()
+                )));
             }
 
-            if selected {
-                matching.push(block_i);
+            if dimensions[..i].contains(&dimension) {
+                return Err(Error::InvalidParameter(format!(
+                    "dimension {} is present multiple times in the selection",
+                    dimension
+                )));
             }
         }
 
-        return Ok(matching);
+        if dimensions.is_empty() {
+            return Ok((0..self.blocks().len()).collect());
+        }
+
+        let entry = LabelsEntry::Int64(values);
+        let mut matching = self.blocks_matching_entries(dimensions, std::iter::once(entry));
+        return Ok(matching.pop().expect("missing selection entry"));
+    }
+
+    /// Get the index of blocks matching each one of the `entries`, which
+    /// contain values for the given keys `dimensions`
+    fn blocks_matching_entries<'a>(
+        &self,
+        dimensions: &[usize],
+        entries: impl Iterator<Item = LabelsEntry<'a>>,
+    ) -> Vec<Vec<usize>> {
+        if dimensions.len() == self.keys.size() {
+            // fast path: the selection contains full keys
+            let mut key = vec![LabelValue::new(0); self.keys.size()];
+            let matching = entries.map(|entry| {
+                for (&dimension, value) in dimensions.iter().zip(entry) {
+                    key[dimension] = value;
+                }
+                self.keys.position(&key).into_iter().collect()
+            }).collect();
+
+            return matching;
+        }
+
+        // use the same index for selections with the same dimensions in a
+        // different order
+        let mut order = (0..dimensions.len()).collect::<Vec<_>>();
+        order.sort_unstable_by_key(|&i| dimensions[i]);
+        let sorted_dimensions = order.iter().map(|&i| dimensions[i]).collect::<Vec<_>>();
+
+        let index = self.partial_index(&sorted_dimensions);
+        let mut values = Vec::with_capacity(order.len());
+        let matching = entries.map(|entry| {
+            values.clear();
+            values.extend(order.iter().map(|&i| entry.get(i)));
+            index.get(&values).cloned().unwrap_or_default()
+        }).collect();
+
+        return matching;
+    }
+
+    /// Get the index for the given subset of the keys `dimensions`, creating
+    /// it if needed
+    fn partial_index(&self, dimensions: &[usize]) -> Arc<PartialKeysIndex> {
+        let mut indexes = self.partial_indexes.lock().expect("mutex got poisoned");
+        if let Some(index) = indexes.get(dimensions) {
+            return Arc::clone(index);
+        }
+
+        let mut index = PartialKeysIndex::new();
+        for (block_i, key) in self.keys.iter().enumerate() {
+            let values = dimensions.iter().map(|&i| key.get(i)).collect();
+            index.entry(values).or_default().push(block_i);
+        }
+
+        let index = Arc::new(index);
+        indexes.insert(dimensions.to_vec(), Arc::clone(&index));
+        return index;
     }
 
     /// Move the given dimensions from the component labels to the property labels
@@ -464,5 +866,149 @@ mod tests {
             result.unwrap_err().to_string(),
             "invalid parameter: 'key_3' is not part of the keys for this tensor"
         );
+
+        // selection with dimensions in a different order than the keys
+        let mut selection = LabelsBuilder::new(vec!["key_2", "key_1"]).unwrap();
+        selection.add(&[2, 1]).unwrap();
+        assert_eq!(
+            tensor.blocks_matching(&selection.finish()).unwrap(),
+            [3]
+        );
+
+        // multiple entries at once
+        let selection = example_labels(vec!["key_2"], vec![[1], [5], [3]]);
+        assert_eq!(
+            tensor.blocks_matching_many(&selection).unwrap(),
+            [vec![0, 2], vec![], vec![5]]
+        );
+
+        let selection = example_labels(vec!["key_1", "key_2"], vec![[3, 0], [3, 1]]);
+        assert_eq!(
+            tensor.blocks_matching_many(&selection).unwrap(),
+            [vec![4], vec![]]
+        );
+
+        // raw values for a subset of the dimensions
+        assert_eq!(tensor.blocks_matching_values(&[1, 0], &[2, 1]).unwrap(), [3]);
+        assert_eq!(tensor.blocks_matching_values(&[0], &[1]).unwrap(), [2, 3]);
+        assert_eq!(tensor.blocks_matching_values(&[1], &[7]).unwrap(), []);
+        assert_eq!(tensor.blocks_matching_values(&[0, 1], &[4, 3]).unwrap(), [5]);
+        assert_eq!(tensor.blocks_matching_values(&[], &[]).unwrap(), [0, 1, 2, 3, 4, 5]);
+
+        let result = tensor.blocks_matching_values(&[2], &[1]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid parameter: dimension 2 is out of bounds for keys with 2 dimensions"
+        );
+
+        let result = tensor.blocks_matching_values(&[0, 0], &[1, 1]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid parameter: dimension 0 is present multiple times in the selection"
+        );
+
+        let result = tensor.blocks_matching_values(&[0, 1], &[1]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid parameter: expected the same number of dimensions and values, got 2 and 1"
+        );
+    }
+
+    #[test]
+    fn blocks_matching_after_modifications() {
+        let block = || {
+            TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap()
+        };
+        let key = |values: [i32; 2]| values.map(LabelValue::from);
+
+        let keys = example_labels(vec!["key_1", "key_2"], vec![[0, 1], [0, 2]]);
+        let mut tensor = TensorMap::new(keys, vec![block(), block()]).unwrap();
+
+        let selection = example_labels(vec!["key_1"], vec![[0], [1]]);
+        assert_eq!(tensor.blocks_matching_many(&selection).unwrap(), [vec![0, 1], vec![]]);
+
+        // the partial index must be rebuilt when the keys change
+        tensor.insert_block(&key([1, 1]), block()).unwrap();
+        assert_eq!(tensor.blocks_matching_many(&selection).unwrap(), [vec![0, 1], vec![2]]);
+
+        tensor.remove_block(&key([0, 1])).unwrap();
+        assert_eq!(tensor.blocks_matching_many(&selection).unwrap(), [vec![0], vec![1]]);
+
+        tensor.drop_blocks(&example_labels(vec!["key_2"], vec![[2]])).unwrap();
+        assert_eq!(tensor.blocks_matching_many(&selection).unwrap(), [vec![], vec![0]]);
+    }
+
+    #[test]
+    fn insert_remove_blocks() {
+        let block = |n_samples: i32| {
+            TensorBlock::new(
+                TestArray::new(vec![usize::try_from(n_samples).unwrap(), 1]),
+                example_labels(vec!["samples"], (0..n_samples).map(|i| [i]).collect()),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap()
+        };
+        let key = |values: [i32; 2]| values.map(LabelValue::from);
+
+        let keys = example_labels(vec!["key_1", "key_2"], vec![[0, 1], [0, 2]]);
+        let mut tensor = TensorMap::new(keys, vec![block(1), block(2)]).unwrap();
+
+        tensor.insert_block(&key([1, 1]), block(3)).unwrap();
+        assert_eq!(**tensor.keys(), *example_labels(vec!["key_1", "key_2"], vec![[0, 1], [0, 2], [1, 1]]));
+        assert_eq!(tensor.blocks()[2].samples.count(), 3);
+
+        let error = tensor.insert_block(&key([0, 2]), block(1)).unwrap_err();
+        assert_eq!(error.to_string(),
+            "invalid parameter: can not have the same label value multiple \
+            time: [0, 2] is already present at position 1"
+        );
+
+        let error = tensor.insert_block(&[LabelValue::new(3)], block(1)).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: expected a key with 2 values to insert a block, got 1");
+
+        let wrong_block = TensorBlock::new(
+            TestArray::new(vec![1, 1]),
+            example_labels(vec!["other"], vec![[0]]),
+            vec![],
+            example_labels(vec!["properties"], vec![[0]]),
+        ).unwrap();
+        let error = tensor.insert_block(&key([3, 3]), wrong_block).unwrap_err();
+        assert_eq!(error.to_string(),
+            "invalid parameter: all blocks must have the same sample names, \
+            got [other] and [samples]"
+        );
+        assert_eq!(tensor.keys().count(), 3);
+
+        let removed = tensor.remove_block(&key([0, 2])).unwrap();
+        assert_eq!(removed.samples.count(), 2);
+        assert_eq!(**tensor.keys(), *example_labels(vec!["key_1", "key_2"], vec![[0, 1], [1, 1]]));
+        assert_eq!(tensor.blocks()[1].samples.count(), 3);
+
+        let error = tensor.remove_block(&key([0, 2])).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: there is no block with key [0, 2] in this TensorMap");
+
+        let previous = tensor.replace_block(0, block(4)).unwrap();
+        assert_eq!(previous.samples.count(), 1);
+        assert_eq!(tensor.blocks()[0].samples.count(), 4);
+
+        let error = tensor.replace_block(2, block(4)).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: block index out of bounds: we have 2 blocks but the index is 2");
+
+        tensor.insert_block(&key([2, 1]), block(5)).unwrap();
+        tensor.drop_blocks(&example_labels(vec!["key_2"], vec![[1], [7]])).unwrap();
+        assert_eq!(tensor.keys().count(), 0);
+        assert!(tensor.blocks().is_empty());
+
+        // any block can be inserted in an empty tensor
+        tensor.insert_block(&key([0, 0]), block(1)).unwrap();
+        assert_eq!(tensor.blocks().len(), 1);
+
+        let error = tensor.drop_blocks(&example_labels(vec!["key_3"], vec![[1]])).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: 'key_3' in selection is not part of these Labels");
     }
 }
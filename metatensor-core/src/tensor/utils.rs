@@ -1,9 +1,10 @@
 use std::collections::BTreeSet;
+use std::ops::Range;
 use std::sync::Arc;
 
 use indexmap::IndexSet;
 
-use crate::labels::{Labels, LabelsBuilder, LabelValue};
+use crate::labels::{Labels, LabelsBuilder, LabelsDType, LabelValue};
 use crate::{Error, TensorBlock, mts_sample_mapping_t};
 
 /// single block and part of the associated key, this is used for the various
@@ -62,12 +63,12 @@ pub fn remove_dimensions_from_keys(keys: &Labels, dimensions: &[&str]) -> Result
         for key in keys {
             let mut label = Vec::new();
             for &i in &remaining_i {
-                label.push(key[i]);
+                label.push(key.get(i));
             }
             remaining_keys.insert(label);
         }
 
-        let mut remaining_keys_builder = LabelsBuilder::new(remaining_names)?;
+        let mut remaining_keys_builder = LabelsBuilder::new_with_dtype(remaining_names, keys.dtype())?;
         for entry in remaining_keys {
             remaining_keys_builder.add(&entry)?;
         }
@@ -80,19 +81,21 @@ pub fn remove_dimensions_from_keys(keys: &Labels, dimensions: &[&str]) -> Result
     });
 }
 
-pub fn merge_gradient_samples(
-    blocks: &[KeyAndBlock],
-    gradient_name: &str,
+/// Merge the samples of the `gradients` of multiple blocks, translating the
+/// `"sample"` dimension with the corresponding `samples_mappings` of the
+/// parent blocks.
+fn merge_gradient_samples(
+    gradients: &[&TensorBlock],
     samples_mappings: &[Vec<mts_sample_mapping_t>],
 ) -> Result<Arc<Labels>, Error> {
     let mut new_gradient_samples = BTreeSet::new();
     let mut new_gradient_sample_names = None;
-    for (KeyAndBlock{block, ..}, samples_mapping) in blocks.iter().zip(samples_mappings) {
-        let gradient = block.gradient(gradient_name).expect("missing gradient");
-
+    let mut new_gradient_samples_dtype = LabelsDType::Int32;
+    for (gradient, samples_mapping) in gradients.iter().zip(samples_mappings) {
         if new_gradient_sample_names.is_none() {
             new_gradient_sample_names = Some(gradient.samples.names());
         }
+        new_gradient_samples_dtype = std::cmp::max(new_gradient_samples_dtype, gradient.samples.dtype());
 
         for grad_sample in &*gradient.samples {
             // translate from the old sample id in gradients to the new ones
@@ -107,8 +110,9 @@ pub fn merge_gradient_samples(
         }
     }
 
-    let mut new_gradient_samples_builder = LabelsBuilder::new(
-        new_gradient_sample_names.expect("missing gradient sample names")
+    let mut new_gradient_samples_builder = LabelsBuilder::new_with_dtype(
+        new_gradient_sample_names.expect("missing gradient sample names"),
+        new_gradient_samples_dtype,
     )?;
 
     for sample in new_gradient_samples {
@@ -118,13 +122,109 @@ pub fn merge_gradient_samples(
     return Ok(Arc::new(new_gradient_samples_builder.finish()));
 }
 
+/// Merge the gradients of all the `blocks` and add them to `new_block`,
+/// including gradients of gradients.
+///
+/// `samples_mappings` contains the position of the samples of each block in
+/// `new_block`, and `property_ranges` the properties of `new_block` where the
+/// data of each block should go (`None` to skip the data of this block).
+pub fn merge_gradients(
+    new_block: &mut TensorBlock,
+    blocks: &[&TensorBlock],
+    samples_mappings: &[Vec<mts_sample_mapping_t>],
+    property_ranges: &[Option<Range<usize>>],
+) -> Result<(), Error> {
+    debug_assert_eq!(blocks.len(), samples_mappings.len());
+    debug_assert_eq!(blocks.len(), property_ranges.len());
+
+    let first_block = blocks[0];
+    for (parameter, first_gradient) in first_block.gradients() {
+        let mut gradients = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            let Some(gradient) = block.gradient(parameter) else {
+                return Err(Error::InvalidParameter(format!(
+                    "all blocks must have the same gradients, but some blocks \
+                    are missing the gradient with respect to '{}'",
+                    parameter
+                )));
+            };
+            gradients.push(gradient);
+        }
+
+        let new_gradient_samples = merge_gradient_samples(&gradients, samples_mappings)?;
+
+        let mut new_shape = first_gradient.values.shape()?.to_vec();
+        new_shape[0] = new_gradient_samples.count();
+        let property_axis = new_shape.len() - 1;
+        new_shape[property_axis] = new_block.properties.count();
+
+        let mut new_data = first_gradient.values.create(&new_shape)?;
+        let new_components = first_gradient.components.to_vec();
+
+        // the mappings for the gradient samples are also needed to merge the
+        // gradients of these gradients, so we compute them for all blocks
+        let mut gradient_mappings = Vec::new();
+        for ((gradient, samples_mapping), property_range) in gradients.iter().zip(samples_mappings).zip(property_ranges) {
+            debug_assert!(*gradient.components == *new_components);
+
+            let mut samples_to_move = Vec::new();
+            for (sample_i, grad_sample) in gradient.samples.iter().enumerate() {
+                // translate from the old sample id in gradients to the new ones
+                let mut grad_sample = grad_sample.to_vec();
+                let old_sample_i = grad_sample[0].usize();
+
+                let mapping = &samples_mapping[old_sample_i];
+                debug_assert_eq!(mapping.input, old_sample_i);
+                grad_sample[0] = mapping.output.into();
+
+                let new_sample_i = new_gradient_samples.position(&grad_sample).expect("missing entry in merged samples");
+                samples_to_move.push(mts_sample_mapping_t {
+                    input: sample_i,
+                    output: new_sample_i,
+                });
+            }
+
+            if let Some(property_range) = property_range {
+                new_data.move_samples_from(
+                    &gradient.values,
+                    &samples_to_move,
+                    property_range.clone(),
+                )?;
+            }
+
+            gradient_mappings.push(samples_to_move);
+        }
+
+        let mut new_gradient = TensorBlock::new(
+            new_data,
+            new_gradient_samples,
+            new_components,
+            new_block.properties.clone()
+        )?;
+
+        merge_gradients(&mut new_gradient, &gradients, &gradient_mappings, property_ranges)?;
+
+        new_block.add_gradient(parameter, new_gradient)?;
+    }
+
+    return Ok(());
+}
+
 pub fn merge_samples(
     blocks: &[KeyAndBlock],
     new_sample_names: Vec<&str>,
+    keys_dtype: LabelsDType,
     sort: bool
 ) -> (Arc<Labels>, Vec<Vec<mts_sample_mapping_t>>) {
     let add_key_to_samples = blocks[0].block.samples.size() < new_sample_names.len();
 
+    let mut dtype = blocks.iter()
+        .map(|KeyAndBlock{block, ..}| block.samples.dtype())
+        .fold(LabelsDType::Int32, std::cmp::max);
+    if add_key_to_samples {
+        dtype = std::cmp::max(dtype, keys_dtype);
+    }
+
     // Collect samples in an IndexSet to keep them in the same order as they
     // were in the blocks, and then optionally sort them later below
     let mut merged_samples = IndexSet::new();
@@ -143,7 +243,7 @@ pub fn merge_samples(
         merged_samples.sort_unstable();
     }
 
-    let mut merged_samples_builder = LabelsBuilder::new(new_sample_names).expect("invalid new sample names");
+    let mut merged_samples_builder = LabelsBuilder::new_with_dtype(new_sample_names, dtype).expect("invalid new sample names");
     for sample in merged_samples {
         merged_samples_builder.add(&sample).expect("got duplicated samples");
     }
@@ -20,12 +20,15 @@ if arch == "32bit":
 elif arch == "64bit":
     c_uintptr_t = ctypes.c_uint64
 
+MTS_ARRAY_OPERATIONS_VERSION = 2
 MTS_SUCCESS = 0
 MTS_INVALID_PARAMETER_ERROR = 1
 MTS_IO_ERROR = 2
 MTS_SERIALIZATION_ERROR = 3
 MTS_BUFFER_SIZE_ERROR = 254
 MTS_INTERNAL_ERROR = 255
+MTS_LABELS_INT32 = 0
+MTS_LABELS_INT64 = 1
 
 
 mts_status_t = ctypes.c_int32
@@ -37,6 +40,10 @@ class mts_block_t(ctypes.Structure):
     pass
 
 
+class mts_tensormap_builder_t(ctypes.Structure):
+    pass
+
+
 class mts_tensormap_t(ctypes.Structure):
     pass
 
@@ -50,6 +57,8 @@ mts_labels_t._fields_ = [
     ("values", POINTER(ctypes.c_int32)),
     ("size", c_uintptr_t),
     ("count", c_uintptr_t),
+    ("dtype", ctypes.c_int32),
+    ("values_i64", POINTER(ctypes.c_int64)),
 ]
 
 
@@ -79,7 +88,48 @@ mts_array_t._fields_ = [
 ]
 
 
+class mts_array_descriptor_t(ctypes.Structure):
+    pass
+
+mts_array_descriptor_t._fields_ = [
+    ("device_type", ctypes.c_int32),
+    ("device_id", ctypes.c_int32),
+    ("dtype_code", ctypes.c_uint8),
+    ("dtype_bits", ctypes.c_uint8),
+    ("dtype_lanes", ctypes.c_uint16),
+]
+
+
+class mts_index_mapping_t(ctypes.Structure):
+    pass
+
+mts_index_mapping_t._fields_ = [
+    ("input", c_uintptr_t),
+    ("output", c_uintptr_t),
+]
+
+
+class mts_array_operations_t(ctypes.Structure):
+    pass
+
+mts_array_operations_t._fields_ = [
+    ("version", ctypes.c_uint64),
+    ("add", CFUNCTYPE(mts_status_t, ctypes.c_void_p, ctypes.c_void_p)),
+    ("mul", CFUNCTYPE(mts_status_t, ctypes.c_void_p, ctypes.c_void_p)),
+    ("scale", CFUNCTYPE(mts_status_t, ctypes.c_void_p, ctypes.c_double)),
+    ("matmul", CFUNCTYPE(mts_status_t, ctypes.c_void_p, ctypes.c_void_p, POINTER(mts_array_t))),
+    ("sum_axis", CFUNCTYPE(mts_status_t, ctypes.c_void_p, c_uintptr_t, POINTER(mts_array_t))),
+    ("copy_from", CFUNCTYPE(mts_status_t, ctypes.c_void_p, ctypes.c_void_p)),
+    ("descriptor", CFUNCTYPE(mts_status_t, ctypes.c_void_p, POINTER(mts_array_descriptor_t))),
+    ("raw_data", CFUNCTYPE(mts_status_t, ctypes.c_void_p, POINTER(POINTER(None)))),
+    ("move_along_axis", CFUNCTYPE(mts_status_t, ctypes.c_void_p, ctypes.c_void_p, c_uintptr_t, POINTER(mts_index_mapping_t), c_uintptr_t, POINTER(c_uintptr_t))),
+    ("sparse_csr", CFUNCTYPE(mts_status_t, ctypes.c_void_p, POINTER(POINTER(ctypes.c_double)), POINTER(POINTER(c_uintptr_t)), POINTER(POINTER(c_uintptr_t)), POINTER(c_uintptr_t))),
+    ("create_sparse_csr", CFUNCTYPE(mts_status_t, ctypes.c_void_p, POINTER(c_uintptr_t), c_uintptr_t, POINTER(ctypes.c_double), POINTER(c_uintptr_t), POINTER(c_uintptr_t), c_uintptr_t, POINTER(mts_array_t))),
+]
+
+
 mts_create_array_callback_t = CFUNCTYPE(mts_status_t, POINTER(c_uintptr_t), c_uintptr_t, POINTER(mts_array_t))
+mts_array_converter_t = CFUNCTYPE(mts_status_t, POINTER(mts_array_t), POINTER(mts_array_t))
 
 
 def setup_functions(lib):
@@ -105,6 +155,14 @@ def setup_functions(lib):
     ]
     lib.mts_labels_position.restype = _check_status
 
+    lib.mts_labels_position_i64.argtypes = [
+        mts_labels_t,
+        POINTER(ctypes.c_int64),
+        c_uintptr_t,
+        POINTER(ctypes.c_int64),
+    ]
+    lib.mts_labels_position_i64.restype = _check_status
+
     lib.mts_labels_create.argtypes = [
         POINTER(mts_labels_t),
     ]
@@ -129,6 +187,12 @@ def setup_functions(lib):
     ]
     lib.mts_labels_clone.restype = _check_status
 
+    lib.mts_labels_fingerprint.argtypes = [
+        mts_labels_t,
+        POINTER(ctypes.c_uint64),
+    ]
+    lib.mts_labels_fingerprint.restype = _check_status
+
     lib.mts_labels_union.argtypes = [
         mts_labels_t,
         mts_labels_t,
@@ -177,6 +241,86 @@ def setup_functions(lib):
     ]
     lib.mts_get_data_origin.restype = _check_status
 
+    lib.mts_register_array_operations.argtypes = [
+        mts_data_origin_t,
+        POINTER(mts_array_operations_t),
+    ]
+    lib.mts_register_array_operations.restype = _check_status
+
+    lib.mts_register_array_converter.argtypes = [
+        mts_data_origin_t,
+        mts_data_origin_t,
+        mts_array_converter_t,
+    ]
+    lib.mts_register_array_converter.restype = _check_status
+
+    lib.mts_array_to_origin.argtypes = [
+        POINTER(mts_array_t),
+        mts_data_origin_t,
+        POINTER(mts_array_t),
+    ]
+    lib.mts_array_to_origin.restype = _check_status
+
+    lib.mts_array_descriptor.argtypes = [
+        POINTER(mts_array_t),
+        POINTER(mts_array_descriptor_t),
+    ]
+    lib.mts_array_descriptor.restype = _check_status
+
+    lib.mts_array_to_dlpack.argtypes = [
+        POINTER(mts_array_t),
+        POINTER(POINTER(None)),
+    ]
+    lib.mts_array_to_dlpack.restype = _check_status
+
+    lib.mts_array_add.argtypes = [
+        POINTER(mts_array_t),
+        POINTER(mts_array_t),
+    ]
+    lib.mts_array_add.restype = _check_status
+
+    lib.mts_array_mul.argtypes = [
+        POINTER(mts_array_t),
+        POINTER(mts_array_t),
+    ]
+    lib.mts_array_mul.restype = _check_status
+
+    lib.mts_array_scale.argtypes = [
+        POINTER(mts_array_t),
+        ctypes.c_double,
+    ]
+    lib.mts_array_scale.restype = _check_status
+
+    lib.mts_array_matmul.argtypes = [
+        POINTER(mts_array_t),
+        POINTER(mts_array_t),
+        POINTER(mts_array_t),
+    ]
+    lib.mts_array_matmul.restype = _check_status
+
+    lib.mts_array_sum_axis.argtypes = [
+        POINTER(mts_array_t),
+        c_uintptr_t,
+        POINTER(mts_array_t),
+    ]
+    lib.mts_array_sum_axis.restype = _check_status
+
+    lib.mts_array_copy_from.argtypes = [
+        POINTER(mts_array_t),
+        POINTER(mts_array_t),
+    ]
+    lib.mts_array_copy_from.restype = _check_status
+
+    lib.mts_array_move_along_axis.argtypes = [
+        POINTER(mts_array_t),
+        POINTER(mts_array_t),
+        c_uintptr_t,
+        POINTER(mts_index_mapping_t),
+        c_uintptr_t,
+        POINTER(c_uintptr_t),
+    ]
+    lib.mts_array_move_along_axis.restype = _check_status
+
     lib.mts_block.argtypes = [
         mts_array_t,
         mts_labels_t,
@@ -196,6 +340,12 @@ def setup_functions(lib):
     ]
     lib.mts_block_copy.restype = POINTER(mts_block_t)
 
+    lib.mts_block_to_origin.argtypes = [
+        POINTER(mts_block_t),
+        mts_data_origin_t,
+    ]
+    lib.mts_block_to_origin.restype = POINTER(mts_block_t)
+
     lib.mts_block_labels.argtypes = [
         POINTER(mts_block_t),
         c_uintptr_t,
@@ -223,6 +373,18 @@ def setup_functions(lib):
     ]
     lib.mts_block_add_gradient.restype = _check_status
 
+    lib.mts_block_take_gradient.argtypes = [
+        POINTER(mts_block_t),
+        ctypes.c_char_p,
+    ]
+    lib.mts_block_take_gradient.restype = POINTER(mts_block_t)
+
+    lib.mts_block_remove_gradient.argtypes = [
+        POINTER(mts_block_t),
+        ctypes.c_char_p,
+    ]
+    lib.mts_block_remove_gradient.restype = _check_status
+
     lib.mts_block_gradients_list.argtypes = [
         POINTER(mts_block_t),
         POINTER(POINTER(ctypes.c_char_p)),
@@ -247,6 +409,19 @@ def setup_functions(lib):
     ]
     lib.mts_tensormap_copy.restype = POINTER(mts_tensormap_t)
 
+    lib.mts_tensormap_to_origin.argtypes = [
+        POINTER(mts_tensormap_t),
+        mts_data_origin_t,
+    ]
+    lib.mts_tensormap_to_origin.restype = POINTER(mts_tensormap_t)
+
+    lib.mts_tensormap_into_blocks.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(POINTER(mts_block_t)),
+        c_uintptr_t,
+    ]
+    lib.mts_tensormap_into_blocks.restype = _check_status
+
     lib.mts_tensormap_keys.argtypes = [
         POINTER(mts_tensormap_t),
         POINTER(mts_labels_t),
@@ -260,6 +435,46 @@ def setup_functions(lib):
     ]
     lib.mts_tensormap_block_by_id.restype = _check_status
 
+    lib.mts_tensormap_insert_block.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(ctypes.c_int64),
+        c_uintptr_t,
+        POINTER(mts_block_t),
+    ]
+    lib.mts_tensormap_insert_block.restype = _check_status
+
+    lib.mts_tensormap_remove_block.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(ctypes.c_int64),
+        c_uintptr_t,
+    ]
+    lib.mts_tensormap_remove_block.restype = POINTER(mts_block_t)
+
+    lib.mts_tensormap_replace_block.argtypes = [
+        POINTER(mts_tensormap_t),
+        c_uintptr_t,
+        POINTER(mts_block_t),
+    ]
+    lib.mts_tensormap_replace_block.restype = POINTER(mts_block_t)
+
+    lib.mts_tensormap_drop_blocks.argtypes = [
+        POINTER(mts_tensormap_t),
+        mts_labels_t,
+    ]
+    lib.mts_tensormap_drop_blocks.restype = _check_status
+
+    lib.mts_tensormap_remove_gradients.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(ctypes.c_char_p),
+        c_uintptr_t,
+    ]
+    lib.mts_tensormap_remove_gradients.restype = _check_status
+
+    lib.mts_tensormap_share_gradient_samples.argtypes = [
+        POINTER(mts_tensormap_t),
+    ]
+    lib.mts_tensormap_share_gradient_samples.restype = _check_status
+
     lib.mts_tensormap_blocks_matching.argtypes = [
         POINTER(mts_tensormap_t),
         POINTER(c_uintptr_t),
@@ -268,6 +483,26 @@ def setup_functions(lib):
     ]
     lib.mts_tensormap_blocks_matching.restype = _check_status
 
+    lib.mts_tensormap_blocks_matching_values.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(c_uintptr_t),
+        POINTER(ctypes.c_int64),
+        c_uintptr_t,
+        POINTER(c_uintptr_t),
+        POINTER(c_uintptr_t),
+    ]
+    lib.mts_tensormap_blocks_matching_values.restype = _check_status
+
+    lib.mts_tensormap_blocks_matching_many.argtypes = [
+        POINTER(mts_tensormap_t),
+        mts_labels_t,
+        POINTER(c_uintptr_t),
+        c_uintptr_t,
+        POINTER(c_uintptr_t),
+        c_uintptr_t,
+    ]
+    lib.mts_tensormap_blocks_matching_many.restype = _check_status
+
     lib.mts_tensormap_keys_to_properties.argtypes = [
         POINTER(mts_tensormap_t),
         mts_labels_t,
@@ -289,6 +524,31 @@ def setup_functions(lib):
     ]
     lib.mts_tensormap_keys_to_samples.restype = POINTER(mts_tensormap_t)
 
+    lib.mts_tensormap_builder.argtypes = [
+        POINTER(ctypes.c_char_p),
+        c_uintptr_t,
+        ctypes.c_bool,
+    ]
+    lib.mts_tensormap_builder.restype = POINTER(mts_tensormap_builder_t)
+
+    lib.mts_tensormap_builder_free.argtypes = [
+        POINTER(mts_tensormap_builder_t),
+    ]
+    lib.mts_tensormap_builder_free.restype = _check_status
+
+    lib.mts_tensormap_builder_add.argtypes = [
+        POINTER(mts_tensormap_builder_t),
+        POINTER(ctypes.c_int64),
+        c_uintptr_t,
+        POINTER(mts_block_t),
+    ]
+    lib.mts_tensormap_builder_add.restype = _check_status
+
+    lib.mts_tensormap_builder_finish.argtypes = [
+        POINTER(mts_tensormap_builder_t),
+    ]
+    lib.mts_tensormap_builder_finish.restype = POINTER(mts_tensormap_t)
+
     lib.mts_labels_load.argtypes = [
         ctypes.c_char_p,
         POINTER(mts_labels_t),
@@ -364,6 +624,12 @@ def setup_functions(lib):
     ]
     lib.mts_tensormap_save.restype = _check_status
 
+    lib.mts_tensormap_save_shared_gradient_samples.argtypes = [
+        ctypes.c_char_p,
+        POINTER(mts_tensormap_t),
+    ]
+    lib.mts_tensormap_save_shared_gradient_samples.restype = _check_status
+
     lib.mts_tensormap_save_buffer.argtypes = [
         POINTER(ctypes.c_char_p),
         POINTER(c_uintptr_t),
@@ -372,3 +638,12 @@ def setup_functions(lib):
         POINTER(mts_tensormap_t),
     ]
     lib.mts_tensormap_save_buffer.restype = _check_status
+
+    lib.mts_tensormap_save_buffer_shared_gradient_samples.argtypes = [
+        POINTER(ctypes.c_char_p),
+        POINTER(c_uintptr_t),
+        ctypes.c_void_p,
+        mts_realloc_buffer_t,
+        POINTER(mts_tensormap_t),
+    ]
+    lib.mts_tensormap_save_buffer_shared_gradient_samples.restype = _check_status
@@ -6,7 +6,7 @@ from typing import BinaryIO, List, Optional, Sequence, Tuple, Union, overload
 
 import numpy as np
 
-from ._c_api import c_uintptr_t, mts_labels_t
+from ._c_api import MTS_LABELS_INT32, c_uintptr_t, mts_labels_t
 from ._c_lib import _get_library
 from .utils import _ptr_to_const_ndarray
 
@@ -364,6 +364,10 @@ class Labels:
     def _from_mts_labels_t(cls, labels: mts_labels_t):
         assert labels.internal_ptr_ is not None
 
+        if labels.dtype != MTS_LABELS_INT32:
+            _get_library().mts_labels_free(labels)
+            raise ValueError("Labels using 64-bit values are not yet supported in Python")
+
         obj = cls.__new__(cls)
         obj._lib = _get_library()
         obj._labels = labels
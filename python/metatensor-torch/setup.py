@@ -324,7 +324,7 @@ if __name__ == "__main__":
         )
     else:
         # we are building from a sdist/installing from a wheel
-        install_requires.append("metatensor-core >=0.1.10,<0.2.0")
+        install_requires.append("metatensor-core >=0.2.0,<0.3.0")
 
     setup(
         version=create_version_number(METATENSOR_TORCH_VERSION),
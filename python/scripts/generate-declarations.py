@@ -107,6 +107,8 @@ def c_type_name(name):
         return "None"
     elif name == "uint8_t":
         return "ctypes.c_uint8"
+    elif name == "uint16_t":
+        return "ctypes.c_uint16"
     elif name == "int32_t":
         return "ctypes.c_int32"
     elif name == "uint32_t":
@@ -271,9 +273,10 @@ elif arch == "64bit":
             file.write(f"{name} = {value}\n")
         file.write("\n\n")
 
+        # these callbacks will be generated below, they depend on the structs
+        struct_callbacks = ["mts_create_array_callback_t", "mts_array_converter_t"]
         for name, c_type in data.types.items():
-            if name == "mts_create_array_callback_t":
-                # will be generated below, it depends on the structs
+            if name in struct_callbacks:
                 continue
             file.write(f"{name} = {type_to_ctypes(c_type)}\n")
 
@@ -281,8 +284,9 @@ elif arch == "64bit":
         generate_structs(file, data.structs)
 
         file.write("\n\n")
-        callback_type = type_to_ctypes(data.types["mts_create_array_callback_t"])
-        file.write(f"mts_create_array_callback_t = {callback_type}\n")
+        for name in struct_callbacks:
+            callback_type = type_to_ctypes(data.types[name])
+            file.write(f"{name} = {callback_type}\n")
 
         generate_functions(file, data.functions)
 
@@ -3,4 +3,5 @@ typedef int int64_t;
 typedef int int32_t;
 typedef int uint32_t;
 typedef int uint8_t;
+typedef int uint16_t;
 typedef int uintptr_t;
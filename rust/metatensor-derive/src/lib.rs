@@ -0,0 +1,94 @@
+//! Derive macros for the [metatensor](https://docs.rs/metatensor) crate.
+//!
+//! This crate should not be used directly, instead enable the `derive` feature
+//! of the `metatensor` crate.
+
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::needless_return, clippy::uninlined_format_args)]
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, Data, Fields};
+
+/// Implement `metatensor::LabelsEntry` for a struct with named fields.
+///
+/// Each field of the struct corresponds to one dimension of the `Labels`, using
+/// the name of the field as the name of the dimension. The type of the fields
+/// must implement `TryFrom<i64>`, and creating the struct panics if a value in
+/// the labels does not fit in the type of the corresponding field.
+#[proc_macro_derive(LabelsEntry)]
+pub fn derive_labels_entry(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    return match labels_entry_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    };
+}
+
+fn labels_entry_impl(input: &DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "LabelsEntry can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "LabelsEntry can only be derived for structs",
+            ));
+        }
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "LabelsEntry can not be derived for generic structs",
+        ));
+    }
+
+    let mut names = Vec::new();
+    let mut initializers = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let ident = field.ident.as_ref().expect("named field without a name");
+        // remove the `r#` prefix of raw identifiers
+        let name = ident.to_string().trim_start_matches("r#").to_string();
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!("'{}' is not a valid label name", name),
+            ));
+        }
+
+        initializers.push(quote! {
+            #ident: match ::std::convert::TryFrom::try_from(values[#i]) {
+                ::std::result::Result::Ok(value) => value,
+                ::std::result::Result::Err(_) => ::std::panic!(
+                    "label value {} for '{}' does not fit in the type of the field", values[#i], #name
+                ),
+            }
+        });
+        names.push(name);
+    }
+
+    let size = names.len();
+    let ident = &input.ident;
+    return Ok(quote! {
+        impl ::metatensor::LabelsEntry for #ident {
+            const NAMES: &'static [&'static str] = &[#(#names),*];
+
+            #[inline]
+            fn from_values(values: &[i64]) -> Self {
+                ::std::assert_eq!(values.len(), #size);
+                #ident {
+                    #(#initializers),*
+                }
+            }
+        }
+    });
+}
@@ -9,12 +9,15 @@
 #[cfg_attr(all(not(feature="static"), target_os="windows"), link(name="metatensor.dll", kind = "dylib"))]
 extern "C" {}
 
+pub const MTS_ARRAY_OPERATIONS_VERSION: u64 = 2;
 pub const MTS_SUCCESS: i32 = 0;
 pub const MTS_INVALID_PARAMETER_ERROR: i32 = 1;
 pub const MTS_IO_ERROR: i32 = 2;
 pub const MTS_SERIALIZATION_ERROR: i32 = 3;
 pub const MTS_BUFFER_SIZE_ERROR: i32 = 254;
 pub const MTS_INTERNAL_ERROR: i32 = 255;
+pub const MTS_LABELS_INT32: i32 = 0;
+pub const MTS_LABELS_INT64: i32 = 1;
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct mts_block_t {
@@ -22,6 +25,11 @@ pub struct mts_block_t {
 }
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
+pub struct mts_tensormap_builder_t {
+    _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
 pub struct mts_tensormap_t {
     _unused: [u8; 0],
 }
@@ -34,6 +42,8 @@ pub struct mts_labels_t {
     pub values: *const i32,
     pub size: usize,
     pub count: usize,
+    pub dtype: i32,
+    pub values_i64: *const i64,
 }
 #[test]
 fn bindgen_test_layout_mts_labels_t() {
@@ -41,7 +51,7 @@ fn bindgen_test_layout_mts_labels_t() {
     let ptr = UNINIT.as_ptr();
     assert_eq!(
         ::std::mem::size_of::<mts_labels_t>(),
-        40usize,
+        56usize,
         concat!("Size of: ", stringify!(mts_labels_t))
     );
     assert_eq!(
@@ -99,6 +109,26 @@ fn bindgen_test_layout_mts_labels_t() {
             stringify!(count)
         )
     );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).dtype) as usize - ptr as usize },
+        40usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_labels_t),
+            "::",
+            stringify!(dtype)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).values_i64) as usize - ptr as usize },
+        48usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_labels_t),
+            "::",
+            stringify!(values_i64)
+        )
+    );
 }
 pub type mts_data_origin_t = u64;
 #[repr(C)]
@@ -320,6 +350,344 @@ fn bindgen_test_layout_mts_array_t() {
         )
     );
 }
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct mts_array_descriptor_t {
+    pub device_type: i32,
+    pub device_id: i32,
+    pub dtype_code: u8,
+    pub dtype_bits: u8,
+    pub dtype_lanes: u16,
+}
+#[test]
+fn bindgen_test_layout_mts_array_descriptor_t() {
+    const UNINIT: ::std::mem::MaybeUninit<mts_array_descriptor_t> =
+        ::std::mem::MaybeUninit::uninit();
+    let ptr = UNINIT.as_ptr();
+    assert_eq!(
+        ::std::mem::size_of::<mts_array_descriptor_t>(),
+        12usize,
+        concat!("Size of: ", stringify!(mts_array_descriptor_t))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<mts_array_descriptor_t>(),
+        4usize,
+        concat!("Alignment of ", stringify!(mts_array_descriptor_t))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).device_type) as usize - ptr as usize },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_descriptor_t),
+            "::",
+            stringify!(device_type)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).device_id) as usize - ptr as usize },
+        4usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_descriptor_t),
+            "::",
+            stringify!(device_id)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).dtype_code) as usize - ptr as usize },
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_descriptor_t),
+            "::",
+            stringify!(dtype_code)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).dtype_bits) as usize - ptr as usize },
+        9usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_descriptor_t),
+            "::",
+            stringify!(dtype_bits)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).dtype_lanes) as usize - ptr as usize },
+        10usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_descriptor_t),
+            "::",
+            stringify!(dtype_lanes)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mts_index_mapping_t {
+    pub input: usize,
+    pub output: usize,
+}
+#[test]
+fn bindgen_test_layout_mts_index_mapping_t() {
+    const UNINIT: ::std::mem::MaybeUninit<mts_index_mapping_t> = ::std::mem::MaybeUninit::uninit();
+    let ptr = UNINIT.as_ptr();
+    assert_eq!(
+        ::std::mem::size_of::<mts_index_mapping_t>(),
+        16usize,
+        concat!("Size of: ", stringify!(mts_index_mapping_t))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<mts_index_mapping_t>(),
+        8usize,
+        concat!("Alignment of ", stringify!(mts_index_mapping_t))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).input) as usize - ptr as usize },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_index_mapping_t),
+            "::",
+            stringify!(input)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).output) as usize - ptr as usize },
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_index_mapping_t),
+            "::",
+            stringify!(output)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mts_array_operations_t {
+    pub version: u64,
+    pub add: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *mut ::std::os::raw::c_void,
+            other: *const ::std::os::raw::c_void,
+        ) -> mts_status_t,
+    >,
+    pub mul: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *mut ::std::os::raw::c_void,
+            other: *const ::std::os::raw::c_void,
+        ) -> mts_status_t,
+    >,
+    pub scale: ::std::option::Option<
+        unsafe extern "C" fn(array: *mut ::std::os::raw::c_void, factor: f64) -> mts_status_t,
+    >,
+    pub matmul: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *const ::std::os::raw::c_void,
+            other: *const ::std::os::raw::c_void,
+            output: *mut mts_array_t,
+        ) -> mts_status_t,
+    >,
+    pub sum_axis: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *const ::std::os::raw::c_void,
+            axis: usize,
+            output: *mut mts_array_t,
+        ) -> mts_status_t,
+    >,
+    pub copy_from: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *mut ::std::os::raw::c_void,
+            other: *const ::std::os::raw::c_void,
+        ) -> mts_status_t,
+    >,
+    pub descriptor: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *const ::std::os::raw::c_void,
+            descriptor: *mut mts_array_descriptor_t,
+        ) -> mts_status_t,
+    >,
+    pub raw_data: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *mut ::std::os::raw::c_void,
+            data: *mut *mut ::std::os::raw::c_void,
+        ) -> mts_status_t,
+    >,
+    pub move_along_axis: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *mut ::std::os::raw::c_void,
+            input: *const ::std::os::raw::c_void,
+            axis: usize,
+            mapping: *const mts_index_mapping_t,
+            mapping_count: usize,
+            output_offsets: *const usize,
+        ) -> mts_status_t,
+    >,
+    pub sparse_csr: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *const ::std::os::raw::c_void,
+            values: *mut *const f64,
+            indices: *mut *const usize,
+            indptr: *mut *const usize,
+            nnz: *mut usize,
+        ) -> mts_status_t,
+    >,
+    pub create_sparse_csr: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *const ::std::os::raw::c_void,
+            shape: *const usize,
+            shape_count: usize,
+            values: *const f64,
+            indices: *const usize,
+            indptr: *const usize,
+            nnz: usize,
+            output: *mut mts_array_t,
+        ) -> mts_status_t,
+    >,
+}
+#[test]
+fn bindgen_test_layout_mts_array_operations_t() {
+    const UNINIT: ::std::mem::MaybeUninit<mts_array_operations_t> =
+        ::std::mem::MaybeUninit::uninit();
+    let ptr = UNINIT.as_ptr();
+    assert_eq!(
+        ::std::mem::size_of::<mts_array_operations_t>(),
+        96usize,
+        concat!("Size of: ", stringify!(mts_array_operations_t))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<mts_array_operations_t>(),
+        8usize,
+        concat!("Alignment of ", stringify!(mts_array_operations_t))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).version) as usize - ptr as usize },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(version)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).add) as usize - ptr as usize },
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(add)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).mul) as usize - ptr as usize },
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(mul)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).scale) as usize - ptr as usize },
+        24usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(scale)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).matmul) as usize - ptr as usize },
+        32usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(matmul)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).sum_axis) as usize - ptr as usize },
+        40usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(sum_axis)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).copy_from) as usize - ptr as usize },
+        48usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(copy_from)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).descriptor) as usize - ptr as usize },
+        56usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(descriptor)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).raw_data) as usize - ptr as usize },
+        64usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(raw_data)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).move_along_axis) as usize - ptr as usize },
+        72usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(move_along_axis)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).sparse_csr) as usize - ptr as usize },
+        80usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(sparse_csr)
+        )
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).create_sparse_csr) as usize - ptr as usize },
+        88usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_operations_t),
+            "::",
+            stringify!(create_sparse_csr)
+        )
+    );
+}
+pub type mts_array_converter_t = ::std::option::Option<
+    unsafe extern "C" fn(array: *const mts_array_t, output: *mut mts_array_t) -> mts_status_t,
+>;
 pub type mts_realloc_buffer_t = ::std::option::Option<
     unsafe extern "C" fn(
         user_data: *mut ::std::os::raw::c_void,
@@ -346,6 +714,13 @@ extern "C" {
         result: *mut i64,
     ) -> mts_status_t;
     #[must_use]
+    pub fn mts_labels_position_i64(
+        labels: mts_labels_t,
+        values: *const i64,
+        values_count: usize,
+        result: *mut i64,
+    ) -> mts_status_t;
+    #[must_use]
     pub fn mts_labels_create(labels: *mut mts_labels_t) -> mts_status_t;
     #[must_use]
     pub fn mts_labels_set_user_data(
@@ -363,6 +738,8 @@ extern "C" {
     #[must_use]
     pub fn mts_labels_clone(labels: mts_labels_t, clone: *mut mts_labels_t) -> mts_status_t;
     #[must_use]
+    pub fn mts_labels_fingerprint(labels: mts_labels_t, fingerprint: *mut u64) -> mts_status_t;
+    #[must_use]
     pub fn mts_labels_union(
         first: mts_labels_t,
         second: mts_labels_t,
@@ -402,6 +779,62 @@ extern "C" {
         buffer: *mut ::std::os::raw::c_char,
         buffer_size: usize,
     ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_register_array_operations(
+        origin: mts_data_origin_t,
+        operations: *const mts_array_operations_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_register_array_converter(
+        from: mts_data_origin_t,
+        to: mts_data_origin_t,
+        converter: mts_array_converter_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_to_origin(
+        array: *const mts_array_t,
+        origin: mts_data_origin_t,
+        output: *mut mts_array_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_descriptor(
+        array: *const mts_array_t,
+        descriptor: *mut mts_array_descriptor_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_to_dlpack(
+        array: *const mts_array_t,
+        tensor: *mut *mut ::std::os::raw::c_void,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_add(array: *mut mts_array_t, other: *const mts_array_t) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_mul(array: *mut mts_array_t, other: *const mts_array_t) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_scale(array: *mut mts_array_t, factor: f64) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_matmul(
+        array: *const mts_array_t,
+        other: *const mts_array_t,
+        output: *mut mts_array_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_sum_axis(
+        array: *const mts_array_t,
+        axis: usize,
+        output: *mut mts_array_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_copy_from(array: *mut mts_array_t, other: *const mts_array_t) -> mts_status_t;
+    #[must_use]
+    pub fn mts_array_move_along_axis(
+        array: *mut mts_array_t,
+        input: *const mts_array_t,
+        axis: usize,
+        mapping: *const mts_index_mapping_t,
+        mapping_count: usize,
+        output_offsets: *const usize,
+    ) -> mts_status_t;
     pub fn mts_block(
         data: mts_array_t,
         samples: mts_labels_t,
@@ -412,6 +845,10 @@ extern "C" {
     #[must_use]
     pub fn mts_block_free(block: *mut mts_block_t) -> mts_status_t;
     pub fn mts_block_copy(block: *const mts_block_t) -> *mut mts_block_t;
+    pub fn mts_block_to_origin(
+        block: *const mts_block_t,
+        origin: mts_data_origin_t,
+    ) -> *mut mts_block_t;
     #[must_use]
     pub fn mts_block_labels(
         block: *const mts_block_t,
@@ -432,6 +869,15 @@ extern "C" {
         parameter: *const ::std::os::raw::c_char,
         gradient: *mut mts_block_t,
     ) -> mts_status_t;
+    pub fn mts_block_take_gradient(
+        block: *mut mts_block_t,
+        parameter: *const ::std::os::raw::c_char,
+    ) -> *mut mts_block_t;
+    #[must_use]
+    pub fn mts_block_remove_gradient(
+        block: *mut mts_block_t,
+        parameter: *const ::std::os::raw::c_char,
+    ) -> mts_status_t;
     #[must_use]
     pub fn mts_block_gradients_list(
         block: *const mts_block_t,
@@ -446,6 +892,16 @@ extern "C" {
     #[must_use]
     pub fn mts_tensormap_free(tensor: *mut mts_tensormap_t) -> mts_status_t;
     pub fn mts_tensormap_copy(tensor: *const mts_tensormap_t) -> *mut mts_tensormap_t;
+    pub fn mts_tensormap_to_origin(
+        tensor: *const mts_tensormap_t,
+        origin: mts_data_origin_t,
+    ) -> *mut mts_tensormap_t;
+    #[must_use]
+    pub fn mts_tensormap_into_blocks(
+        tensor: *mut mts_tensormap_t,
+        blocks: *mut *mut mts_block_t,
+        blocks_count: usize,
+    ) -> mts_status_t;
     #[must_use]
     pub fn mts_tensormap_keys(
         tensor: *const mts_tensormap_t,
@@ -458,12 +914,62 @@ extern "C" {
         index: usize,
     ) -> mts_status_t;
     #[must_use]
+    pub fn mts_tensormap_insert_block(
+        tensor: *mut mts_tensormap_t,
+        key: *const i64,
+        key_count: usize,
+        block: *mut mts_block_t,
+    ) -> mts_status_t;
+    pub fn mts_tensormap_remove_block(
+        tensor: *mut mts_tensormap_t,
+        key: *const i64,
+        key_count: usize,
+    ) -> *mut mts_block_t;
+    pub fn mts_tensormap_replace_block(
+        tensor: *mut mts_tensormap_t,
+        index: usize,
+        block: *mut mts_block_t,
+    ) -> *mut mts_block_t;
+    #[must_use]
+    pub fn mts_tensormap_drop_blocks(
+        tensor: *mut mts_tensormap_t,
+        selection: mts_labels_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_remove_gradients(
+        tensor: *mut mts_tensormap_t,
+        parameters: *const *const ::std::os::raw::c_char,
+        parameters_count: usize,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_share_gradient_samples(
+        tensor: *mut mts_tensormap_t,
+    ) -> mts_status_t;
+    #[must_use]
     pub fn mts_tensormap_blocks_matching(
         tensor: *const mts_tensormap_t,
         block_indexes: *mut usize,
         count: *mut usize,
         selection: mts_labels_t,
     ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_blocks_matching_values(
+        tensor: *const mts_tensormap_t,
+        dimensions: *const usize,
+        values: *const i64,
+        values_count: usize,
+        block_indexes: *mut usize,
+        count: *mut usize,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_blocks_matching_many(
+        tensor: *const mts_tensormap_t,
+        selection: mts_labels_t,
+        block_indexes: *mut usize,
+        block_indexes_count: usize,
+        offsets: *mut usize,
+        offsets_count: usize,
+    ) -> mts_status_t;
     pub fn mts_tensormap_keys_to_properties(
         tensor: *const mts_tensormap_t,
         keys_to_move: mts_labels_t,
@@ -479,6 +985,23 @@ extern "C" {
         keys_to_move: mts_labels_t,
         sort_samples: bool,
     ) -> *mut mts_tensormap_t;
+    pub fn mts_tensormap_builder(
+        keys_names: *const *const ::std::os::raw::c_char,
+        keys_names_count: usize,
+        sort_samples: bool,
+    ) -> *mut mts_tensormap_builder_t;
+    #[must_use]
+    pub fn mts_tensormap_builder_free(builder: *mut mts_tensormap_builder_t) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_builder_add(
+        builder: *mut mts_tensormap_builder_t,
+        key: *const i64,
+        key_count: usize,
+        chunk: *mut mts_block_t,
+    ) -> mts_status_t;
+    pub fn mts_tensormap_builder_finish(
+        builder: *mut mts_tensormap_builder_t,
+    ) -> *mut mts_tensormap_t;
     #[must_use]
     pub fn mts_labels_load(
         path: *const ::std::os::raw::c_char,
@@ -540,6 +1063,11 @@ extern "C" {
         tensor: *const mts_tensormap_t,
     ) -> mts_status_t;
     #[must_use]
+    pub fn mts_tensormap_save_shared_gradient_samples(
+        path: *const ::std::os::raw::c_char,
+        tensor: *const mts_tensormap_t,
+    ) -> mts_status_t;
+    #[must_use]
     pub fn mts_tensormap_save_buffer(
         buffer: *mut *mut u8,
         buffer_count: *mut usize,
@@ -547,4 +1075,12 @@ extern "C" {
         realloc: mts_realloc_buffer_t,
         tensor: *const mts_tensormap_t,
     ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_save_buffer_shared_gradient_samples(
+        buffer: *mut *mut u8,
+        buffer_count: *mut usize,
+        realloc_user_data: *mut ::std::os::raw::c_void,
+        realloc: mts_realloc_buffer_t,
+        tensor: *const mts_tensormap_t,
+    ) -> mts_status_t;
 }
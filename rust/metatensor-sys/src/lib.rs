@@ -14,6 +14,35 @@ impl mts_labels_t {
             values: std::ptr::null(),
             size: 0,
             count: 0,
+            dtype: MTS_LABELS_INT32,
+            values_i64: std::ptr::null(),
+        }
+    }
+}
+
+impl mts_array_descriptor_t {
+    /// Descriptor used for arrays with unknown device and data type
+    pub fn unknown() -> mts_array_descriptor_t {
+        mts_array_descriptor_t {
+            device_type: 0,
+            device_id: 0,
+            dtype_code: 0,
+            dtype_bits: 0,
+            dtype_lanes: 0,
+        }
+    }
+
+    /// Descriptor for arrays on CPU containing floating point values with the
+    /// given number of `bits`
+    pub fn cpu_float(bits: u8) -> mts_array_descriptor_t {
+        mts_array_descriptor_t {
+            // kDLCPU
+            device_type: 1,
+            device_id: 0,
+            // kDLFloat
+            dtype_code: 2,
+            dtype_bits: bits,
+            dtype_lanes: 1,
         }
     }
 }
@@ -56,11 +85,23 @@ impl From<Box<dyn std::any::Any + Send + 'static>> for Error {
     }
 }
 
+/// Error code used to indicate failure of a Rust function
+#[doc(hidden)]
+pub const RUST_FUNCTION_FAILED_ERROR_CODE: mts_status_t = -4242;
+
+thread_local! {
+    /// Storage for the last error coming from a Rust function
+    #[doc(hidden)]
+    pub static LAST_RUST_ERROR: std::cell::RefCell<Error> = const { std::cell::RefCell::new(Error {code: None, message: String::new()}) };
+}
+
 /// Check the status code returned by arbitrary functions inside an
 /// `mts_array_t`
 fn check_status_external(status: mts_status_t, function: &str) -> Result<(), Error> {
     if status == MTS_SUCCESS {
         return Ok(());
+    } else if status == RUST_FUNCTION_FAILED_ERROR_CODE {
+        return Err(LAST_RUST_ERROR.with(|e| e.borrow().clone()));
     } else if status > 0 {
         let message = unsafe {
             std::ffi::CStr::from_ptr(mts_last_error())
@@ -235,6 +276,118 @@ impl mts_array_t {
 
         return Ok(());
     }
+
+    /// call `mts_array_descriptor` with a more convenient API, returning
+    /// `None` if the device and data type of this array are unknown
+    pub fn descriptor(&self) -> Result<Option<mts_array_descriptor_t>, Error> {
+        let mut descriptor = mts_array_descriptor_t::unknown();
+        unsafe {
+            check_status_external(mts_array_descriptor(self, &mut descriptor), "mts_array_descriptor")?;
+        }
+
+        if descriptor.device_type == 0 {
+            return Ok(None);
+        }
+        return Ok(Some(descriptor));
+    }
+
+    /// call `mts_array_to_dlpack` with a more convenient API. The returned
+    /// pointer is a `DLManagedTensor*`, which borrows data from this array.
+    pub fn to_dlpack(&self) -> Result<*mut std::os::raw::c_void, Error> {
+        let mut tensor = std::ptr::null_mut();
+        unsafe {
+            check_status_external(mts_array_to_dlpack(self, &mut tensor), "mts_array_to_dlpack")?;
+        }
+        return Ok(tensor);
+    }
+
+    /// call `mts_array_to_origin` with a more convenient API
+    pub fn to_origin(&self, origin: mts_data_origin_t) -> Result<mts_array_t, Error> {
+        let mut output = mts_array_t::null();
+        unsafe {
+            check_status_external(mts_array_to_origin(self, origin, &mut output), "mts_array_to_origin")?;
+        }
+        return Ok(output);
+    }
+
+    /// call `mts_array_add` with a more convenient API
+    pub fn add(&mut self, other: &mts_array_t) -> Result<(), Error> {
+        unsafe {
+            check_status_external(mts_array_add(self, other), "mts_array_add")?;
+        }
+        return Ok(());
+    }
+
+    /// call `mts_array_mul` with a more convenient API
+    pub fn mul(&mut self, other: &mts_array_t) -> Result<(), Error> {
+        unsafe {
+            check_status_external(mts_array_mul(self, other), "mts_array_mul")?;
+        }
+        return Ok(());
+    }
+
+    /// call `mts_array_scale` with a more convenient API
+    pub fn scale(&mut self, factor: f64) -> Result<(), Error> {
+        unsafe {
+            check_status_external(mts_array_scale(self, factor), "mts_array_scale")?;
+        }
+        return Ok(());
+    }
+
+    /// call `mts_array_matmul` with a more convenient API
+    pub fn matmul(&self, other: &mts_array_t) -> Result<mts_array_t, Error> {
+        let mut output = mts_array_t::null();
+        unsafe {
+            check_status_external(mts_array_matmul(self, other, &mut output), "mts_array_matmul")?;
+        }
+        return Ok(output);
+    }
+
+    /// call `mts_array_sum_axis` with a more convenient API
+    pub fn sum_axis(&self, axis: usize) -> Result<mts_array_t, Error> {
+        let mut output = mts_array_t::null();
+        unsafe {
+            check_status_external(mts_array_sum_axis(self, axis, &mut output), "mts_array_sum_axis")?;
+        }
+        return Ok(output);
+    }
+
+    /// call `mts_array_copy_from` with a more convenient API
+    pub fn copy_from(&mut self, other: &mts_array_t) -> Result<(), Error> {
+        unsafe {
+            check_status_external(mts_array_copy_from(self, other), "mts_array_copy_from")?;
+        }
+        return Ok(());
+    }
+
+    /// call `mts_array_move_along_axis` with a more convenient API
+    pub fn move_along_axis(
+        &mut self,
+        input: &mts_array_t,
+        axis: usize,
+        mapping: &[mts_index_mapping_t],
+        output_offsets: &[usize],
+    ) -> Result<(), Error> {
+        let ndim = input.shape()?.len();
+        if output_offsets.len() != ndim {
+            return Err(Error {
+                code: None,
+                message: format!("expected {} output offsets, got {}", ndim, output_offsets.len()),
+            });
+        }
+
+        unsafe {
+            check_status_external(mts_array_move_along_axis(
+                self,
+                input,
+                axis,
+                mapping.as_ptr(),
+                mapping.len(),
+                output_offsets.as_ptr(),
+            ), "mts_array_move_along_axis")?;
+        }
+        return Ok(());
+    }
 }
 
 /// Path where the metatensor shared library has been built
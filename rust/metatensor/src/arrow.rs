@@ -0,0 +1,340 @@
+//! Conversion of [`Labels`] and [`TensorBlock`] to and from
+//! [Apache Arrow](https://arrow.apache.org/) `RecordBatch`.
+//!
+//! This module is only available with the `arrow` feature.
+
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use arrow_array::{Array as _, ArrayRef as ArrowArrayRef, RecordBatch, RecordBatchOptions};
+use arrow_array::{Int32Array, Int64Array, Float64Array, FixedSizeListArray};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Int32Type, Int64Type};
+use arrow_buffer::{Buffer, ScalarBuffer};
+use arrow_schema::{DataType, Field, Schema};
+
+use crate::{Labels, LabelsBuilder, LabelsDType, TensorBlock, TensorBlockRef, Error};
+
+/// Name of the column containing the values in the `RecordBatch` created by
+/// [`TensorBlockRef::to_arrow`] and [`TensorBlock::into_arrow`]
+pub const VALUES_COLUMN: &str = "values";
+
+impl Labels {
+    /// Convert these `Labels` to an Arrow `RecordBatch`, with one column for
+    /// each dimension of the labels.
+    ///
+    /// The columns use `Int32` or `Int64` depending on the [`LabelsDType`] of
+    /// these labels. Since `Labels` are stored row by row and Arrow is a
+    /// columnar format, the values are always copied.
+    pub fn to_arrow(&self) -> RecordBatch {
+        let names = self.names();
+        let size = self.size();
+
+        let mut fields = Vec::new();
+        let mut columns: Vec<ArrowArrayRef> = Vec::new();
+        match self.dtype() {
+            LabelsDType::Int32 => {
+                let values = self.values();
+                for (i, name) in names.iter().enumerate() {
+                    fields.push(Field::new(*name, DataType::Int32, false));
+                    let column = values.iter().skip(i).step_by(size).map(|v| v.i32());
+                    columns.push(Arc::new(Int32Array::from_iter_values(column)));
+                }
+            }
+            LabelsDType::Int64 => {
+                let values = self.values_i64();
+                for (i, name) in names.iter().enumerate() {
+                    fields.push(Field::new(*name, DataType::Int64, false));
+                    let column = values.iter().skip(i).step_by(size).copied();
+                    columns.push(Arc::new(Int64Array::from_iter_values(column)));
+                }
+            }
+        }
+
+        let options = RecordBatchOptions::new().with_row_count(Some(self.count()));
+        return RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)
+            .expect("failed to create RecordBatch from Labels");
+    }
+
+    /// Create new `Labels` from an Arrow `RecordBatch`.
+    ///
+    /// Each column of the batch becomes one dimension of the labels, using the
+    /// column name as the dimension name. All the columns must contain `Int32`
+    /// or `Int64` values without nulls. If any of the column uses `Int64`, the
+    /// resulting labels will use 64-bit values.
+    pub fn from_arrow(batch: &RecordBatch) -> Result<Labels, Error> {
+        let schema = batch.schema();
+        let names = schema.fields().iter().map(|f| f.name().as_str()).collect::<Vec<_>>();
+
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return Err(Error {
+                    code: None,
+                    message: format!("invalid labels: the name '{}' is used multiple times", name),
+                });
+            }
+        }
+
+        let mut dtype = LabelsDType::Int32;
+        for (field, column) in schema.fields().iter().zip(batch.columns()) {
+            match field.data_type() {
+                DataType::Int32 => {},
+                DataType::Int64 => dtype = LabelsDType::Int64,
+                other => {
+                    return Err(Error {
+                        code: None,
+                        message: format!(
+                            "invalid column '{}' for Labels: expected Int32 or Int64 data, got {}",
+                            field.name(), other
+                        ),
+                    });
+                }
+            }
+
+            if column.null_count() != 0 {
+                return Err(Error {
+                    code: None,
+                    message: format!("invalid column '{}' for Labels: it contains null values", field.name()),
+                });
+            }
+        }
+
+        let count = batch.num_rows();
+        if names.is_empty() && count != 0 {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "invalid labels: can not create Labels without dimensions from a batch with {} rows",
+                    count
+                ),
+            });
+        }
+
+        let mut builder = match dtype {
+            LabelsDType::Int32 => LabelsBuilder::new(names),
+            LabelsDType::Int64 => LabelsBuilder::new_i64(names),
+        };
+        builder.reserve(count);
+
+        let mut entry = vec![0; batch.num_columns()];
+        for row in 0..count {
+            for (value, column) in entry.iter_mut().zip(batch.columns()) {
+                *value = match column.data_type() {
+                    DataType::Int32 => i64::from(column.as_primitive::<Int32Type>().value(row)),
+                    DataType::Int64 => column.as_primitive::<Int64Type>().value(row),
+                    _ => unreachable!(),
+                };
+            }
+
+            match dtype {
+                LabelsDType::Int32 => {
+                    let entry = entry.iter()
+                        .map(|&v| i32::try_from(v).expect("all columns should be Int32"))
+                        .collect::<Vec<_>>();
+                    builder.add(&entry);
+                }
+                LabelsDType::Int64 => builder.add_i64(&entry),
+            }
+        }
+
+        return builder.try_finish();
+    }
+}
+
+impl TensorBlockRef<'_> {
+    /// Convert the values of this block to an Arrow `RecordBatch`.
+    ///
+    /// The block must not have any components. The batch contains one column
+    /// for each dimension of the samples, and a [`VALUES_COLUMN`] column
+    /// containing a fixed size list of `Float64` with one element for each
+    /// property. The values are copied in the `RecordBatch`, use
+    /// [`TensorBlock::into_arrow`] to avoid this copy.
+    pub fn to_arrow(&self) -> Result<RecordBatch, Error> {
+        let values = Buffer::from_slice_ref(block_values(self)?);
+        return block_record_batch(&self.samples(), &self.properties(), values);
+    }
+}
+
+impl TensorBlock {
+    /// Convert the values of this block to an Arrow `RecordBatch`, without
+    /// copying the values.
+    ///
+    /// The layout of the `RecordBatch` is the same as for
+    /// [`TensorBlockRef::to_arrow`]. The block is kept alive until the values
+    /// in the `RecordBatch` are no longer used.
+    pub fn into_arrow(self) -> Result<RecordBatch, Error> {
+        let block = self.as_ref();
+        let values = block_values(&block)?;
+        let (ptr, len) = (values.as_ptr(), std::mem::size_of_val(values));
+
+        let samples = block.samples();
+        let properties = block.properties();
+
+        let values = if len == 0 {
+            Buffer::from_vec(Vec::<f64>::new())
+        } else {
+            let ptr = NonNull::new(ptr as *mut u8).expect("got a NULL pointer for the data");
+            // SAFETY: `ptr` points to `len` bytes owned by the block, and the
+            // block is kept alive as long as the buffer is alive. Moving the
+            // block does not move the data.
+            unsafe {
+                Buffer::from_custom_allocation(ptr, len, Arc::new(self))
+            }
+        };
+
+        return block_record_batch(&samples, &properties, values);
+    }
+}
+
+/// Get the values of a 2D block as a slice
+fn block_values<'a>(block: &TensorBlockRef<'a>) -> Result<&'a [f64], Error> {
+    let array = block.values();
+    let shape = array.as_raw().shape()?;
+    if shape.len() != 2 {
+        return Err(Error {
+            code: None,
+            message: format!(
+                "can only convert blocks without components to Arrow, this block values have {} dimensions",
+                shape.len()
+            ),
+        });
+    }
+
+    // `mts_array_t::data` requires a mutable reference, but we only read from
+    // the data here
+    let mut raw = *array.as_raw();
+    let data = raw.data()?;
+    // SAFETY: the data is owned by the block, which lives for 'a
+    return Ok(unsafe { std::slice::from_raw_parts(data.as_ptr(), data.len()) });
+}
+
+/// Create the `RecordBatch` for a 2D block, using the given buffer as values
+fn block_record_batch(samples: &Labels, properties: &Labels, values: Buffer) -> Result<RecordBatch, Error> {
+    let samples = samples.to_arrow();
+    let n_properties = properties.count();
+
+    let list_size = i32::try_from(n_properties).map_err(|_| Error {
+        code: None,
+        message: format!("too many properties to convert this block to Arrow ({})", n_properties),
+    })?;
+
+    if samples.schema().field_with_name(VALUES_COLUMN).is_ok() {
+        return Err(Error {
+            code: None,
+            message: format!(
+                "can not convert this block to Arrow: the samples already contain a '{}' dimension",
+                VALUES_COLUMN
+            ),
+        });
+    }
+
+    let item = Arc::new(Field::new("item", DataType::Float64, false));
+    let values = FixedSizeListArray::try_new(
+        Arc::clone(&item),
+        list_size,
+        Arc::new(Float64Array::new(ScalarBuffer::from(values), None)),
+        None,
+    ).map_err(|e| Error { code: None, message: e.to_string() })?;
+
+    let mut fields = samples.schema().fields().iter().cloned().collect::<Vec<_>>();
+    fields.push(Arc::new(Field::new(VALUES_COLUMN, DataType::FixedSizeList(item, list_size), false)));
+
+    let mut columns = samples.columns().to_vec();
+    columns.push(Arc::new(values));
+
+    let options = RecordBatchOptions::new().with_row_count(Some(samples.num_rows()));
+    return RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)
+        .map_err(|e| Error { code: None, message: e.to_string() });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, Int32Array, RecordBatch};
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Int32Type, Float64Type};
+    use arrow_schema::{DataType, Field, Schema};
+
+    use crate::{Labels, TensorBlock};
+
+    #[test]
+    fn labels() {
+        let labels = Labels::new(["system", "atom"], &[[0, 1], [0, 2], [3, 4]]);
+        let batch = labels.to_arrow();
+
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(batch.schema().field(0).name(), "system");
+        assert_eq!(batch.schema().field(1).name(), "atom");
+        assert_eq!(batch.column(0).as_primitive::<Int32Type>().values(), &[0, 0, 3]);
+        assert_eq!(batch.column(1).as_primitive::<Int32Type>().values(), &[1, 2, 4]);
+
+        assert_eq!(Labels::from_arrow(&batch).unwrap(), labels);
+
+        let labels = Labels::new_i64(["system"], &[[0], [1 << 40]]);
+        let converted = Labels::from_arrow(&labels.to_arrow()).unwrap();
+        assert_eq!(converted.dtype(), crate::LabelsDType::Int64);
+        assert_eq!(converted, labels);
+
+        let labels = Labels::empty(vec!["a", "b"]);
+        let converted = Labels::from_arrow(&labels.to_arrow()).unwrap();
+        assert_eq!(converted.names(), ["a", "b"]);
+        assert_eq!(converted.count(), 0);
+    }
+
+    #[test]
+    fn labels_errors() {
+        let labels = Labels::new(["system", "atom"], &[[0, 1], [0, 2]]);
+        let batch = labels.to_arrow();
+
+        let error = Labels::from_arrow(&batch.project(&[0, 0]).unwrap()).unwrap_err();
+        assert_eq!(error.message, "invalid labels: the name 'system' is used multiple times");
+
+        let error = Labels::from_arrow(&batch.project(&[0]).unwrap()).unwrap_err();
+        assert!(error.message.contains("can not have the same label value multiple time"));
+
+        let error = Labels::from_arrow(&batch.project(&[]).unwrap()).unwrap_err();
+        assert_eq!(error.message, "invalid labels: can not create Labels without dimensions from a batch with 2 rows");
+
+        let schema = Schema::new(vec![Field::new("a\0b", DataType::Int32, false)]);
+        let column = Arc::new(Int32Array::from(vec![0, 1])) as ArrayRef;
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![column]).unwrap();
+        let error = Labels::from_arrow(&batch).unwrap_err();
+        assert_eq!(error.message, "invalid labels: the name \"a\\0b\" contains a NUL byte");
+    }
+
+    #[test]
+    fn block() {
+        let values = ndarray::ArrayD::from_shape_vec(vec![3, 2], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        let block = TensorBlock::new(
+            values,
+            &Labels::new(["system", "atom"], &[[0, 1], [0, 2], [3, 4]]),
+            &[],
+            &Labels::new(["n"], &[[0], [1]]),
+        ).unwrap();
+
+        let batch = block.as_ref().to_arrow().unwrap();
+        assert_eq!(batch.num_columns(), 3);
+        assert_eq!(batch.schema().field(2).name(), super::VALUES_COLUMN);
+        let values = batch.column(2).as_fixed_size_list();
+        assert_eq!(values.value_length(), 2);
+        assert_eq!(values.values().as_primitive::<Float64Type>().values(), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let data_ptr = block.values().as_array().as_ptr();
+        let batch = block.into_arrow().unwrap();
+        let values = batch.column(2).as_fixed_size_list().values().as_primitive::<Float64Type>();
+        assert_eq!(values.values(), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(values.values().as_ptr(), data_ptr);
+
+        let block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![3, 2, 2], 1.0),
+            &Labels::new(["s"], &[[0], [1], [2]]),
+            &[Labels::new(["c"], &[[0], [1]])],
+            &Labels::new(["n"], &[[0], [1]]),
+        ).unwrap();
+
+        let error = block.as_ref().to_arrow().unwrap_err();
+        assert_eq!(error.message, "can only convert blocks without components to Arrow, this block values have 3 dimensions");
+    }
+}
@@ -2,15 +2,19 @@ use std::ffi::{CString, CStr};
 use std::iter::FusedIterator;
 
 use crate::c_api::{mts_block_t, mts_array_t, MTS_INVALID_PARAMETER_ERROR};
-use crate::{ArrayRef, ArrayRefMut, Labels, Error};
+use crate::{Array, ArrayRef, ArrayRefMut, Labels, Error, TensorBlock};
 
 use super::{TensorBlockRef, LazyMetadata};
-use super::block_ref::{get_samples, get_components, get_properties};
+use super::block_ref::{get_samples, get_components, get_properties, missing_gradient};
 
 /// Mutable reference to a [`TensorBlock`](crate::TensorBlock)
 #[derive(Debug)]
 pub struct TensorBlockRefMut<'a> {
     ptr: *mut mts_block_t,
+    /// Is this block (or the parent of this gradient) part of a `TensorMap`?
+    /// Adding or taking gradients is not allowed for these blocks, since all
+    /// the blocks in a `TensorMap` must have the same gradients.
+    in_tensor_map: bool,
     marker: std::marker::PhantomData<&'a mut mts_block_t>,
 }
 
@@ -73,10 +77,33 @@ impl<'a> TensorBlockRefMut<'a> {
 
         TensorBlockRefMut {
             ptr: ptr,
+            in_tensor_map: false,
             marker: std::marker::PhantomData,
         }
     }
 
+    /// Mark this block as being part of a `TensorMap`
+    pub(crate) fn in_tensor_map(mut self) -> TensorBlockRefMut<'a> {
+        self.in_tensor_map = true;
+        return self;
+    }
+
+    /// Get an error if this block is part of a `TensorMap`, where gradients
+    /// can not be added, taken or removed from a single block.
+    fn check_not_in_tensor_map(&self, action: &str) -> Result<(), Error> {
+        if self.in_tensor_map {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "can not {} gradients of a block inside a TensorMap, since \
+                    all the blocks must have the same gradients",
+                    action
+                ),
+            });
+        }
+        return Ok(());
+    }
+
     /// Get the underlying raw pointer
     pub(super) fn as_ptr(&self) -> *const mts_block_t {
         self.ptr
@@ -133,6 +160,22 @@ impl<'a> TensorBlockRefMut<'a> {
         return self.as_ref().values();
     }
 
+    /// Get the values in this block as a reference to `T`, or an error
+    /// containing the data origin of the values if they are not stored in a
+    /// `T`.
+    #[inline]
+    pub fn values_as<T: Array>(&self) -> Result<&T, Error> {
+        return self.values().try_to();
+    }
+
+    /// Get the values in this block as a mutable reference to `T`, or an
+    /// error containing the data origin of the values if they are not stored
+    /// in a `T`.
+    #[inline]
+    pub fn values_as_mut<T: Array>(&mut self) -> Result<&mut T, Error> {
+        return self.values_mut().try_to_mut();
+    }
+
     /// Get the samples for this block
     #[inline]
     pub fn samples(&self) -> Labels {
@@ -161,10 +204,116 @@ impl<'a> TensorBlockRefMut<'a> {
             .map(|gradient_block| {
                 // SAFETY: we are returning an `TensorBlockRefMut` mutably
                 // borrowing from `self`
-                unsafe { TensorBlockRefMut::from_raw(gradient_block) }
+                let mut gradient = unsafe { TensorBlockRefMut::from_raw(gradient_block) };
+                gradient.in_tensor_map = self.in_tensor_map;
+                gradient
             })
     }
 
+    /// Get the values of the gradient with respect to the given `parameter` as
+    /// a mutable reference to `T`, or an error if the gradient does not exist
+    /// or if its values are not stored in a `T`.
+    #[inline]
+    pub fn gradient_values_as_mut<T: Array>(&mut self, parameter: &str) -> Result<&mut T, Error> {
+        let gradient = self.gradient_mut(parameter).ok_or_else(|| missing_gradient(parameter))?;
+        return gradient.try_into_values_as_mut();
+    }
+
+    /// Transform this `TensorBlockRefMut` into a mutable reference to its
+    /// values as `T`, keeping the lifetime of the block reference
+    fn try_into_values_as_mut<T: Array>(mut self) -> Result<&'a mut T, Error> {
+        let mut array = mts_array_t::null();
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_data(
+                self.as_mut_ptr(),
+                &mut array
+            ))?;
+        };
+
+        // SAFETY: we consume the `TensorBlockRefMut`, so the array can borrow
+        // mutably for the whole `'a` lifetime
+        return unsafe { ArrayRefMut::new(array) }.try_to_mut();
+    }
+
+    /// Add a gradient with respect to `parameter` to this block.
+    ///
+    /// The property of the gradient should match the ones of this block. The
+    /// components of the gradients must contain at least the same entries as
+    /// the value components, and can prepend other components.
+    ///
+    /// This returns an error when this block is part of a
+    /// [`TensorMap`](crate::TensorMap), since all the blocks in a `TensorMap`
+    /// must have the same gradients.
+    #[allow(clippy::needless_pass_by_value)]
+    #[inline]
+    pub fn add_gradient(
+        &mut self,
+        parameter: &str,
+        mut gradient: TensorBlock
+    ) -> Result<(), Error> {
+        self.check_not_in_tensor_map("add")?;
+
+        let parameter = CString::new(parameter).expect("invalid C string");
+
+        let gradient_ptr = gradient.as_mut_ptr();
+        // we give ownership of the gradient to `self`, so we should not free
+        // them again from here
+        std::mem::forget(gradient);
+
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_add_gradient(
+                self.as_mut_ptr(),
+                parameter.as_ptr(),
+                gradient_ptr,
+            ))?;
+        }
+
+        return Ok(());
+    }
+
+    /// Remove the gradient with respect to `parameter` from this block, and
+    /// return it.
+    ///
+    /// This returns an error when this block is part of a
+    /// [`TensorMap`](crate::TensorMap), use
+    /// [`TensorMap::remove_gradients`](crate::TensorMap::remove_gradients) to
+    /// remove gradients from all the blocks instead.
+    #[inline]
+    pub fn take_gradient(&mut self, parameter: &str) -> Result<TensorBlock, Error> {
+        self.check_not_in_tensor_map("take")?;
+
+        let parameter = CString::new(parameter).expect("invalid C string");
+
+        let ptr = unsafe {
+            crate::c_api::mts_block_take_gradient(self.as_mut_ptr(), parameter.as_ptr())
+        };
+        crate::errors::check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorBlock::from_raw(ptr) });
+    }
+
+    /// Remove the gradient with respect to `parameter` from this block.
+    ///
+    /// This returns an error when this block is part of a
+    /// [`TensorMap`](crate::TensorMap), use
+    /// [`TensorMap::remove_gradients`](crate::TensorMap::remove_gradients) to
+    /// remove gradients from all the blocks instead.
+    #[inline]
+    pub fn remove_gradient(&mut self, parameter: &str) -> Result<(), Error> {
+        self.check_not_in_tensor_map("remove")?;
+
+        let parameter = CString::new(parameter).expect("invalid C string");
+
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_remove_gradient(
+                self.as_mut_ptr(),
+                parameter.as_ptr(),
+            ))?;
+        }
+
+        return Ok(());
+    }
+
     /// Get an iterator over parameter/[`TensorBlockRefMut`] pairs for all gradients
     /// in this block
     #[inline]
@@ -173,6 +322,7 @@ impl<'a> TensorBlockRefMut<'a> {
         GradientsMutIter {
             parameters: self.as_ref().gradient_list().into_iter(),
             block: block_ptr,
+            in_tensor_map: self.in_tensor_map,
         }
     }
 
@@ -196,6 +346,7 @@ impl<'a> TensorBlockRefMut<'a> {
 pub struct GradientsMutIter<'a> {
     parameters: std::vec::IntoIter<&'a str>,
     block: *mut mts_block_t,
+    in_tensor_map: bool,
 }
 
 impl<'a> Iterator for GradientsMutIter<'a> {
@@ -210,7 +361,8 @@ impl<'a> Iterator for GradientsMutIter<'a> {
             // SAFETY: all blocks are disjoint, and we are only returning a
             // mutable reference to each once. The reference lifetime is
             // constrained by the lifetime of the parent TensorBlockRefMut
-            let block = unsafe { TensorBlockRefMut::from_raw(block) };
+            let mut block = unsafe { TensorBlockRefMut::from_raw(block) };
+            block.in_tensor_map = self.in_tensor_map;
             return (parameter, block);
         })
     }
@@ -231,5 +383,37 @@ impl<'a> FusedIterator for GradientsMutIter<'a> {}
 
 #[cfg(test)]
 mod tests {
-    // TODO
+    use ndarray::ArrayD;
+
+    use crate::{Labels, TensorBlock, VecArray};
+
+    #[test]
+    fn values_as() {
+        let properties = Labels::new(["p"], &[[0], [1]]);
+        let mut block = TensorBlock::new(
+            ArrayD::from_elem(vec![1, 2], 1.0), &Labels::new(["s"], &[[0]]), &[], &properties,
+        ).unwrap();
+        let gradient = TensorBlock::new(
+            VecArray::new(vec![2.0; 2], vec![1, 2]), &Labels::new(["sample"], &[[0]]), &[], &properties,
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let mut block = block.as_ref_mut();
+        assert_eq!(block.values_as::<ArrayD<f64>>().unwrap(), ArrayD::from_elem(vec![1, 2], 1.0));
+        block.values_as_mut::<ArrayD<f64>>().unwrap().fill(3.0);
+        assert_eq!(block.as_ref().values_as::<ArrayD<f64>>().unwrap(), ArrayD::from_elem(vec![1, 2], 3.0));
+
+        let error = block.values_as::<VecArray>().unwrap_err();
+        assert!(error.message.starts_with("expected the array to be a `metatensor::data::vec_array::VecArray`"));
+        assert!(error.message.ends_with(&format!("but it is a `{}`", std::any::type_name::<ArrayD<f64>>())));
+
+        block.gradient_values_as_mut::<VecArray>("g").unwrap().as_mut_slice().fill(4.0);
+        let gradient = block.as_ref().gradient_values_as::<VecArray>("g").unwrap();
+        assert_eq!(gradient.as_slice(), [4.0, 4.0]);
+
+        assert!(block.as_ref().gradient_values_as::<ArrayD<f64>>("g").is_err());
+
+        let error = block.gradient_values_as_mut::<VecArray>("other").unwrap_err();
+        assert_eq!(error.message, "this block does not have gradients with respect to 'other'");
+    }
 }
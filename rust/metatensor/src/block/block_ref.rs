@@ -5,7 +5,7 @@ use crate::c_api::{mts_block_t, mts_array_t, mts_labels_t};
 use crate::c_api::MTS_INVALID_PARAMETER_ERROR;
 
 use crate::errors::check_status;
-use crate::{ArrayRef, Labels, Error};
+use crate::{Array, ArrayRef, Labels, Error};
 
 use super::{TensorBlock, LazyMetadata};
 
@@ -102,6 +102,14 @@ pub(super) fn get_properties(ptr: *const mts_block_t) -> Labels {
     }
 }
 
+/// Get the error for a missing gradient with respect to `parameter`
+pub(super) fn missing_gradient(parameter: &str) -> Error {
+    return Error {
+        code: Some(MTS_INVALID_PARAMETER_ERROR),
+        message: format!("this block does not have gradients with respect to '{}'", parameter),
+    };
+}
+
 impl<'a> TensorBlockRef<'a> {
     /// Get all the data and metadata inside this `TensorBlockRef` as a
     /// struct with separate fields, to allow borrowing them separately.
@@ -134,6 +142,14 @@ impl<'a> TensorBlockRef<'a> {
         unsafe { ArrayRef::from_raw(array) }
     }
 
+    /// Get the values in this block as a reference to `T`, or an error
+    /// containing the data origin of the values if they are not stored in a
+    /// `T`.
+    #[inline]
+    pub fn values_as<T: Array>(&self) -> Result<&'a T, Error> {
+        return self.values().try_to();
+    }
+
     #[inline]
     fn labels(&self, dimension: usize) -> Labels {
         let mut labels = mts_labels_t::null();
@@ -223,6 +239,15 @@ impl<'a> TensorBlockRef<'a> {
         })
     }
 
+    /// Get the values of the gradient with respect to the given `parameter` as
+    /// a reference to `T`, or an error if the gradient does not exist or if
+    /// its values are not stored in a `T`.
+    #[inline]
+    pub fn gradient_values_as<T: Array>(&self, parameter: &str) -> Result<&'a T, Error> {
+        let gradient = self.gradient(parameter).ok_or_else(|| missing_gradient(parameter))?;
+        return gradient.values_as();
+    }
+
     /// Clone this block, cloning all the data and metadata contained inside.
     ///
     /// This can fail if the external data held inside an `mts_array_t` can not
@@ -237,6 +262,22 @@ impl<'a> TensorBlockRef<'a> {
         return Ok(unsafe { TensorBlock::from_raw(ptr) });
     }
 
+    /// Create a copy of this block, with the values and gradients converted
+    /// to the data origin named `origin`.
+    ///
+    /// The conversion uses the converters registered with
+    /// [`register_array_converter`](crate::register_array_converter).
+    #[inline]
+    pub fn to_origin(&self, origin: &str) -> Result<TensorBlock, Error> {
+        let origin = crate::data::find_data_origin(origin)?;
+        let ptr = unsafe {
+            crate::c_api::mts_block_to_origin(self.as_ptr(), origin)
+        };
+        crate::errors::check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorBlock::from_raw(ptr) });
+    }
+
     /// Get an iterator over parameter/[`TensorBlockRef`] pairs for all gradients in
     /// this block
     #[inline]
@@ -247,6 +288,22 @@ impl<'a> TensorBlockRef<'a> {
         }
     }
 
+    /// Get an iterator over all the gradients in this block, including
+    /// gradients of gradients.
+    ///
+    /// The iterator yields the path to each gradient (i.e. the list of
+    /// parameters, starting from this block) together with the corresponding
+    /// [`TensorBlockRef`]. Gradients are visited depth-first, so the gradients
+    /// of a gradient come right after it.
+    #[inline]
+    pub fn gradients_tree(&self) -> GradientsTreeIter<'a> {
+        let mut iter = GradientsTreeIter {
+            stack: Vec::new(),
+        };
+        iter.push_gradients(&[], *self);
+        return iter;
+    }
+
     /// Save the given block to the file at `path`
     ///
     /// This is a convenience function calling [`crate::io::save_block`]
@@ -300,7 +357,75 @@ impl<'a> ExactSizeIterator for GradientsIter<'a> {
 
 impl<'a> FusedIterator for GradientsIter<'a> {}
 
+/// Iterator over all the gradients in a [`TensorBlockRef`], including
+/// gradients of gradients, created by [`TensorBlockRef::gradients_tree`]
+pub struct GradientsTreeIter<'a> {
+    /// gradients still to visit, the next one is at the end
+    stack: Vec<(Vec<&'a str>, TensorBlockRef<'a>)>,
+}
+
+impl<'a> GradientsTreeIter<'a> {
+    /// Add all the gradients of `block` (found at `path`) to the stack
+    fn push_gradients(&mut self, path: &[&'a str], block: TensorBlockRef<'a>) {
+        // push in reverse order to visit the gradients in the same order
+        // as `gradient_list`
+        for parameter in block.gradient_list().into_iter().rev() {
+            let gradient = block.gradient(parameter).expect("missing gradient");
+
+            let mut gradient_path = path.to_vec();
+            gradient_path.push(parameter);
+            self.stack.push((gradient_path, gradient));
+        }
+    }
+}
+
+impl<'a> Iterator for GradientsTreeIter<'a> {
+    type Item = (Vec<&'a str>, TensorBlockRef<'a>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, gradient) = self.stack.pop()?;
+        self.push_gradients(&path, gradient);
+        return Some((path, gradient));
+    }
+}
+
+impl FusedIterator for GradientsTreeIter<'_> {}
+
 #[cfg(test)]
 mod tests {
     // TODO: check gradient/gradient iter code
+    use crate::{Labels, TensorBlock};
+
+    #[test]
+    fn gradients_tree() {
+        fn block(name: &str, n_samples: usize) -> TensorBlock {
+            let samples = (0..n_samples).map(|i| [i32::try_from(i).unwrap()]).collect::<Vec<_>>();
+            return TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![n_samples, 1], 1.0),
+                &Labels::new([name], &samples),
+                &[],
+                &Labels::new(["p"], &[[0]]),
+            ).unwrap();
+        }
+
+        let mut gradient = block("sample", 1);
+        gradient.add_gradient("b", block("sample", 1)).unwrap();
+        gradient.add_gradient("c", block("sample", 1)).unwrap();
+
+        let mut values = block("s", 2);
+        values.add_gradient("a", gradient).unwrap();
+        values.add_gradient("d", block("sample", 1)).unwrap();
+
+        let paths = values.as_ref().gradients_tree()
+            .map(|(path, _)| path.join("/"))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["a", "a/b", "a/c", "d"]);
+
+        let (path, gradient) = values.as_ref().gradients_tree().nth(2).unwrap();
+        assert_eq!(path, ["a", "c"]);
+        assert_eq!(gradient.samples(), Labels::new(["sample"], &[[0]]));
+
+        assert_eq!(block("s", 2).as_ref().gradients_tree().count(), 0);
+    }
 }
@@ -6,7 +6,7 @@
 mod block_ref;
 use crate::c_api::mts_block_t;
 
-pub use self::block_ref::{TensorBlockRef, TensorBlockData, GradientsIter};
+pub use self::block_ref::{TensorBlockRef, TensorBlockData, GradientsIter, GradientsTreeIter};
 
 mod block_mut;
 pub use self::block_mut::{TensorBlockRefMut, TensorBlockDataMut, GradientsMutIter};
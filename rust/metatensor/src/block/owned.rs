@@ -1,5 +1,4 @@
 use crate::c_api::mts_block_t;
-use crate::errors::check_status;
 use crate::{Array, ArrayRef, Labels, Error};
 
 use super::{TensorBlockRef, TensorBlockRefMut};
@@ -50,7 +49,7 @@ impl TensorBlock {
     }
 
     /// Get the underlying (mutable) raw pointer
-    pub(super) fn as_mut_ptr(&mut self) -> *mut mts_block_t {
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut mts_block_t {
         self.ptr
     }
 
@@ -129,31 +128,30 @@ impl TensorBlock {
     /// The property of the gradient should match the ones of this block. The
     /// components of the gradients must contain at least the same entries as
     /// the value components, and can prepend other components.
-    #[allow(clippy::needless_pass_by_value)]
+    ///
+    /// Using the same [`Labels`] to create the samples of multiple gradients
+    /// shares them in memory, see also
+    /// [`TensorMap::share_gradient_samples`](crate::TensorMap::share_gradient_samples).
     #[inline]
     pub fn add_gradient(
         &mut self,
         parameter: &str,
-        mut gradient: TensorBlock
+        gradient: TensorBlock
     ) -> Result<(), Error> {
-        let mut parameter = parameter.to_owned().into_bytes();
-        parameter.push(b'\0');
-
-
-        let gradient_ptr = gradient.as_ref_mut().as_mut_ptr();
-        // we give ownership of the gradient to `self`, so we should not free
-        // them again from here
-        std::mem::forget(gradient);
+        return self.as_ref_mut().add_gradient(parameter, gradient);
+    }
 
-        unsafe {
-            check_status(crate::c_api::mts_block_add_gradient(
-                self.as_ref_mut().as_mut_ptr(),
-                parameter.as_ptr().cast(),
-                gradient_ptr,
-            ))?;
-        }
+    /// Remove the gradient with respect to `parameter` from this block, and
+    /// return it.
+    #[inline]
+    pub fn take_gradient(&mut self, parameter: &str) -> Result<TensorBlock, Error> {
+        return self.as_ref_mut().take_gradient(parameter);
+    }
 
-        return Ok(());
+    /// Remove the gradient with respect to `parameter` from this block.
+    #[inline]
+    pub fn remove_gradient(&mut self, parameter: &str) -> Result<(), Error> {
+        return self.as_ref_mut().remove_gradient(parameter);
     }
 
     /// Load a `TensorBlock` from the file at `path`
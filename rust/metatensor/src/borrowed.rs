@@ -0,0 +1,266 @@
+use crate::data::BorrowedArray;
+use crate::{Array, ArrayRefMut, Labels, Error};
+use crate::{TensorBlock, TensorBlockRefMut, TensorBlockDataMut, TensorMap};
+
+/// A [`TensorBlock`] or [`TensorMap`] containing data borrowed for the
+/// lifetime `'a`, created with [`TensorBlock::from_borrowed`] or
+/// [`TensorMap::from_borrowed`].
+///
+/// This gives access to the inner block or tensor map through `Deref`, and
+/// prevents it from outliving the borrowed data. Functions creating new data
+/// from a borrowed block or tensor map (`try_clone`, `keys_to_samples`, ...)
+/// return owned data, which can be used without restrictions.
+///
+/// Mutable access to borrowed blocks is given as `Borrowed<TensorBlockRefMut>`,
+/// which allows modifying the data in the block, but not moving gradients in
+/// or out of it. Otherwise, a gradient containing borrowed data could be taken
+/// out of the block and outlive the data:
+///
+/// ```compile_fail
+/// # use metatensor::{Labels, TensorBlock};
+/// let mut data = vec![1.0, 2.0];
+/// let mut block = TensorBlock::from_borrowed(
+///     &mut data, &[1, 2], &Labels::new(["s"], &[[0]]), &[], &Labels::new(["p"], &[[0], [1]])
+/// ).unwrap();
+/// let gradient = block.as_ref_mut().take_gradient("g");
+/// ```
+pub struct Borrowed<'a, T> {
+    inner: T,
+    marker: std::marker::PhantomData<&'a mut [f64]>,
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Borrowed<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Borrowed").field(&self.inner).finish()
+    }
+}
+
+impl<T> std::ops::Deref for Borrowed<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<'a, T> Borrowed<'a, T> {
+    /// Wrap `inner` in a `Borrowed`
+    ///
+    /// # Safety
+    ///
+    /// All the data borrowed by `inner` must live for `'a`
+    unsafe fn new(inner: T) -> Borrowed<'a, T> {
+        Borrowed {
+            inner,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+/// A `TensorBlock` owning its data can be used anywhere a borrowed block is
+/// expected, for example to create a `TensorMap` with a mix of owned and
+/// borrowed blocks.
+impl From<TensorBlock> for Borrowed<'_, TensorBlock> {
+    fn from(block: TensorBlock) -> Self {
+        // SAFETY: the block owns all its data
+        unsafe { Borrowed::new(block) }
+    }
+}
+
+impl TensorBlock {
+    /// Create a new [`TensorBlock`] using the given `data` as values, without
+    /// copying it. The returned block can not outlive the data.
+    ///
+    /// The `data` is interpreted as a C-contiguous (row-major) array with the
+    /// given `shape`, and is described by the `samples`, `components`, and
+    /// `properties` labels.
+    ///
+    /// The borrowed data is never freed by metatensor, and copying the block
+    /// (with [`TensorBlockRef::try_clone`](crate::TensorBlockRef::try_clone))
+    /// creates a copy of the data, owned by the new block.
+    pub fn from_borrowed<'a>(
+        data: &'a mut [f64],
+        shape: &[usize],
+        samples: &Labels,
+        components: &[Labels],
+        properties: &Labels,
+    ) -> Result<Borrowed<'a, TensorBlock>, Error> {
+        // SAFETY: the returned block can not outlive 'a
+        let array = unsafe { BorrowedArray::new(data, shape)? };
+        let block = TensorBlock::new(array, samples, components, properties)?;
+        return Ok(unsafe { Borrowed::new(block) });
+    }
+}
+
+impl<'a> Borrowed<'a, TensorBlock> {
+    /// Get a mutable reference to this block
+    pub fn as_ref_mut(&mut self) -> Borrowed<'_, TensorBlockRefMut<'_>> {
+        // SAFETY: the reference can not outlive the block
+        unsafe { Borrowed::new(self.inner.as_ref_mut()) }
+    }
+
+    /// Add a gradient with respect to `parameter` to this block, see
+    /// [`TensorBlock::add_gradient`]. The gradient can either own its data or
+    /// borrow it for the same lifetime as this block.
+    pub fn add_gradient(
+        &mut self,
+        parameter: &str,
+        gradient: impl Into<Borrowed<'a, TensorBlock>>,
+    ) -> Result<(), Error> {
+        return self.inner.add_gradient(parameter, gradient.into().inner);
+    }
+}
+
+impl TensorMap {
+    /// Create a new `TensorMap` with the given `keys` and `blocks`, where the
+    /// blocks can contain borrowed data. The returned tensor map can not
+    /// outlive the data borrowed by any of the blocks.
+    ///
+    /// See [`TensorMap::new`] for the requirements on keys and blocks.
+    pub fn from_borrowed(keys: Labels, blocks: Vec<Borrowed<'_, TensorBlock>>) -> Result<Borrowed<'_, TensorMap>, Error> {
+        let blocks = blocks.into_iter().map(|block| block.inner).collect();
+        let tensor = TensorMap::new(keys, blocks)?;
+        // SAFETY: all the blocks borrow their data for at least 'a
+        return Ok(unsafe { Borrowed::new(tensor) });
+    }
+}
+
+impl Borrowed<'_, TensorMap> {
+    /// Get a mutable reference to the block at the given `index` in this
+    /// tensor map, see [`TensorMap::block_mut_by_id`].
+    pub fn block_mut_by_id(&mut self, index: usize) -> Borrowed<'_, TensorBlockRefMut<'_>> {
+        // SAFETY: the reference can not outlive the tensor map
+        unsafe { Borrowed::new(self.inner.block_mut_by_id(index)) }
+    }
+
+    /// Get a mutable reference to all the blocks in this tensor map, see
+    /// [`TensorMap::blocks_mut`].
+    pub fn blocks_mut(&mut self) -> Vec<Borrowed<'_, TensorBlockRefMut<'_>>> {
+        self.inner.blocks_mut().into_iter().map(|block| {
+            // SAFETY: the references can not outlive the tensor map
+            unsafe { Borrowed::new(block) }
+        }).collect()
+    }
+}
+
+impl Borrowed<'_, TensorBlockRefMut<'_>> {
+    /// Get a mutable reference to the values in this block, see
+    /// [`TensorBlockRefMut::values_mut`].
+    pub fn values_mut(&mut self) -> ArrayRefMut<'_> {
+        self.inner.values_mut()
+    }
+
+    /// Get the values in this block as a mutable reference to `T`, see
+    /// [`TensorBlockRefMut::values_as_mut`].
+    pub fn values_as_mut<T: Array>(&mut self) -> Result<&mut T, Error> {
+        self.inner.values_as_mut()
+    }
+
+    /// Get all the data and metadata inside this block, see
+    /// [`TensorBlockRefMut::data_mut`].
+    pub fn data_mut(&mut self) -> TensorBlockDataMut<'_> {
+        self.inner.data_mut()
+    }
+
+    /// Get a mutable reference to the gradient with respect to the given
+    /// parameter in this block, if it exists.
+    pub fn gradient_mut(&mut self, parameter: &str) -> Option<Borrowed<'_, TensorBlockRefMut<'_>>> {
+        // SAFETY: the reference can not outlive the block
+        self.inner.gradient_mut(parameter).map(|gradient| unsafe { Borrowed::new(gradient) })
+    }
+
+    /// Get the values of the gradient with respect to the given `parameter` as
+    /// a mutable reference to `T`, see
+    /// [`TensorBlockRefMut::gradient_values_as_mut`].
+    pub fn gradient_values_as_mut<T: Array>(&mut self, parameter: &str) -> Result<&mut T, Error> {
+        self.inner.gradient_values_as_mut(parameter)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{Labels, TensorBlock, TensorMap};
+
+    #[test]
+    fn borrowed_block() {
+        let mut data = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let data_ptr = data.as_ptr();
+
+        let mut block = TensorBlock::from_borrowed(
+            &mut data,
+            &[3, 2],
+            &Labels::new(["s"], &[[0], [1], [2]]),
+            &[],
+            &Labels::new(["p"], &[[0], [1]]),
+        ).unwrap();
+
+        let mut block_mut = block.as_ref_mut();
+        let mut values = block_mut.values_mut();
+        let values = values.as_raw_mut().data().unwrap();
+        assert_eq!(values.as_ptr(), data_ptr);
+        values[0] = 42.0;
+
+        // copies own their data
+        let copy = block.as_ref().try_clone().unwrap();
+        assert_eq!(copy.values().as_array(), ndarray::arr2(&[[42.0, 2.0], [3.0, 4.0], [5.0, 6.0]]).into_dyn());
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 2], 1.0),
+            &Labels::new(["sample"], &[[1]]),
+            &[],
+            &Labels::new(["p"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+        assert_eq!(block.as_ref().gradient_list(), ["g"]);
+
+        let mut block_mut = block.as_ref_mut();
+        let mut gradient = block_mut.gradient_mut("g").unwrap();
+        gradient.values_as_mut::<ndarray::ArrayD<f64>>().unwrap()[[0, 1]] = 2.0;
+        assert_eq!(block.as_ref().gradient("g").unwrap().values().as_array(), ndarray::arr2(&[[1.0, 2.0]]).into_dyn());
+
+        let mut block_2 = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 2], 1.0),
+            &Labels::new(["s"], &[[0]]),
+            &[],
+            &Labels::new(["p"], &[[0], [1]]),
+        ).unwrap();
+        block_2.add_gradient("g", TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![0, 2], 1.0),
+            &Labels::empty(vec!["sample"]),
+            &[],
+            &Labels::new(["p"], &[[0], [1]]),
+        ).unwrap()).unwrap();
+
+        let mut tensor = TensorMap::from_borrowed(
+            Labels::new(["key"], &[[0], [1]]),
+            vec![block, block_2.into()],
+        ).unwrap();
+
+        let mut block_mut = tensor.block_mut_by_id(0);
+        block_mut.values_mut().as_raw_mut().data().unwrap()[1] = 33.0;
+
+        let merged = tensor.keys_to_samples(&Labels::empty(vec!["key"]), true).unwrap();
+        assert_eq!(
+            merged.block_by_id(0).values().as_array(),
+            ndarray::arr2(&[[42.0, 33.0], [1.0, 1.0], [3.0, 4.0], [5.0, 6.0]]).into_dyn()
+        );
+
+        std::mem::drop(tensor);
+        assert_eq!(data, [42.0, 33.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn wrong_shape() {
+        let mut data = vec![1.0, 2.0, 3.0];
+        let error = TensorBlock::from_borrowed(
+            &mut data,
+            &[2, 2],
+            &Labels::new(["s"], &[[0], [1]]),
+            &[],
+            &Labels::new(["p"], &[[0], [1]]),
+        ).unwrap_err();
+
+        assert_eq!(error.message, "the data size (3) does not match the shape [2, 2]");
+    }
+}
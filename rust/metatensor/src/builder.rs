@@ -0,0 +1,195 @@
+use std::ffi::CString;
+
+use crate::c_api::mts_tensormap_builder_t;
+use crate::errors::{check_status, check_ptr};
+use crate::{Error, TensorBlock, TensorMap};
+
+/// Builder creating a [`TensorMap`] from chunks of data given in any order.
+///
+/// Each chunk is a [`TensorBlock`] associated with a key. Chunks with the same
+/// key are merged together along the samples axis in
+/// [`TensorMapBuilder::finish`], and must have the same components,
+/// properties and gradients. The blocks are stored in the final `TensorMap` in
+/// the order in which their key was first seen.
+pub struct TensorMapBuilder {
+    ptr: *mut mts_tensormap_builder_t,
+}
+
+// SAFETY: Send is fine since we can free a TensorMapBuilder from any thread
+unsafe impl Send for TensorMapBuilder {}
+// SAFETY: Sync is fine since there is no internal mutability in TensorMapBuilder
+unsafe impl Sync for TensorMapBuilder {}
+
+impl std::fmt::Debug for TensorMapBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TensorMapBuilder @ {:p}", self.ptr)
+    }
+}
+
+impl std::ops::Drop for TensorMapBuilder {
+    #[allow(unused_must_use)]
+    fn drop(&mut self) {
+        unsafe {
+            crate::c_api::mts_tensormap_builder_free(self.ptr);
+        }
+    }
+}
+
+impl TensorMapBuilder {
+    /// Create a new empty `TensorMapBuilder`, for keys with the given `names`.
+    ///
+    /// If `sort_samples` is true, the samples of the blocks will be
+    /// lexicographically sorted in the final `TensorMap`. Otherwise they are
+    /// kept in the order in which they were added, like for
+    /// [`TensorMap::keys_to_samples`].
+    #[inline]
+    pub fn new(keys_names: &[&str], sort_samples: bool) -> Result<TensorMapBuilder, Error> {
+        let keys_names = keys_names.iter()
+            .map(|&name| CString::new(name).expect("invalid C string"))
+            .collect::<Vec<_>>();
+        let keys_names_ptr = keys_names.iter().map(|name| name.as_ptr()).collect::<Vec<_>>();
+
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_builder(
+                keys_names_ptr.as_ptr(),
+                keys_names_ptr.len(),
+                sort_samples,
+            )
+        };
+        check_ptr(ptr)?;
+
+        return Ok(TensorMapBuilder { ptr });
+    }
+
+    /// Add a new `chunk` of data associated with the given `key`.
+    ///
+    /// The samples of the chunk must not already be present in previous
+    /// chunks for the same key. The builder takes ownership of the chunk,
+    /// which is dropped if this function returns an error, in the same way as
+    /// `mts_tensormap_builder_add` in the C API.
+    #[allow(clippy::needless_pass_by_value)]
+    #[inline]
+    pub fn add(&mut self, key: &[impl Into<i64> + Copy], mut chunk: TensorBlock) -> Result<(), Error> {
+        let key = key.iter().map(|&v| v.into()).collect::<Vec<i64>>();
+
+        let chunk_ptr = chunk.as_mut_ptr();
+        // we give ownership of the chunk to the builder, so we should not
+        // free it again from here
+        std::mem::forget(chunk);
+
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_builder_add(
+                self.ptr,
+                key.as_ptr(),
+                key.len(),
+                chunk_ptr,
+            ))
+        }
+    }
+
+    /// Merge all the chunks for each key and create the corresponding
+    /// [`TensorMap`].
+    #[inline]
+    pub fn finish(self) -> Result<TensorMap, Error> {
+        let builder_ptr = self.ptr;
+        // `mts_tensormap_builder_finish` releases the builder, so we should
+        // not free it again from here
+        std::mem::forget(self);
+
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_builder_finish(builder_ptr)
+        };
+        check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::ArrayD;
+
+    use crate::{Labels, TensorBlock};
+    use super::TensorMapBuilder;
+
+    fn chunk(samples: &[[i32; 1]], value: f64) -> TensorBlock {
+        let properties = Labels::new(["p"], &[[0], [1]]);
+        let mut block = TensorBlock::new(
+            ArrayD::from_elem(vec![samples.len(), 2], value),
+            &Labels::new(["s"], samples),
+            &[],
+            &properties,
+        ).unwrap();
+
+        let gradient_samples = (0..samples.len()).map(|i| [i32::try_from(i).unwrap()]).collect::<Vec<_>>();
+        let gradient = TensorBlock::new(
+            ArrayD::from_elem(vec![samples.len(), 2], -value),
+            &Labels::new(["sample"], &gradient_samples),
+            &[],
+            &properties,
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        return block;
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn merge_chunks() {
+        let mut builder = TensorMapBuilder::new(&["key"], false).unwrap();
+        builder.add(&[2], chunk(&[[3], [1]], 1.0)).unwrap();
+        builder.add(&[0], chunk(&[[0]], 2.0)).unwrap();
+        builder.add(&[2], chunk(&[[0]], 3.0)).unwrap();
+
+        let error = builder.add(&[2], chunk(&[[1]], 4.0)).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: sample [1] is present in multiple chunks for key [2]");
+
+        let tensor = builder.finish().unwrap();
+        assert_eq!(*tensor.keys(), Labels::new(["key"], &[[2], [0]]));
+
+        let block = tensor.block_by_id(0);
+        assert_eq!(block.samples(), Labels::new(["s"], &[[3], [1], [0]]));
+        let values = block.values().to_array();
+        assert_eq!(values.shape(), [3, 2]);
+        assert_eq!(values[[0, 0]], 1.0);
+        assert_eq!(values[[1, 1]], 1.0);
+        assert_eq!(values[[2, 0]], 3.0);
+
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample"], &[[0], [1], [2]]));
+        assert_eq!(gradient.values().as_array()[[2, 0]], -3.0);
+
+        // sorted samples
+        let mut builder = TensorMapBuilder::new(&["key"], true).unwrap();
+        builder.add(&[2], chunk(&[[3], [1]], 1.0)).unwrap();
+        builder.add(&[2], chunk(&[[0]], 3.0)).unwrap();
+        let tensor = builder.finish().unwrap();
+
+        let block = tensor.block_by_id(0);
+        assert_eq!(block.samples(), Labels::new(["s"], &[[0], [1], [3]]));
+        assert_eq!(block.values().as_array()[[0, 0]], 3.0);
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.values().as_array()[[0, 0]], -3.0);
+
+        let error = TensorMapBuilder::new(&[], false).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: can not create a TensorMapBuilder without keys dimensions");
+    }
+    #[test]
+    fn different_dtype() {
+        let mut builder = TensorMapBuilder::new(&["key"], false).unwrap();
+        builder.add(&[2], chunk(&[[0]], 1.0)).unwrap();
+
+        let chunk_f32 = TensorBlock::new(
+            ArrayD::from_elem(vec![1, 2], 2.0_f32),
+            &Labels::new(["s"], &[[1]]),
+            &[],
+            &Labels::new(["p"], &[[0], [1]]),
+        ).unwrap();
+
+        let error = builder.add(&[2], chunk_f32).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: the chunk for key [2] has different device or dtype than the previous chunks for this key"
+        );
+    }
+}
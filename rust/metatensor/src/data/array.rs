@@ -4,12 +4,28 @@ use std::os::raw::c_void;
 use once_cell::sync::Lazy;
 
 use crate::c_api::{mts_array_t, mts_data_origin_t, mts_sample_mapping_t, mts_status_t};
+use crate::c_api::{mts_array_operations_t, mts_array_descriptor_t, MTS_ARRAY_OPERATIONS_VERSION};
+use crate::c_api::mts_index_mapping_t;
+use crate::Error;
 
 /// The Array trait is used by metatensor to manage different kind of data array
 /// with a single API. Metatensor only knows about `Box<dyn Array>`, and
 /// manipulate the data through the functions on this trait.
 ///
 /// This corresponds to the `mts_array_t` struct in metatensor-core.
+///
+/// Most functions in this trait have a fallible `try_*` counterpart, which is
+/// the one called by metatensor-core. By default, these call the infallible
+/// version and can only fail by panicking. Implementations can override the
+/// `try_*` functions to report errors (wrong device, wrong data type, etc.)
+/// which will be returned to the caller with their message.
+///
+/// The element-wise and linear algebra operations ([`Array::add`],
+/// [`Array::mul`], [`Array::scale`], [`Array::matmul`], [`Array::sum_axis`]
+/// and [`Array::copy_from`]) are used by metatensor-core through
+/// `mts_array_operations_t`. Their default implementations go through
+/// [`Array::try_data`], and can be overridden to use the native operations of
+/// the array type instead.
 pub trait Array: std::any::Any + Send + Sync {
     /// Get the array as a `Any` reference
     fn as_any(&self) -> &dyn std::any::Any;
@@ -33,12 +49,19 @@ pub trait Array: std::any::Any + Send + Sync {
     ///
     /// This function is allowed to panic if the data is not accessible in RAM,
     /// not stored as 64-bit floating point values, or not stored as a
-    /// C-contiguous array.
+    /// C-contiguous array. Override [`Array::try_data`] to report these cases
+    /// as errors instead.
     fn data(&mut self) -> &mut [f64];
 
     /// Get the shape of the array
     fn shape(&self) -> &[usize];
 
+    /// Get the name of the type of this array, used in error messages. The
+    /// default implementation uses [`std::any::type_name`].
+    fn type_name(&self) -> &'static str {
+        return std::any::type_name::<Self>();
+    }
+
     /// Change the shape of the array to the given `shape`
     fn reshape(&mut self, shape: &[usize]);
 
@@ -63,6 +86,238 @@ pub trait Array: std::any::Any + Send + Sync {
         samples: &[mts_sample_mapping_t],
         properties: Range<usize>,
     );
+
+    /// Fallible version of [`Array::create`]
+    fn try_create(&self, shape: &[usize]) -> Result<Box<dyn Array>, Error> {
+        return Ok(self.create(shape));
+    }
+
+    /// Fallible version of [`Array::copy`]
+    fn try_copy(&self) -> Result<Box<dyn Array>, Error> {
+        return Ok(self.copy());
+    }
+
+    /// Fallible version of [`Array::data`]
+    fn try_data(&mut self) -> Result<&mut [f64], Error> {
+        return Ok(self.data());
+    }
+
+    /// Fallible version of [`Array::reshape`]
+    fn try_reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
+        self.reshape(shape);
+        return Ok(());
+    }
+
+    /// Fallible version of [`Array::swap_axes`]
+    fn try_swap_axes(&mut self, axis_1: usize, axis_2: usize) -> Result<(), Error> {
+        self.swap_axes(axis_1, axis_2);
+        return Ok(());
+    }
+
+    /// Fallible version of [`Array::move_samples_from`]
+    fn try_move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) -> Result<(), Error> {
+        self.move_samples_from(input, samples, properties);
+        return Ok(());
+    }
+
+    /// Get the device and data type of this array. This is used to check
+    /// that all the arrays in a block or tensor map are compatible.
+    ///
+    /// The default implementation describes arrays of 64-bit floating point
+    /// values on CPU. Implementations can return
+    /// [`mts_array_descriptor_t::unknown`] to disable the checks.
+    fn descriptor(&self) -> mts_array_descriptor_t {
+        return mts_array_descriptor_t::cpu_float(64);
+    }
+
+    /// Get a pointer to the C-contiguous data of this array, in the format
+    /// given by [`Array::descriptor`]. This is used to share the data without
+    /// copies through `DLPack`.
+    ///
+    /// The default implementation uses [`Array::try_data`].
+    fn raw_data(&mut self) -> Result<*mut c_void, Error> {
+        return Ok(self.try_data()?.as_mut_ptr().cast());
+    }
+
+    /// Add the values in `other` to the values in this array, element-wise.
+    /// Both arrays have the same shape.
+    ///
+    /// The default implementation uses [`Array::try_data`] on this array and
+    /// on a copy of `other`.
+    fn add(&mut self, other: &dyn Array) -> Result<(), Error> {
+        return add_with_data(self, other);
+    }
+
+    /// Multiply the values in this array by the values in `other`,
+    /// element-wise. Both arrays have the same shape.
+    ///
+    /// The default implementation uses [`Array::try_data`] on this array and
+    /// on a copy of `other`.
+    fn mul(&mut self, other: &dyn Array) -> Result<(), Error> {
+        return mul_with_data(self, other);
+    }
+
+    /// Multiply all the values in this array by `factor`.
+    ///
+    /// The default implementation uses [`Array::try_data`].
+    fn scale(&mut self, factor: f64) -> Result<(), Error> {
+        for value in self.try_data()? {
+            *value *= factor;
+        }
+        return Ok(());
+    }
+
+    /// Compute the matrix product of this array and the 2-dimensional `other`
+    /// array, contracting the last axis of this array with the first axis of
+    /// `other`.
+    ///
+    /// The default implementation uses [`Array::try_create`], and
+    /// [`Array::try_data`] on copies of both arrays.
+    fn matmul(&self, other: &dyn Array) -> Result<Box<dyn Array>, Error> {
+        return matmul_with_data(self, other);
+    }
+
+    /// Sum the values in this array along the given `axis`. The returned array
+    /// should have the same shape as this one, except for `axis` which should
+    /// have a size of 1.
+    ///
+    /// The default implementation uses [`Array::try_create`], and
+    /// [`Array::try_data`] on a copy of this array.
+    fn sum_axis(&self, axis: usize) -> Result<Box<dyn Array>, Error> {
+        return sum_axis_with_data(self, axis);
+    }
+
+    /// Set all the values in this array to the values in `other`. Both arrays
+    /// have the same shape.
+    ///
+    /// The default implementation uses [`Array::try_data`] on this array and
+    /// on a copy of `other`.
+    fn copy_from(&mut self, other: &dyn Array) -> Result<(), Error> {
+        let mut other = other.try_copy()?;
+        self.try_data()?.copy_from_slice(other.try_data()?);
+        return Ok(());
+    }
+
+    /// Copy values from `input` to this array along `axis`. For each entry in
+    /// `mapping`, `input[..., mapping.input, ...]` is copied to `self[...,
+    /// mapping.output, ...]`, where the index is on `axis`. On all the other
+    /// axes `i`, the whole input is copied to `output_offsets[i]..
+    /// output_offsets[i] + input.shape()[i]`, and the entry of
+    /// `output_offsets` for `axis` is ignored.
+    ///
+    /// Both arrays have the same number of dimensions, and all the indexes are
+    /// checked to be in bounds before calling this function.
+    ///
+    /// The default implementation uses [`Array::try_data`] on this array and
+    /// on a copy of `input`.
+    fn move_along_axis(
+        &mut self,
+        input: &dyn Array,
+        axis: usize,
+        mapping: &[mts_index_mapping_t],
+        output_offsets: &[usize],
+    ) -> Result<(), Error> {
+        return move_along_axis_with_data(self, input, axis, mapping, output_offsets);
+    }
+}
+
+/// Implementation of [`Array::add`] using [`Array::try_data`]
+fn add_with_data<A: Array + ?Sized>(array: &mut A, other: &dyn Array) -> Result<(), Error> {
+    let mut other = other.try_copy()?;
+    for (value, other) in array.try_data()?.iter_mut().zip(other.try_data()?) {
+        *value += *other;
+    }
+    return Ok(());
+}
+
+/// Implementation of [`Array::mul`] using [`Array::try_data`]
+fn mul_with_data<A: Array + ?Sized>(array: &mut A, other: &dyn Array) -> Result<(), Error> {
+    let mut other = other.try_copy()?;
+    for (value, other) in array.try_data()?.iter_mut().zip(other.try_data()?) {
+        *value *= *other;
+    }
+    return Ok(());
+}
+
+/// Implementation of [`Array::matmul`] using [`Array::try_data`]
+fn matmul_with_data<A: Array + ?Sized>(array: &A, other: &dyn Array) -> Result<Box<dyn Array>, Error> {
+    let (size, other_size) = (other.shape()[0], other.shape()[1]);
+
+    let mut output_shape = array.shape().to_vec();
+    *output_shape.last_mut().expect("shape should not be empty") = other_size;
+    let mut output = array.try_create(&output_shape)?;
+
+    if size != 0 && other_size != 0 {
+        let mut array = array.try_copy()?;
+        let mut other = other.try_copy()?;
+        let other = other.try_data()?;
+
+        let rows = array.try_data()?.chunks_exact(size);
+        for (row, output_row) in rows.zip(output.try_data()?.chunks_exact_mut(other_size)) {
+            for (value, other_row) in row.iter().zip(other.chunks_exact(other_size)) {
+                for (output, other) in output_row.iter_mut().zip(other_row) {
+                    *output += value * other;
+                }
+            }
+        }
+    }
+
+    return Ok(output);
+}
+
+/// Implementation of [`Array::sum_axis`] using [`Array::try_data`]
+fn sum_axis_with_data<A: Array + ?Sized>(array: &A, axis: usize) -> Result<Box<dyn Array>, Error> {
+    let shape = array.shape();
+    let size = shape[axis];
+    let inner_size = shape[axis + 1..].iter().product::<usize>();
+
+    let mut output_shape = shape.to_vec();
+    output_shape[axis] = 1;
+    let mut output = array.try_create(&output_shape)?;
+
+    if size != 0 && inner_size != 0 {
+        let mut array = array.try_copy()?;
+        let outer = array.try_data()?.chunks_exact(size * inner_size);
+        for (values, output) in outer.zip(output.try_data()?.chunks_exact_mut(inner_size)) {
+            for values in values.chunks_exact(inner_size) {
+                for (output, value) in output.iter_mut().zip(values) {
+                    *output += value;
+                }
+            }
+        }
+    }
+
+    return Ok(output);
+}
+
+/// Implementation of [`Array::move_along_axis`] using [`Array::try_data`]
+fn move_along_axis_with_data<A: Array + ?Sized>(
+    array: &mut A,
+    input: &dyn Array,
+    axis: usize,
+    mapping: &[mts_index_mapping_t],
+    output_offsets: &[usize],
+) -> Result<(), Error> {
+    let mut input = input.try_copy()?;
+    let input_shape = input.shape().to_vec();
+    let input = ndarray::ArrayViewD::from_shape(input_shape, input.try_data()?).map_err(|e| Error {
+        code: None,
+        message: format!("invalid input array: {}", e),
+    })?;
+
+    let shape = array.shape().to_vec();
+    let output = ndarray::ArrayViewMutD::from_shape(shape, array.try_data()?).map_err(|e| Error {
+        code: None,
+        message: format!("invalid output array: {}", e),
+    })?;
+
+    move_along_axis_between_views(output, &input, axis, mapping, output_offsets);
+    return Ok(());
 }
 
 impl From<Box<dyn Array>> for mts_array_t {
@@ -102,7 +357,25 @@ macro_rules! check_pointers {
 }
 
 pub(super) static RUST_DATA_ORIGIN: Lazy<mts_data_origin_t> = Lazy::new(|| {
-    super::origin::register_data_origin("rust.Box<dyn Array>".into()).expect("failed to register a new origin")
+    let origin = super::origin::register_data_origin("rust.Box<dyn Array>".into()).expect("failed to register a new origin");
+
+    let operations = mts_array_operations_t {
+        version: MTS_ARRAY_OPERATIONS_VERSION,
+        add: Some(rust_array_add),
+        mul: Some(rust_array_mul),
+        scale: Some(rust_array_scale),
+        matmul: Some(rust_array_matmul),
+        sum_axis: Some(rust_array_sum_axis),
+        copy_from: Some(rust_array_copy_from),
+        descriptor: Some(rust_array_descriptor),
+        raw_data: Some(rust_array_raw_data),
+        move_along_axis: Some(rust_array_move_along_axis),
+        sparse_csr: Some(rust_array_sparse_csr),
+        create_sparse_csr: Some(rust_array_create_sparse_csr),
+    };
+    super::origin::register_array_operations(origin, &operations).expect("failed to register array operations");
+
+    return origin;
 });
 
 /// Implementation of `mts_array_t.origin` using `Box<dyn Array>`
@@ -139,13 +412,13 @@ unsafe extern fn rust_array_reshape(
     shape: *const usize,
     shape_count: usize,
 ) -> mts_status_t {
-    crate::errors::catch_unwind(|| {
+    crate::errors::catch_unwind_result(|| {
         assert!(shape_count > 0);
         assert!(!shape.is_null());
         check_pointers!(array);
         let array = array.cast::<Box<dyn Array>>();
         let shape = std::slice::from_raw_parts(shape, shape_count);
-        (*array).reshape(shape);
+        (*array).try_reshape(shape)
     })
 }
 
@@ -156,10 +429,10 @@ unsafe extern fn rust_array_swap_axes(
     axis_1: usize,
     axis_2: usize,
 ) -> mts_status_t {
-    crate::errors::catch_unwind(|| {
+    crate::errors::catch_unwind_result(|| {
         check_pointers!(array);
         let array = array.cast::<Box<dyn Array>>();
-        (*array).swap_axes(axis_1, axis_2);
+        (*array).try_swap_axes(axis_1, axis_2)
     })
 }
 
@@ -171,16 +444,17 @@ unsafe extern fn rust_array_create(
     shape_count: usize,
     array_storage: *mut mts_array_t,
 ) -> mts_status_t {
-    crate::errors::catch_unwind(|| {
+    crate::errors::catch_unwind_result(|| {
         assert!(shape_count > 0);
         assert!(!shape.is_null());
         check_pointers!(array, shape, array_storage);
         let array = array.cast::<Box<dyn Array>>();
 
         let shape = std::slice::from_raw_parts(shape, shape_count);
-        let new_array = (*array).create(shape);
+        let new_array = (*array).try_create(shape)?;
 
         *array_storage = new_array.into();
+        Ok(())
     })
 }
 
@@ -189,10 +463,11 @@ unsafe extern fn rust_array_data(
     array: *mut c_void,
     data: *mut *mut f64,
 ) -> mts_status_t {
-    crate::errors::catch_unwind(|| {
+    crate::errors::catch_unwind_result(|| {
         check_pointers!(array, data);
         let array = array.cast::<Box<dyn Array>>();
-        *data = (*array).data().as_mut_ptr();
+        *data = (*array).try_data()?.as_mut_ptr();
+        Ok(())
     })
 }
 
@@ -202,10 +477,11 @@ unsafe extern fn rust_array_copy(
     array: *const c_void,
     array_storage: *mut mts_array_t,
 ) -> mts_status_t {
-    crate::errors::catch_unwind(|| {
+    crate::errors::catch_unwind_result(|| {
         check_pointers!(array, array_storage);
         let array = array.cast::<Box<dyn Array>>();
-        *array_storage = (*array).copy().into();
+        *array_storage = (*array).try_copy()?.into();
+        Ok(())
     })
 }
 
@@ -230,7 +506,7 @@ unsafe extern fn rust_array_move_samples_from(
     property_start: usize,
     property_end: usize,
 ) -> mts_status_t {
-    crate::errors::catch_unwind(|| {
+    crate::errors::catch_unwind_result(|| {
         check_pointers!(output, input);
         let output = output.cast::<Box<dyn Array>>();
         let input = input.cast::<Box<dyn Array>>();
@@ -242,7 +518,196 @@ unsafe extern fn rust_array_move_samples_from(
             std::slice::from_raw_parts(samples, samples_count)
         };
 
-        (*output).move_samples_from(&**input, samples, property_start..property_end);
+        (*output).try_move_samples_from(&**input, samples, property_start..property_end)
+    })
+}
+
+/// Implementation of `mts_array_operations_t.add` using `Box<dyn Array>`
+unsafe extern fn rust_array_add(
+    array: *mut c_void,
+    other: *const c_void,
+) -> mts_status_t {
+    crate::errors::catch_unwind_result(|| {
+        check_pointers!(array, other);
+        let array = array.cast::<Box<dyn Array>>();
+        let other = other.cast::<Box<dyn Array>>();
+        (*array).add(&**other)
+    })
+}
+
+/// Implementation of `mts_array_operations_t.mul` using `Box<dyn Array>`
+unsafe extern fn rust_array_mul(
+    array: *mut c_void,
+    other: *const c_void,
+) -> mts_status_t {
+    crate::errors::catch_unwind_result(|| {
+        check_pointers!(array, other);
+        let array = array.cast::<Box<dyn Array>>();
+        let other = other.cast::<Box<dyn Array>>();
+        (*array).mul(&**other)
+    })
+}
+
+/// Implementation of `mts_array_operations_t.scale` using `Box<dyn Array>`
+unsafe extern fn rust_array_scale(
+    array: *mut c_void,
+    factor: f64,
+) -> mts_status_t {
+    crate::errors::catch_unwind_result(|| {
+        check_pointers!(array);
+        let array = array.cast::<Box<dyn Array>>();
+        (*array).scale(factor)
+    })
+}
+
+/// Implementation of `mts_array_operations_t.matmul` using `Box<dyn Array>`
+unsafe extern fn rust_array_matmul(
+    array: *const c_void,
+    other: *const c_void,
+    output: *mut mts_array_t,
+) -> mts_status_t {
+    crate::errors::catch_unwind_result(|| {
+        check_pointers!(array, other, output);
+        let array = array.cast::<Box<dyn Array>>();
+        let other = other.cast::<Box<dyn Array>>();
+        *output = (*array).matmul(&**other)?.into();
+        Ok(())
+    })
+}
+
+/// Implementation of `mts_array_operations_t.sum_axis` using `Box<dyn Array>`
+unsafe extern fn rust_array_sum_axis(
+    array: *const c_void,
+    axis: usize,
+    output: *mut mts_array_t,
+) -> mts_status_t {
+    crate::errors::catch_unwind_result(|| {
+        check_pointers!(array, output);
+        let array = array.cast::<Box<dyn Array>>();
+        *output = (*array).sum_axis(axis)?.into();
+        Ok(())
+    })
+}
+
+/// Implementation of `mts_array_operations_t.copy_from` using `Box<dyn Array>`
+unsafe extern fn rust_array_copy_from(
+    array: *mut c_void,
+    other: *const c_void,
+) -> mts_status_t {
+    crate::errors::catch_unwind_result(|| {
+        check_pointers!(array, other);
+        let array = array.cast::<Box<dyn Array>>();
+        let other = other.cast::<Box<dyn Array>>();
+        (*array).copy_from(&**other)
+    })
+}
+
+/// Implementation of `mts_array_operations_t.descriptor` using `Box<dyn Array>`
+unsafe extern fn rust_array_descriptor(
+    array: *const c_void,
+    descriptor: *mut mts_array_descriptor_t,
+) -> mts_status_t {
+    crate::errors::catch_unwind(|| {
+        check_pointers!(array, descriptor);
+        let array = array.cast::<Box<dyn Array>>();
+        *descriptor = (*array).descriptor();
+    })
+}
+
+/// Implementation of `mts_array_operations_t.move_along_axis` using `Box<dyn Array>`
+unsafe extern fn rust_array_move_along_axis(
+    array: *mut c_void,
+    input: *const c_void,
+    axis: usize,
+    mapping: *const mts_index_mapping_t,
+    mapping_count: usize,
+    output_offsets: *const usize,
+) -> mts_status_t {
+    crate::errors::catch_unwind_result(|| {
+        check_pointers!(array, input, output_offsets);
+        let array = array.cast::<Box<dyn Array>>();
+        let input = input.cast::<Box<dyn Array>>();
+
+        let mapping = if mapping_count == 0 {
+            &[]
+        } else {
+            check_pointers!(mapping);
+            std::slice::from_raw_parts(mapping, mapping_count)
+        };
+
+        let output_offsets = std::slice::from_raw_parts(output_offsets, (*array).shape().len());
+        (*array).move_along_axis(&**input, axis, mapping, output_offsets)
+    })
+}
+
+/// Implementation of `mts_array_operations_t.sparse_csr` using `Box<dyn Array>`
+unsafe extern fn rust_array_sparse_csr(
+    array: *const c_void,
+    values: *mut *const f64,
+    indices: *mut *const usize,
+    indptr: *mut *const usize,
+    nnz: *mut usize,
+) -> mts_status_t {
+    crate::errors::catch_unwind(|| {
+        check_pointers!(array, values, indices, indptr, nnz);
+        let array = array.cast::<Box<dyn Array>>();
+
+        if let Some(sparse) = (*array).as_any().downcast_ref::<super::SparseArray>() {
+            *values = sparse.values().as_ptr();
+            *indices = sparse.indices().as_ptr();
+            *indptr = sparse.indptr().as_ptr();
+            *nnz = sparse.nnz();
+        } else {
+            *values = std::ptr::null();
+        }
+    })
+}
+
+/// Implementation of `mts_array_operations_t.create_sparse_csr` using
+/// `Box<dyn Array>`, creating a [`SparseArray`](super::SparseArray)
+#[allow(clippy::too_many_arguments)]
+unsafe extern fn rust_array_create_sparse_csr(
+    array: *const c_void,
+    shape: *const usize,
+    shape_count: usize,
+    values: *const f64,
+    indices: *const usize,
+    indptr: *const usize,
+    nnz: usize,
+    output: *mut mts_array_t,
+) -> mts_status_t {
+    crate::errors::catch_unwind_result(|| {
+        check_pointers!(array, shape, indptr, output);
+
+        let shape = std::slice::from_raw_parts(shape, shape_count).to_vec();
+        let (values, indices) = if nnz == 0 {
+            (Vec::new(), Vec::new())
+        } else {
+            check_pointers!(values, indices);
+            (
+                std::slice::from_raw_parts(values, nnz).to_vec(),
+                std::slice::from_raw_parts(indices, nnz).to_vec(),
+            )
+        };
+        let rows = shape.first().copied().unwrap_or(0);
+        let indptr = std::slice::from_raw_parts(indptr, rows + 1).to_vec();
+
+        let sparse = super::SparseArray::new(shape, values, indices, indptr)?;
+        *output = (Box::new(sparse) as Box<dyn Array>).into();
+        Ok(())
+    })
+}
+
+/// Implementation of `mts_array_operations_t.raw_data` using `Box<dyn Array>`
+unsafe extern fn rust_array_raw_data(
+    array: *mut c_void,
+    data: *mut *mut c_void,
+) -> mts_status_t {
+    crate::errors::catch_unwind_result(|| {
+        check_pointers!(array, data);
+        let array = array.cast::<Box<dyn Array>>();
+        *data = (*array).raw_data()?;
+        Ok(())
     })
 }
 
@@ -266,7 +731,7 @@ impl Array for ndarray::ArrayD<f64> {
     }
 
     fn data(&mut self) -> &mut [f64] {
-        return self.as_slice_mut().expect("array is not contiguous")
+        return self.try_data().unwrap_or_else(|e| panic!("{}", e));
     }
 
     fn shape(&self) -> &[usize] {
@@ -274,9 +739,7 @@ impl Array for ndarray::ArrayD<f64> {
     }
 
     fn reshape(&mut self, shape: &[usize]) {
-        let mut array = std::mem::take(self);
-        array = array.to_shape(shape).expect("invalid shape").to_owned();
-        std::mem::swap(self, &mut array);
+        self.try_reshape(shape).unwrap_or_else(|e| panic!("{}", e));
     }
 
     fn swap_axes(&mut self, axis_1: usize, axis_2: usize) {
@@ -289,22 +752,327 @@ impl Array for ndarray::ArrayD<f64> {
         samples: &[mts_sample_mapping_t],
         property: Range<usize>,
     ) {
-        use ndarray::{Axis, Slice};
+        self.try_move_samples_from(input, samples, property).unwrap_or_else(|e| panic!("{}", e));
+    }
 
-        // -2 since we also remove one axis with `index_axis_mut` below
-        let property_axis = self.shape().len() - 2;
+    fn try_data(&mut self) -> Result<&mut [f64], Error> {
+        return self.as_slice_mut().ok_or_else(|| Error {
+            code: None,
+            message: "array is not contiguous".into(),
+        });
+    }
 
-        let input = input.as_any().downcast_ref::<ndarray::ArrayD<f64>>().expect("input must be a ndarray");
-        for sample in samples {
-            let value = input.index_axis(Axis(0), sample.input);
+    fn try_reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
+        return ndarray_reshape(self, shape);
+    }
 
-            let mut output_location = self.index_axis_mut(Axis(0), sample.output);
-            let mut output_location = output_location.slice_axis_mut(
-                Axis(property_axis), Slice::from(property.clone())
-            );
+    fn try_move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        property: Range<usize>,
+    ) -> Result<(), Error> {
+        let input = if let Some(input) = input.as_any().downcast_ref::<ndarray::ArrayD<f64>>() {
+            input.view()
+        } else if let Some(input) = input.as_any().downcast_ref::<super::BorrowedArray<f64>>() {
+            input.view()
+        } else if let Some(input) = input.as_any().downcast_ref::<crate::dlpack::DLPackArray>().and_then(|a| a.view_f64()) {
+            input
+        } else {
+            return Err(Error {
+                code: None,
+                message: "input must be a ndarray of f64".into(),
+            });
+        };
+
+        move_samples_between_views(self.view_mut(), &input, samples, property);
+        return Ok(());
+    }
+
+    fn add(&mut self, other: &dyn Array) -> Result<(), Error> {
+        if let Some(other) = other.as_any().downcast_ref::<ndarray::ArrayD<f64>>() {
+            *self += other;
+            return Ok(());
+        }
+        return add_with_data(self, other);
+    }
+
+    fn mul(&mut self, other: &dyn Array) -> Result<(), Error> {
+        if let Some(other) = other.as_any().downcast_ref::<ndarray::ArrayD<f64>>() {
+            *self *= other;
+            return Ok(());
+        }
+        return mul_with_data(self, other);
+    }
+
+    fn scale(&mut self, factor: f64) -> Result<(), Error> {
+        *self *= factor;
+        return Ok(());
+    }
 
-            output_location.assign(&value);
+    fn matmul(&self, other: &dyn Array) -> Result<Box<dyn Array>, Error> {
+        if let Some(other) = other.as_any().downcast_ref::<ndarray::ArrayD<f64>>() {
+            return Ok(Box::new(ndarray_matmul(self, other)?));
         }
+        return matmul_with_data(self, other);
+    }
+
+    fn sum_axis(&self, axis: usize) -> Result<Box<dyn Array>, Error> {
+        let axis = ndarray::Axis(axis);
+        return Ok(Box::new(self.sum_axis(axis).insert_axis(axis)));
+    }
+
+    fn copy_from(&mut self, other: &dyn Array) -> Result<(), Error> {
+        if let Some(other) = other.as_any().downcast_ref::<ndarray::ArrayD<f64>>() {
+            self.assign(other);
+            return Ok(());
+        }
+        let mut other = other.try_copy()?;
+        self.try_data()?.copy_from_slice(other.try_data()?);
+        return Ok(());
+    }
+
+    fn move_along_axis(
+        &mut self,
+        input: &dyn Array,
+        axis: usize,
+        mapping: &[mts_index_mapping_t],
+        output_offsets: &[usize],
+    ) -> Result<(), Error> {
+        if let Some(input) = input.as_any().downcast_ref::<ndarray::ArrayD<f64>>() {
+            move_along_axis_between_views(self.view_mut(), &input.view(), axis, mapping, output_offsets);
+            return Ok(());
+        }
+        return move_along_axis_with_data(self, input, axis, mapping, output_offsets);
+    }
+}
+
+/// `Array` implementation for 32-bit floating point arrays. Since the data is
+/// not stored as 64-bit floating points, [`Array::data`] is not available for
+/// these arrays, and they can not be serialized.
+impl Array for ndarray::ArrayD<f32> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn create(&self, shape: &[usize]) -> Box<dyn Array> {
+        return Box::new(ndarray::Array::from_elem(shape, 0.0_f32));
+    }
+
+    fn copy(&self) -> Box<dyn Array> {
+        return Box::new(self.clone());
+    }
+
+    fn data(&mut self) -> &mut [f64] {
+        return self.try_data().unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn shape(&self) -> &[usize] {
+        return self.shape();
+    }
+
+    fn reshape(&mut self, shape: &[usize]) {
+        self.try_reshape(shape).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn swap_axes(&mut self, axis_1: usize, axis_2: usize) {
+        self.swap_axes(axis_1, axis_2);
+    }
+
+    fn move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        property: Range<usize>,
+    ) {
+        self.try_move_samples_from(input, samples, property).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn try_data(&mut self) -> Result<&mut [f64], Error> {
+        return Err(Error {
+            code: None,
+            message: "can not access the data of an array of 32-bit floats as 64-bit floats".into(),
+        });
+    }
+
+    fn try_reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
+        return ndarray_reshape(self, shape);
+    }
+
+    fn try_move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        property: Range<usize>,
+    ) -> Result<(), Error> {
+        let input = if let Some(input) = input.as_any().downcast_ref::<ndarray::ArrayD<f32>>() {
+            input.view()
+        } else if let Some(input) = input.as_any().downcast_ref::<super::BorrowedArray<f32>>() {
+            input.view()
+        } else if let Some(input) = input.as_any().downcast_ref::<crate::dlpack::DLPackArray>().and_then(|a| a.view_f32()) {
+            input
+        } else {
+            return Err(Error {
+                code: None,
+                message: "input must be a ndarray of f32".into(),
+            });
+        };
+
+        move_samples_between_views(self.view_mut(), &input, samples, property);
+        return Ok(());
+    }
+
+    fn descriptor(&self) -> mts_array_descriptor_t {
+        return mts_array_descriptor_t::cpu_float(32);
+    }
+
+    fn raw_data(&mut self) -> Result<*mut c_void, Error> {
+        let data = self.as_slice_mut().ok_or_else(|| Error {
+            code: None,
+            message: "array is not contiguous".into(),
+        })?;
+        return Ok(data.as_mut_ptr().cast());
+    }
+
+    fn add(&mut self, other: &dyn Array) -> Result<(), Error> {
+        *self += downcast_f32(other)?;
+        return Ok(());
+    }
+
+    fn mul(&mut self, other: &dyn Array) -> Result<(), Error> {
+        *self *= downcast_f32(other)?;
+        return Ok(());
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn scale(&mut self, factor: f64) -> Result<(), Error> {
+        *self *= factor as f32;
+        return Ok(());
+    }
+
+    fn matmul(&self, other: &dyn Array) -> Result<Box<dyn Array>, Error> {
+        return Ok(Box::new(ndarray_matmul(self, downcast_f32(other)?)?));
+    }
+
+    fn sum_axis(&self, axis: usize) -> Result<Box<dyn Array>, Error> {
+        let axis = ndarray::Axis(axis);
+        return Ok(Box::new(self.sum_axis(axis).insert_axis(axis)));
+    }
+
+    fn copy_from(&mut self, other: &dyn Array) -> Result<(), Error> {
+        self.assign(downcast_f32(other)?);
+        return Ok(());
+    }
+
+    fn move_along_axis(
+        &mut self,
+        input: &dyn Array,
+        axis: usize,
+        mapping: &[mts_index_mapping_t],
+        output_offsets: &[usize],
+    ) -> Result<(), Error> {
+        let input = downcast_f32(input)?;
+        move_along_axis_between_views(self.view_mut(), &input.view(), axis, mapping, output_offsets);
+        return Ok(());
+    }
+}
+
+/// Get `other` as a 32-bit floating point ndarray, for operations between
+/// arrays
+fn downcast_f32(other: &dyn Array) -> Result<&ndarray::ArrayD<f32>, Error> {
+    return other.as_any().downcast_ref::<ndarray::ArrayD<f32>>().ok_or_else(|| Error {
+        code: None,
+        message: "other array must be a ndarray of f32".into(),
+    });
+}
+
+/// Implementation of `Array::matmul` for all `ndarray::ArrayD`
+fn ndarray_matmul<T: ndarray::LinalgScalar>(
+    array: &ndarray::ArrayD<T>,
+    other: &ndarray::ArrayD<T>,
+) -> Result<ndarray::ArrayD<T>, Error> {
+    let shape_error = |e: ndarray::ShapeError| Error {
+        code: None,
+        message: format!("invalid shape: {}", e),
+    };
+
+    let shape = array.shape();
+    let size = shape[shape.len() - 1];
+    let rows = shape[..shape.len() - 1].iter().product::<usize>();
+
+    let array = array.as_standard_layout();
+    let array = array.view().into_shape_with_order((rows, size)).map_err(shape_error)?;
+    let other = other.view().into_dimensionality::<ndarray::Ix2>().map_err(shape_error)?;
+
+    let mut output_shape = shape.to_vec();
+    *output_shape.last_mut().expect("shape should not be empty") = other.ncols();
+
+    return array.dot(&other).into_shape_with_order(output_shape).map_err(shape_error);
+}
+
+/// Implementation of `Array::try_reshape` for all `ndarray::ArrayD`
+fn ndarray_reshape<T: Clone>(array: &mut ndarray::ArrayD<T>, shape: &[usize]) -> Result<(), Error> {
+    let reshaped = array.to_shape(shape).map_err(|e| Error {
+        code: None,
+        message: format!("invalid shape: {}", e),
+    })?;
+
+    *array = reshaped.into_owned();
+    return Ok(());
+}
+
+/// Copy data from `input[sample.input, ..., :]` to `output[sample.output, ...,
+/// property]` for all `samples`
+pub(super) fn move_samples_between_views<T: Clone>(
+    mut output: ndarray::ArrayViewMutD<'_, T>,
+    input: &ndarray::ArrayViewD<'_, T>,
+    samples: &[mts_sample_mapping_t],
+    property: Range<usize>,
+) {
+    use ndarray::{Axis, Slice};
+
+    // -2 since we also remove one axis with `index_axis_mut` below
+    let property_axis = output.shape().len() - 2;
+
+    for sample in samples {
+        let value = input.index_axis(Axis(0), sample.input);
+
+        let mut output_location = output.index_axis_mut(Axis(0), sample.output);
+        let mut output_location = output_location.slice_axis_mut(
+            Axis(property_axis), Slice::from(property.clone())
+        );
+
+        output_location.assign(&value);
+    }
+}
+
+/// Copy values from `input` to `output` along `axis`, following the
+/// conventions of [`Array::move_along_axis`]
+fn move_along_axis_between_views<T: Clone>(
+    mut output: ndarray::ArrayViewMutD<'_, T>,
+    input: &ndarray::ArrayViewD<'_, T>,
+    axis: usize,
+    mapping: &[mts_index_mapping_t],
+    output_offsets: &[usize],
+) {
+    use ndarray::{Axis, Slice};
+
+    for entry in mapping {
+        let value = input.index_axis(Axis(axis), entry.input);
+
+        let mut output_location = output.index_axis_mut(Axis(axis), entry.output);
+        let mut output_location = output_location.slice_each_axis_mut(|description| {
+            let index = description.axis.index();
+            // `index_axis_mut` removed `axis` from the view
+            let offset = if index < axis { output_offsets[index] } else { output_offsets[index + 1] };
+            Slice::from(offset..offset + value.shape()[index])
+        });
+
+        output_location.assign(&value);
     }
 }
 
@@ -338,6 +1106,13 @@ impl Array for EmptyArray {
         panic!("can not call Array::data() for EmptyArray");
     }
 
+    fn try_data(&mut self) -> Result<&mut [f64], Error> {
+        return Err(Error {
+            code: None,
+            message: "can not call Array::data() for EmptyArray".into(),
+        });
+    }
+
     fn create(&self, shape: &[usize]) -> Box<dyn Array> {
         Box::new(EmptyArray { shape: shape.to_vec() })
     }
@@ -361,4 +1136,15 @@ impl Array for EmptyArray {
     fn move_samples_from(&mut self, _: &dyn Array, _: &[mts_sample_mapping_t], _: Range<usize>) {
         panic!("can not call Array::move_samples_from() for EmptyArray");
     }
+
+    fn try_move_samples_from(&mut self, _: &dyn Array, _: &[mts_sample_mapping_t], _: Range<usize>) -> Result<(), Error> {
+        return Err(Error {
+            code: None,
+            message: "can not call Array::move_samples_from() for EmptyArray".into(),
+        });
+    }
+
+    fn descriptor(&self) -> mts_array_descriptor_t {
+        return mts_array_descriptor_t::unknown();
+    }
 }
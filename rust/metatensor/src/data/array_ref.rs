@@ -1,5 +1,6 @@
 use crate::c_api::mts_array_t;
 use crate::data::origin::get_data_origin;
+use crate::Error;
 
 use super::Array;
 
@@ -84,6 +85,22 @@ impl<'a> ArrayRef<'a> {
         self.to_any().downcast_ref().expect("this is not a ndarray::ArrayD")
     }
 
+    /// Get the data in this `ArrayRef` as a reference to `T`, or an error if
+    /// the data is not a `T` created through the [`Array`] trait.
+    #[inline]
+    pub fn try_as<T: Array>(&self) -> Result<&T, Error> {
+        return self.try_to();
+    }
+
+    /// Transform this `ArrayRef` into a reference to `T`, keeping the
+    /// lifetime of the `ArrayRef`, or return an error if the data is not a
+    /// `T` created through the [`Array`] trait.
+    #[inline]
+    pub fn try_to<T: Array>(self) -> Result<&'a T, Error> {
+        // SAFETY: the lifetime is constrained by the lifetime of `self`
+        return unsafe { downcast_ref(&self.array) };
+    }
+
     /// Get the raw underlying `mts_array_t`
     pub fn as_raw(&self) -> &mts_array_t {
         &self.array
@@ -231,6 +248,31 @@ impl<'a> ArrayRefMut<'a> {
         self.to_any_mut().downcast_mut().expect("this is not a ndarray::ArrayD")
     }
 
+    /// Get the data in this `ArrayRefMut` as a reference to `T`, or an error
+    /// if the data is not a `T` created through the [`Array`] trait.
+    #[inline]
+    pub fn try_as<T: Array>(&self) -> Result<&T, Error> {
+        // SAFETY: the lifetime is constrained by the lifetime of `self`
+        return unsafe { downcast_ref(&self.array) };
+    }
+
+    /// Get the data in this `ArrayRefMut` as a mutable reference to `T`, or
+    /// an error if the data is not a `T` created through the [`Array`] trait.
+    #[inline]
+    pub fn try_as_mut<T: Array>(&mut self) -> Result<&mut T, Error> {
+        // SAFETY: the lifetime is constrained by the lifetime of `self`
+        return unsafe { downcast_mut(&self.array) };
+    }
+
+    /// Transform this `ArrayRefMut` into a mutable reference to `T`, keeping
+    /// the lifetime of the `ArrayRefMut`, or return an error if the data is
+    /// not a `T` created through the [`Array`] trait.
+    #[inline]
+    pub fn try_to_mut<T: Array>(self) -> Result<&'a mut T, Error> {
+        // SAFETY: the lifetime is constrained by the lifetime of `self`
+        return unsafe { downcast_mut(&self.array) };
+    }
+
     /// Get the raw underlying `mts_array_t`
     pub fn as_raw(&self) -> &mts_array_t {
         &self.array
@@ -241,3 +283,57 @@ impl<'a> ArrayRefMut<'a> {
         &mut self.array
     }
 }
+
+/// Get a pointer to the rust [`Array`] inside `array`, or an error if the
+/// array was not created through the [`Array`] trait.
+fn rust_array<T>(array: &mts_array_t) -> Result<*mut Box<dyn Array>, Error> {
+    if array.origin()? != *super::array::RUST_DATA_ORIGIN {
+        let origin = array.origin().and_then(get_data_origin).unwrap_or_else(|_| "unknown".into());
+        return Err(Error {
+            code: None,
+            message: format!(
+                "expected the array to be a `{}`, but it has a different type (data origin is '{}')",
+                std::any::type_name::<T>(), origin
+            ),
+        });
+    }
+
+    return Ok(array.ptr.cast());
+}
+
+/// Get the error for a failed conversion of a rust array with the given
+/// `type_name` to `T`
+fn downcast_error<T>(type_name: &str) -> Error {
+    return Error {
+        code: None,
+        message: format!(
+            "expected the array to be a `{}`, but it is a `{}`",
+            std::any::type_name::<T>(), type_name
+        ),
+    };
+}
+
+/// Get the data in `array` as a reference to `T`, or an error if the data is
+/// not a `T` created through the [`Array`] trait.
+///
+/// # Safety
+///
+/// The lifetime `'a` must be constrained by the lifetime of the owner of the
+/// `array`.
+unsafe fn downcast_ref<'a, T: Array>(array: &mts_array_t) -> Result<&'a T, Error> {
+    let array = &*rust_array::<T>(array)?;
+    return array.as_any().downcast_ref().ok_or_else(|| downcast_error::<T>(array.type_name()));
+}
+
+/// Get the data in `array` as a mutable reference to `T`, or an error if the
+/// data is not a `T` created through the [`Array`] trait.
+///
+/// # Safety
+///
+/// The lifetime `'a` must be constrained by the lifetime of the owner of the
+/// `array`, which must be mutably borrowed for `'a`.
+unsafe fn downcast_mut<'a, T: Array>(array: &mts_array_t) -> Result<&'a mut T, Error> {
+    let array = &mut *rust_array::<T>(array)?;
+    let type_name = array.type_name();
+    return array.as_any_mut().downcast_mut().ok_or_else(|| downcast_error::<T>(type_name));
+}
@@ -0,0 +1,256 @@
+use std::ops::Range;
+use std::os::raw::c_void;
+
+use crate::c_api::{mts_sample_mapping_t, mts_array_descriptor_t};
+use crate::Error;
+
+use super::Array;
+use super::array::move_samples_between_views;
+
+/// Implementation of the [`Array`] trait for data borrowed from somewhere
+/// else, used by [`TensorBlock::from_borrowed`](crate::TensorBlock::from_borrowed).
+///
+/// This array does not own its data, and the lifetime of the data is checked
+/// by the [`Borrowed`](crate::Borrowed) wrapper instead of this type. Arrays
+/// created with `Array::create` or `Array::copy` from this one are owned
+/// `ndarray::ArrayD<T>`.
+///
+/// The `Array` trait is implemented for `f64` and `f32` data.
+pub(crate) struct BorrowedArray<T = f64> {
+    ptr: *mut T,
+    len: usize,
+    shape: Vec<usize>,
+}
+
+// SAFETY: this is equivalent to a `&mut [T]`, which is Send
+unsafe impl<T: Send> Send for BorrowedArray<T> {}
+// SAFETY: this is equivalent to a `&mut [T]`, which is Sync
+unsafe impl<T: Sync> Sync for BorrowedArray<T> {}
+
+impl<T: Copy> BorrowedArray<T> {
+    /// Create a new `BorrowedArray` containing the given `data`, with the given
+    /// `shape`.
+    ///
+    /// # Safety
+    ///
+    /// The returned array must not be used after the end of the `data`
+    /// lifetime.
+    pub(crate) unsafe fn new(data: &mut [T], shape: &[usize]) -> Result<BorrowedArray<T>, Error> {
+        if shape.iter().product::<usize>() != data.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "the data size ({}) does not match the shape {:?}",
+                    data.len(), shape
+                ),
+            });
+        }
+
+        return Ok(BorrowedArray {
+            ptr: data.as_mut_ptr(),
+            len: data.len(),
+            shape: shape.to_vec(),
+        });
+    }
+
+    fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+
+    /// Get a `ndarray` view inside this array
+    pub(crate) fn view(&self) -> ndarray::ArrayViewD<'_, T> {
+        return ndarray::ArrayViewD::from_shape(&*self.shape, self.as_slice())
+            .expect("the data size does not match the shape");
+    }
+
+    fn view_mut(&mut self) -> ndarray::ArrayViewMutD<'_, T> {
+        let shape = self.shape.clone();
+        return ndarray::ArrayViewMutD::from_shape(shape, self.as_mut_slice())
+            .expect("the data size does not match the shape");
+    }
+
+    /// Swap the axes `axis_1` and `axis_2`, transposing the data in place in
+    /// the borrowed memory
+    fn swap_axes_in_place(&mut self, axis_1: usize, axis_2: usize) {
+        let mut view = self.view();
+        view.swap_axes(axis_1, axis_2);
+        let transposed = view.iter().copied().collect::<Vec<_>>();
+
+        self.as_mut_slice().copy_from_slice(&transposed);
+        self.shape.swap(axis_1, axis_2);
+    }
+
+    /// Implementation of `Array::try_reshape` for all data types
+    fn reshape_in_place(&mut self, shape: &[usize]) -> Result<(), Error> {
+        if shape.iter().product::<usize>() != self.len {
+            return Err(Error {
+                code: None,
+                message: format!("invalid shape: can not reshape array of shape {:?} to {:?}", self.shape, shape),
+            });
+        }
+
+        self.shape = shape.to_vec();
+        return Ok(());
+    }
+}
+
+impl Array for BorrowedArray<f64> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn create(&self, shape: &[usize]) -> Box<dyn Array> {
+        return Box::new(ndarray::ArrayD::from_elem(shape, 0.0));
+    }
+
+    fn copy(&self) -> Box<dyn Array> {
+        return Box::new(self.view().to_owned());
+    }
+
+    fn data(&mut self) -> &mut [f64] {
+        return self.as_mut_slice();
+    }
+
+    fn shape(&self) -> &[usize] {
+        return &self.shape;
+    }
+
+    fn reshape(&mut self, shape: &[usize]) {
+        self.try_reshape(shape).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn swap_axes(&mut self, axis_1: usize, axis_2: usize) {
+        self.swap_axes_in_place(axis_1, axis_2);
+    }
+
+    fn move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) {
+        self.try_move_samples_from(input, samples, properties).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn try_reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
+        return self.reshape_in_place(shape);
+    }
+
+    fn try_move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) -> Result<(), Error> {
+        let input = if let Some(input) = input.as_any().downcast_ref::<ndarray::ArrayD<f64>>() {
+            input.view()
+        } else if let Some(input) = input.as_any().downcast_ref::<BorrowedArray<f64>>() {
+            input.view()
+        } else if let Some(input) = input.as_any().downcast_ref::<crate::dlpack::DLPackArray>().and_then(|a| a.view_f64()) {
+            input
+        } else {
+            return Err(Error {
+                code: None,
+                message: "input must be a ndarray of f64 or borrowed data".into(),
+            });
+        };
+
+        move_samples_between_views(self.view_mut(), &input, samples, properties);
+        return Ok(());
+    }
+}
+
+/// `Array` implementation for borrowed 32-bit floating point data. As for
+/// `ndarray::ArrayD<f32>`, [`Array::data`] is not available for this array.
+impl Array for BorrowedArray<f32> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn create(&self, shape: &[usize]) -> Box<dyn Array> {
+        return Box::new(ndarray::ArrayD::from_elem(shape, 0.0_f32));
+    }
+
+    fn copy(&self) -> Box<dyn Array> {
+        return Box::new(self.view().to_owned());
+    }
+
+    fn data(&mut self) -> &mut [f64] {
+        return self.try_data().unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn shape(&self) -> &[usize] {
+        return &self.shape;
+    }
+
+    fn reshape(&mut self, shape: &[usize]) {
+        self.try_reshape(shape).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn swap_axes(&mut self, axis_1: usize, axis_2: usize) {
+        self.swap_axes_in_place(axis_1, axis_2);
+    }
+
+    fn move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) {
+        self.try_move_samples_from(input, samples, properties).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn try_data(&mut self) -> Result<&mut [f64], Error> {
+        return Err(Error {
+            code: None,
+            message: "can not access the data of an array of 32-bit floats as 64-bit floats".into(),
+        });
+    }
+
+    fn try_reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
+        return self.reshape_in_place(shape);
+    }
+
+    fn try_move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) -> Result<(), Error> {
+        let input = if let Some(input) = input.as_any().downcast_ref::<ndarray::ArrayD<f32>>() {
+            input.view()
+        } else if let Some(input) = input.as_any().downcast_ref::<BorrowedArray<f32>>() {
+            input.view()
+        } else if let Some(input) = input.as_any().downcast_ref::<crate::dlpack::DLPackArray>().and_then(|a| a.view_f32()) {
+            input
+        } else {
+            return Err(Error {
+                code: None,
+                message: "input must be a ndarray of f32 or borrowed data".into(),
+            });
+        };
+
+        move_samples_between_views(self.view_mut(), &input, samples, properties);
+        return Ok(());
+    }
+
+    fn descriptor(&self) -> mts_array_descriptor_t {
+        return mts_array_descriptor_t::cpu_float(32);
+    }
+
+    fn raw_data(&mut self) -> Result<*mut c_void, Error> {
+        return Ok(self.ptr.cast());
+    }
+}
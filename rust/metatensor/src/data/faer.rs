@@ -0,0 +1,140 @@
+use std::ops::Range;
+
+use faer::Mat;
+
+use crate::c_api::mts_sample_mapping_t;
+use crate::Error;
+
+use super::Array;
+
+/// Implementation of the [`Array`] trait for 2D blocks (without components)
+/// using `faer::Mat<f64>`. The rows of the matrix correspond to the samples,
+/// and the columns to the properties.
+///
+/// `faer` stores data in column-major order, so [`Array::data`] is not
+/// available for this array, and blocks using it can not be serialized.
+#[derive(Debug, Clone)]
+pub struct FaerArray {
+    matrix: Mat<f64>,
+    shape: [usize; 2],
+}
+
+impl FaerArray {
+    /// Create a new `FaerArray` containing the given `matrix`
+    pub fn new(matrix: Mat<f64>) -> FaerArray {
+        let shape = [matrix.nrows(), matrix.ncols()];
+        FaerArray { matrix, shape }
+    }
+
+    /// Get the matrix inside this array
+    pub fn matrix(&self) -> &Mat<f64> {
+        &self.matrix
+    }
+
+    /// Get a mutable view inside the matrix of this array
+    pub fn matrix_mut(&mut self) -> faer::MatMut<'_, f64> {
+        self.matrix.as_mut()
+    }
+
+    /// Extract the matrix inside this array
+    pub fn into_inner(self) -> Mat<f64> {
+        self.matrix
+    }
+}
+
+impl Array for FaerArray {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn create(&self, shape: &[usize]) -> Box<dyn Array> {
+        self.try_create(shape).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn copy(&self) -> Box<dyn Array> {
+        Box::new(self.clone())
+    }
+
+    fn data(&mut self) -> &mut [f64] {
+        self.try_data().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    fn reshape(&mut self, shape: &[usize]) {
+        self.try_reshape(shape).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn swap_axes(&mut self, axis_1: usize, axis_2: usize) {
+        self.try_swap_axes(axis_1, axis_2).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) {
+        self.try_move_samples_from(input, samples, properties).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn try_create(&self, shape: &[usize]) -> Result<Box<dyn Array>, Error> {
+        let shape = super::check_2d_shape(shape)?;
+        return Ok(Box::new(FaerArray::new(Mat::zeros(shape[0], shape[1]))));
+    }
+
+    fn try_data(&mut self) -> Result<&mut [f64], Error> {
+        return Err(Error {
+            code: None,
+            message: "can not access the data of a faer matrix, it is stored in column-major order".into(),
+        });
+    }
+
+    fn try_reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
+        let shape = super::check_2d_shape(shape)?;
+        if shape != self.shape {
+            return Err(Error {
+                code: None,
+                message: format!("invalid shape: can not reshape faer matrix of shape {:?} to {:?}", self.shape, shape),
+            });
+        }
+        return Ok(());
+    }
+
+    fn try_swap_axes(&mut self, axis_1: usize, axis_2: usize) -> Result<(), Error> {
+        super::check_2d_axes(axis_1, axis_2)?;
+        if axis_1 != axis_2 {
+            self.matrix = self.matrix.transpose().to_owned();
+            self.shape.swap(0, 1);
+        }
+        return Ok(());
+    }
+
+    fn try_move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) -> Result<(), Error> {
+        let input = input.as_any().downcast_ref::<FaerArray>().ok_or_else(|| Error {
+            code: None,
+            message: "input must be a FaerArray".into(),
+        })?;
+
+        super::check_2d_move_samples(input.shape, self.shape, samples, &properties)?;
+
+        for sample in samples {
+            for (i, property) in properties.clone().enumerate() {
+                self.matrix[(sample.output, property)] = input.matrix[(sample.input, i)];
+            }
+        }
+
+        return Ok(());
+    }
+}
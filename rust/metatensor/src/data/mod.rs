@@ -1,4 +1,8 @@
 mod origin;
+pub(crate) use self::origin::find_data_origin;
+#[cfg(test)]
+pub(crate) use self::origin::register_data_origin;
+pub use self::origin::register_array_converter;
 
 mod array_ref;
 pub use self::array_ref::{ArrayRef, ArrayRefMut};
@@ -7,6 +11,81 @@ mod array;
 pub use self::array::Array;
 pub use self::array::EmptyArray;
 
+mod vec_array;
+pub use self::vec_array::VecArray;
+
+mod borrowed;
+pub(crate) use self::borrowed::BorrowedArray;
+
+mod sparse;
+pub use self::sparse::SparseArray;
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra;
+#[cfg(feature = "nalgebra")]
+pub use self::nalgebra::NalgebraArray;
+
+#[cfg(feature = "faer")]
+mod faer;
+#[cfg(feature = "faer")]
+pub use self::faer::FaerArray;
+
+/// Check that the given shape is 2-dimensional, for arrays implemented with
+/// matrices
+#[cfg(any(feature = "nalgebra", feature = "faer"))]
+fn check_2d_shape(shape: &[usize]) -> Result<[usize; 2], crate::Error> {
+    return shape.try_into().map_err(|_| crate::Error {
+        code: None,
+        message: format!("matrices can only be used with 2-dimensional arrays, got shape {:?}", shape),
+    });
+}
+
+/// Check that the given axes can be swapped in a 2-dimensional matrix
+#[cfg(any(feature = "nalgebra", feature = "faer"))]
+fn check_2d_axes(axis_1: usize, axis_2: usize) -> Result<(), crate::Error> {
+    if axis_1 >= 2 || axis_2 >= 2 {
+        return Err(crate::Error {
+            code: None,
+            message: format!("invalid axes: can not swap axes {} and {} of a 2-dimensional matrix", axis_1, axis_2),
+        });
+    }
+    return Ok(());
+}
+
+/// Check that `samples` and `properties` are in bounds when moving samples
+/// from a matrix with shape `input` to a matrix with shape `output`
+#[cfg(any(feature = "nalgebra", feature = "faer"))]
+fn check_2d_move_samples(
+    input: [usize; 2],
+    output: [usize; 2],
+    samples: &[crate::c_api::mts_sample_mapping_t],
+    properties: &std::ops::Range<usize>,
+) -> Result<(), crate::Error> {
+    if input[1] != properties.len() || properties.end > output[1] {
+        return Err(crate::Error {
+            code: None,
+            message: format!(
+                "invalid shape: can not move samples from an array of shape {:?} to properties {:?} of an array of shape {:?}",
+                input, properties, output
+            ),
+        });
+    }
+
+    for sample in samples {
+        if sample.input >= input[0] || sample.output >= output[0] {
+            return Err(crate::Error {
+                code: None,
+                message: format!(
+                    "invalid sample mapping: {} -> {} is out of bounds for arrays with {} and {} samples",
+                    sample.input, sample.output, input[0], output[0]
+                ),
+            });
+        }
+    }
+
+    return Ok(());
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -45,6 +124,87 @@ mod tests {
         assert_eq!(other.as_array(), ArrayD::from_elem(vec![5, 3, 7, 12], 0.0));
     }
 
+    #[test]
+    fn errors() {
+        struct GpuArray {
+            shape: Vec<usize>,
+        }
+
+        impl Array for GpuArray {
+            fn as_any(&self) -> &dyn std::any::Any { self }
+            fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+            fn create(&self, shape: &[usize]) -> Box<dyn Array> { Box::new(GpuArray { shape: shape.to_vec() }) }
+            fn copy(&self) -> Box<dyn Array> { Box::new(GpuArray { shape: self.shape.clone() }) }
+            fn data(&mut self) -> &mut [f64] { &mut [] }
+            fn shape(&self) -> &[usize] { &self.shape }
+            fn reshape(&mut self, shape: &[usize]) { self.shape = shape.to_vec(); }
+            fn swap_axes(&mut self, axis_1: usize, axis_2: usize) { self.shape.swap(axis_1, axis_2); }
+            fn move_samples_from(&mut self, _: &dyn Array, _: &[mts_sample_mapping_t], _: std::ops::Range<usize>) {}
+
+            fn try_data(&mut self) -> Result<&mut [f64], crate::Error> {
+                Err(crate::Error { code: None, message: "wrong device: data is on the GPU".into() })
+            }
+
+            fn try_move_samples_from(&mut self, _: &dyn Array, _: &[mts_sample_mapping_t], _: std::ops::Range<usize>) -> Result<(), crate::Error> {
+                Err(crate::Error { code: None, message: "wrong device: data is on the GPU".into() })
+            }
+        }
+
+        let array = Box::new(GpuArray { shape: vec![3, 2] }) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+        let error = array.as_raw_mut().data().unwrap_err();
+        assert_eq!(error.message, "wrong device: data is on the GPU");
+
+        let array = Box::new(ArrayD::from_elem(vec![3, 2], 1.0)) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+        let error = array.as_raw_mut().reshape(&[4, 2]).unwrap_err();
+        assert!(error.message.starts_with("invalid shape: "));
+
+        let input = Box::new(EmptyArray::new(vec![3, 2])) as Box<dyn Array>;
+        let input = unsafe { ArrayRef::from_raw(input.into()) };
+        let mapping = mts_sample_mapping_t { output: 0, input: 1 };
+        let error = array.as_raw_mut().move_samples_from(input.as_raw(), &[mapping], 0..2).unwrap_err();
+        assert_eq!(error.message, "input must be a ndarray of f64");
+    }
+
+    #[test]
+    fn try_as() {
+        use crate::c_api::{mts_array_t, mts_data_origin_t, mts_status_t, MTS_SUCCESS};
+
+        unsafe extern "C" fn numpy_origin(_: *const std::os::raw::c_void, origin: *mut mts_data_origin_t) -> mts_status_t {
+            *origin = super::origin::register_data_origin("python.numpy".into()).unwrap();
+            return MTS_SUCCESS;
+        }
+
+        let array = Box::new(ArrayD::from_elem(vec![3, 2], 1.0)) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+
+        assert_eq!(array.try_as::<ArrayD<f64>>().unwrap(), ArrayD::from_elem(vec![3, 2], 1.0));
+        array.try_as_mut::<ArrayD<f64>>().unwrap().fill(3.0);
+        assert_eq!(*array.try_to_mut::<ArrayD<f64>>().unwrap(), ArrayD::from_elem(vec![3, 2], 3.0));
+
+        let array = Box::new(VecArray::new(vec![1.0; 6], vec![3, 2])) as Box<dyn Array>;
+        let array = unsafe { ArrayRef::from_raw(array.into()) };
+        assert_eq!(array.try_as::<VecArray>().unwrap().shape(), [3, 2]);
+
+        let error = array.try_as::<ArrayD<f64>>().unwrap_err();
+        assert_eq!(error.message, format!(
+            "expected the array to be a `{}`, but it is a `{}`",
+            std::any::type_name::<ArrayD<f64>>(), std::any::type_name::<VecArray>(),
+        ));
+
+        let array = mts_array_t {
+            origin: Some(numpy_origin),
+            ..mts_array_t::null()
+        };
+        let array = unsafe { ArrayRef::from_raw(array) };
+        let error = array.try_to::<VecArray>().unwrap_err();
+        assert_eq!(error.message, format!(
+            "expected the array to be a `{}`, but it has a different type (data origin is 'python.numpy')",
+            std::any::type_name::<VecArray>()
+        ));
+    }
+
     #[test]
     fn move_samples_from() {
         let array = Box::new(ArrayD::from_elem(vec![3, 2, 2, 4], 1.0)) as Box<dyn Array>;
@@ -66,4 +226,285 @@ mod tests {
         ]).unwrap();
         assert_eq!(other.as_array(), expected);
     }
+
+    #[test]
+    fn vec_array_move_samples_from() {
+        let input = VecArray::new(vec![1.0, 2.0, 3.0, 4.0], vec![2, 2]);
+        let mut output = VecArray::zeros(vec![1, 3]);
+
+        let mapping = mts_sample_mapping_t { output: 0, input: 1 };
+        output.try_move_samples_from(&input, &[mapping], 1..3).unwrap();
+        assert_eq!(output.as_slice(), [0.0, 3.0, 4.0]);
+
+        let error = output.try_move_samples_from(&input, &[mapping], 0..3).unwrap_err();
+        assert!(error.message.starts_with("invalid shape: "));
+
+        let error = output.try_move_samples_from(&VecArray::zeros(vec![2]), &[mapping], 0..2).unwrap_err();
+        assert!(error.message.starts_with("invalid shape: "));
+
+        let mut output_1d = VecArray::zeros(vec![2]);
+        let error = output_1d.try_move_samples_from(&input, &[mapping], 0..2).unwrap_err();
+        assert!(error.message.starts_with("invalid shape: "));
+
+        let mapping = mts_sample_mapping_t { output: 0, input: 2 };
+        let error = output.try_move_samples_from(&input, &[mapping], 1..3).unwrap_err();
+        assert_eq!(error.message, "invalid sample mapping: 2 -> 0 is out of bounds for arrays with 2 and 1 samples");
+    }
+
+    #[test]
+    fn operations() {
+        let array = ndarray::arr2(&[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]).into_dyn();
+        let array = Box::new(array) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+
+        let other = Box::new(ArrayD::from_elem(vec![2, 3], 2.0)) as Box<dyn Array>;
+        let other = unsafe { ArrayRef::from_raw(other.into()) };
+
+        array.as_raw_mut().add(other.as_raw()).unwrap();
+        array.as_raw_mut().mul(other.as_raw()).unwrap();
+        array.as_raw_mut().scale(0.5).unwrap();
+        assert_eq!(array.as_array(), ndarray::arr2(&[[3.0, 4.0, 5.0], [6.0, 7.0, 8.0]]).into_dyn());
+
+        let sum = unsafe { ArrayRef::from_raw(array.as_raw().sum_axis(1).unwrap()) };
+        assert_eq!(sum.as_array(), ndarray::arr2(&[[12.0], [21.0]]).into_dyn());
+
+        let matrix = ndarray::arr2(&[[1.0, 0.0], [0.0, 1.0], [1.0, 1.0]]).into_dyn();
+        let matrix = Box::new(matrix) as Box<dyn Array>;
+        let matrix = unsafe { ArrayRef::from_raw(matrix.into()) };
+        let product = unsafe { ArrayRef::from_raw(array.as_raw().matmul(matrix.as_raw()).unwrap()) };
+        assert_eq!(product.as_array(), ndarray::arr2(&[[8.0, 9.0], [14.0, 15.0]]).into_dyn());
+
+        // operations between different array types use the data
+        let vec_array = Box::new(VecArray::new(vec![1.0; 6], vec![2, 3])) as Box<dyn Array>;
+        let mut vec_array = unsafe { ArrayRefMut::new(vec_array.into()) };
+        array.as_raw_mut().copy_from(vec_array.as_raw()).unwrap();
+        assert_eq!(array.as_array(), ArrayD::from_elem(vec![2, 3], 1.0));
+
+        vec_array.as_raw_mut().add(other.as_raw()).unwrap();
+        let sum = unsafe { ArrayRefMut::new(vec_array.as_raw().sum_axis(0).unwrap()) };
+        assert_eq!(sum.as_any().downcast_ref::<VecArray>().unwrap().as_slice(), [6.0, 6.0, 6.0]);
+
+        let error = array.as_raw_mut().add(matrix.as_raw()).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: can not add arrays with different shapes: [2, 3] and [3, 2]");
+
+        let array_f32 = Box::new(ArrayD::<f32>::from_elem(vec![2, 3], 1.0)) as Box<dyn Array>;
+        let mut array_f32 = unsafe { ArrayRefMut::new(array_f32.into()) };
+        array_f32.as_raw_mut().scale(3.0).unwrap();
+        let sum = unsafe { ArrayRef::from_raw(array_f32.as_raw().sum_axis(0).unwrap()) };
+        assert_eq!(sum.as_any().downcast_ref::<ArrayD<f32>>().unwrap(), ArrayD::<f32>::from_elem(vec![1, 3], 6.0));
+
+        let error = array_f32.as_raw_mut().add(other.as_raw()).unwrap_err();
+        assert_eq!(error.message, "other array must be a ndarray of f32");
+    }
+
+    #[test]
+    fn move_along_axis() {
+        use crate::c_api::mts_index_mapping_t;
+
+        let input = ndarray::arr3(&[[[1.0, 2.0], [3.0, 4.0]], [[5.0, 6.0], [7.0, 8.0]]]).into_dyn();
+        let input = Box::new(input) as Box<dyn Array>;
+        let input = unsafe { ArrayRef::from_raw(input.into()) };
+
+        let output = Box::new(ArrayD::<f64>::zeros(vec![2, 1, 3])) as Box<dyn Array>;
+        let mut output = unsafe { ArrayRefMut::new(output.into()) };
+
+        let mapping = [mts_index_mapping_t { input: 1, output: 0 }];
+        output.as_raw_mut().move_along_axis(input.as_raw(), 1, &mapping, &[0, 0, 1]).unwrap();
+        assert_eq!(output.as_array(), ndarray::arr3(&[[[0.0, 3.0, 4.0]], [[0.0, 7.0, 8.0]]]).into_dyn());
+
+        // moving data between different array types uses the data
+        let vec_array = Box::new(VecArray::new(vec![0.0; 4], vec![2, 1, 2])) as Box<dyn Array>;
+        let mut vec_array = unsafe { ArrayRefMut::new(vec_array.into()) };
+        let mapping = [mts_index_mapping_t { input: 0, output: 0 }];
+        vec_array.as_raw_mut().move_along_axis(input.as_raw(), 1, &mapping, &[0, 0, 0]).unwrap();
+        assert_eq!(vec_array.as_any().downcast_ref::<VecArray>().unwrap().as_slice(), [1.0, 2.0, 5.0, 6.0]);
+
+        let error = output.as_raw_mut().move_along_axis(input.as_raw(), 1, &mapping, &[0, 0, 2]).unwrap_err();
+        assert_eq!(error.message,
+            "invalid parameter: can not move data from an array with shape [2, 2, 2] \
+            to an array with shape [2, 1, 3] with offset 2 on axis 2"
+        );
+    }
+
+    #[test]
+    fn descriptor() {
+        use crate::{Labels, TensorBlock, TensorMap};
+
+        let array = Box::new(ArrayD::<f32>::from_elem(vec![3, 2], 1.0)) as Box<dyn Array>;
+        let array = unsafe { ArrayRef::from_raw(array.into()) };
+        let descriptor = array.as_raw().descriptor().unwrap().unwrap();
+        assert_eq!(descriptor, crate::c_api::mts_array_descriptor_t::cpu_float(32));
+
+        let array = Box::new(EmptyArray::new(vec![3, 2])) as Box<dyn Array>;
+        let array = unsafe { ArrayRef::from_raw(array.into()) };
+        assert!(array.as_raw().descriptor().unwrap().is_none());
+
+        let properties = Labels::new(["p"], &[[0], [1]]);
+        let mut block = TensorBlock::new(
+            ArrayD::<f64>::zeros(vec![1, 2]), &Labels::new(["s"], &[[0]]), &[], &properties,
+        ).unwrap();
+        let gradient = TensorBlock::new(
+            ArrayD::<f32>::zeros(vec![1, 2]), &Labels::new(["sample"], &[[0]]), &[], &properties,
+        ).unwrap();
+        let error = block.add_gradient("g", gradient).unwrap_err();
+        assert_eq!(error.message,
+            "invalid parameter: the gradient data is on a different device or \
+            has a different dtype (cpu:0 float32) than the value data (cpu:0 float64)"
+        );
+
+        let block_f32 = TensorBlock::new(
+            ArrayD::<f32>::zeros(vec![1, 2]), &Labels::new(["s"], &[[0]]), &[], &properties,
+        ).unwrap();
+        let error = TensorMap::new(Labels::new(["key"], &[[0], [1]]), vec![block, block_f32]).unwrap_err();
+        assert_eq!(error.message,
+            "invalid parameter: tried to build a TensorMap from blocks on different \
+            devices or with different dtypes: block 0 is cpu:0 float64 and block 1 \
+            is cpu:0 float32"
+        );
+    }
+
+    #[test]
+    fn ndarray_f32() {
+        let array = Box::new(ArrayD::<f32>::from_elem(vec![3, 2, 4], 1.0)) as Box<dyn Array>;
+        let array = unsafe { ArrayRef::from_raw(array.into()) };
+
+        let mut other = unsafe { ArrayRefMut::new(array.as_raw().create(&[2, 2, 6]).unwrap()) };
+        let mapping = mts_sample_mapping_t { output: 1, input: 0 };
+        other.as_raw_mut().move_samples_from(array.as_raw(), &[mapping], 1..5).unwrap();
+
+        let expected = ArrayD::<f32>::from_shape_vec(vec![2, 2, 6], vec![
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 1.0, 1.0, 1.0, 0.0,
+            0.0, 1.0, 1.0, 1.0, 1.0, 0.0,
+        ]).unwrap();
+        assert_eq!(other.as_any().downcast_ref::<ArrayD<f32>>().unwrap(), expected);
+
+        let array = Box::new(ArrayD::<f32>::from_elem(vec![3, 2], 1.0)) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+        let error = array.as_raw_mut().data().unwrap_err();
+        assert_eq!(error.message, "can not access the data of an array of 32-bit floats as 64-bit floats");
+    }
+
+    #[test]
+    fn vec_array() {
+        let array = VecArray::new(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], vec![3, 1, 2]);
+        let array = Box::new(array) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+
+        let mut other = unsafe { ArrayRefMut::new(array.as_raw().create(&[2, 1, 4]).unwrap()) };
+        let mapping = [
+            mts_sample_mapping_t { output: 0, input: 2 },
+            mts_sample_mapping_t { output: 1, input: 0 },
+        ];
+        other.as_raw_mut().move_samples_from(array.as_raw(), &mapping, 1..3).unwrap();
+        assert_eq!(other.as_raw_mut().data().unwrap(), [0.0, 5.0, 6.0, 0.0, 0.0, 1.0, 2.0, 0.0]);
+
+        array.as_raw_mut().reshape(&[3, 2]).unwrap();
+        array.as_raw_mut().swap_axes(0, 1).unwrap();
+        assert_eq!(array.as_raw().shape().unwrap(), [2, 3]);
+        assert_eq!(array.as_raw_mut().data().unwrap(), [1.0, 3.0, 5.0, 2.0, 4.0, 6.0]);
+
+        let error = array.as_raw_mut().reshape(&[4, 2]).unwrap_err();
+        assert_eq!(error.message, "invalid shape: can not reshape array of shape [2, 3] to [4, 2]");
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn nalgebra() {
+        let matrix = ::nalgebra::DMatrix::from_row_slice(3, 2, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let array = Box::new(NalgebraArray::new(matrix)) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+
+        let mut other = unsafe { ArrayRefMut::new(array.as_raw().create(&[2, 3]).unwrap()) };
+        let mapping = mts_sample_mapping_t { output: 1, input: 2 };
+        other.as_raw_mut().move_samples_from(array.as_raw(), &[mapping], 1..3).unwrap();
+
+        let other = other.as_any().downcast_ref::<NalgebraArray>().unwrap();
+        assert_eq!(*other.matrix(), ::nalgebra::DMatrix::from_row_slice(2, 3, &[0.0, 0.0, 0.0, 0.0, 5.0, 6.0]));
+
+        array.as_raw_mut().swap_axes(0, 1).unwrap();
+        assert_eq!(array.as_raw().shape().unwrap(), [2, 3]);
+
+        let error = array.as_raw().create(&[2, 3, 4]).unwrap_err();
+        assert_eq!(error.message, "matrices can only be used with 2-dimensional arrays, got shape [2, 3, 4]");
+        assert!(array.as_raw_mut().data().is_err());
+
+        let error = array.as_raw_mut().swap_axes(0, 2).unwrap_err();
+        assert_eq!(error.message, "invalid axes: can not swap axes 0 and 2 of a 2-dimensional matrix");
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn nalgebra_move_samples_from() {
+        let input = NalgebraArray::new(::nalgebra::DMatrix::from_row_slice(2, 2, &[1.0, 2.0, 3.0, 4.0]));
+        let mut output = NalgebraArray::new(::nalgebra::DMatrix::zeros(1, 3));
+
+        let mapping = mts_sample_mapping_t { output: 0, input: 1 };
+        output.try_move_samples_from(&input, &[mapping], 1..3).unwrap();
+        assert_eq!(*output.matrix(), ::nalgebra::DMatrix::from_row_slice(1, 3, &[0.0, 3.0, 4.0]));
+
+        let error = output.try_move_samples_from(&input, &[mapping], 0..3).unwrap_err();
+        assert_eq!(error.message, "invalid shape: can not move samples from an array of shape [2, 2] to properties 0..3 of an array of shape [1, 3]");
+
+        let error = output.try_move_samples_from(&input, &[mapping], 2..4).unwrap_err();
+        assert!(error.message.starts_with("invalid shape: "));
+
+        let mapping = mts_sample_mapping_t { output: 0, input: 2 };
+        let error = output.try_move_samples_from(&input, &[mapping], 1..3).unwrap_err();
+        assert_eq!(error.message, "invalid sample mapping: 2 -> 0 is out of bounds for arrays with 2 and 1 samples");
+
+        let mapping = mts_sample_mapping_t { output: 1, input: 0 };
+        let error = output.try_move_samples_from(&input, &[mapping], 1..3).unwrap_err();
+        assert_eq!(error.message, "invalid sample mapping: 0 -> 1 is out of bounds for arrays with 2 and 1 samples");
+    }
+
+    #[cfg(feature = "faer")]
+    #[test]
+    fn faer() {
+        let values = [[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]];
+        let matrix = ::faer::Mat::from_fn(3, 2, |i, j| values[i][j]);
+        let array = Box::new(FaerArray::new(matrix)) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+
+        let mut other = unsafe { ArrayRefMut::new(array.as_raw().create(&[2, 3]).unwrap()) };
+        let mapping = mts_sample_mapping_t { output: 1, input: 2 };
+        other.as_raw_mut().move_samples_from(array.as_raw(), &[mapping], 1..3).unwrap();
+
+        let other = other.as_any().downcast_ref::<FaerArray>().unwrap();
+        let expected = ::faer::mat![[0.0, 0.0, 0.0], [0.0, 5.0, 6.0]];
+        assert_eq!(*other.matrix(), expected);
+
+        array.as_raw_mut().swap_axes(0, 1).unwrap();
+        assert_eq!(array.as_raw().shape().unwrap(), [2, 3]);
+        assert!(array.as_raw_mut().data().is_err());
+
+        let error = array.as_raw_mut().swap_axes(3, 1).unwrap_err();
+        assert_eq!(error.message, "invalid axes: can not swap axes 3 and 1 of a 2-dimensional matrix");
+    }
+
+    #[cfg(feature = "faer")]
+    #[test]
+    fn faer_move_samples_from() {
+        let input = FaerArray::new(::faer::mat![[1.0, 2.0], [3.0, 4.0]]);
+        let mut output = FaerArray::new(::faer::Mat::zeros(1, 3));
+
+        let mapping = mts_sample_mapping_t { output: 0, input: 1 };
+        output.try_move_samples_from(&input, &[mapping], 1..3).unwrap();
+        assert_eq!(*output.matrix(), ::faer::mat![[0.0, 3.0, 4.0]]);
+
+        let error = output.try_move_samples_from(&input, &[mapping], 0..3).unwrap_err();
+        assert_eq!(error.message, "invalid shape: can not move samples from an array of shape [2, 2] to properties 0..3 of an array of shape [1, 3]");
+
+        let error = output.try_move_samples_from(&input, &[mapping], 2..4).unwrap_err();
+        assert!(error.message.starts_with("invalid shape: "));
+
+        let mapping = mts_sample_mapping_t { output: 0, input: 2 };
+        let error = output.try_move_samples_from(&input, &[mapping], 1..3).unwrap_err();
+        assert_eq!(error.message, "invalid sample mapping: 2 -> 0 is out of bounds for arrays with 2 and 1 samples");
+
+        let mapping = mts_sample_mapping_t { output: 1, input: 0 };
+        let error = output.try_move_samples_from(&input, &[mapping], 1..3).unwrap_err();
+        assert_eq!(error.message, "invalid sample mapping: 0 -> 1 is out of bounds for arrays with 2 and 1 samples");
+    }
 }
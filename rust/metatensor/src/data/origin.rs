@@ -1,9 +1,9 @@
-use crate::c_api::mts_data_origin_t;
+use crate::c_api::{mts_data_origin_t, mts_array_operations_t, mts_array_converter_t};
 
 use crate::errors::{check_status, Error};
 
 /// Register a new data origin with the given name
-pub(super) fn register_data_origin(name: String) -> Result<mts_data_origin_t, Error> {
+pub(crate) fn register_data_origin(name: String) -> Result<mts_data_origin_t, Error> {
     let mut origin = 0 as mts_data_origin_t;
 
     let mut name = name.into_bytes();
@@ -19,6 +19,62 @@ pub(super) fn register_data_origin(name: String) -> Result<mts_data_origin_t, Er
     return Ok(origin);
 }
 
+/// Find the data origin with the given name, returning an error if no data
+/// origin with this name was registered
+pub(crate) fn find_data_origin(name: &str) -> Result<mts_data_origin_t, Error> {
+    // data origins are numbered sequentially starting at 1, and getting the
+    // name of an origin which is not registered gives "unregistered origin"
+    let mut origin = 1;
+    loop {
+        let current = get_data_origin(origin)?;
+        if current == name {
+            return Ok(origin);
+        } else if current == "unregistered origin" {
+            return Err(Error {
+                code: None,
+                message: format!("unknown data origin '{}'", name),
+            });
+        }
+        origin += 1;
+    }
+}
+
+/// Register the array `operations` for the given data origin
+pub(super) fn register_array_operations(
+    origin: mts_data_origin_t,
+    operations: &mts_array_operations_t,
+) -> Result<(), Error> {
+    unsafe {
+        check_status(crate::c_api::mts_register_array_operations(origin, operations))?;
+    }
+
+    return Ok(());
+}
+
+/// Register a `converter` from arrays with the data origin named `from` to
+/// arrays with the data origin named `to`, registering the data origins if
+/// needed.
+///
+/// The converter is used by [`TensorMap::to_origin`](crate::TensorMap::to_origin)
+/// and [`TensorBlockRef::to_origin`](crate::TensorBlockRef::to_origin), as well
+/// as by array operations taking two arrays with different origins. Arrays
+/// created through the [`Array`](crate::Array) trait use the
+/// `"rust.Box<dyn Array>"` origin.
+pub fn register_array_converter(
+    from: &str,
+    to: &str,
+    converter: mts_array_converter_t,
+) -> Result<(), Error> {
+    let from = register_data_origin(from.into())?;
+    let to = register_data_origin(to.into())?;
+
+    unsafe {
+        check_status(crate::c_api::mts_register_array_converter(from, to, converter))?;
+    }
+
+    return Ok(());
+}
+
 /// Get the name associated with a data origin
 pub(super) fn get_data_origin(origin: mts_data_origin_t) -> Result<String, Error> {
     use std::ffi::CStr;
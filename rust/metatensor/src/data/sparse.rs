@@ -0,0 +1,544 @@
+use std::ops::Range;
+
+use crate::c_api::mts_sample_mapping_t;
+use crate::Error;
+
+use super::Array;
+
+/// An implementation of the [`Array`] trait storing only the non-zero values,
+/// as a sparse matrix in compressed sparse row (CSR) format.
+///
+/// The rows of the matrix correspond to the first dimension of the array (the
+/// samples of a block), and the columns to all the other dimensions flattened
+/// together in row-major order (the components and properties of a block).
+///
+/// Since the data is not stored as a dense array, [`Array::data`] is not
+/// available for this array: it panics, and [`Array::try_data`] (used by
+/// `mts_array_t.data`) returns an error. Use [`SparseArray::to_dense`] to
+/// access the data as a dense array instead, or [`SparseArray::values`] to
+/// access the non-zero values. Blocks using this array are saved with a sparse
+/// layout by the functions in [`crate::io`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseArray {
+    shape: Vec<usize>,
+    values: Vec<f64>,
+    indices: Vec<usize>,
+    indptr: Vec<usize>,
+}
+
+impl SparseArray {
+    /// Create a new `SparseArray` with the given `shape`, from the CSR data in
+    /// `values`, `indices` and `indptr`.
+    ///
+    /// `values` contains the non-zero values, and `indices` the corresponding
+    /// columns. Values for row `i` are stored in `indptr[i]..indptr[i + 1]`,
+    /// and the columns in each row must be sorted.
+    pub fn new(
+        shape: Vec<usize>,
+        values: Vec<f64>,
+        indices: Vec<usize>,
+        indptr: Vec<usize>,
+    ) -> Result<SparseArray, Error> {
+        let (rows, columns) = matrix_shape(&shape)?;
+
+        if indices.len() != values.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "invalid sparse data: got {} values but {} indices",
+                    values.len(), indices.len()
+                ),
+            });
+        }
+
+        if indptr.len() != rows + 1 || indptr[0] != 0 || indptr[rows] != values.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "invalid sparse data: indptr must contain {} entries, starting at 0 and ending at {}",
+                    rows + 1, values.len()
+                ),
+            });
+        }
+
+        for range in indptr.windows(2) {
+            if range[0] > range[1] {
+                return Err(Error {
+                    code: None,
+                    message: "invalid sparse data: indptr must be sorted".into(),
+                });
+            }
+
+            let row = &indices[range[0]..range[1]];
+            if row.windows(2).any(|w| w[0] >= w[1]) || row.last().is_some_and(|&i| i >= columns) {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "invalid sparse data: column indexes must be sorted, \
+                        unique and smaller than {} in each row",
+                        columns
+                    ),
+                });
+            }
+        }
+
+        return Ok(SparseArray { shape, values, indices, indptr });
+    }
+
+    /// Create a new `SparseArray` with the given `shape`, without any
+    /// non-zero values.
+    ///
+    /// # Panics
+    ///
+    /// If the shape is empty.
+    pub fn zeros(shape: Vec<usize>) -> SparseArray {
+        let (rows, _) = matrix_shape(&shape).unwrap_or_else(|e| panic!("{}", e));
+        SparseArray {
+            shape,
+            values: Vec::new(),
+            indices: Vec::new(),
+            indptr: vec![0; rows + 1],
+        }
+    }
+
+    /// Create a new `SparseArray` containing the non-zero values of `array`.
+    ///
+    /// # Panics
+    ///
+    /// If `array` is 0-dimensional.
+    pub fn from_dense(array: &ndarray::ArrayD<f64>) -> SparseArray {
+        let shape = array.shape().to_vec();
+        let (rows, columns) = matrix_shape(&shape).unwrap_or_else(|e| panic!("{}", e));
+
+        let mut values = Vec::new();
+        let mut indices = Vec::new();
+        let mut indptr = Vec::with_capacity(rows + 1);
+        indptr.push(0);
+        if columns != 0 {
+            for (flat, &value) in array.iter().enumerate() {
+                if value != 0.0 {
+                    values.push(value);
+                    indices.push(flat % columns);
+                }
+
+                if flat % columns == columns - 1 {
+                    indptr.push(values.len());
+                }
+            }
+        } else {
+            indptr.resize(rows + 1, 0);
+        }
+
+        return SparseArray { shape, values, indices, indptr };
+    }
+
+    /// Get the data in this array as a dense `ndarray::ArrayD`
+    pub fn to_dense(&self) -> ndarray::ArrayD<f64> {
+        let mut array = ndarray::ArrayD::zeros(self.shape.clone());
+        let data = array.as_slice_mut().expect("new arrays are contiguous");
+        let columns = self.columns();
+        for (row, column, value) in self.entries() {
+            data[row * columns + column] = value;
+        }
+        return array;
+    }
+
+    /// Get the non-zero values in this array
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Get the column of each of the non-zero values in this array
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Get the start of each row in [`SparseArray::values`], with one more
+    /// entry than the number of rows
+    pub fn indptr(&self) -> &[usize] {
+        &self.indptr
+    }
+
+    /// Get the number of non-zero values stored in this array
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Get the number of columns in the CSR matrix
+    fn columns(&self) -> usize {
+        self.shape[1..].iter().product()
+    }
+
+    /// Iterate over the `(row, column, value)` of all the non-zero entries
+    fn entries(&self) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+        self.indptr.windows(2).enumerate().flat_map(move |(row, range)| {
+            let indices = &self.indices[range[0]..range[1]];
+            let values = &self.values[range[0]..range[1]];
+            indices.iter().zip(values).map(move |(&column, &value)| (row, column, value))
+        })
+    }
+
+    /// Create a new `SparseArray` with the given `shape` from a list of
+    /// `(flat index, value)` entries, where the flat index is the position of
+    /// the value in the dense row-major array.
+    fn from_flat_entries(shape: Vec<usize>, mut entries: Vec<(usize, f64)>) -> SparseArray {
+        entries.sort_unstable_by_key(|&(flat, _)| flat);
+
+        let (rows, columns) = matrix_shape(&shape).expect("shape should not be empty");
+        let mut values = Vec::with_capacity(entries.len());
+        let mut indices = Vec::with_capacity(entries.len());
+        let mut indptr = vec![0; rows + 1];
+        for (flat, value) in entries {
+            values.push(value);
+            indices.push(flat % columns);
+            indptr[flat / columns + 1] += 1;
+        }
+
+        for row in 0..rows {
+            indptr[row + 1] += indptr[row];
+        }
+
+        return SparseArray { shape, values, indices, indptr };
+    }
+
+    /// Copy the given `rows` of this array at the end of the CSR data in
+    /// `values`, `indices` and `indptr`
+    fn copy_rows(
+        &self,
+        rows: Range<usize>,
+        values: &mut Vec<f64>,
+        indices: &mut Vec<usize>,
+        indptr: &mut Vec<usize>,
+    ) {
+        let start = self.indptr[rows.start];
+        let end = self.indptr[rows.end];
+        let offset = values.len();
+
+        values.extend_from_slice(&self.values[start..end]);
+        indices.extend_from_slice(&self.indices[start..end]);
+        indptr.extend(self.indptr[rows.start + 1..=rows.end].iter().map(|&i| i - start + offset));
+    }
+
+    fn data_error() -> Error {
+        return Error {
+            code: None,
+            message: "can not access the data of a SparseArray as a dense array, use SparseArray::to_dense instead".into(),
+        };
+    }
+}
+
+/// Get the number of rows and columns of the CSR matrix for the given shape
+fn matrix_shape(shape: &[usize]) -> Result<(usize, usize), Error> {
+    if shape.is_empty() {
+        return Err(Error {
+            code: None,
+            message: "sparse arrays must have at least one dimension".into(),
+        });
+    }
+    return Ok((shape[0], shape[1..].iter().product()));
+}
+
+impl Array for SparseArray {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn create(&self, shape: &[usize]) -> Box<dyn Array> {
+        return Box::new(SparseArray::zeros(shape.to_vec()));
+    }
+
+    fn copy(&self) -> Box<dyn Array> {
+        return Box::new(self.clone());
+    }
+
+    fn data(&mut self) -> &mut [f64] {
+        return self.try_data().unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn shape(&self) -> &[usize] {
+        return &self.shape;
+    }
+
+    fn reshape(&mut self, shape: &[usize]) {
+        self.try_reshape(shape).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn swap_axes(&mut self, axis_1: usize, axis_2: usize) {
+        let mut strides = vec![1; self.shape.len()];
+        for i in (0..self.shape.len() - 1).rev() {
+            strides[i] = strides[i + 1] * self.shape[i + 1];
+        }
+
+        let mut new_shape = self.shape.clone();
+        new_shape.swap(axis_1, axis_2);
+        let mut new_strides = strides.clone();
+        for i in (0..new_shape.len() - 1).rev() {
+            new_strides[i] = new_strides[i + 1] * new_shape[i + 1];
+        }
+        new_strides.swap(axis_1, axis_2);
+
+        let columns = self.columns();
+        let entries = self.entries().map(|(row, column, value)| {
+            let mut flat = row * columns + column;
+            let mut new_flat = 0;
+            for (stride, new_stride) in strides.iter().zip(&new_strides) {
+                new_flat += (flat / stride) * new_stride;
+                flat %= stride;
+            }
+            (new_flat, value)
+        }).collect();
+
+        *self = SparseArray::from_flat_entries(new_shape, entries);
+    }
+
+    fn move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) {
+        self.try_move_samples_from(input, samples, properties).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn try_data(&mut self) -> Result<&mut [f64], Error> {
+        return Err(SparseArray::data_error());
+    }
+
+    fn try_reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
+        if shape.is_empty() || shape.iter().product::<usize>() != self.shape.iter().product::<usize>() {
+            return Err(Error {
+                code: None,
+                message: format!("invalid shape: can not reshape array of shape {:?} to {:?}", self.shape, shape),
+            });
+        }
+
+        let columns = self.columns();
+        let entries = self.entries().map(|(row, column, value)| (row * columns + column, value)).collect();
+        *self = SparseArray::from_flat_entries(shape.to_vec(), entries);
+
+        return Ok(());
+    }
+
+    fn try_move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) -> Result<(), Error> {
+        let input = input.as_any().downcast_ref::<SparseArray>().ok_or_else(|| Error {
+            code: None,
+            message: "input must be a SparseArray".into(),
+        })?;
+
+        if self.shape.len() < 2 || input.shape.len() != self.shape.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "invalid shape: can not move samples from an array of shape {:?} to an array of shape {:?}",
+                    input.shape, self.shape
+                ),
+            });
+        }
+
+        let last = self.shape.len() - 1;
+        let properties_count = self.shape[last];
+        if input.shape[1..last] != self.shape[1..last] || input.shape[last] != properties.len() || properties.end > properties_count {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "invalid shape: can not move samples from an array of shape {:?} to properties {:?} of an array of shape {:?}",
+                    input.shape, properties, self.shape
+                ),
+            });
+        }
+        let input_properties_count = properties.len();
+
+        for sample in samples {
+            if sample.input >= input.shape[0] || sample.output >= self.shape[0] {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "invalid sample mapping: {} -> {} is out of bounds for arrays with {} and {} samples",
+                        sample.input, sample.output, input.shape[0], self.shape[0]
+                    ),
+                });
+            }
+        }
+
+        // sort the samples by output row, keeping the order of samples with
+        // the same output. Only the rows listed in `samples` are rebuilt, the
+        // other rows are copied as a whole.
+        let mut samples = samples.to_vec();
+        samples.sort_by_key(|sample| sample.output);
+
+        let mut values = Vec::with_capacity(self.values.len());
+        let mut indices = Vec::with_capacity(self.indices.len());
+        let mut indptr = Vec::with_capacity(self.indptr.len());
+        indptr.push(0);
+
+        let mut next_row = 0;
+        let mut start = 0;
+        while start < samples.len() {
+            let output = samples[start].output;
+            let count = samples[start..].iter().take_while(|s| s.output == output).count();
+            let same_output = &samples[start..start + count];
+            start += count;
+
+            self.copy_rows(next_row..output, &mut values, &mut indices, &mut indptr);
+
+            let range = self.indptr[output]..self.indptr[output + 1];
+            let mut row = self.indices[range.clone()].iter().copied()
+                .zip(self.values[range].iter().copied())
+                .collect::<Vec<_>>();
+
+            for sample in same_output {
+                // remove the values which are going to be replaced
+                row.retain(|&(column, _)| !properties.contains(&(column % properties_count)));
+
+                let range = input.indptr[sample.input]..input.indptr[sample.input + 1];
+                for (&column, &value) in input.indices[range.clone()].iter().zip(&input.values[range]) {
+                    let component = column / input_properties_count;
+                    let property = column % input_properties_count;
+                    row.push((component * properties_count + properties.start + property, value));
+                }
+            }
+            row.sort_unstable_by_key(|&(column, _)| column);
+
+            for (column, value) in row {
+                indices.push(column);
+                values.push(value);
+            }
+            indptr.push(values.len());
+            next_row = output + 1;
+        }
+        self.copy_rows(next_row..self.shape[0], &mut values, &mut indices, &mut indptr);
+
+        self.values = values;
+        self.indices = indices;
+        self.indptr = indptr;
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::ArrayD;
+
+    use crate::c_api::mts_sample_mapping_t;
+    use crate::{Array, ArrayRefMut};
+
+    use super::SparseArray;
+
+    fn dense() -> ArrayD<f64> {
+        return ndarray::arr3(&[
+            [[0.0, 1.0, 0.0], [0.0, 0.0, 2.0]],
+            [[0.0, 0.0, 0.0], [0.0, 0.0, 0.0]],
+            [[3.0, 0.0, 0.0], [0.0, 4.0, 0.0]],
+        ]).into_dyn();
+    }
+
+    #[test]
+    fn dense_conversions() {
+        let array = SparseArray::from_dense(&dense());
+        assert_eq!(array.values(), [1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(array.indices(), [1, 5, 0, 4]);
+        assert_eq!(array.indptr(), [0, 2, 2, 4]);
+        assert_eq!(array.nnz(), 4);
+        assert_eq!(array.to_dense(), dense());
+
+        let other = SparseArray::new(vec![3, 2, 3], vec![1.0, 2.0, 3.0, 4.0], vec![1, 5, 0, 4], vec![0, 2, 2, 4]).unwrap();
+        assert_eq!(other, array);
+
+        let error = SparseArray::new(vec![3, 2, 3], vec![1.0], vec![6], vec![0, 1, 1, 1]).unwrap_err();
+        assert_eq!(error.message, "invalid sparse data: column indexes must be sorted, unique and smaller than 6 in each row");
+
+        let error = SparseArray::new(vec![3, 2, 3], vec![1.0], vec![0], vec![0, 1, 1]).unwrap_err();
+        assert_eq!(error.message, "invalid sparse data: indptr must contain 4 entries, starting at 0 and ending at 1");
+
+        let mut array = SparseArray::zeros(vec![3, 4]);
+        assert_eq!(array.to_dense(), ArrayD::zeros(vec![3, 4]));
+
+        let error = array.try_data().unwrap_err();
+        assert_eq!(error.message, "can not access the data of a SparseArray as a dense array, use SparseArray::to_dense instead");
+
+        // the error is also returned through `mts_array_t.data`
+        let array = Box::new(array) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+        let error = array.as_raw_mut().data().unwrap_err();
+        assert_eq!(error.message, "can not access the data of a SparseArray as a dense array, use SparseArray::to_dense instead");
+    }
+
+    #[test]
+    fn reshape_and_swap_axes() {
+        let mut array = SparseArray::from_dense(&dense());
+
+        array.try_reshape(&[6, 3]).unwrap();
+        assert_eq!(array.to_dense(), dense().into_shape_with_order(vec![6, 3]).unwrap());
+
+        array.try_reshape(&[3, 2, 3]).unwrap();
+        assert_eq!(array.to_dense(), dense());
+
+        let error = array.try_reshape(&[3, 2]).unwrap_err();
+        assert_eq!(error.message, "invalid shape: can not reshape array of shape [3, 2, 3] to [3, 2]");
+
+        Array::swap_axes(&mut array, 1, 2);
+        assert_eq!(array.shape, [3, 3, 2]);
+        let mut expected = dense();
+        expected.swap_axes(1, 2);
+        assert_eq!(array.to_dense(), expected);
+
+        Array::swap_axes(&mut array, 0, 2);
+        expected.swap_axes(0, 2);
+        assert_eq!(array.to_dense(), expected);
+    }
+
+    #[test]
+    fn move_samples_from() {
+        let input = SparseArray::from_dense(&dense());
+
+        let mut output = SparseArray::from_dense(&ArrayD::from_elem(vec![2, 2, 4], 7.0));
+        let samples = [
+            mts_sample_mapping_t { input: 2, output: 0 },
+            mts_sample_mapping_t { input: 1, output: 1 },
+        ];
+        output.try_move_samples_from(&input, &samples, 1..4).unwrap();
+
+        let expected = ndarray::arr3(&[
+            [[7.0, 3.0, 0.0, 0.0], [7.0, 0.0, 4.0, 0.0]],
+            [[7.0, 0.0, 0.0, 0.0], [7.0, 0.0, 0.0, 0.0]],
+        ]).into_dyn();
+        assert_eq!(output.to_dense(), expected);
+
+        // samples in any order, leaving some rows untouched
+        let mut other = SparseArray::from_dense(&ArrayD::from_elem(vec![4, 2, 4], 7.0));
+        let other_samples = [
+            mts_sample_mapping_t { input: 0, output: 3 },
+            mts_sample_mapping_t { input: 2, output: 1 },
+        ];
+        other.try_move_samples_from(&input, &other_samples, 1..4).unwrap();
+
+        let mut expected = ArrayD::from_elem(vec![4, 2, 4], 7.0);
+        expected.slice_mut(ndarray::s![3, .., 1..4]).assign(&dense().slice(ndarray::s![0, .., ..]));
+        expected.slice_mut(ndarray::s![1, .., 1..4]).assign(&dense().slice(ndarray::s![2, .., ..]));
+        assert_eq!(other.to_dense(), expected);
+
+        let created = input.create(&[2, 2, 3]);
+        let created = created.as_any().downcast_ref::<SparseArray>().unwrap();
+        assert_eq!(created.nnz(), 0);
+
+        let error = output.try_move_samples_from(&dense(), &samples, 1..4).unwrap_err();
+        assert_eq!(error.message, "input must be a SparseArray");
+
+        let error = output.try_move_samples_from(&input, &samples, 0..4).unwrap_err();
+        assert!(error.message.starts_with("invalid shape: "));
+
+        let samples = [mts_sample_mapping_t { input: 3, output: 0 }];
+        let error = output.try_move_samples_from(&input, &samples, 1..4).unwrap_err();
+        assert_eq!(error.message, "invalid sample mapping: 3 -> 0 is out of bounds for arrays with 3 and 2 samples");
+    }
+}
@@ -0,0 +1,182 @@
+use std::ops::Range;
+
+use crate::c_api::mts_sample_mapping_t;
+use crate::Error;
+
+use super::Array;
+
+/// A simple implementation of the [`Array`] trait, storing data in a `Vec<f64>`
+/// in row-major (C) order, together with the corresponding shape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VecArray {
+    data: Vec<f64>,
+    shape: Vec<usize>,
+}
+
+impl VecArray {
+    /// Create a new `VecArray` with the given `data` and `shape`.
+    ///
+    /// # Panics
+    ///
+    /// If the size of `data` does not match the `shape`.
+    pub fn new(data: Vec<f64>, shape: Vec<usize>) -> VecArray {
+        assert_eq!(
+            data.len(), shape.iter().product::<usize>(),
+            "the data size does not match the shape {:?}", shape
+        );
+        VecArray { data, shape }
+    }
+
+    /// Create a new `VecArray` with the given `shape`, filled with zeros.
+    pub fn zeros(shape: Vec<usize>) -> VecArray {
+        VecArray {
+            data: vec![0.0; shape.iter().product()],
+            shape,
+        }
+    }
+
+    /// Get the data in this array as a slice
+    pub fn as_slice(&self) -> &[f64] {
+        &self.data
+    }
+
+    /// Get the data in this array as a mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [f64] {
+        &mut self.data
+    }
+
+    /// Get the shape of this array
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Extract the data and shape of this array
+    pub fn into_parts(self) -> (Vec<f64>, Vec<usize>) {
+        (self.data, self.shape)
+    }
+}
+
+impl Array for VecArray {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn create(&self, shape: &[usize]) -> Box<dyn Array> {
+        Box::new(VecArray::zeros(shape.to_vec()))
+    }
+
+    fn copy(&self) -> Box<dyn Array> {
+        Box::new(self.clone())
+    }
+
+    fn data(&mut self) -> &mut [f64] {
+        &mut self.data
+    }
+
+    fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    fn reshape(&mut self, shape: &[usize]) {
+        self.try_reshape(shape).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn swap_axes(&mut self, axis_1: usize, axis_2: usize) {
+        let mut array = ndarray::ArrayViewD::from_shape(&*self.shape, &self.data)
+            .expect("the data size does not match the shape");
+        array.swap_axes(axis_1, axis_2);
+
+        // iterating over the view gives the data in row-major order for the
+        // new shape
+        self.data = array.iter().copied().collect();
+        self.shape.swap(axis_1, axis_2);
+    }
+
+    fn move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) {
+        self.try_move_samples_from(input, samples, properties).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    fn try_reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
+        if shape.iter().product::<usize>() != self.data.len() {
+            return Err(Error {
+                code: None,
+                message: format!("invalid shape: can not reshape array of shape {:?} to {:?}", self.shape, shape),
+            });
+        }
+
+        self.shape = shape.to_vec();
+        return Ok(());
+    }
+
+    fn try_move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) -> Result<(), Error> {
+        let input = input.as_any().downcast_ref::<VecArray>().ok_or_else(|| Error {
+            code: None,
+            message: "input must be a VecArray".into(),
+        })?;
+
+        if self.shape.len() < 2 || input.shape.len() != self.shape.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "invalid shape: can not move samples from an array of shape {:?} to an array of shape {:?}",
+                    input.shape, self.shape
+                ),
+            });
+        }
+
+        let last = self.shape.len() - 1;
+        let n_properties_input = properties.len();
+        let n_properties_output = self.shape[last];
+        if input.shape[1..last] != self.shape[1..last] || input.shape[last] != n_properties_input || properties.end > n_properties_output {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "invalid shape: can not move samples from an array of shape {:?} to properties {:?} of an array of shape {:?}",
+                    input.shape, properties, self.shape
+                ),
+            });
+        }
+
+        for sample in samples {
+            if sample.input >= input.shape[0] || sample.output >= self.shape[0] {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "invalid sample mapping: {} -> {} is out of bounds for arrays with {} and {} samples",
+                        sample.input, sample.output, input.shape[0], self.shape[0]
+                    ),
+                });
+            }
+        }
+
+        // number of entries in the components dimensions
+        let n_components = self.shape[1..last].iter().product::<usize>();
+
+        for sample in samples {
+            for component in 0..n_components {
+                let input_start = (sample.input * n_components + component) * n_properties_input;
+                let output_start = (sample.output * n_components + component) * n_properties_output + properties.start;
+
+                self.data[output_start..output_start + n_properties_input].copy_from_slice(
+                    &input.data[input_start..input_start + n_properties_input]
+                );
+            }
+        }
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,499 @@
+//! Sharing data with other libraries through
+//! [DLPack](https://dmlc.github.io/dlpack/latest/), without copies.
+//!
+//! [`TensorBlock::into_dlpack`] exports the values of a block as a
+//! `DLManagedTensor`, and [`DLPackArray`] wraps a `DLManagedTensor` created by
+//! another library in an [`Array`] which can be used to create blocks.
+
+use std::ops::Range;
+use std::os::raw::c_void;
+
+use crate::c_api::{mts_sample_mapping_t, mts_array_descriptor_t, mts_index_mapping_t};
+use crate::data::BorrowedArray;
+use crate::{Array, TensorBlock, Error};
+
+/// `DLDeviceType` value for CPU memory
+pub const DL_CPU: i32 = 1;
+/// `DLDataTypeCode` value for floating point data
+pub const DL_FLOAT: u8 = 2;
+
+/// Device where the data of a [`DLTensor`] lives, following `dlpack.h`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DLDevice {
+    /// Type of the device, [`DL_CPU`] for CPU memory
+    pub device_type: i32,
+    /// Index of the device
+    pub device_id: i32,
+}
+
+/// Type of the data in a [`DLTensor`], following `dlpack.h`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DLDataType {
+    /// Kind of data, [`DL_FLOAT`] for floating point data
+    pub code: u8,
+    /// Number of bits in each lane
+    pub bits: u8,
+    /// Number of lanes, 1 for scalar data
+    pub lanes: u16,
+}
+
+/// Plain tensor object, following `dlpack.h`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DLTensor {
+    /// Pointer to the start of the data
+    pub data: *mut c_void,
+    /// Device where the data lives
+    pub device: DLDevice,
+    /// Number of dimensions
+    pub ndim: i32,
+    /// Type of the data
+    pub dtype: DLDataType,
+    /// Shape of the tensor, with `ndim` entries
+    pub shape: *mut i64,
+    /// Strides of the tensor in number of elements, with `ndim` entries. This
+    /// can be NULL for C-contiguous data.
+    pub strides: *mut i64,
+    /// Offset in bytes from `data` to the first element
+    pub byte_offset: u64,
+}
+
+/// Tensor with memory management, following `dlpack.h`
+#[repr(C)]
+#[derive(Debug)]
+pub struct DLManagedTensor {
+    /// The tensor itself
+    pub dl_tensor: DLTensor,
+    /// Context used by the producer of this tensor to manage its memory
+    pub manager_ctx: *mut c_void,
+    /// Function to call to free this tensor, once the consumer no longer needs
+    /// it
+    pub deleter: Option<unsafe extern fn(*mut DLManagedTensor)>,
+}
+
+/// Implementation of the [`Array`] trait for data imported from a `DLPack`
+/// `DLManagedTensor`, without copying it.
+///
+/// Only C-contiguous 64-bit or 32-bit floating point data on CPU is
+/// supported. The tensor is released by calling its `deleter` when this array
+/// is dropped. Arrays created with `Array::create` or `Array::copy` from this
+/// one are owned `ndarray::ArrayD<f64>` or `ndarray::ArrayD<f32>`, depending
+/// on the data type of the tensor. As for `ndarray::ArrayD<f32>`,
+/// [`Array::data`] is not available for 32-bit data.
+pub struct DLPackArray {
+    tensor: *mut DLManagedTensor,
+    array: DLPackData,
+}
+
+/// Data of a [`DLPackArray`], borrowed from the `DLManagedTensor`
+enum DLPackData {
+    F64(BorrowedArray<f64>),
+    F32(BorrowedArray<f32>),
+}
+
+// SAFETY: the data is only accessed through this struct, and the tensor is
+// owned by it
+unsafe impl Send for DLPackArray {}
+// SAFETY: the data is only accessed through this struct, and the tensor is
+// owned by it
+unsafe impl Sync for DLPackArray {}
+
+impl std::fmt::Debug for DLPackArray {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DLPackArray")
+            .field("tensor", &self.tensor)
+            .field("shape", &self.inner().shape())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for DLPackArray {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(deleter) = (*self.tensor).deleter {
+                deleter(self.tensor);
+            }
+        }
+    }
+}
+
+impl DLPackArray {
+    /// Create a new `DLPackArray` taking ownership of the given `DLPack`
+    /// `tensor`.
+    ///
+    /// If this function returns an error, the ownership of the tensor stays
+    /// with the caller, who is responsible for calling its `deleter`.
+    ///
+    /// # Safety
+    ///
+    /// `tensor` must point to a valid `DLManagedTensor`, which is not used
+    /// anywhere else after a successful call to this function.
+    pub unsafe fn from_raw(tensor: *mut DLManagedTensor) -> Result<DLPackArray, Error> {
+        if tensor.is_null() {
+            return Err(Error {
+                code: None,
+                message: "got a NULL pointer for the DLPack tensor".into(),
+            });
+        }
+
+        let dl_tensor = &(*tensor).dl_tensor;
+        if dl_tensor.device.device_type != DL_CPU {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "only DLPack tensors on CPU are supported, got device type {}",
+                    dl_tensor.device.device_type
+                ),
+            });
+        }
+
+        let dtype = dl_tensor.dtype;
+        if dtype.code != DL_FLOAT || !(dtype.bits == 64 || dtype.bits == 32) || dtype.lanes != 1 {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "only DLPack tensors containing 64-bit or 32-bit floats are supported, \
+                    got dtype code {} with {} bits and {} lanes",
+                    dtype.code, dtype.bits, dtype.lanes
+                ),
+            });
+        }
+
+        let ndim = usize::try_from(dl_tensor.ndim).map_err(|_| Error {
+            code: None,
+            message: format!("invalid number of dimensions in DLPack tensor: {}", dl_tensor.ndim),
+        })?;
+
+        let mut shape = Vec::with_capacity(ndim);
+        if ndim != 0 {
+            for &size in std::slice::from_raw_parts(dl_tensor.shape, ndim) {
+                shape.push(usize::try_from(size).map_err(|_| Error {
+                    code: None,
+                    message: format!("invalid shape in DLPack tensor: {}", size),
+                })?);
+            }
+        }
+
+        if !dl_tensor.strides.is_null() && ndim != 0 {
+            let strides = std::slice::from_raw_parts(dl_tensor.strides, ndim);
+            let mut expected = 1;
+            for (&size, &stride) in shape.iter().zip(strides).rev() {
+                // strides for dimensions of size 1 do not matter
+                if size != 1 && usize::try_from(stride) != Ok(expected) {
+                    return Err(Error {
+                        code: None,
+                        message: "only C-contiguous DLPack tensors are supported".into(),
+                    });
+                }
+                expected *= size;
+            }
+        }
+
+        let byte_offset = usize::try_from(dl_tensor.byte_offset).map_err(|_| Error {
+            code: None,
+            message: format!("invalid byte offset in DLPack tensor: {}", dl_tensor.byte_offset),
+        })?;
+
+        let ptr = dl_tensor.data.cast::<u8>().wrapping_add(byte_offset);
+        let len = shape.iter().product::<usize>();
+
+        // SAFETY: the data is kept alive until the tensor is deleted, in
+        // `DLPackArray::drop`
+        let array = if dtype.bits == 64 {
+            DLPackData::F64(BorrowedArray::new(borrow_data(ptr, len)?, &shape)?)
+        } else {
+            DLPackData::F32(BorrowedArray::new(borrow_data(ptr, len)?, &shape)?)
+        };
+
+        return Ok(DLPackArray { tensor, array });
+    }
+
+    /// Get a `ndarray` view inside this array, if it contains 64-bit floats
+    pub(crate) fn view_f64(&self) -> Option<ndarray::ArrayViewD<'_, f64>> {
+        match &self.array {
+            DLPackData::F64(array) => Some(array.view()),
+            DLPackData::F32(_) => None,
+        }
+    }
+
+    /// Get a `ndarray` view inside this array, if it contains 32-bit floats
+    pub(crate) fn view_f32(&self) -> Option<ndarray::ArrayViewD<'_, f32>> {
+        match &self.array {
+            DLPackData::F64(_) => None,
+            DLPackData::F32(array) => Some(array.view()),
+        }
+    }
+
+    fn inner(&self) -> &dyn Array {
+        match &self.array {
+            DLPackData::F64(array) => array,
+            DLPackData::F32(array) => array,
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut dyn Array {
+        match &mut self.array {
+            DLPackData::F64(array) => array,
+            DLPackData::F32(array) => array,
+        }
+    }
+}
+
+/// Get a slice with `len` elements of type `T` starting at `ptr`, checking
+/// the alignment of the pointer.
+///
+/// # Safety
+///
+/// `ptr` must point to `len` valid elements of type `T`, which stay alive and
+/// are not accessed elsewhere for the lifetime `'a`.
+unsafe fn borrow_data<'a, T>(ptr: *mut u8, len: usize) -> Result<&'a mut [T], Error> {
+    if len == 0 {
+        return Ok(&mut []);
+    }
+
+    if ptr.align_offset(std::mem::align_of::<T>()) != 0 {
+        return Err(Error {
+            code: None,
+            message: format!(
+                "the data of the DLPack tensor is not aligned for {}",
+                std::any::type_name::<T>()
+            ),
+        });
+    }
+
+    #[allow(clippy::cast_ptr_alignment)]
+    return Ok(std::slice::from_raw_parts_mut(ptr.cast::<T>(), len));
+}
+
+impl Array for DLPackArray {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn create(&self, shape: &[usize]) -> Box<dyn Array> {
+        return self.inner().create(shape);
+    }
+
+    fn copy(&self) -> Box<dyn Array> {
+        return self.inner().copy();
+    }
+
+    fn data(&mut self) -> &mut [f64] {
+        return self.inner_mut().data();
+    }
+
+    fn shape(&self) -> &[usize] {
+        return self.inner().shape();
+    }
+
+    fn reshape(&mut self, shape: &[usize]) {
+        self.inner_mut().reshape(shape);
+    }
+
+    fn swap_axes(&mut self, axis_1: usize, axis_2: usize) {
+        self.inner_mut().swap_axes(axis_1, axis_2);
+    }
+
+    fn move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) {
+        self.inner_mut().move_samples_from(input, samples, properties);
+    }
+
+    fn try_data(&mut self) -> Result<&mut [f64], Error> {
+        return self.inner_mut().try_data();
+    }
+
+    fn try_reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
+        return self.inner_mut().try_reshape(shape);
+    }
+
+    fn try_move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        properties: Range<usize>,
+    ) -> Result<(), Error> {
+        return self.inner_mut().try_move_samples_from(input, samples, properties);
+    }
+
+    fn descriptor(&self) -> mts_array_descriptor_t {
+        return self.inner().descriptor();
+    }
+
+    fn raw_data(&mut self) -> Result<*mut c_void, Error> {
+        return self.inner_mut().raw_data();
+    }
+
+    fn move_along_axis(
+        &mut self,
+        input: &dyn Array,
+        axis: usize,
+        mapping: &[mts_index_mapping_t],
+        output_offsets: &[usize],
+    ) -> Result<(), Error> {
+        return self.inner_mut().move_along_axis(input, axis, mapping, output_offsets);
+    }
+}
+
+/// Memory owned by a `DLManagedTensor` created by [`TensorBlock::into_dlpack`]
+struct BlockContext {
+    /// the block owning the data
+    block: TensorBlock,
+    /// the tensor created by metatensor-core, borrowing data from the block
+    tensor: *mut DLManagedTensor,
+}
+
+/// Implementation of `DLManagedTensor.deleter` for tensors created by
+/// [`TensorBlock::into_dlpack`]
+unsafe extern fn delete_block_tensor(tensor: *mut DLManagedTensor) {
+    if tensor.is_null() {
+        return;
+    }
+
+    let tensor = Box::from_raw(tensor);
+    let context = Box::from_raw(tensor.manager_ctx.cast::<BlockContext>());
+    if let Some(deleter) = (*context.tensor).deleter {
+        deleter(context.tensor);
+    }
+    std::mem::drop(context.block);
+}
+
+impl TensorBlock {
+    /// Export the values of this block as a `DLPack` `DLManagedTensor`, without
+    /// copying them.
+    ///
+    /// The block is kept alive until the `deleter` of the tensor is called,
+    /// which is the responsibility of the consumer of the tensor. This
+    /// requires the values array to support `DLPack` export (see
+    /// `mts_array_to_dlpack` in metatensor-core), which is the case for
+    /// arrays implementing the [`Array`] trait.
+    pub fn into_dlpack(self) -> Result<*mut DLManagedTensor, Error> {
+        let tensor = self.values().as_raw().to_dlpack()?.cast::<DLManagedTensor>();
+        let dl_tensor = unsafe { (*tensor).dl_tensor };
+
+        let context = Box::new(BlockContext {
+            block: self,
+            tensor,
+        });
+
+        let exported = Box::new(DLManagedTensor {
+            dl_tensor,
+            manager_ctx: Box::into_raw(context).cast(),
+            deleter: Some(delete_block_tensor),
+        });
+
+        return Ok(Box::into_raw(exported));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::ArrayD;
+
+    use crate::{Labels, TensorBlock};
+    use super::*;
+
+    fn block(values: impl Array) -> TensorBlock {
+        return TensorBlock::new(
+            values,
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1], [2]]),
+        ).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let values = ArrayD::from_shape_vec(vec![2, 3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        let block = block(values);
+        let ptr = block.values().as_array().as_ptr();
+
+        let tensor = block.into_dlpack().unwrap();
+        unsafe {
+            let dl_tensor = &(*tensor).dl_tensor;
+            assert_eq!(dl_tensor.data.cast_const().cast::<f64>(), ptr);
+            assert_eq!(dl_tensor.ndim, 2);
+            assert_eq!(std::slice::from_raw_parts(dl_tensor.shape, 2), [2, 3]);
+            assert_eq!(dl_tensor.device, DLDevice { device_type: DL_CPU, device_id: 0 });
+            assert_eq!(dl_tensor.dtype, DLDataType { code: DL_FLOAT, bits: 64, lanes: 1 });
+        }
+
+        let array = unsafe { DLPackArray::from_raw(tensor).unwrap() };
+        assert_eq!(array.shape(), [2, 3]);
+
+        let mut block = self::block(array);
+        let mut block = block.as_ref_mut();
+        let array = block.values_mut().to_any_mut().downcast_mut::<DLPackArray>().unwrap();
+        assert_eq!(array.view_f64().unwrap().as_ptr(), ptr);
+        assert_eq!(array.view_f64().unwrap().as_slice().unwrap(), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        array.data()[0] = 42.0;
+        assert_eq!(array.view_f64().unwrap().as_slice().unwrap(), [42.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn round_trip_f32() {
+        let values = ArrayD::from_shape_vec(vec![2, 3], vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        let block = block(values);
+        let ptr = block.values().to_any().downcast_ref::<ArrayD<f32>>().unwrap().as_ptr();
+
+        let tensor = block.into_dlpack().unwrap();
+        unsafe {
+            assert_eq!((*tensor).dl_tensor.data.cast_const().cast::<f32>(), ptr);
+            assert_eq!((*tensor).dl_tensor.dtype, DLDataType { code: DL_FLOAT, bits: 32, lanes: 1 });
+        }
+
+        let array = unsafe { DLPackArray::from_raw(tensor).unwrap() };
+        assert_eq!(array.shape(), [2, 3]);
+        assert_eq!(array.descriptor(), mts_array_descriptor_t::cpu_float(32));
+
+        let mut block = self::block(array);
+        let mut block = block.as_ref_mut();
+        let array = block.values_mut().to_any_mut().downcast_mut::<DLPackArray>().unwrap();
+        assert!(array.view_f64().is_none());
+        assert_eq!(array.view_f32().unwrap().as_ptr(), ptr);
+        assert_eq!(array.view_f32().unwrap().as_slice().unwrap(), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let error = array.try_data().unwrap_err();
+        assert_eq!(error.message, "can not access the data of an array of 32-bit floats as 64-bit floats");
+
+        // arrays created from this one use 32-bit floats
+        let mut output = array.create(&[1, 3]);
+        let mapping = mts_sample_mapping_t { output: 0, input: 1 };
+        output.try_move_samples_from(array, &[mapping], 0..3).unwrap();
+        let output = output.as_any().downcast_ref::<ArrayD<f32>>().unwrap();
+        assert_eq!(output.as_slice().unwrap(), [4.0, 5.0, 6.0]);
+
+        // and the data can be exported again
+        let tensor = block.values().as_raw().to_dlpack().unwrap().cast::<DLManagedTensor>();
+        unsafe {
+            assert_eq!((*tensor).dl_tensor.data.cast_const().cast::<f32>(), ptr);
+            ((*tensor).deleter.unwrap())(tensor);
+        }
+    }
+
+    #[test]
+    fn unsupported_dtype() {
+        let values = ArrayD::from_shape_vec(vec![2, 3], vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        let tensor = block(values).into_dlpack().unwrap();
+
+        unsafe {
+            (*tensor).dl_tensor.dtype.bits = 16;
+            let error = DLPackArray::from_raw(tensor).unwrap_err();
+            assert_eq!(error.message, "only DLPack tensors containing 64-bit or 32-bit floats are supported, got dtype code 2 with 16 bits and 1 lanes");
+
+            // the tensor is still owned by us after an error
+            ((*tensor).deleter.unwrap())(tensor);
+        }
+    }
+}
@@ -1,16 +1,12 @@
 use std::ffi::CStr;
 use std::ptr::NonNull;
-use std::cell::RefCell;
 
 use crate::c_api::{mts_status_t, MTS_SUCCESS, mts_last_error};
+use crate::c_api::RUST_FUNCTION_FAILED_ERROR_CODE;
 
-/// Error code used to indicate failure of a Rust function
-const RUST_FUNCTION_FAILED_ERROR_CODE: i32 = -4242;
-
-thread_local! {
-    /// Storage for the last error coming from a Rust function
-    pub static LAST_RUST_ERROR: RefCell<Error> = RefCell::new(Error {code: None, message: String::new()});
-}
+/// Storage for the last error coming from a Rust function. This lives in
+/// `metatensor-sys`, so it can be used when calling `mts_array_t` functions.
+pub use metatensor_sys::LAST_RUST_ERROR;
 
 pub use metatensor_sys::Error;
 
@@ -64,3 +60,27 @@ pub(crate) fn catch_unwind<F>(function: F) -> mts_status_t where F: FnOnce() + s
         }
     }
 }
+
+/// Same as [`catch_unwind`], but also store the error returned by `function`
+/// in `LAST_RUST_ERROR`, so that it can be extracted by `check_status`.
+pub(crate) fn catch_unwind_result<F>(function: F) -> mts_status_t
+    where F: FnOnce() -> Result<(), Error> + std::panic::UnwindSafe
+{
+    match std::panic::catch_unwind(function) {
+        Ok(Ok(())) => MTS_SUCCESS,
+        Ok(Err(e)) => {
+            LAST_RUST_ERROR.with(|last_error| {
+                *last_error.borrow_mut() = e;
+            });
+
+            RUST_FUNCTION_FAILED_ERROR_CODE
+        }
+        Err(e) => {
+            LAST_RUST_ERROR.with(|last_error| {
+                *last_error.borrow_mut() = e.into();
+            });
+
+            RUST_FUNCTION_FAILED_ERROR_CODE
+        }
+    }
+}
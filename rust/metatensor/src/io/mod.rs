@@ -8,6 +8,7 @@ use crate::Array;
 
 mod tensor;
 pub use self::tensor::{load, save, load_buffer, save_buffer};
+pub use self::tensor::{save_shared_gradient_samples, save_buffer_shared_gradient_samples};
 
 mod block;
 pub use self::block::{load_block, load_block_buffer, save_block, save_block_buffer};
@@ -108,3 +108,41 @@ pub fn save_buffer(tensor: &TensorMap, buffer: &mut Vec<u8>) -> Result<(), Error
 
     Ok(())
 }
+
+/// Save the given tensor to a file, storing gradient samples shared between
+/// multiple gradients (see [`TensorMap::share_gradient_samples`]) only once.
+///
+/// If the file already exists, it is overwritten. Files saved with this
+/// function can only be loaded by metatensor-core 0.2.0 or later, use [`save`]
+/// to write gradient samples separately for each gradient.
+pub fn save_shared_gradient_samples(path: impl AsRef<std::path::Path>, tensor: &TensorMap) -> Result<(), Error> {
+    let path = path.as_ref().as_os_str().to_str().expect("this path is not valid UTF8");
+    let path = CString::new(path).expect("this path contains a NULL byte");
+
+    unsafe {
+        check_status(crate::c_api::mts_tensormap_save_shared_gradient_samples(path.as_ptr(), tensor.ptr))
+    }
+}
+
+/// Save the given `tensor` to an in-memory `buffer`, storing gradient samples
+/// shared between multiple gradients only once.
+///
+/// See [`save_shared_gradient_samples`] for more information.
+pub fn save_buffer_shared_gradient_samples(tensor: &TensorMap, buffer: &mut Vec<u8>) -> Result<(), Error> {
+    let mut buffer_ptr = buffer.as_mut_ptr();
+    let mut buffer_count = buffer.len();
+
+    unsafe {
+        check_status(crate::c_api::mts_tensormap_save_buffer_shared_gradient_samples(
+            &mut buffer_ptr,
+            &mut buffer_count,
+            (buffer as *mut Vec<u8>).cast(),
+            Some(realloc_vec),
+            tensor.ptr,
+        ))?;
+    }
+
+    buffer.resize(buffer_count, 0);
+
+    Ok(())
+}
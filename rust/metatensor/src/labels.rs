@@ -1,11 +1,13 @@
 use std:: ffi::CStr;
 use std::ffi::CString;
+use std::borrow::Cow;
 use std::collections::BTreeSet;
 use std::iter::FusedIterator;
+use std::sync::OnceLock;
 
 use smallvec::SmallVec;
 
-use crate::c_api::mts_labels_t;
+use crate::c_api::{mts_labels_t, MTS_LABELS_INT32, MTS_LABELS_INT64};
 use crate::errors::{Error, check_status};
 
 /// A single value inside a label.
@@ -76,6 +78,27 @@ impl From<isize> for LabelValue {
     }
 }
 
+impl From<LabelValue> for i32 {
+    #[inline]
+    fn from(value: LabelValue) -> i32 {
+        value.0
+    }
+}
+
+impl From<LabelValue> for i64 {
+    #[inline]
+    fn from(value: LabelValue) -> i64 {
+        i64::from(value.0)
+    }
+}
+
+impl From<LabelValue> for isize {
+    #[inline]
+    fn from(value: LabelValue) -> isize {
+        value.0 as isize
+    }
+}
+
 impl LabelValue {
     /// Create a `LabelValue` with the given `value`
     #[inline]
@@ -104,6 +127,15 @@ impl LabelValue {
     }
 }
 
+/// Integer type used to store the values of a set of [`Labels`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LabelsDType {
+    /// 32-bit signed integers, this is the default
+    Int32,
+    /// 64-bit signed integers
+    Int64,
+}
+
 /// A set of labels used to carry metadata associated with a tensor map.
 ///
 /// This is similar to a list of named tuples, but stored as a 2D array of shape
@@ -113,10 +145,23 @@ impl LabelValue {
 ///
 /// The main way to construct a new set of labels is to use a `LabelsBuilder`.
 ///
+/// The values of the labels are stored as 32-bit integers by default, and all
+/// the functions using [`LabelValue`] work with these. Labels can also store
+/// 64-bit integers (see [`LabelsBuilder::new_i64`] and [`Labels::new_i64`]),
+/// in which case the values should be accessed with the `*_i64` functions.
+/// The functions using [`LabelValue`] also work with 64-bit labels as long as
+/// all the values fit in 32-bit integers, using a copy of the values created
+/// on first use.
+///
 /// Labels are internally reference counted and immutable, so cloning a `Labels`
 /// should be a cheap operation.
 pub struct Labels {
     pub(crate) raw: mts_labels_t,
+    /// values of 64-bit labels converted to 32-bit, or `None` if some values
+    /// do not fit in 32-bit integers
+    values_i32: OnceLock<Option<Vec<LabelValue>>>,
+    /// values of 32-bit labels converted to 64-bit
+    values_i64: OnceLock<Vec<i64>>,
 }
 
 // Labels can be sent to other thread safely since mts_labels_t uses an
@@ -144,10 +189,10 @@ pub(crate) fn pretty_print_labels(
     writeln!(f, "{}    {}", offset, names.join(", "))?;
 
     let widths = names.iter().map(|s| s.len()).collect::<Vec<_>>();
-    for values in labels {
+    for values in labels.iter_i64() {
         write!(f, "{}    ", offset)?;
         for (value, width) in values.iter().zip(&widths) {
-            write!(f, "{:^width$}  ", value.isize(), width=width)?;
+            write!(f, "{:^width$}  ", value, width=width)?;
         }
         writeln!(f)?;
     }
@@ -197,6 +242,23 @@ impl Labels {
         return builder.finish();
     }
 
+    /// Create a new set of Labels with the given names and 64-bit values.
+    ///
+    /// This is the same as [`Labels::new`], but the values of the labels are
+    /// stored as 64-bit integers.
+    ///
+    /// # Panics
+    ///
+    /// If the set of names is not valid, or any of the value is duplicated
+    #[inline]
+    pub fn new_i64<const N: usize>(names: [&str; N], values: &[[i64; N]]) -> Labels {
+        let mut builder = LabelsBuilder::new_i64(names.to_vec());
+        for entry in values {
+            builder.add_i64(entry);
+        }
+        return builder.finish();
+    }
+
     /// Create a set of `Labels` with the given names, containing no entries.
     #[inline]
     pub fn empty(names: Vec<&str>) -> Labels {
@@ -267,6 +329,35 @@ impl Labels {
         return self.raw.count;
     }
 
+    /// Get a 64-bit fingerprint of the content (names and values) of these
+    /// labels.
+    ///
+    /// Labels that are equal always have the same fingerprint, and labels with
+    /// different fingerprints are always different. The fingerprint is cached
+    /// after the first call, and it is not guaranteed to be stable across
+    /// different versions of metatensor.
+    #[inline]
+    pub fn fingerprint(&self) -> u64 {
+        let mut fingerprint = 0;
+        unsafe {
+            check_status(crate::c_api::mts_labels_fingerprint(
+                self.raw,
+                &mut fingerprint,
+            )).expect("failed to get labels fingerprint");
+        }
+        return fingerprint;
+    }
+
+    /// Get the type used to store the values of these labels
+    #[inline]
+    pub fn dtype(&self) -> LabelsDType {
+        match self.raw.dtype {
+            MTS_LABELS_INT32 => LabelsDType::Int32,
+            MTS_LABELS_INT64 => LabelsDType::Int64,
+            other => panic!("unknown dtype in mts_labels_t: {}", other),
+        }
+    }
+
     /// Check if this set of Labels is empty (contains no entry)
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -298,6 +389,27 @@ impl Labels {
         return result.try_into().ok();
     }
 
+    /// Get the position (i.e. row index) of the given 64-bit label in the full
+    /// labels array, or None.
+    ///
+    /// This works with both 32-bit and 64-bit labels.
+    #[inline]
+    pub fn position_i64(&self, value: &[i64]) -> Option<usize> {
+        assert!(value.len() == self.size(), "invalid size of index in Labels::position_i64");
+
+        let mut result = 0;
+        unsafe {
+            check_status(crate::c_api::mts_labels_position_i64(
+                self.raw,
+                value.as_ptr(),
+                value.len(),
+                &mut result,
+            )).expect("failed to check label position");
+        }
+
+        return result.try_into().ok();
+    }
+
     /// Take the union of `self` with `other`.
     ///
     /// If requested, this function can also give the positions in the union
@@ -389,6 +501,11 @@ impl Labels {
     }
 
     /// Iterate over the entries in this set of labels
+    ///
+    /// # Panics
+    ///
+    /// If these labels use 64-bit values which do not fit in 32-bit integers,
+    /// use [`Labels::iter_i64`] instead.
     #[inline]
     pub fn iter(&self) -> LabelsIter<'_> {
         return LabelsIter {
@@ -396,19 +513,54 @@ impl Labels {
         };
     }
 
+    /// Iterate over the entries in this set of labels as 64-bit values.
+    ///
+    /// This works for both 32-bit and 64-bit labels. The entries of 64-bit
+    /// labels are borrowed, while the entries of 32-bit labels are converted
+    /// to 64-bit integers (use [`Labels::iter`] to access them without
+    /// copies).
+    #[inline]
+    pub fn iter_i64(&self) -> LabelsIterI64<'_> {
+        return LabelsIterI64 {
+            chunks: self.chunks(),
+        };
+    }
+
     /// Iterate over the entries in this set of labels in parallel
+    ///
+    /// # Panics
+    ///
+    /// If these labels use 64-bit values which do not fit in 32-bit integers,
+    /// use [`Labels::par_iter_i64`] instead.
     #[cfg(feature = "rayon")]
     #[inline]
-    pub fn par_iter(&self) -> LabelsParIter<'_> {
+    pub fn par_iter(&self) -> LabelsParIter<'_, LabelValue> {
         use rayon::prelude::*;
         return LabelsParIter {
             chunks: self.values().par_chunks_exact(self.raw.size)
         };
     }
 
+    /// Iterate over the entries in this set of labels in parallel, as 64-bit
+    /// values. This works for both 32-bit and 64-bit labels.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_iter_i64(&self) -> LabelsParIter<'_, i64> {
+        use rayon::prelude::*;
+        return LabelsParIter {
+            chunks: self.values_i64().par_chunks_exact(self.raw.size)
+        };
+    }
+
     /// Iterate over the entries in this set of labels as fixed-size arrays
+    ///
+    /// # Panics
+    ///
+    /// If these labels use 64-bit values which do not fit in 32-bit integers
+    /// (use [`Labels::iter_fixed_size_i64`] instead), or if `N` is not the
+    /// size of these labels.
     #[inline]
-    pub fn iter_fixed_size<const N: usize>(&self) -> LabelsFixedSizeIter<N> {
+    pub fn iter_fixed_size<const N: usize>(&self) -> LabelsFixedSizeIter<'_, LabelValue, N> {
         assert!(N == self.size(),
             "wrong label size in `iter_fixed_size`: the entries contains {} element \
             but this function was called with size of {}",
@@ -420,6 +572,61 @@ impl Labels {
         };
     }
 
+    /// Iterate over the entries in this set of labels as fixed-size arrays of
+    /// 64-bit values. This works for both 32-bit and 64-bit labels.
+    ///
+    /// # Panics
+    ///
+    /// If `N` is not the size of these labels.
+    #[inline]
+    pub fn iter_fixed_size_i64<const N: usize>(&self) -> LabelsFixedSizeIter<'_, i64, N> {
+        assert!(N == self.size(),
+            "wrong label size in `iter_fixed_size_i64`: the entries contains {} element \
+            but this function was called with size of {}",
+            self.size(), N
+        );
+
+        return LabelsFixedSizeIter {
+            values: self.values_i64()
+        };
+    }
+
+    /// Iterate over the entries in this set of labels as instances of `T`.
+    ///
+    /// `T` is usually a struct using `#[derive(LabelsEntry)]` (this requires
+    /// the `derive` feature of this crate), with one field for each dimension
+    /// of the labels. This works for both 32-bit and 64-bit labels.
+    ///
+    /// # Panics
+    ///
+    /// If the names of these labels do not match `T::NAMES`.
+    #[inline]
+    pub fn iter_as<T: LabelsEntry>(&self) -> LabelsEntryIter<'_, T> {
+        assert!(self.names() == T::NAMES,
+            "wrong label names in `iter_as`: the labels have names {:?} \
+            but this function was called with a type using {:?}",
+            self.names(), T::NAMES
+        );
+
+        return LabelsEntryIter {
+            chunks: self.chunks(),
+            buffer: SmallVec::new(),
+            _marker: std::marker::PhantomData,
+        };
+    }
+
+    /// Get the entries of these labels as chunks of the values, for both
+    /// 32-bit and 64-bit labels
+    fn chunks(&self) -> LabelsChunks<'_> {
+        // `chunks_exact` does not accept a size of 0, but there are no values
+        // in labels without dimensions
+        let size = usize::max(self.size(), 1);
+        match self.dtype() {
+            LabelsDType::Int32 => LabelsChunks::Int32(self.values().chunks_exact(size)),
+            LabelsDType::Int64 => LabelsChunks::Int64(self.raw_values_i64().chunks_exact(size)),
+        }
+    }
+
     /// Select entries in these `Labels` that match the `selection`.
     ///
     /// The selection's names must be a subset of the names of these labels.
@@ -445,15 +652,62 @@ impl Labels {
         return Ok(selected);
     }
 
+    /// Get the values of these labels as `LabelValue`, converting the values
+    /// of 64-bit labels on the first call.
+    ///
+    /// # Panics
+    ///
+    /// If these labels use 64-bit values which do not fit in 32-bit integers.
     pub(crate) fn values(&self) -> &[LabelValue] {
         if self.count() == 0 || self.size() == 0 {
-            return &[]
-        } else {
-            unsafe {
+            return &[];
+        }
+
+        match self.dtype() {
+            LabelsDType::Int32 => unsafe {
                 std::slice::from_raw_parts(self.raw.values.cast(), self.count() * self.size())
+            },
+            LabelsDType::Int64 => {
+                let values = self.values_i32.get_or_init(|| {
+                    self.raw_values_i64().iter()
+                        .map(|&value| i32::try_from(value).ok().map(LabelValue::new))
+                        .collect()
+                });
+
+                values.as_deref().expect(
+                    "these Labels contain values which do not fit in 32-bit \
+                    integers, use the `*_i64` functions to access them"
+                )
             }
         }
     }
+
+    /// Get the values of these labels as a linearized 2D array of 64-bit
+    /// integers in row-major order.
+    ///
+    /// This works for both 32-bit and 64-bit labels. The values of 32-bit
+    /// labels are converted to 64-bit integers on the first call to this
+    /// function.
+    pub fn values_i64(&self) -> &[i64] {
+        match self.dtype() {
+            LabelsDType::Int32 => self.values_i64.get_or_init(|| {
+                self.values().iter().map(|&value| i64::from(value)).collect()
+            }),
+            LabelsDType::Int64 => self.raw_values_i64(),
+        }
+    }
+
+    /// Get the values of 64-bit labels, without any conversion
+    fn raw_values_i64(&self) -> &[i64] {
+        debug_assert!(self.dtype() == LabelsDType::Int64);
+        if self.count() == 0 || self.size() == 0 {
+            return &[];
+        }
+
+        unsafe {
+            std::slice::from_raw_parts(self.raw.values_i64, self.count() * self.size())
+        }
+    }
 }
 
 impl Labels {
@@ -476,6 +730,8 @@ impl Labels {
         assert!(!raw.internal_ptr_.is_null(), "expected mts_labels_t.internal_ptr_ to not be NULL");
         Labels {
             raw: raw,
+            values_i32: OnceLock::new(),
+            values_i64: OnceLock::new(),
         }
     }
 }
@@ -483,10 +739,39 @@ impl Labels {
 impl std::cmp::PartialEq<Labels> for Labels {
     #[inline]
     fn eq(&self, other: &Labels) -> bool {
-        self.names() == other.names() && self.values() == other.values()
+        if self.raw.internal_ptr_ == other.raw.internal_ptr_ {
+            // both labels point to the same Rust data
+            return true;
+        }
+
+        if self.fingerprint() != other.fingerprint() {
+            return false;
+        }
+
+        if self.names() != other.names() {
+            return false;
+        }
+
+        match (self.dtype(), other.dtype()) {
+            (LabelsDType::Int32, LabelsDType::Int32) => self.values() == other.values(),
+            (LabelsDType::Int64, LabelsDType::Int64) => self.raw_values_i64() == other.raw_values_i64(),
+            (LabelsDType::Int32, LabelsDType::Int64) => equal_values(self.values(), other.raw_values_i64()),
+            (LabelsDType::Int64, LabelsDType::Int32) => equal_values(other.values(), self.raw_values_i64()),
+        }
     }
 }
 
+/// Check that 32-bit and 64-bit label values are the same
+fn equal_values(values: &[LabelValue], values_i64: &[i64]) -> bool {
+    values.len() == values_i64.len() && values.iter().zip(values_i64).all(|(a, &b)| i64::from(a.i32()) == b)
+}
+
+/// Get the entry at the given index in these `Labels`.
+///
+/// # Panics
+///
+/// If these labels use 64-bit values which do not fit in 32-bit integers, use
+/// [`Labels::values_i64`] instead.
 impl std::ops::Index<usize> for Labels {
     type Output = [LabelValue];
 
@@ -536,16 +821,67 @@ impl<'a> IntoIterator for &'a Labels {
     }
 }
 
-/// Parallel iterator over entries in a set of [`Labels`]
+/// Entries in a set of [`Labels`] with either 32-bit or 64-bit values
+#[derive(Debug, Clone)]
+enum LabelsChunks<'a> {
+    Int32(std::slice::ChunksExact<'a, LabelValue>),
+    Int64(std::slice::ChunksExact<'a, i64>),
+}
+
+impl LabelsChunks<'_> {
+    fn len(&self) -> usize {
+        match self {
+            LabelsChunks::Int32(chunks) => chunks.len(),
+            LabelsChunks::Int64(chunks) => chunks.len(),
+        }
+    }
+}
+
+/// Iterator over [`Labels`] entries as 64-bit values, see
+/// [`Labels::iter_i64`]
+#[derive(Debug, Clone)]
+pub struct LabelsIterI64<'a> {
+    chunks: LabelsChunks<'a>,
+}
+
+impl<'a> Iterator for LabelsIterI64<'a> {
+    type Item = Cow<'a, [i64]>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.chunks {
+            LabelsChunks::Int32(chunks) => chunks.next().map(|entry| {
+                Cow::Owned(entry.iter().map(|&value| i64::from(value)).collect())
+            }),
+            LabelsChunks::Int64(chunks) => chunks.next().map(Cow::Borrowed),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+impl ExactSizeIterator for LabelsIterI64<'_> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+impl FusedIterator for LabelsIterI64<'_> {}
+
+/// Parallel iterator over entries in a set of [`Labels`], as [`LabelValue`]
+/// or 64-bit integers
 #[cfg(feature = "rayon")]
 #[derive(Debug, Clone)]
-pub struct LabelsParIter<'a> {
-    chunks: rayon::slice::ChunksExact<'a, LabelValue>,
+pub struct LabelsParIter<'a, T = LabelValue> {
+    chunks: rayon::slice::ChunksExact<'a, T>,
 }
 
 #[cfg(feature = "rayon")]
-impl<'a> rayon::iter::ParallelIterator for LabelsParIter<'a> {
-    type Item = &'a [LabelValue];
+impl<'a, T: Sync> rayon::iter::ParallelIterator for LabelsParIter<'a, T> {
+    type Item = &'a [T];
 
     #[inline]
     fn drive_unindexed<C>(self, consumer: C) -> C::Result
@@ -556,7 +892,7 @@ impl<'a> rayon::iter::ParallelIterator for LabelsParIter<'a> {
 }
 
 #[cfg(feature = "rayon")]
-impl<'a> rayon::iter::IndexedParallelIterator for LabelsParIter<'a> {
+impl<'a, T: Sync> rayon::iter::IndexedParallelIterator for LabelsParIter<'a, T> {
     #[inline]
     fn len(&self) -> usize {
         self.chunks.len()
@@ -573,14 +909,15 @@ impl<'a> rayon::iter::IndexedParallelIterator for LabelsParIter<'a> {
     }
 }
 
-/// Iterator over entries in a set of [`Labels`] as fixed size arrays
+/// Iterator over entries in a set of [`Labels`] as fixed size arrays of
+/// [`LabelValue`] or 64-bit integers
 #[derive(Debug, Clone)]
-pub struct LabelsFixedSizeIter<'a, const N: usize> {
-    values: &'a [LabelValue],
+pub struct LabelsFixedSizeIter<'a, T, const N: usize> {
+    values: &'a [T],
 }
 
-impl<'a, const N: usize> Iterator for LabelsFixedSizeIter<'a, N> {
-    type Item = &'a [LabelValue; N];
+impl<'a, T, const N: usize> Iterator for LabelsFixedSizeIter<'a, T, N> {
+    type Item = &'a [T; N];
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -598,19 +935,92 @@ impl<'a, const N: usize> Iterator for LabelsFixedSizeIter<'a, N> {
     }
 }
 
-impl<'a, const N: usize> ExactSizeIterator for LabelsFixedSizeIter<'a, N> {
+impl<'a, T, const N: usize> ExactSizeIterator for LabelsFixedSizeIter<'a, T, N> {
     #[inline]
     fn len(&self) -> usize {
         self.values.len() / N
     }
 }
 
+/// A type that can be created from a single entry in a set of [`Labels`].
+///
+/// This trait is usually implemented with `#[derive(LabelsEntry)]`, which
+/// requires the `derive` feature of this crate:
+///
+/// ```ignore
+/// use metatensor::LabelsEntry;
+///
+/// #[derive(LabelsEntry)]
+/// struct Sample {
+///     system: i32,
+///     atom: i32,
+/// }
+///
+/// let labels = metatensor::labels!{"system", "atom" => [0, 1], [0, 2]};
+/// for sample in labels.iter_as::<Sample>() {
+///     println!("system={} atom={}", sample.system, sample.atom);
+/// }
+/// ```
+pub trait LabelsEntry: Sized {
+    /// Names of the dimensions of the labels this type corresponds to
+    const NAMES: &'static [&'static str];
+
+    /// Create a new instance of this type from the values in a single entry.
+    /// `values` contains one value for each name in `Self::NAMES`, as 64-bit
+    /// integers for both 32-bit and 64-bit labels.
+    fn from_values(values: &[i64]) -> Self;
+}
+
+/// Iterator over entries in a set of [`Labels`] as instances of a type
+/// implementing [`LabelsEntry`]
+#[derive(Debug, Clone)]
+pub struct LabelsEntryIter<'a, T> {
+    chunks: LabelsChunks<'a>,
+    /// storage for the entries of 32-bit labels converted to 64-bit
+    buffer: SmallVec<[i64; 8]>,
+    _marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: LabelsEntry> Iterator for LabelsEntryIter<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.chunks {
+            LabelsChunks::Int32(chunks) => {
+                let entry = chunks.next()?;
+                self.buffer.clear();
+                self.buffer.extend(entry.iter().map(|&value| i64::from(value)));
+                return Some(T::from_values(&self.buffer));
+            }
+            LabelsChunks::Int64(chunks) => chunks.next().map(T::from_values),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+impl<T: LabelsEntry> ExactSizeIterator for LabelsEntryIter<'_, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+impl<T: LabelsEntry> FusedIterator for LabelsEntryIter<'_, T> {}
+
 /// Builder for [`Labels`]
 #[derive(Debug, Clone)]
 pub struct LabelsBuilder {
     // cf `Labels` for the documentation of the fields
     names: Vec<String>,
+    dtype: LabelsDType,
+    /// values for `LabelsDType::Int32`
     values: Vec<LabelValue>,
+    /// values for `LabelsDType::Int64`
+    values_i64: Vec<i64>,
 }
 
 impl LabelsBuilder {
@@ -622,14 +1032,28 @@ impl LabelsBuilder {
 
         LabelsBuilder {
             names: names.into_iter().map(|s| s.into()).collect(),
+            dtype: LabelsDType::Int32,
             values: Vec::new(),
+            values_i64: Vec::new(),
         }
     }
 
+    /// Create a new empty `LabelsBuilder` with the given `names`, for
+    /// `Labels` storing their values as 64-bit integers.
+    #[inline]
+    pub fn new_i64(names: Vec<&str>) -> LabelsBuilder {
+        let mut builder = LabelsBuilder::new(names);
+        builder.dtype = LabelsDType::Int64;
+        return builder;
+    }
+
     /// Reserve space for `additional` other entries in the labels.
     #[inline]
     pub fn reserve(&mut self, additional: usize) {
-        self.values.reserve(additional * self.names.len());
+        match self.dtype {
+            LabelsDType::Int32 => self.values.reserve(additional * self.names.len()),
+            LabelsDType::Int64 => self.values_i64.reserve(additional * self.names.len()),
+        }
     }
 
     /// Get the number of labels in a single value
@@ -653,44 +1077,211 @@ impl LabelsBuilder {
         // SmallVec allows us to convert everything to `LabelValue` without
         // requiring an extra heap allocation
         let entry = entry.iter().copied().map(Into::into).collect::<SmallVec<[LabelValue; 16]>>();
-        self.values.extend(&entry);
+        match self.dtype {
+            LabelsDType::Int32 => self.values.extend(&entry),
+            LabelsDType::Int64 => self.values_i64.extend(entry.iter().map(|v| i64::from(v.i32()))),
+        }
+    }
+
+    /// Add a single `entry` with 64-bit values to this set of labels.
+    ///
+    /// This function will panic when attempting to add the same `label` more
+    /// than once, or if this builder was not created with
+    /// [`LabelsBuilder::new_i64`].
+    #[inline]
+    pub fn add_i64(&mut self, entry: &[i64]) {
+        assert_eq!(
+            self.size(), entry.len(),
+            "wrong size for added label: got {}, but expected {}",
+            entry.len(), self.size()
+        );
+
+        assert!(
+            self.dtype == LabelsDType::Int64,
+            "can not add 64-bit values to 32-bit Labels, use `LabelsBuilder::new_i64`"
+        );
+        self.values_i64.extend_from_slice(entry);
     }
 
     /// Finish building the `Labels`
     #[inline]
     pub fn finish(self) -> Labels {
+        return self.try_finish().expect("invalid labels?");
+    }
+
+    /// Finish building the `Labels`, returning an error instead of panicking
+    /// if the entries are not valid.
+    pub(crate) fn try_finish(self) -> Result<Labels, Error> {
         let mut raw_names = Vec::new();
         let mut raw_names_ptr = Vec::new();
 
         let mut raw_labels = if self.names.is_empty() {
-            assert!(self.values.is_empty());
-            mts_labels_t::null()
+            assert!(self.values.is_empty() && self.values_i64.is_empty());
+            let mut raw_labels = mts_labels_t::null();
+            if self.dtype == LabelsDType::Int64 {
+                raw_labels.dtype = MTS_LABELS_INT64;
+            }
+            raw_labels
         } else {
             for name in &self.names {
-                let name = CString::new(&**name).expect("name contains a NULL byte");
-                raw_names_ptr.push(name.as_ptr());
-                raw_names.push(name);
+                let Ok(c_name) = CString::new(&**name) else {
+                    return Err(Error {
+                        code: None,
+                        message: format!("invalid labels: the name {:?} contains a NUL byte", name),
+                    });
+                };
+                raw_names_ptr.push(c_name.as_ptr());
+                raw_names.push(c_name);
             }
 
-            mts_labels_t {
+            let mut raw_labels = mts_labels_t {
                 internal_ptr_: std::ptr::null_mut(),
                 names: raw_names_ptr.as_ptr(),
-                values: self.values.as_ptr().cast(),
+                values: std::ptr::null(),
                 size: self.size(),
-                count: self.values.len() / self.size(),
+                count: 0,
+                dtype: MTS_LABELS_INT32,
+                values_i64: std::ptr::null(),
+            };
+
+            match self.dtype {
+                LabelsDType::Int32 => {
+                    raw_labels.values = self.values.as_ptr().cast();
+                    raw_labels.count = self.values.len() / self.size();
+                }
+                LabelsDType::Int64 => {
+                    raw_labels.dtype = MTS_LABELS_INT64;
+                    raw_labels.values_i64 = self.values_i64.as_ptr();
+                    raw_labels.count = self.values_i64.len() / self.size();
+                }
             }
+
+            raw_labels
         };
 
         unsafe {
-            check_status(
-                crate::c_api::mts_labels_create(&mut raw_labels)
-            ).expect("invalid labels?");
+            check_status(crate::c_api::mts_labels_create(&mut raw_labels))?;
         }
 
-        return unsafe { Labels::from_raw(raw_labels) };
+        return Ok(unsafe { Labels::from_raw(raw_labels) });
     }
 }
 
+/// Check if the given name is a valid identifier, to be used as a column name
+/// in `Labels`.
+///
+/// This function can be used in `const` context, and is used by the
+/// [`labels!`](crate::labels!) macro to validate names at compile time.
+pub const fn is_valid_label_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    if bytes.is_empty() || bytes[0].is_ascii_digit() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if !(c.is_ascii_alphanumeric() || c == b'_') {
+            return false;
+        }
+        i += 1;
+    }
+
+    return true;
+}
+
+/// Check that all the `names` are different. This is used by the
+/// [`labels!`](crate::labels!) macro to validate names at compile time.
+#[doc(hidden)]
+pub const fn all_names_unique(names: &[&str]) -> bool {
+    let mut i = 0;
+    while i < names.len() {
+        let mut j = i + 1;
+        while j < names.len() {
+            if const_str_eq(names[i], names[j]) {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+
+    return true;
+}
+
+const fn const_str_eq(a: &str, b: &str) -> bool {
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    return true;
+}
+
+/// Create a new set of [`Labels`] with the given names and values.
+///
+/// The names are validated at compile time, and all entries must have the same
+/// size as the list of names.
+///
+/// ```
+/// # use metatensor::labels;
+/// let labels = labels!{"system", "atom" => [0, 1], [0, 2], [1, 0]};
+/// assert_eq!(labels.names(), ["system", "atom"]);
+/// assert_eq!(labels.count(), 3);
+///
+/// // Labels without any entry
+/// let empty = labels!{"system", "atom" =>};
+/// assert_eq!(empty.count(), 0);
+/// ```
+///
+/// Invalid names result in a compilation error:
+///
+/// ```compile_fail
+/// # use metatensor::labels;
+/// let labels = labels!{"system", "33 atom" => [0, 1]};
+/// ```
+///
+/// ```compile_fail
+/// # use metatensor::labels;
+/// let labels = labels!{"system", "system" => [0, 1]};
+/// ```
+///
+/// # Panics
+///
+/// If any of the entries is duplicated.
+#[macro_export]
+macro_rules! labels {
+    (@check $($name: literal),+) => {
+        $(
+            const _: () = ::std::assert!(
+                $crate::is_valid_label_name($name),
+                ::std::concat!("'", $name, "' is not a valid label name")
+            );
+        )+
+        const _: () = ::std::assert!(
+            $crate::__all_names_unique(&[$($name),+]),
+            "invalid labels: the same name is used multiple times"
+        );
+    };
+    ($($name: literal),+ $(,)? => $(,)?) => {{
+        $crate::labels!(@check $($name),+);
+        $crate::Labels::empty(::std::vec![$($name),+])
+    }};
+    ($($name: literal),+ $(,)? => $([$($value: expr),+ $(,)?]),+ $(,)?) => {{
+        $crate::labels!(@check $($name),+);
+        $crate::Labels::new([$($name),+], &[$([$($value),+]),+])
+    }};
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -755,6 +1346,51 @@ mod tests {
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn labels_macro() {
+        let labels = crate::labels!{"foo", "bar" => [2, 3], [1, 243], [-4, -2413]};
+        assert_eq!(labels, Labels::new(["foo", "bar"], &[[2, 3], [1, 243], [-4, -2413]]));
+
+        let labels = crate::labels!{"foo" =>};
+        assert_eq!(labels.names(), ["foo"]);
+        assert_eq!(labels.count(), 0);
+
+        assert!(is_valid_label_name("foo_2"));
+        assert!(!is_valid_label_name("2foo"));
+        assert!(!is_valid_label_name("foo bar"));
+        assert!(!is_valid_label_name(""));
+
+        assert!(all_names_unique(&["foo", "bar", "fo"]));
+        assert!(!all_names_unique(&["foo", "bar", "foo"]));
+    }
+
+    #[test]
+    fn labels_iter_as() {
+        struct Sample {
+            system: i32,
+            atom: i64,
+        }
+
+        impl LabelsEntry for Sample {
+            const NAMES: &'static [&'static str] = &["system", "atom"];
+            fn from_values(values: &[i64]) -> Self {
+                Sample { system: values[0].try_into().unwrap(), atom: values[1] }
+            }
+        }
+
+        let labels = crate::labels!{"system", "atom" => [0, 1], [0, 2], [3, 4]};
+        let mut iter = labels.iter_as::<Sample>();
+        assert_eq!(iter.len(), 3);
+
+        let sample = iter.next().unwrap();
+        assert_eq!((sample.system, sample.atom), (0, 1));
+        let sample = iter.next().unwrap();
+        assert_eq!((sample.system, sample.atom), (0, 2));
+        let sample = iter.next().unwrap();
+        assert_eq!((sample.system, sample.atom), (3, 4));
+        assert!(iter.next().is_none());
+    }
+
     #[test]
     #[should_panic(expected = "'33 bar' is not a valid label name")]
     fn invalid_label_name() {
@@ -868,6 +1504,99 @@ mod tests {
         assert_eq!(second_mapping, [-1, 0, -1]);
     }
 
+    #[test]
+    fn labels_i64() {
+        #[derive(Debug, PartialEq)]
+        struct Entry {
+            foo: i64,
+            bar: i64,
+        }
+
+        impl LabelsEntry for Entry {
+            const NAMES: &'static [&'static str] = &["foo", "bar"];
+            fn from_values(values: &[i64]) -> Self {
+                Entry { foo: values[0], bar: values[1] }
+            }
+        }
+
+        let large = i64::from(i32::MAX) + 1;
+        let labels = Labels::new_i64(["foo", "bar"], &[[large, 3], [1, -large]]);
+
+        assert_eq!(labels.dtype(), LabelsDType::Int64);
+        assert_eq!(labels.names(), &["foo", "bar"]);
+        assert_eq!(labels.count(), 2);
+        assert_eq!(labels.values_i64(), [large, 3, 1, -large]);
+
+        let mut iter = labels.iter_i64();
+        assert_eq!(iter.next().unwrap(), &[large, 3][..]);
+        assert_eq!(iter.next().unwrap(), &[1, -large][..]);
+        assert_eq!(iter.next(), None);
+
+        let entries = labels.iter_as::<Entry>().collect::<Vec<_>>();
+        assert_eq!(entries, [Entry { foo: large, bar: 3 }, Entry { foo: 1, bar: -large }]);
+
+        // 32-bit labels can also be accessed as 64-bit values
+        let small = Labels::new(["foo", "bar"], &[[1, 3], [-2, 4]]);
+        let entries = small.iter_i64().collect::<Vec<_>>();
+        assert_eq!(entries, [&[1, 3][..], &[-2, 4][..]]);
+        assert_eq!(small.iter_as::<Entry>().next(), Some(Entry { foo: 1, bar: 3 }));
+
+        // Debug works for both dtypes
+        assert!(format!("{:?}", labels).contains(&large.to_string()));
+
+        assert_eq!(labels.position_i64(&[1, -large]), Some(1));
+        assert_eq!(labels.position_i64(&[1, 2]), None);
+        assert_eq!(labels.position(&[LabelValue::new(1), LabelValue::new(3)]), None);
+
+        // 32-bit labels still default to i32, and compare equal to the same
+        // 64-bit values
+        let small = Labels::new(["foo", "bar"], &[[1, 3]]);
+        assert_eq!(small.dtype(), LabelsDType::Int32);
+        assert_eq!(small, Labels::new_i64(["foo", "bar"], &[[1, 3]]));
+        assert_eq!(small.position_i64(&[1, 3]), Some(0));
+
+        let union = small.union(&labels, None, None).unwrap();
+        assert_eq!(union.dtype(), LabelsDType::Int64);
+        assert_eq!(union.values_i64(), [1, 3, large, 3, 1, -large]);
+
+        // 32-bit values of 64-bit labels can be accessed as `LabelValue`
+        let labels = Labels::new_i64(["foo", "bar"], &[[1, 3], [-2, 4]]);
+        assert_eq!(labels[1], [-2, 4]);
+        assert_eq!(labels.iter().collect::<Vec<_>>(), [&[1, 3], &[-2, 4]]);
+        assert_eq!(labels.iter_fixed_size().collect::<Vec<_>>(), [&[1, 3], &[-2, 4]]);
+
+        // and any labels can be accessed as 64-bit values
+        let small = Labels::new(["foo", "bar"], &[[1, 3], [-2, 4]]);
+        assert_eq!(small.values_i64(), [1, 3, -2, 4]);
+        assert_eq!(small.iter_fixed_size_i64().collect::<Vec<_>>(), [&[1, 3], &[-2, 4]]);
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            let entries = small.par_iter_i64().collect::<Vec<_>>();
+            assert_eq!(entries, [&[1, 3][..], &[-2, 4][..]]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "these Labels contain values which do not fit in 32-bit integers, use the `*_i64` functions to access them")]
+    fn labels_i64_access_i32() {
+        let labels = Labels::new_i64(["foo"], &[[0], [i64::from(i32::MAX) + 1]]);
+        let _ = labels.iter();
+    }
+
+    #[test]
+    fn fingerprint() {
+        let first = Labels::new(["aa", "bb"], &[[0, 1], [1, 2]]);
+        let second = Labels::new(["aa", "bb"], &[[0, 1], [1, 2]]);
+        assert_eq!(first.fingerprint(), second.fingerprint());
+        assert_eq!(first.fingerprint(), first.clone().fingerprint());
+
+        let second = Labels::new(["aa", "bb"], &[[0, 1], [2, 1]]);
+        assert_ne!(first.fingerprint(), second.fingerprint());
+        assert_ne!(first, second);
+    }
+
     #[test]
     fn selection() {
         // selection with a subset of names
@@ -19,6 +19,19 @@
 //! [dependencies]
 //! metatensor = {version = "...", features = ["static"]}
 //! ```
+//!
+//! The `derive` feature enables `#[derive(LabelsEntry)]`, to access the entries
+//! of [`Labels`] as instances of your own structs (see [`Labels::iter_as`]).
+//!
+//! The `arrow` feature enables conversions of [`Labels`] and [`TensorBlock`] to
+//! and from [Apache Arrow](https://arrow.apache.org/) `RecordBatch`, see the
+//! `arrow` module for more information. The dependencies of this feature
+//! require Rust 1.81 or later.
+//!
+//! The `nalgebra` and `faer` features enable [`Array`] implementations for
+//! 2-dimensional blocks using matrices from these crates (`NalgebraArray` and
+//! `FaerArray`). Note that `faer` 0.22 requires Rust 1.84 or later, and that
+//! both features are only tested on the latest stable Rust compiler.
 
 #![warn(clippy::all, clippy::pedantic)]
 
@@ -37,11 +50,22 @@ pub use self::errors::Error;
 
 mod data;
 pub use self::data::{ArrayRef, ArrayRefMut};
-pub use self::data::{Array, EmptyArray};
+pub use self::data::{Array, EmptyArray, VecArray, SparseArray};
+pub use self::data::register_array_converter;
+#[cfg(feature = "nalgebra")]
+pub use self::data::NalgebraArray;
+#[cfg(feature = "faer")]
+pub use self::data::FaerArray;
 
 mod labels;
-pub use self::labels::{Labels, LabelsBuilder, LabelValue};
-pub use self::labels::{LabelsIter, LabelsFixedSizeIter};
+pub use self::labels::{Labels, LabelsBuilder, LabelValue, LabelsDType};
+pub use self::labels::{LabelsIter, LabelsIterI64, LabelsFixedSizeIter};
+pub use self::labels::{LabelsEntry, LabelsEntryIter, is_valid_label_name};
+#[doc(hidden)]
+pub use self::labels::all_names_unique as __all_names_unique;
+
+#[cfg(feature = "derive")]
+pub use metatensor_derive::LabelsEntry;
 
 #[cfg(feature = "rayon")]
 pub use self::labels::LabelsParIter;
@@ -49,7 +73,7 @@ pub use self::labels::LabelsParIter;
 mod block;
 pub use self::block::{TensorBlock, TensorBlockRef, TensorBlockRefMut};
 pub use self::block::{TensorBlockData, TensorBlockDataMut};
-pub use self::block::{GradientsIter, GradientsMutIter};
+pub use self::block::{GradientsIter, GradientsTreeIter, GradientsMutIter};
 pub use self::block::LazyMetadata;
 
 mod tensor;
@@ -58,4 +82,17 @@ pub use self::tensor::{TensorMapIter, TensorMapIterMut};
 #[cfg(feature = "rayon")]
 pub use self::tensor::{TensorMapParIter, TensorMapParIterMut};
 
+mod builder;
+pub use self::builder::TensorMapBuilder;
+
+mod borrowed;
+pub use self::borrowed::Borrowed;
+
 pub mod io;
+
+pub mod dlpack;
+
+pub mod testing;
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
@@ -5,7 +5,7 @@ use crate::block::TensorBlockRefMut;
 use crate::c_api::{mts_tensormap_t, mts_labels_t};
 
 use crate::errors::{check_status, check_ptr};
-use crate::{Error, TensorBlock, TensorBlockRef, Labels, LabelValue};
+use crate::{Error, TensorBlock, TensorBlockRef, Labels, LabelsBuilder, LabelValue};
 
 /// [`TensorMap`] is the main user-facing struct of this library, and can
 /// store any kind of data used in atomistic machine learning.
@@ -18,8 +18,10 @@ use crate::{Error, TensorBlock, TensorBlockRef, Labels, LabelValue};
 /// representation of the data to a dense one.
 pub struct TensorMap {
     pub(crate) ptr: *mut mts_tensormap_t,
-    /// cache for the keys labels
-    keys: Labels,
+    /// cache for the keys labels. This is released while modifying the
+    /// blocks, so that metatensor-core can update the keys in place instead of
+    /// copying them.
+    keys: Option<Labels>,
 }
 
 // SAFETY: Send is fine since we can free a TensorMap from any thread
@@ -100,7 +102,7 @@ impl TensorMap {
 
         return TensorMap {
             ptr,
-            keys
+            keys: Some(keys),
         };
     }
 
@@ -115,6 +117,34 @@ impl TensorMap {
         return ptr;
     }
 
+    /// Split this `TensorMap` into its keys and the corresponding blocks,
+    /// without copying any data.
+    ///
+    /// The blocks can be used to create a new `TensorMap` with
+    /// [`TensorMap::new`], also without copying data.
+    #[inline]
+    pub fn into_blocks(self) -> (Labels, Vec<TensorBlock>) {
+        let mut blocks = vec![std::ptr::null_mut(); self.keys().count()];
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_into_blocks(
+                self.ptr,
+                blocks.as_mut_ptr(),
+                blocks.len(),
+            )).expect("failed to move blocks out of the tensor map");
+        }
+
+        // the tensor map pointer was released by `mts_tensormap_into_blocks`,
+        // we only need to keep the keys
+        let tensor = std::mem::ManuallyDrop::new(self);
+        let keys = unsafe { std::ptr::read(&tensor.keys) }.expect("the keys should be loaded");
+
+        let blocks = blocks.into_iter()
+            .map(|block| unsafe { TensorBlock::from_raw(block) })
+            .collect();
+
+        return (keys, blocks);
+    }
+
     /// Clone this `TensorMap`, cloning all the data and metadata contained inside.
     ///
     /// This can fail if the external data held inside an `mts_array_t` can not
@@ -129,6 +159,24 @@ impl TensorMap {
         return Ok(unsafe { TensorMap::from_raw(ptr) });
     }
 
+    /// Create a copy of this `TensorMap`, with the values and gradients of
+    /// all blocks converted to the data origin named `origin`.
+    ///
+    /// The conversion uses the converters registered with
+    /// [`register_array_converter`](crate::register_array_converter), and
+    /// fails if `origin` is not a registered data origin or if there is no
+    /// converter from the current origin of some array to `origin`.
+    #[inline]
+    pub fn to_origin(&self, origin: &str) -> Result<TensorMap, Error> {
+        let origin = crate::data::find_data_origin(origin)?;
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_to_origin(self.ptr, origin)
+        };
+        crate::errors::check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
+    }
+
     /// Load a `TensorMap` from the file at `path`
     ///
     /// This is a convenience function calling [`crate::io::load`]
@@ -160,7 +208,7 @@ impl TensorMap {
     /// Get the keys defined in this `TensorMap`
     #[inline]
     pub fn keys(&self) -> &Labels {
-        &self.keys
+        self.keys.as_ref().expect("the keys should be loaded")
     }
 
     /// Get a reference to the block at the given `index` in this `TensorMap`
@@ -214,7 +262,7 @@ impl TensorMap {
             index,
         )).expect("failed to get a block");
 
-        return TensorBlockRefMut::from_raw(block);
+        return TensorBlockRefMut::from_raw(block).in_tensor_map();
     }
 
     /// Get the index of blocks matching the given selection.
@@ -239,6 +287,33 @@ impl TensorMap {
         return Ok(indexes);
     }
 
+    /// Get the index of blocks matching each one of the entries in the given
+    /// selection.
+    ///
+    /// This is equivalent to calling [`TensorMap::blocks_matching`] separately
+    /// for each entry in `selection`, but faster when there are many entries.
+    #[inline]
+    pub fn blocks_matching_many(&self, selection: &Labels) -> Result<Vec<Vec<usize>>, Error> {
+        let mut indexes = vec![0; self.keys().count()];
+        let mut offsets = vec![0; selection.count() + 1];
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_blocks_matching_many(
+                self.ptr,
+                selection.as_mts_labels_t(),
+                indexes.as_mut_ptr(),
+                indexes.len(),
+                offsets.as_mut_ptr(),
+                offsets.len(),
+            ))?;
+        }
+
+        let matching = offsets.windows(2)
+            .map(|window| indexes[window[0]..window[1]].to_vec())
+            .collect();
+
+        return Ok(matching);
+    }
+
     /// Get the index of the single block matching the given selection.
     ///
     /// This function is similar to [`TensorMap::blocks_matching`], but also
@@ -287,6 +362,281 @@ impl TensorMap {
         return Ok(self.block_by_id(id));
     }
 
+    /// Get a reference to the block associated with the given `key`.
+    ///
+    /// The `key` must contain one value for each dimension of the keys, in the
+    /// same order as [`TensorMap::keys`]. Contrary to [`TensorMap::block`],
+    /// this does not require creating `Labels` for the selection.
+    ///
+    /// ```
+    /// # use metatensor::{Labels, TensorBlock, TensorMap};
+    /// # let block = TensorBlock::new(
+    /// #     ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+    /// #     &Labels::new(["samples"], &[[0]]),
+    /// #     &[],
+    /// #     &Labels::new(["properties"], &[[0]]),
+    /// # ).unwrap();
+    /// # let keys = Labels::new(["o3_lambda", "center_type", "neighbor_type"], &[[1, 6, 8]]);
+    /// # let tensor = TensorMap::new(keys, vec![block]).unwrap();
+    /// let block = tensor.block_by_key(&[1, 6, 8]).unwrap();
+    /// ```
+    #[inline]
+    pub fn block_by_key(&self, key: &[impl Into<i64> + Copy]) -> Result<TensorBlockRef<'_>, Error> {
+        let key = key.iter().map(|&v| v.into()).collect::<Vec<i64>>();
+        if key.len() != self.keys().size() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "expected a key with {} values, got {}",
+                    self.keys().size(), key.len()
+                ),
+            });
+        }
+
+        let Some(index) = self.keys().position_i64(&key) else {
+            let names = self.keys().names();
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "there is no block with key ({}) in this TensorMap, available keys are {}",
+                    format_key(&names, &key), self.available_keys(&names)
+                ),
+            });
+        };
+
+        return Ok(self.block_by_id(index));
+    }
+
+    /// Get a reference to the single block matching the given `selection`,
+    /// given as a list of `(dimension, value)` pairs.
+    ///
+    /// The selection can contain any subset of the keys dimensions, in any
+    /// order. An error is returned if no block or more than one block match
+    /// the selection.
+    ///
+    /// ```
+    /// # use metatensor::{Labels, TensorBlock, TensorMap};
+    /// # let block = TensorBlock::new(
+    /// #     ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+    /// #     &Labels::new(["samples"], &[[0]]),
+    /// #     &[],
+    /// #     &Labels::new(["properties"], &[[0]]),
+    /// # ).unwrap();
+    /// # let keys = Labels::new(["o3_lambda", "center_type"], &[[1, 6]]);
+    /// # let tensor = TensorMap::new(keys, vec![block]).unwrap();
+    /// let block = tensor.get(&[("center_type", 6)]).unwrap();
+    /// ```
+    #[inline]
+    pub fn get(&self, selection: &[(&str, impl Into<i64> + Copy)]) -> Result<TensorBlockRef<'_>, Error> {
+        let names = self.keys().names();
+
+        let mut dimensions = Vec::with_capacity(selection.len());
+        let mut values = Vec::with_capacity(selection.len());
+        for &(name, value) in selection {
+            let Some(dimension) = names.iter().position(|&n| n == name) else {
+                return Err(Error {
+                    code: None,
+                    message: format!("'{}' is not part of the keys for this tensor", name),
+                });
+            };
+
+            if dimensions.contains(&dimension) {
+                return Err(Error {
+                    code: None,
+                    message: format!("'{}' is present multiple times in the selection", name),
+                });
+            }
+
+            dimensions.push(dimension);
+            values.push(value.into());
+        }
+
+        // this re-uses the index created by metatensor-core for partial
+        // selections, instead of going over all the keys every time
+        let mut matching = vec![0; self.keys().count()];
+        let mut count = matching.len();
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_blocks_matching_values(
+                self.ptr,
+                dimensions.as_ptr(),
+                values.as_ptr(),
+                values.len(),
+                matching.as_mut_ptr(),
+                &mut count,
+            ))?;
+        }
+        matching.truncate(count);
+
+        if matching.len() != 1 {
+            let selection_str = selection.iter()
+                .map(|&(name, value)| format!("{}={}", name, value.into()))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let message = if matching.is_empty() {
+                format!(
+                    "no blocks matched the selection ({}), available keys are {}",
+                    selection_str, self.available_keys(&names)
+                )
+            } else {
+                format!(
+                    "{} blocks matched the selection ({}), expected only one",
+                    matching.len(), selection_str
+                )
+            };
+
+            return Err(Error { code: None, message });
+        }
+
+        return Ok(self.block_by_id(matching[0]));
+    }
+
+    /// Get a list of the keys in this tensor map to include in error
+    /// messages, truncated if there are too many of them
+    fn available_keys(&self, names: &[&str]) -> String {
+        const MAX_KEYS: usize = 10;
+
+        if self.keys().is_empty() || names.is_empty() {
+            return "[]".into();
+        }
+
+        let mut keys = self.keys().iter_i64()
+            .take(MAX_KEYS)
+            .map(|key| format!("({})", format_key(names, &key)))
+            .collect::<Vec<_>>();
+
+        if self.keys().count() > MAX_KEYS {
+            keys.push(format!("... and {} more", self.keys().count() - MAX_KEYS));
+        }
+
+        return format!("[{}]", keys.join(", "));
+    }
+
+    /// Add a new `block` to this `TensorMap`, associated with the given `key`.
+    ///
+    /// The block is added after all the existing blocks. The `key` must not
+    /// already be present in the keys, and the block must contain the same
+    /// kind of data as the existing blocks, as checked by [`TensorMap::new`].
+    #[allow(clippy::needless_pass_by_value)]
+    #[inline]
+    pub fn insert_block(&mut self, key: &[impl Into<i64> + Copy], mut block: TensorBlock) -> Result<(), Error> {
+        let key = key.iter().map(|&v| v.into()).collect::<Vec<i64>>();
+
+        let block_ptr = block.as_mut_ptr();
+        // we give ownership of the block to the tensor map, so we should not
+        // free it again from here
+        std::mem::forget(block);
+
+        self.keys = None;
+        let status = unsafe {
+            crate::c_api::mts_tensormap_insert_block(self.ptr, key.as_ptr(), key.len(), block_ptr)
+        };
+        self.refresh_keys();
+
+        return check_status(status);
+    }
+
+    /// Remove the block associated with the given `key` from this
+    /// `TensorMap`, and return it.
+    #[inline]
+    pub fn remove_block(&mut self, key: &[impl Into<i64> + Copy]) -> Result<TensorBlock, Error> {
+        let key = key.iter().map(|&v| v.into()).collect::<Vec<i64>>();
+
+        self.keys = None;
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_remove_block(self.ptr, key.as_ptr(), key.len())
+        };
+        self.refresh_keys();
+        check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorBlock::from_raw(ptr) });
+    }
+
+    /// Replace the block at the given `index` with `block`, and return the
+    /// previous block. The new block must contain the same kind of data as
+    /// the other blocks, as checked by [`TensorMap::new`].
+    #[allow(clippy::needless_pass_by_value)]
+    #[inline]
+    pub fn replace_block(&mut self, index: usize, mut block: TensorBlock) -> Result<TensorBlock, Error> {
+        let block_ptr = block.as_mut_ptr();
+        // we give ownership of the block to the tensor map, so we should not
+        // free it again from here
+        std::mem::forget(block);
+
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_replace_block(self.ptr, index, block_ptr)
+        };
+        check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorBlock::from_raw(ptr) });
+    }
+
+    /// Remove all the blocks with keys matching one of the entries in
+    /// `selection` from this `TensorMap`.
+    ///
+    /// The selection can contain a subset of the dimensions of the keys, and
+    /// multiple entries. Entries without a corresponding block are ignored.
+    #[inline]
+    pub fn drop_blocks(&mut self, selection: &Labels) -> Result<(), Error> {
+        self.keys = None;
+        let status = unsafe {
+            crate::c_api::mts_tensormap_drop_blocks(self.ptr, selection.as_mts_labels_t())
+        };
+        self.refresh_keys();
+
+        return check_status(status);
+    }
+
+    /// Remove the gradients with respect to all the given `parameters` from
+    /// all the blocks in this `TensorMap`. Parameters without corresponding
+    /// gradients are ignored.
+    #[inline]
+    pub fn remove_gradients(&mut self, parameters: &[&str]) -> Result<(), Error> {
+        let parameters = parameters.iter()
+            .map(|&p| CString::new(p).expect("invalid C string"))
+            .collect::<Vec<_>>();
+        let parameters_ptr = parameters.iter().map(|p| p.as_ptr()).collect::<Vec<_>>();
+
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_remove_gradients(
+                self.ptr,
+                parameters_ptr.as_ptr(),
+                parameters_ptr.len(),
+            ))?;
+        }
+
+        return Ok(());
+    }
+
+    /// Use a single set of [`Labels`] for all the identical gradient samples
+    /// in this `TensorMap`, across all blocks and gradient parameters.
+    ///
+    /// This reduces the memory used by the gradient samples, and samples used
+    /// by more than one gradient are then stored only once when saving this
+    /// `TensorMap`.
+    #[inline]
+    pub fn share_gradient_samples(&mut self) -> Result<(), Error> {
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_share_gradient_samples(self.ptr))?;
+        }
+
+        return Ok(());
+    }
+
+    /// Update the cached keys after the set of blocks in this `TensorMap` was
+    /// modified
+    fn refresh_keys(&mut self) {
+        let mut keys = mts_labels_t::null();
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_keys(
+                self.ptr,
+                &mut keys
+            )).expect("failed to get the keys");
+
+            self.keys = Some(Labels::from_raw(keys));
+        }
+    }
+
     /// Get a reference to every blocks in this `TensorMap`
     #[inline]
     pub fn blocks(&self) -> Vec<TensorBlockRef<'_>> {
@@ -406,6 +756,10 @@ impl TensorMap {
     }
 
     /// Get an iterator over the keys and associated blocks
+    ///
+    /// # Panics
+    ///
+    /// If the keys use 64-bit values which do not fit in 32-bit integers.
     #[inline]
     pub fn iter(&self) -> TensorMapIter<'_> {
         return TensorMapIter {
@@ -457,6 +811,80 @@ impl TensorMap {
             inner: self.keys().par_iter().zip_eq(blocks)
         }
     }
+
+    /// Create a new `TensorMap` with the same keys as this one, where each
+    /// block is created by calling `function` with the key and block of this
+    /// `TensorMap`.
+    ///
+    /// The key is given to `function` as 64-bit values, which works for both
+    /// 32-bit and 64-bit keys. The keys are shared between the two
+    /// `TensorMap` without copying them. Errors returned by `function` are
+    /// prefixed with the corresponding key.
+    #[inline]
+    pub fn map_blocks<F>(&self, mut function: F) -> Result<TensorMap, Error>
+        where F: FnMut(&[i64], TensorBlockRef<'_>) -> Result<TensorBlock, Error>
+    {
+        let mut blocks = Vec::with_capacity(self.keys().count());
+        for (key, block) in self.keys().iter_i64().zip(self.blocks()) {
+            blocks.push(function(&key, block).map_err(|error| self.key_error(&key, error))?);
+        }
+
+        return TensorMap::new(self.keys().clone(), blocks);
+    }
+
+    /// Parallel version of [`TensorMap::map_blocks`], calling `function` on
+    /// multiple blocks at the same time.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_map_blocks<F>(&self, function: F) -> Result<TensorMap, Error>
+        where F: Fn(&[i64], TensorBlockRef<'_>) -> Result<TensorBlock, Error> + Sync + Send
+    {
+        use rayon::prelude::*;
+
+        let keys = self.keys().iter_i64().collect::<Vec<_>>();
+        let blocks = keys.par_iter().enumerate()
+            .map(|(i, key)| function(key, self.block_by_id(i)).map_err(|error| self.key_error(key, error)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        return TensorMap::new(self.keys().clone(), blocks);
+    }
+
+    /// Keep only the blocks for which `predicate` returns `true` in this
+    /// `TensorMap`. The other blocks are removed in place, without copying
+    /// data, and the key is given to `predicate` as 64-bit values.
+    #[inline]
+    pub fn filter_blocks<F>(mut self, mut predicate: F) -> Result<TensorMap, Error>
+        where F: FnMut(&[i64], TensorBlockRef<'_>) -> bool
+    {
+        let mut removed = LabelsBuilder::new_i64(self.keys().names());
+        for (key, block) in self.keys().iter_i64().zip(self.blocks()) {
+            if !predicate(&key, block) {
+                removed.add_i64(&key);
+            }
+        }
+
+        let removed = removed.finish();
+        if !removed.is_empty() {
+            self.drop_blocks(&removed)?;
+        }
+
+        return Ok(self);
+    }
+
+    /// Add the given `key` to the message of `error`
+    fn key_error(&self, key: &[i64], mut error: Error) -> Error {
+        let key = format_key(&self.keys().names(), key);
+        error.message = format!("error for the block with key ({}): {}", key, error.message);
+        return error;
+    }
+}
+
+/// Format a single key as `name=value` pairs
+fn format_key(names: &[&str], key: &[i64]) -> String {
+    return names.iter().zip(key)
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join(", ");
 }
 
 /******************************************************************************/
@@ -620,7 +1048,17 @@ impl<'a> rayon::iter::IndexedParallelIterator for TensorMapParIterMut<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Labels, TensorBlock, TensorMap};
+    use crate::{Error, Labels, LabelsDType, TensorBlock, TensorMap};
+
+    /// Create a block with a single sample and property, containing `value`
+    fn new_block(value: f64) -> TensorBlock {
+        return TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 1], value),
+            &Labels::new(["samples"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+    }
 
     #[test]
     #[allow(clippy::cast_lossless, clippy::float_cmp)]
@@ -663,4 +1101,437 @@ mod tests {
             assert_eq!(array[[0, 0]], 2.0 * (key[0].i32() as f64));
         }
     }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn insert_remove_blocks() {
+        let mut tensor = TensorMap::new(
+            Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2]]),
+            vec![new_block(1.0), new_block(2.0)],
+        ).unwrap();
+
+        // keys shared with another tensor map are not modified
+        let mapped = tensor.map_blocks(|_, block| block.try_clone()).unwrap();
+
+        tensor.insert_block(&[1, 1], new_block(3.0)).unwrap();
+        assert_eq!(*tensor.keys(), Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2], [1, 1]]));
+        assert_eq!(*mapped.keys(), Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2]]));
+        assert_eq!(tensor.block_by_id(2).values().as_array()[[0, 0]], 3.0);
+
+        let error = tensor.insert_block(&[2], new_block(4.0)).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: expected a key with 2 values to insert a block, got 1");
+        assert_eq!(tensor.keys().count(), 3);
+
+        let removed = tensor.remove_block(&[0, 2]).unwrap();
+        assert_eq!(removed.values().as_array()[[0, 0]], 2.0);
+        assert_eq!(*tensor.keys(), Labels::new(["key_1", "key_2"], &[[0, 1], [1, 1]]));
+
+        let previous = tensor.replace_block(0, new_block(5.0)).unwrap();
+        assert_eq!(previous.values().as_array()[[0, 0]], 1.0);
+        assert_eq!(tensor.block_by_id(0).values().as_array()[[0, 0]], 5.0);
+
+        let error = tensor.replace_block(3, new_block(6.0)).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: block index out of bounds: we have 2 blocks but the index is 3");
+
+        tensor.drop_blocks(&Labels::new(["key_1"], &[[1]])).unwrap();
+        assert_eq!(*tensor.keys(), Labels::new(["key_1", "key_2"], &[[0, 1]]));
+        assert_eq!(tensor.block_by_id(0).values().as_array()[[0, 0]], 5.0);
+    }
+
+    #[test]
+    fn keys_modified_in_place() {
+        let mut tensor = TensorMap::new(
+            Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2]]),
+            vec![new_block(1.0), new_block(2.0)],
+        ).unwrap();
+        // make sure the keys are only owned by this tensor map
+        tensor.insert_block(&[1, 1], new_block(3.0)).unwrap();
+
+        // the keys are updated in place by metatensor-core, without being
+        // rebuilt from scratch
+        let keys_ptr = tensor.keys().as_mts_labels_t().internal_ptr_;
+
+        tensor.insert_block(&[1, 2], new_block(4.0)).unwrap();
+        assert_eq!(tensor.keys().as_mts_labels_t().internal_ptr_, keys_ptr);
+        assert_eq!(*tensor.keys(), Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2], [1, 1], [1, 2]]));
+
+        tensor.remove_block(&[0, 2]).unwrap();
+        assert_eq!(tensor.keys().as_mts_labels_t().internal_ptr_, keys_ptr);
+        assert_eq!(*tensor.keys(), Labels::new(["key_1", "key_2"], &[[0, 1], [1, 1], [1, 2]]));
+
+        // keys shared with another Labels are copied before being modified
+        let keys = tensor.keys().clone();
+        tensor.remove_block(&[1, 1]).unwrap();
+        assert_ne!(tensor.keys().as_mts_labels_t().internal_ptr_, keys_ptr);
+        assert_eq!(keys.as_mts_labels_t().internal_ptr_, keys_ptr);
+        assert_eq!(keys.count(), 3);
+    }
+
+    #[test]
+    fn blocks_matching_many() {
+        let tensor = TensorMap::new(
+            Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2], [1, 1]]),
+            vec![new_block(1.0), new_block(1.0), new_block(1.0)],
+        ).unwrap();
+
+        let matching = tensor.blocks_matching_many(&Labels::new(["key_2"], &[[1], [3], [2]])).unwrap();
+        assert_eq!(matching, [vec![0, 2], vec![], vec![1]]);
+
+        let matching = tensor.blocks_matching_many(&Labels::new(["key_2", "key_1"], &[[1, 1], [1, 2]])).unwrap();
+        assert_eq!(matching, [vec![2], vec![]]);
+
+        let error = tensor.blocks_matching_many(&Labels::new(["key_3"], &[[1]])).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: 'key_3' is not part of the keys for this tensor");
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn block_by_key_get() {
+        let tensor = TensorMap::new(
+            Labels::new(["o3_lambda", "center_type", "neighbor_type"], &[[1, 6, 8], [1, 8, 8], [0, 6, 1]]),
+            vec![new_block(1.0), new_block(2.0), new_block(3.0)],
+        ).unwrap();
+
+        let block = tensor.block_by_key(&[1, 8, 8]).unwrap();
+        assert_eq!(block.values().as_array()[[0, 0]], 2.0);
+
+        let error = tensor.block_by_key(&[1, 6]).unwrap_err();
+        assert_eq!(error.message, "expected a key with 3 values, got 2");
+
+        let error = tensor.block_by_key(&[2, 6, 8]).unwrap_err();
+        assert_eq!(error.message,
+            "there is no block with key (o3_lambda=2, center_type=6, neighbor_type=8) \
+            in this TensorMap, available keys are [\
+            (o3_lambda=1, center_type=6, neighbor_type=8), \
+            (o3_lambda=1, center_type=8, neighbor_type=8), \
+            (o3_lambda=0, center_type=6, neighbor_type=1)]"
+        );
+
+        let block = tensor.get(&[("neighbor_type", 1)]).unwrap();
+        assert_eq!(block.values().as_array()[[0, 0]], 3.0);
+
+        let block = tensor.get(&[("neighbor_type", 8), ("center_type", 6), ("o3_lambda", 1)]).unwrap();
+        assert_eq!(block.values().as_array()[[0, 0]], 1.0);
+
+        let error = tensor.get(&[("center_type", 6)]).unwrap_err();
+        assert_eq!(error.message, "2 blocks matched the selection (center_type=6), expected only one");
+
+        let error = tensor.get(&[("center_type", 1)]).unwrap_err();
+        assert!(error.message.starts_with("no blocks matched the selection (center_type=1), available keys are ["));
+
+        let error = tensor.get(&[("species", 1)]).unwrap_err();
+        assert_eq!(error.message, "'species' is not part of the keys for this tensor");
+
+        let error = tensor.get(&[("center_type", 1), ("center_type", 6)]).unwrap_err();
+        assert_eq!(error.message, "'center_type' is present multiple times in the selection");
+
+        // 64-bit keys
+        let tensor = TensorMap::new(
+            Labels::new_i64(["key_1", "key_2"], &[[1, 1 << 40], [2, 1 << 40]]),
+            vec![new_block(4.0), new_block(5.0)],
+        ).unwrap();
+
+        let block = tensor.block_by_key(&[2, 1_i64 << 40]).unwrap();
+        assert_eq!(block.values().as_array()[[0, 0]], 5.0);
+
+        let block = tensor.get(&[("key_1", 1)]).unwrap();
+        assert_eq!(block.values().as_array()[[0, 0]], 4.0);
+
+        let error = tensor.get(&[("key_2", 1_i64 << 40)]).unwrap_err();
+        assert_eq!(error.message, "2 blocks matched the selection (key_2=1099511627776), expected only one");
+    }
+
+    #[test]
+    fn into_blocks() {
+        let tensor = TensorMap::new(
+            Labels::new(["key"], &[[0], [1]]),
+            vec![new_block(1.0), new_block(2.0)],
+        ).unwrap();
+        let data_ptr = tensor.block_by_id(1).values().as_array().as_ptr();
+
+        let (keys, mut blocks) = tensor.into_blocks();
+        assert_eq!(keys, Labels::new(["key"], &[[0], [1]]));
+        assert_eq!(blocks.len(), 2);
+
+        // the data is not copied
+        assert_eq!(blocks[1].values().as_array().as_ptr(), data_ptr);
+
+        blocks[0].as_ref_mut().values_mut().to_array_mut().fill(3.0);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+        assert_eq!(tensor.block_by_id(0).values().as_array(), ndarray::ArrayD::from_elem(vec![1, 1], 3.0));
+        assert_eq!(tensor.block_by_id(1).values().as_array().as_ptr(), data_ptr);
+
+        let empty = TensorMap::new(Labels::empty(vec!["key"]), vec![]).unwrap();
+        let (keys, blocks) = empty.into_blocks();
+        assert_eq!(keys.count(), 0);
+        assert!(blocks.is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn map_filter_blocks() {
+        let tensor = TensorMap::new(
+            Labels::new(["key"], &[[0], [1], [2]]),
+            vec![new_block(0.0), new_block(1.0), new_block(2.0)],
+        ).unwrap();
+
+        let doubled = tensor.map_blocks(|key, input| {
+            assert_eq!(input.values().as_array()[[0, 0]], f64::from(i32::try_from(key[0]).unwrap()));
+            Ok(new_block(2.0 * input.values().as_array()[[0, 0]]))
+        }).unwrap();
+        assert_eq!(*doubled.keys(), *tensor.keys());
+        assert_eq!(doubled.block_by_id(2).values().as_array()[[0, 0]], 4.0);
+
+        let error = tensor.map_blocks(|key, _| {
+            if key[0] == 1 {
+                Err(Error { code: None, message: "bad block".into() })
+            } else {
+                Ok(new_block(0.0))
+            }
+        }).unwrap_err();
+        assert_eq!(error.message, "error for the block with key (key=1): bad block");
+
+        #[cfg(feature = "rayon")]
+        {
+            let doubled = tensor.par_map_blocks(|_, input| {
+                Ok(new_block(2.0 * input.values().as_array()[[0, 0]]))
+            }).unwrap();
+            assert_eq!(doubled.block_by_id(1).values().as_array()[[0, 0]], 2.0);
+        }
+
+        let ptr = tensor.block_by_id(2).values().as_raw().ptr;
+        let filtered = tensor.filter_blocks(|key, _| key[0] != 1).unwrap();
+        assert_eq!(*filtered.keys(), Labels::new(["key"], &[[0], [2]]));
+        assert_eq!(filtered.block_by_id(1).values().as_raw().ptr, ptr);
+
+        // 64-bit keys
+        let tensor = TensorMap::new(
+            Labels::new_i64(["key"], &[[0], [1 << 40], [2]]),
+            vec![new_block(0.0), new_block(1.0), new_block(2.0)],
+        ).unwrap();
+
+        let error = tensor.map_blocks(|key, _| {
+            if key[0] == 1 << 40 {
+                Err(Error { code: None, message: "bad block".into() })
+            } else {
+                Ok(new_block(0.0))
+            }
+        }).unwrap_err();
+        assert_eq!(error.message, "error for the block with key (key=1099511627776): bad block");
+
+        let mapped = tensor.map_blocks(|_, input| input.try_clone()).unwrap();
+        assert_eq!(*mapped.keys(), *tensor.keys());
+
+        let filtered = tensor.filter_blocks(|key, _| key[0] != 2).unwrap();
+        assert_eq!(*filtered.keys(), Labels::new_i64(["key"], &[[0], [1 << 40]]));
+        assert_eq!(filtered.keys().dtype(), LabelsDType::Int64);
+
+        let filtered = filtered.filter_blocks(|_, _| true).unwrap();
+        assert_eq!(filtered.keys().count(), 2);
+
+        let filtered = filtered.filter_blocks(|_, _| false).unwrap();
+        assert_eq!(filtered.keys().count(), 0);
+    }
+
+    #[test]
+    fn remove_gradients() {
+        let properties = Labels::new(["properties"], &[[0]]);
+        let block = |parameters: &[&str]| {
+            let mut block = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &properties,
+            ).unwrap();
+            for parameter in parameters {
+                let gradient = TensorBlock::new(
+                    ndarray::ArrayD::from_elem(vec![1, 1], 2.0),
+                    &Labels::new(["sample"], &[[0]]),
+                    &[],
+                    &properties,
+                ).unwrap();
+                block.add_gradient(parameter, gradient).unwrap();
+            }
+            return block;
+        };
+
+        let mut tensor = TensorMap::new(
+            Labels::new(["key"], &[[0], [1]]),
+            vec![block(&["g", "h"]), block(&["g", "h"])],
+        ).unwrap();
+
+        tensor.remove_gradients(&["g", "missing"]).unwrap();
+        for block in tensor.blocks() {
+            assert_eq!(block.gradient_list(), ["h"]);
+        }
+
+        // gradients can not be taken out or added to a single block
+        let error = tensor.block_mut_by_id(0).take_gradient("h").unwrap_err();
+        assert_eq!(error.message,
+            "can not take gradients of a block inside a TensorMap, since all \
+            the blocks must have the same gradients"
+        );
+        let error = tensor.block_mut_by_id(0).add_gradient("g", new_block(1.0)).unwrap_err();
+        assert_eq!(error.message,
+            "can not add gradients of a block inside a TensorMap, since all \
+            the blocks must have the same gradients"
+        );
+        let error = tensor.block_mut_by_id(0).remove_gradient("h").unwrap_err();
+        assert_eq!(error.message,
+            "can not remove gradients of a block inside a TensorMap, since all \
+            the blocks must have the same gradients"
+        );
+        assert_eq!(tensor.block_by_id(0).gradient_list(), ["h"]);
+
+        tensor.remove_gradients(&["h"]).unwrap();
+        for block in tensor.blocks() {
+            assert!(block.gradient_list().is_empty());
+        }
+
+        // standalone blocks can add and take gradients
+        let mut standalone = block(&["g", "h"]);
+        let gradient = standalone.as_ref_mut().take_gradient("h").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample"], &[[0]]));
+        assert_eq!(standalone.as_ref().gradient_list(), ["g"]);
+        standalone.as_ref_mut().add_gradient("h", gradient).unwrap();
+        assert_eq!(standalone.as_ref().gradient_list(), ["g", "h"]);
+
+        let error = standalone.as_ref_mut().take_gradient("missing").unwrap_err();
+        assert_eq!(error.message,
+            "invalid parameter: can not find gradients with respect to 'missing' in this block"
+        );
+
+        // merging blocks with different gradients returns an error
+        let tensor = TensorMap::new(
+            Labels::new(["key"], &[[0], [1]]),
+            vec![block(&["g"]), block(&["g"])],
+        ).unwrap();
+        // go through the C API, since the Rust API prevents this
+        unsafe {
+            let mut block = std::ptr::null_mut();
+            crate::errors::check_status(crate::c_api::mts_tensormap_block_by_id(tensor.ptr, &mut block, 1)).unwrap();
+            let parameter = std::ffi::CString::new("g").unwrap();
+            crate::errors::check_status(crate::c_api::mts_block_remove_gradient(block, parameter.as_ptr())).unwrap();
+        }
+
+        let error = tensor.keys_to_samples(&Labels::empty(vec!["key"]), true).unwrap_err();
+        assert_eq!(error.message,
+            "invalid parameter: all blocks must have the same gradients, but \
+            some blocks are missing the gradient with respect to 'g'"
+        );
+    }
+
+    #[test]
+    fn to_origin() {
+        use crate::c_api::{mts_array_t, mts_data_origin_t, mts_status_t, MTS_SUCCESS};
+
+        unsafe extern "C" fn foreign_origin(_: *const std::os::raw::c_void, origin: *mut mts_data_origin_t) -> mts_status_t {
+            *origin = crate::data::register_data_origin("test.foreign".into()).unwrap();
+            return MTS_SUCCESS;
+        }
+
+        unsafe extern "C" fn to_foreign(array: *const mts_array_t, output: *mut mts_array_t) -> mts_status_t {
+            let mut converted = (*array).create((*array).shape().unwrap()).unwrap();
+            converted.copy_from(&*array).unwrap();
+            converted.origin = Some(foreign_origin);
+            std::ptr::write(output, converted);
+            return MTS_SUCCESS;
+        }
+
+        let mut block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 3], 1.0),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[-2], [0], [1]]),
+        ).unwrap();
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3], 2.0),
+            &Labels::new(["sample"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[-2], [0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+
+        let error = tensor.to_origin("test.unknown").unwrap_err();
+        assert_eq!(error.message, "unknown data origin 'test.unknown'");
+
+        crate::data::register_data_origin("test.foreign".into()).unwrap();
+        let error = tensor.to_origin("test.foreign").unwrap_err();
+        assert_eq!(error.message,
+            "invalid parameter: there is no registered converter from \
+            'rust.Box<dyn Array>' to 'test.foreign'"
+        );
+
+        // converting to the same origin makes a copy
+        let copy = tensor.to_origin("rust.Box<dyn Array>").unwrap();
+        assert_eq!(copy.block_by_id(0).values().as_array(), ndarray::ArrayD::from_elem(vec![2, 3], 1.0));
+
+        crate::register_array_converter("rust.Box<dyn Array>", "test.foreign", Some(to_foreign)).unwrap();
+        let converted = tensor.to_origin("test.foreign").unwrap();
+        let block = converted.block_by_id(0);
+
+        let error = block.values_as::<ndarray::ArrayD<f64>>().unwrap_err();
+        assert!(error.message.ends_with("(data origin is 'test.foreign')"));
+        let error = block.gradient_values_as::<ndarray::ArrayD<f64>>("g").unwrap_err();
+        assert!(error.message.ends_with("(data origin is 'test.foreign')"));
+
+        let block = block.to_origin("rust.Box<dyn Array>").unwrap_err();
+        assert_eq!(block.message,
+            "invalid parameter: there is no registered converter from \
+            'test.foreign' to 'rust.Box<dyn Array>'"
+        );
+    }
+
+    #[test]
+    fn save_with_converter() {
+        use crate::c_api::{mts_array_t, mts_data_origin_t, mts_status_t, MTS_SUCCESS, MTS_INVALID_PARAMETER_ERROR};
+        use crate::Array;
+
+        unsafe extern "C" fn no_data_origin(_: *const std::os::raw::c_void, origin: *mut mts_data_origin_t) -> mts_status_t {
+            *origin = crate::data::register_data_origin("test.no-data".into()).unwrap();
+            return MTS_SUCCESS;
+        }
+
+        unsafe extern "C" fn no_data(_: *mut std::os::raw::c_void, _: *mut *mut f64) -> mts_status_t {
+            return MTS_INVALID_PARAMETER_ERROR;
+        }
+
+        unsafe extern "C" fn to_no_data(array: *const mts_array_t, output: *mut mts_array_t) -> mts_status_t {
+            let mut converted = (*array).create((*array).shape().unwrap()).unwrap();
+            converted.copy_from(&*array).unwrap();
+            converted.origin = Some(no_data_origin);
+            converted.data = Some(no_data);
+            std::ptr::write(output, converted);
+            return MTS_SUCCESS;
+        }
+
+        unsafe extern "C" fn from_no_data(array: *const mts_array_t, output: *mut mts_array_t) -> mts_status_t {
+            // the array was created by `to_no_data`, and still contains a rust array
+            let array = &*(*array).ptr.cast::<Box<dyn Array>>();
+            std::ptr::write(output, array.copy().into());
+            return MTS_SUCCESS;
+        }
+
+        let block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 3], 1.0),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[-2], [0], [1]]),
+        ).unwrap();
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+
+        crate::register_array_converter("rust.Box<dyn Array>", "test.no-data", Some(to_no_data)).unwrap();
+        let converted = tensor.to_origin("test.no-data").unwrap();
+
+        let mut buffer = Vec::new();
+        assert!(converted.save_buffer(&mut buffer).is_err());
+
+        crate::register_array_converter("test.no-data", "rust.Box<dyn Array>", Some(from_no_data)).unwrap();
+        buffer.clear();
+        converted.save_buffer(&mut buffer).unwrap();
+
+        let loaded = TensorMap::load_buffer(&buffer).unwrap();
+        assert_eq!(loaded.block_by_id(0).values().as_array(), ndarray::ArrayD::from_elem(vec![2, 3], 1.0));
+    }
 }
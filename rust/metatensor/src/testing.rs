@@ -0,0 +1,560 @@
+//! Utilities to test code producing [`TensorMap`] with gradients.
+//!
+//! The main entry point is [`check_gradients`], comparing the gradients stored
+//! in a [`TensorMap`] with finite differences of the values.
+
+use std::collections::{BTreeSet, HashSet};
+
+use ndarray::ArrayD;
+
+use crate::c_api::mts_array_t;
+use crate::dlpack::DLPackArray;
+use crate::errors::check_status;
+use crate::{ArrayRef, Error, Labels, SparseArray, TensorBlockRef, TensorMap};
+
+/// A single displacement of the inputs used to compute a [`TensorMap`],
+/// passed to the closure given to [`check_gradients`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Displacement {
+    /// Move the `atom` in the `system` by `delta` along the cartesian axis
+    /// `xyz` (0 for x, 1 for y and 2 for z).
+    Positions {
+        system: i64,
+        atom: i64,
+        xyz: usize,
+        delta: f64,
+    },
+    /// Deform the `system` (both the atomic positions and the cell) with a
+    /// strain matrix containing `delta` at (`xyz_1`, `xyz_2`) and zeros
+    /// everywhere else, i.e. transform each position `r` into `(1 + ε) r`.
+    Strain {
+        system: i64,
+        xyz_1: usize,
+        xyz_2: usize,
+        delta: f64,
+    },
+}
+
+/// Maximal error between the gradients stored in a single block and the
+/// corresponding finite differences, as computed by [`check_gradients`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientError {
+    /// Key of the block containing these gradients
+    pub key: Vec<i64>,
+    /// Parameter of the gradients, either `"positions"` or `"strain"`
+    pub parameter: String,
+    /// Maximal absolute error between the gradients and finite differences
+    pub max_absolute_error: f64,
+    /// Maximal relative error between the gradients and finite differences,
+    /// relative to the largest of the two values
+    pub max_relative_error: f64,
+}
+
+/// Check the `"positions"` and `"strain"` gradients stored in `reference`
+/// against finite differences of the values.
+///
+/// The `compute` closure should re-compute the same `TensorMap` as
+/// `reference` for inputs modified by the given [`Displacement`]. The values
+/// are computed for displacements of `+delta` and `-delta` along each
+/// direction, and the resulting central finite differences are compared with
+/// the stored gradients. Entries without a corresponding gradient sample are
+/// expected to have a zero finite difference.
+///
+/// The `"positions"` gradients must have `["sample", "system", "atom"]` as
+/// their samples names, with each `(sample, atom)` pair appearing only once,
+/// and a first component with 3 entries. The `"strain"` gradients must have
+/// `["sample"]` as their samples names and two first components with 3
+/// entries each, and the values samples must contain a `"system"` dimension.
+/// Gradients with respect to any other parameter are ignored.
+///
+/// This returns the maximal errors for each block and parameter, in the same
+/// order as the blocks in `reference`.
+pub fn check_gradients<F>(reference: &TensorMap, delta: f64, mut compute: F) -> Result<Vec<GradientError>, Error>
+    where F: FnMut(Displacement) -> Result<TensorMap, Error>
+{
+    if delta.is_nan() || delta <= 0.0 {
+        return Err(Error {
+            code: None,
+            message: format!("the finite difference displacement must be positive, got {}", delta),
+        });
+    }
+
+    let mut errors = Vec::new();
+    // index in `errors` for the gradients of each block, if any
+    let mut positions_errors = Vec::new();
+    let mut strain_errors = Vec::new();
+
+    let mut atoms = BTreeSet::new();
+    let mut systems = BTreeSet::new();
+    for (key, block) in reference.keys().iter_i64().zip(reference.blocks()) {
+        positions_errors.push(None);
+        strain_errors.push(None);
+
+        if let Some(gradient) = block.gradient("positions") {
+            check_gradient_metadata(block, gradient, "positions", 1)?;
+            check_unique_atoms(gradient)?;
+
+            for sample in gradient.samples().iter_i64() {
+                atoms.insert((sample[1], sample[2]));
+            }
+
+            *positions_errors.last_mut().expect("missing block") = Some(errors.len());
+            errors.push(GradientError::new(&key, "positions"));
+        }
+
+        if let Some(gradient) = block.gradient("strain") {
+            check_gradient_metadata(block, gradient, "strain", 2)?;
+            for system in system_column(block)? {
+                systems.insert(system);
+            }
+
+            *strain_errors.last_mut().expect("missing block") = Some(errors.len());
+            errors.push(GradientError::new(&key, "strain"));
+        }
+    }
+
+    for &(system, atom) in &atoms {
+        for xyz in 0..3 {
+            let displacement = |delta| Displacement::Positions { system, atom, xyz, delta };
+            let plus = compute_checked(reference, &mut compute, displacement(delta))?;
+            let minus = compute_checked(reference, &mut compute, displacement(-delta))?;
+
+            for (block_i, block) in reference.blocks().iter().enumerate() {
+                let Some(gradient) = block.gradient("positions") else {
+                    continue;
+                };
+
+                let gradient_samples = gradient.samples();
+                let expected = |sample: usize| {
+                    gradient_samples.position_i64(&[to_i64(sample), system, atom]).map(|row| 3 * row + xyz)
+                };
+
+                let error_i = positions_errors[block_i].expect("missing error entry");
+                errors[error_i].update(
+                    *block, gradient, plus.block_by_id(block_i), minus.block_by_id(block_i), delta, expected
+                )?;
+            }
+        }
+    }
+
+    for &system in &systems {
+        for xyz_1 in 0..3 {
+            for xyz_2 in 0..3 {
+                let displacement = |delta| Displacement::Strain { system, xyz_1, xyz_2, delta };
+                let plus = compute_checked(reference, &mut compute, displacement(delta))?;
+                let minus = compute_checked(reference, &mut compute, displacement(-delta))?;
+
+                for (block_i, block) in reference.blocks().iter().enumerate() {
+                    let Some(gradient) = block.gradient("strain") else {
+                        continue;
+                    };
+
+                    let gradient_samples = gradient.samples();
+                    let sample_systems = system_column(*block)?;
+                    let expected = |sample: usize| {
+                        if sample_systems[sample] != system {
+                            return None;
+                        }
+                        gradient_samples.position_i64(&[to_i64(sample)]).map(|row| 9 * row + 3 * xyz_1 + xyz_2)
+                    };
+
+                    let error_i = strain_errors[block_i].expect("missing error entry");
+                    errors[error_i].update(
+                        *block, gradient, plus.block_by_id(block_i), minus.block_by_id(block_i), delta, expected
+                    )?;
+                }
+            }
+        }
+    }
+
+    return Ok(errors);
+}
+
+impl GradientError {
+    fn new(key: &[i64], parameter: &str) -> GradientError {
+        GradientError {
+            key: key.to_vec(),
+            parameter: parameter.into(),
+            max_absolute_error: 0.0,
+            max_relative_error: 0.0,
+        }
+    }
+
+    /// Update the maximal errors with the finite differences between `plus`
+    /// and `minus`. `expected_row` gives the row in the gradient data
+    /// (flattened over the samples and gradient-specific components) for a
+    /// given values sample, or `None` if the gradient should be zero.
+    fn update(
+        &mut self,
+        block: TensorBlockRef<'_>,
+        gradient: TensorBlockRef<'_>,
+        plus: TensorBlockRef<'_>,
+        minus: TensorBlockRef<'_>,
+        delta: f64,
+        expected_row: impl Fn(usize) -> Option<usize>,
+    ) -> Result<(), Error> {
+        let plus = array_data(plus.values())?;
+        let minus = array_data(minus.values())?;
+        let gradient = array_data(gradient.values())?;
+
+        let n_samples = block.samples().count();
+        let row_size = plus.len().checked_div(n_samples).unwrap_or(0);
+
+        for sample in 0..n_samples {
+            let row = expected_row(sample);
+            for i in 0..row_size {
+                let index = sample * row_size + i;
+                let finite_difference = (plus[index] - minus[index]) / (2.0 * delta);
+                let expected = row.map_or(0.0, |row| gradient[row * row_size + i]);
+
+                let absolute = (finite_difference - expected).abs();
+                let scale = f64::max(finite_difference.abs(), expected.abs());
+                let relative = if scale == 0.0 { 0.0 } else { absolute / scale };
+
+                self.max_absolute_error = f64::max(self.max_absolute_error, absolute);
+                self.max_relative_error = f64::max(self.max_relative_error, relative);
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/// Call `compute` with the given `displacement`, and check that the result
+/// has the same metadata as the `reference`
+fn compute_checked<F>(reference: &TensorMap, compute: &mut F, displacement: Displacement) -> Result<TensorMap, Error>
+    where F: FnMut(Displacement) -> Result<TensorMap, Error>
+{
+    let tensor = compute(displacement)?;
+
+    let mismatch = |what: &str| Error {
+        code: None,
+        message: format!(
+            "the TensorMap computed for {:?} has different {} than the reference",
+            displacement, what
+        ),
+    };
+
+    if *tensor.keys() != *reference.keys() {
+        return Err(mismatch("keys"));
+    }
+
+    for (block, reference) in tensor.blocks().iter().zip(reference.blocks()) {
+        if block.samples() != reference.samples() {
+            return Err(mismatch("samples"));
+        }
+
+        if block.components() != reference.components() {
+            return Err(mismatch("components"));
+        }
+
+        if block.properties() != reference.properties() {
+            return Err(mismatch("properties"));
+        }
+    }
+
+    return Ok(tensor);
+}
+
+/// Check the samples and components of a `"positions"` or `"strain"`
+/// gradient, with `n_cartesian` gradient-specific components of size 3
+fn check_gradient_metadata(
+    block: TensorBlockRef<'_>,
+    gradient: TensorBlockRef<'_>,
+    parameter: &str,
+    n_cartesian: usize,
+) -> Result<(), Error> {
+    let expected_names: &[&str] = if parameter == "positions" {
+        &["sample", "system", "atom"]
+    } else {
+        &["sample"]
+    };
+
+    let samples = gradient.samples();
+    if samples.names() != expected_names {
+        return Err(Error {
+            code: None,
+            message: format!(
+                "expected the samples of '{}' gradients to be [{}], got [{}]",
+                parameter, expected_names.join(", "), samples.names().join(", ")
+            ),
+        });
+    }
+
+    let components = gradient.components();
+    let n_extra = components.len() - block.components().len();
+    if n_extra != n_cartesian || components[..n_extra].iter().any(|c| c.count() != 3) {
+        return Err(Error {
+            code: None,
+            message: format!(
+                "expected the '{}' gradients to have {} cartesian component(s) \
+                with 3 entries before the values components",
+                parameter, n_cartesian
+            ),
+        });
+    }
+
+    return Ok(());
+}
+
+/// Check that each (sample, atom) pair only appears once in the samples of a
+/// `"positions"` gradient
+fn check_unique_atoms(gradient: TensorBlockRef<'_>) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    for sample in gradient.samples().iter_i64() {
+        if !seen.insert((sample[0], sample[2])) {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "the samples of 'positions' gradients contain multiple \
+                    entries for sample {} and atom {}", sample[0], sample[2]
+                ),
+            });
+        }
+    }
+
+    return Ok(());
+}
+
+/// Get the `"system"` dimension of the samples of this block
+fn system_column(block: TensorBlockRef<'_>) -> Result<Vec<i64>, Error> {
+    let samples: Labels = block.samples();
+    let index = samples.names().iter().position(|&name| name == "system").ok_or_else(|| Error {
+        code: None,
+        message: "the values samples must contain a 'system' dimension to check 'strain' gradients".into(),
+    })?;
+
+    return Ok(samples.iter_i64().map(|sample| sample[index]).collect());
+}
+
+/// Convert a sample index to a 64-bit label value
+fn to_i64(sample: usize) -> i64 {
+    return i64::try_from(sample).expect("sample index does not fit in i64");
+}
+
+/// Get the data of an array as a vector of `f64`, in row-major order
+fn array_data(array: ArrayRef<'_>) -> Result<Vec<f64>, Error> {
+    if let Ok(array) = array.try_as::<ArrayD<f64>>() {
+        return Ok(array.iter().copied().collect());
+    }
+
+    if let Ok(array) = array.try_as::<ArrayD<f32>>() {
+        return Ok(array.iter().map(|&x| f64::from(x)).collect());
+    }
+
+    if let Ok(array) = array.try_as::<SparseArray>() {
+        return Ok(array.to_dense().into_iter().collect());
+    }
+
+    if let Some(view) = array.try_as::<DLPackArray>().ok().and_then(|array| array.view_f32()) {
+        return Ok(view.iter().map(|&x| f64::from(x)).collect());
+    }
+
+    #[cfg(feature = "nalgebra")]
+    if let Ok(array) = array.try_as::<crate::NalgebraArray>() {
+        return Ok(array.matrix().transpose().iter().copied().collect());
+    }
+
+    #[cfg(feature = "faer")]
+    if let Ok(array) = array.try_as::<crate::FaerArray>() {
+        let matrix = array.matrix();
+        return Ok((0..matrix.nrows())
+            .flat_map(|i| (0..matrix.ncols()).map(move |j| matrix[(i, j)]))
+            .collect()
+        );
+    }
+
+    // for any other array, use `mts_array_t.data` on a copy of the array,
+    // since we only have shared access to the array itself
+    let raw = array.as_raw();
+    let copy_function = raw.copy.expect("mts_array_t.copy function is NULL");
+    let mut copy = mts_array_t::null();
+    unsafe {
+        check_status(copy_function(raw.ptr, &mut copy))?;
+    }
+
+    let data = copy.data().map(|data| data.to_vec());
+    if let Some(destroy) = copy.destroy {
+        unsafe { destroy(copy.ptr) };
+    }
+
+    return data.map_err(|error| Error {
+        code: None,
+        message: format!("check_gradients could not access the values of a block: {}", error.message),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::ArrayD;
+
+    use crate::{Labels, SparseArray, TensorBlock, TensorMap};
+    use super::{array_data, check_gradients, Displacement};
+
+    /// Create labels with 32-bit or 64-bit values
+    fn labels<const N: usize>(names: [&str; N], values: &[[i64; N]], int64: bool) -> Labels {
+        if int64 {
+            return Labels::new_i64(names, values);
+        }
+        let values = values.iter().map(|entry| entry.map(|v| i32::try_from(v).unwrap())).collect::<Vec<_>>();
+        return Labels::new(names, &values);
+    }
+
+    /// Compute `|r|^2` for each atom, with gradients with respect to positions
+    /// and strain. The positions gradients are multiplied by `scale`. With
+    /// `int64`, the keys and samples use 64-bit values and the positions
+    /// gradients are stored in a `SparseArray`.
+    fn compute(positions: &[[f64; 3]], scale: f64, int64: bool) -> TensorMap {
+        let samples = labels(["system", "atom"], &[[0, 0], [0, 1]], int64);
+        let properties = Labels::new(["p"], &[[0]]);
+
+        let values = positions.iter().map(|r| r.iter().map(|x| x * x).sum()).collect::<Vec<f64>>();
+        let mut block = TensorBlock::new(
+            ArrayD::from_shape_vec(vec![2, 1], values).unwrap(), &samples, &[], &properties
+        ).unwrap();
+
+        let mut gradient = ArrayD::from_elem(vec![2, 3, 1], 0.0);
+        for (atom, r) in positions.iter().enumerate() {
+            for xyz in 0..3 {
+                gradient[[atom, xyz, 0]] = scale * 2.0 * r[xyz];
+            }
+        }
+        let gradient_samples = labels(["sample", "system", "atom"], &[[0, 0, 0], [1, 0, 1]], int64);
+        let gradient_components = [Labels::new(["xyz"], &[[0], [1], [2]])];
+        let gradient = if int64 {
+            TensorBlock::new(SparseArray::from_dense(&gradient), &gradient_samples, &gradient_components, &properties)
+        } else {
+            TensorBlock::new(gradient, &gradient_samples, &gradient_components, &properties)
+        }.unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+
+        let mut gradient = ArrayD::from_elem(vec![2, 3, 3, 1], 0.0);
+        for (atom, r) in positions.iter().enumerate() {
+            for xyz_1 in 0..3 {
+                for xyz_2 in 0..3 {
+                    gradient[[atom, xyz_1, xyz_2, 0]] = 2.0 * r[xyz_1] * r[xyz_2];
+                }
+            }
+        }
+        let gradient = TensorBlock::new(
+            gradient,
+            &Labels::new(["sample"], &[[0], [1]]),
+            &[Labels::new(["xyz_1"], &[[0], [1], [2]]), Labels::new(["xyz_2"], &[[0], [1], [2]])],
+            &properties,
+        ).unwrap();
+        block.add_gradient("strain", gradient).unwrap();
+
+        return TensorMap::new(labels(["key"], &[[0]], int64), vec![block]).unwrap();
+    }
+
+    fn displaced(positions: &[[f64; 3]], displacement: Displacement) -> Vec<[f64; 3]> {
+        let mut positions = positions.to_vec();
+        match displacement {
+            Displacement::Positions { atom, xyz, delta, .. } => {
+                positions[usize::try_from(atom).unwrap()][xyz] += delta;
+            }
+            Displacement::Strain { xyz_1, xyz_2, delta, .. } => {
+                for r in &mut positions {
+                    r[xyz_1] += delta * r[xyz_2];
+                }
+            }
+        }
+        return positions;
+    }
+
+    #[test]
+    fn finite_differences() {
+        let positions = [[0.1, -0.3, 1.2], [0.7, 0.2, -0.5]];
+
+        let reference = compute(&positions, 1.0, false);
+        let errors = check_gradients(&reference, 1e-6, |displacement| {
+            Ok(compute(&displaced(&positions, displacement), 1.0, false))
+        }).unwrap();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].key, [0]);
+        assert_eq!(errors[0].parameter, "positions");
+        assert_eq!(errors[1].parameter, "strain");
+        for error in &errors {
+            assert!(error.max_absolute_error < 1e-6, "{:?}", error);
+            assert!(error.max_relative_error < 1e-6, "{:?}", error);
+        }
+
+        // wrong positions gradients are detected
+        let reference = compute(&positions, 2.0, false);
+        let errors = check_gradients(&reference, 1e-6, |displacement| {
+            Ok(compute(&displaced(&positions, displacement), 2.0, false))
+        }).unwrap();
+        assert!(errors[0].max_relative_error > 0.4);
+        assert!(errors[1].max_relative_error < 1e-6);
+
+        let error = check_gradients(&reference, 0.0, |_| unreachable!()).unwrap_err();
+        assert_eq!(error.message, "the finite difference displacement must be positive, got 0");
+    }
+
+    #[test]
+    fn int64_labels_and_other_arrays() {
+        let positions = [[0.1, -0.3, 1.2], [0.7, 0.2, -0.5]];
+
+        let reference = compute(&positions, 1.0, true);
+        let errors = check_gradients(&reference, 1e-6, |displacement| {
+            Ok(compute(&displaced(&positions, displacement), 1.0, true))
+        }).unwrap();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].key, [0]);
+        for error in &errors {
+            assert!(error.max_absolute_error < 1e-6, "{:?}", error);
+            assert!(error.max_relative_error < 1e-6, "{:?}", error);
+        }
+
+        let block = TensorBlock::new(
+            ArrayD::<f32>::from_elem(vec![2, 1], 1.5), &Labels::new(["s"], &[[0], [1]]), &[], &Labels::new(["p"], &[[0]])
+        ).unwrap();
+        assert_eq!(array_data(block.values()).unwrap(), [1.5, 1.5]);
+
+        // other arrays go through `mts_array_t.data`
+        let block = TensorBlock::new(
+            crate::VecArray::new(vec![1.0, 2.0], vec![2, 1]), &Labels::new(["s"], &[[0], [1]]), &[], &Labels::new(["p"], &[[0]])
+        ).unwrap();
+        assert_eq!(array_data(block.values()).unwrap(), [1.0, 2.0]);
+
+        #[cfg(feature = "nalgebra")]
+        {
+            let matrix = ::nalgebra::DMatrix::from_row_slice(2, 2, &[1.0, 2.0, 3.0, 4.0]);
+            let block = TensorBlock::new(
+                crate::NalgebraArray::new(matrix), &Labels::new(["s"], &[[0], [1]]), &[], &Labels::new(["p"], &[[0], [1]])
+            ).unwrap();
+            assert_eq!(array_data(block.values()).unwrap(), [1.0, 2.0, 3.0, 4.0]);
+        }
+
+        #[cfg(feature = "faer")]
+        {
+            let block = TensorBlock::new(
+                crate::FaerArray::new(::faer::mat![[1.0, 2.0], [3.0, 4.0]]), &Labels::new(["s"], &[[0], [1]]), &[], &Labels::new(["p"], &[[0], [1]])
+            ).unwrap();
+            assert_eq!(array_data(block.values()).unwrap(), [1.0, 2.0, 3.0, 4.0]);
+        }
+    }
+
+    #[test]
+    fn invalid_gradients() {
+        let properties = Labels::new(["p"], &[[0]]);
+        let mut block = TensorBlock::new(
+            ArrayD::from_elem(vec![1, 1], 0.0), &Labels::new(["system"], &[[0]]), &[], &properties
+        ).unwrap();
+        let gradient = TensorBlock::new(
+            ArrayD::from_elem(vec![2, 3, 1], 0.0),
+            &Labels::new(["sample", "system", "atom"], &[[0, 0, 0], [0, 1, 0]]),
+            &[Labels::new(["xyz"], &[[0], [1], [2]])],
+            &properties,
+        ).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+
+        let error = check_gradients(&tensor, 1e-6, |_| unreachable!()).unwrap_err();
+        assert_eq!(error.message,
+            "the samples of 'positions' gradients contain multiple entries for sample 0 and atom 0"
+        );
+    }
+}
@@ -3,7 +3,7 @@
 use metatensor::{Labels, TensorMap};
 
 mod utils;
-use utils::{example_tensor, example_block, example_labels};
+use utils::{example_tensor, example_block, example_labels, example_tensor_nested_gradients};
 
 use ndarray::ArrayD;
 
@@ -380,3 +380,32 @@ fn user_provided_entries() {
         ])
     );
 }
+
+#[test]
+fn nested_gradients() {
+    let keys_to_move = Labels::empty(vec!["key_2"]);
+    let tensor = example_tensor_nested_gradients().keys_to_properties(&keys_to_move, true).unwrap();
+
+    assert_eq!(tensor.keys().count(), 1);
+    let block = tensor.block_by_id(0);
+    assert_eq!(block.samples(), Labels::new(["samples"], &[[0], [1], [2]]));
+    assert_eq!(block.properties(), Labels::new(["key_2", "properties"], &[[0, 0], [0, 1], [1, 0], [1, 1]]));
+
+    let gradient = block.gradient("parameter").unwrap();
+    assert_eq!(gradient.samples(), Labels::new(["sample", "parameter"], &[[0, 1], [2, 2], [2, 3]]));
+    let expected = ArrayD::from_shape_vec(vec![3, 4], vec![
+        11.0, 11.0, 0.0, 0.0,
+        11.0, 11.0, 0.0, 0.0,
+        0.0, 0.0, 12.0, 12.0,
+    ]).unwrap();
+    assert_eq!(gradient.values().as_array(), expected);
+
+    let nested = gradient.gradient("other").unwrap();
+    assert_eq!(nested.samples(), Labels::new(["sample", "other"], &[[1, 5], [2, 4]]));
+    assert_eq!(nested.properties(), block.properties());
+    let expected = ArrayD::from_shape_vec(vec![2, 4], vec![
+        101.0, 101.0, 0.0, 0.0,
+        0.0, 0.0, 102.0, 102.0,
+    ]).unwrap();
+    assert_eq!(nested.values().as_array(), expected);
+}
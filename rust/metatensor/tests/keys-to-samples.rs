@@ -1,7 +1,7 @@
 use metatensor::{Labels, TensorMap};
 
 mod utils;
-use utils::{example_tensor, example_block};
+use utils::{example_tensor, example_block, example_tensor_nested_gradients};
 
 use ndarray::ArrayD;
 
@@ -153,3 +153,30 @@ fn empty_samples() {
         ])
     );
 }
+
+#[test]
+fn nested_gradients() {
+    let keys_to_move = Labels::empty(vec!["key_2"]);
+    let tensor = example_tensor_nested_gradients().keys_to_samples(&keys_to_move, true).unwrap();
+
+    assert_eq!(tensor.keys().count(), 1);
+    let block = tensor.block_by_id(0);
+    assert_eq!(block.samples(), Labels::new(["samples", "key_2"], &[[0, 0], [1, 1], [2, 0], [2, 1]]));
+
+    let gradient = block.gradient("parameter").unwrap();
+    assert_eq!(gradient.samples(), Labels::new(["sample", "parameter"], &[[0, 1], [2, 2], [3, 3]]));
+    let expected = ArrayD::from_shape_vec(vec![3, 2], vec![
+        11.0, 11.0,
+        11.0, 11.0,
+        12.0, 12.0,
+    ]).unwrap();
+    assert_eq!(gradient.values().as_array(), expected);
+
+    let nested = gradient.gradient("other").unwrap();
+    assert_eq!(nested.samples(), Labels::new(["sample", "other"], &[[1, 5], [2, 4]]));
+    let expected = ArrayD::from_shape_vec(vec![2, 2], vec![
+        101.0, 101.0,
+        102.0, 102.0,
+    ]).unwrap();
+    assert_eq!(nested.values().as_array(), expected);
+}
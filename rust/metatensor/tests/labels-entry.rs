@@ -0,0 +1,57 @@
+#![cfg(feature = "derive")]
+
+use metatensor::{labels, LabelsEntry};
+
+#[derive(Debug, PartialEq, LabelsEntry)]
+struct Sample {
+    system: i32,
+    atom: i64,
+}
+
+#[derive(Debug, PartialEq, LabelsEntry)]
+struct Raw {
+    r#type: i32,
+}
+
+#[test]
+fn derive() {
+    assert_eq!(Sample::NAMES, ["system", "atom"]);
+    assert_eq!(Raw::NAMES, ["type"]);
+
+    let labels = labels!{"system", "atom" => [0, 1], [0, 2], [1, 0]};
+    let samples = labels.iter_as::<Sample>().collect::<Vec<_>>();
+    assert_eq!(samples, [
+        Sample { system: 0, atom: 1 },
+        Sample { system: 0, atom: 2 },
+        Sample { system: 1, atom: 0 },
+    ]);
+
+    let labels = labels!{"type" => [6], [8]};
+    let types = labels.iter_as::<Raw>().map(|raw| raw.r#type).collect::<Vec<_>>();
+    assert_eq!(types, [6, 8]);
+}
+
+#[test]
+fn derive_i64() {
+    let large = i64::from(i32::MAX) + 1;
+    let labels = metatensor::Labels::new_i64(["system", "atom"], &[[0, large], [3, -large]]);
+    let samples = labels.iter_as::<Sample>().collect::<Vec<_>>();
+    assert_eq!(samples, [
+        Sample { system: 0, atom: large },
+        Sample { system: 3, atom: -large },
+    ]);
+}
+
+#[test]
+#[should_panic(expected = "label value 2147483648 for 'type' does not fit in the type of the field")]
+fn derive_overflow() {
+    let labels = metatensor::Labels::new_i64(["type"], &[[i64::from(i32::MAX) + 1]]);
+    let _ = labels.iter_as::<Raw>().collect::<Vec<_>>();
+}
+
+#[test]
+#[should_panic(expected = "wrong label names in `iter_as`")]
+fn wrong_names() {
+    let labels = labels!{"atom", "system" => [0, 1]};
+    let _ = labels.iter_as::<Sample>();
+}
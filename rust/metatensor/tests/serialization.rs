@@ -1,7 +1,9 @@
+mod utils;
+
 mod tensor {
     use std::io::Read;
 
-    use metatensor::TensorMap;
+    use metatensor::{Labels, TensorBlock, TensorMap};
 
     const DATA_PATH: &str = "../../metatensor-core/tests/data.npz";
 
@@ -44,6 +46,111 @@ mod tensor {
         assert_eq!(buffer, saved);
     }
 
+    #[test]
+    fn nested_gradients() {
+        let tensor = super::utils::example_tensor_nested_gradients();
+
+        let mut buffer = Vec::new();
+        tensor.save_buffer(&mut buffer).unwrap();
+        let loaded = TensorMap::load_buffer(&buffer).unwrap();
+
+        assert_eq!(loaded.keys(), tensor.keys());
+        for (block, loaded_block) in tensor.blocks().iter().zip(loaded.blocks()) {
+            let gradients = block.gradients_tree().collect::<Vec<_>>();
+            let loaded_gradients = loaded_block.gradients_tree().collect::<Vec<_>>();
+            assert_eq!(gradients.len(), 2);
+            assert_eq!(gradients.len(), loaded_gradients.len());
+
+            for ((path, gradient), (loaded_path, loaded_gradient)) in gradients.iter().zip(&loaded_gradients) {
+                assert_eq!(path, loaded_path);
+                assert_eq!(gradient.samples(), loaded_gradient.samples());
+                assert_eq!(gradient.values().as_array(), loaded_gradient.values().as_array());
+            }
+        }
+    }
+
+    #[test]
+    fn shared_gradient_samples() {
+        fn contains(buffer: &[u8], pattern: &str) -> usize {
+            buffer.windows(pattern.len()).filter(|w| *w == pattern.as_bytes()).count()
+        }
+
+        let samples = Labels::new(["s"], &[[0], [1]]);
+        let properties = Labels::new(["p"], &[[0]]);
+        let gradient_samples = Labels::new(["sample", "atom"], &[[0, 0], [0, 1], [1, 1]]);
+
+        let mut blocks = Vec::new();
+        for _ in 0..2 {
+            let mut block = TensorBlock::new(ndarray::ArrayD::from_elem(vec![2, 1], 1.0), &samples, &[], &properties).unwrap();
+            for parameter in ["a", "b"] {
+                // separate (but identical) labels for each gradient
+                let gradient = TensorBlock::new(
+                    ndarray::ArrayD::from_elem(vec![3, 1], 2.0),
+                    &Labels::new(["sample", "atom"], &[[0, 0], [0, 1], [1, 1]]),
+                    &[],
+                    &properties,
+                ).unwrap();
+                block.add_gradient(parameter, gradient).unwrap();
+            }
+            blocks.push(block);
+        }
+        let mut tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), blocks).unwrap();
+
+        // labels are only stored once after calling share_gradient_samples,
+        // and when explicitly requesting the shared layout
+        let mut buffer = Vec::new();
+        metatensor::io::save_buffer_shared_gradient_samples(&tensor, &mut buffer).unwrap();
+        assert_eq!(contains(&buffer, "gradient_samples/"), 0);
+
+        tensor.share_gradient_samples().unwrap();
+        let mut buffer = Vec::new();
+        tensor.save_buffer(&mut buffer).unwrap();
+        assert_eq!(contains(&buffer, "gradient_samples/"), 0);
+        assert_eq!(contains(&buffer, "samples_ref.npy"), 0);
+
+        let mut buffer = Vec::new();
+        metatensor::io::save_buffer_shared_gradient_samples(&tensor, &mut buffer).unwrap();
+        // file names are stored twice in ZIP files, in the local header and
+        // in the central directory
+        assert_eq!(contains(&buffer, "gradient_samples/version.npy"), 2);
+        assert_eq!(contains(&buffer, "gradient_samples/0.npy"), 2);
+        assert_eq!(contains(&buffer, "gradient_samples/1.npy"), 0);
+        assert_eq!(contains(&buffer, "samples_ref.npy"), 2 * 4);
+
+        let loaded = TensorMap::load_buffer(&buffer).unwrap();
+        for block in loaded.blocks() {
+            let mut parameters = block.gradient_list();
+            parameters.sort_unstable();
+            assert_eq!(parameters, ["a", "b"]);
+            for (_, gradient) in block.gradients() {
+                assert_eq!(gradient.samples(), gradient_samples);
+                assert_eq!(gradient.values().as_array(), ndarray::ArrayD::from_elem(vec![3, 1], 2.0));
+            }
+        }
+
+        // sharing is kept when saving the loaded tensor again
+        let mut saved = Vec::new();
+        metatensor::io::save_buffer_shared_gradient_samples(&loaded, &mut saved).unwrap();
+        assert_eq!(contains(&saved, "gradient_samples/0.npy"), 2);
+        assert_eq!(contains(&saved, "samples_ref.npy"), 2 * 4);
+
+        // the shared samples are found again when saving, after removing some
+        // of the gradients using them
+        let mut loaded = loaded;
+        loaded.remove_gradients(&["b"]).unwrap();
+        let mut saved = Vec::new();
+        metatensor::io::save_buffer_shared_gradient_samples(&loaded, &mut saved).unwrap();
+        assert_eq!(contains(&saved, "gradient_samples/0.npy"), 2);
+        assert_eq!(contains(&saved, "samples_ref.npy"), 2 * 2);
+
+        // the default layout can also be used for the loaded tensor
+        let mut saved = Vec::new();
+        loaded.save_buffer(&mut saved).unwrap();
+        assert_eq!(contains(&saved, "gradient_samples/"), 0);
+        let loaded = TensorMap::load_buffer(&saved).unwrap();
+        assert_eq!(loaded.block_by_id(0).gradient("a").unwrap().samples(), gradient_samples);
+    }
+
     fn check_tensor(tensor: &TensorMap) {
         assert_eq!(tensor.keys().names(), ["o3_lambda", "o3_sigma", "center_type", "neighbor_type"]);
         assert_eq!(tensor.keys().count(), 27);
@@ -112,6 +219,37 @@ mod block {
         assert_eq!(buffer, saved);
     }
 
+    #[test]
+    fn sparse() {
+        use metatensor::{Labels, SparseArray};
+
+        let values = ndarray::arr3(&[[[0.0, 1.0]], [[0.0, 0.0]], [[2.0, 0.0]]]).into_dyn();
+        let mut block = TensorBlock::new(
+            SparseArray::from_dense(&values),
+            &Labels::new(["system"], &[[0], [1], [2]]),
+            &[Labels::new(["xyz"], &[[0]])],
+            &Labels::new(["n"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 1, 2], 3.0),
+            &Labels::new(["sample"], &[[2]]),
+            &[Labels::new(["xyz"], &[[0]])],
+            &Labels::new(["n"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let mut buffer = Vec::new();
+        block.save_buffer(&mut buffer).unwrap();
+
+        let loaded = TensorBlock::load_buffer(&buffer).unwrap();
+        let sparse = loaded.values().to_any().downcast_ref::<SparseArray>().unwrap();
+        assert_eq!(sparse.to_dense(), values);
+
+        let gradient = loaded.as_ref().gradient("g").unwrap();
+        assert_eq!(gradient.values().as_array(), ndarray::ArrayD::from_elem(vec![1, 1, 2], 3.0));
+    }
+
 
     fn check_block(block: TensorBlockRef) {
         assert_eq!(block.values().as_array().shape(), [9, 5, 3]);
@@ -176,6 +314,42 @@ mod labels {
         assert_eq!(buffer, saved);
     }
 
+    #[test]
+    fn labels_i64() {
+        let large = i64::from(i32::MAX) + 33;
+        let labels = Labels::new_i64(["frame", "hash"], &[[0, large], [large, -large]]);
+
+        let mut buffer = Vec::new();
+        labels.save_buffer(&mut buffer).unwrap();
+        // the NPY header uses 64-bit integers
+        let header = String::from_utf8_lossy(&buffer[..128]);
+        assert!(header.contains("'<i8'") || header.contains("'>i8'"));
+
+        let loaded = Labels::load_buffer(&buffer).unwrap();
+        assert_eq!(loaded.dtype(), metatensor::LabelsDType::Int64);
+        assert_eq!(loaded.names(), ["frame", "hash"]);
+        assert_eq!(loaded.values_i64(), [0, large, large, -large]);
+        assert_eq!(loaded.position_i64(&[large, -large]), Some(1));
+    }
+
+    #[test]
+    fn labels_duplicated() {
+        let labels = Labels::new(["a"], &[[0], [1]]);
+
+        let mut buffer = Vec::new();
+        labels.save_buffer(&mut buffer).unwrap();
+
+        // modify the file to contain the same entry twice
+        let n_bytes = buffer.len();
+        buffer[(n_bytes - 4)..].copy_from_slice(&0_i32.to_ne_bytes());
+
+        let error = Labels::load_buffer(&buffer).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not have the same label value multiple time: \
+            [0] is already present at position 0"
+        );
+    }
 
     fn check_labels(labels: &Labels) {
         assert_eq!(labels.names(), ["o3_lambda", "o3_sigma", "center_type", "neighbor_type"]);
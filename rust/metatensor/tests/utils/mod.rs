@@ -98,3 +98,44 @@ pub fn example_tensor() -> TensorMap {
 
     return TensorMap::new(keys, vec![block_1, block_2, block_3, block_4]).unwrap();
 }
+
+/// Create a `TensorMap` where the gradients with respect to `"parameter"` also
+/// contain gradients with respect to `"other"`
+pub fn example_tensor_nested_gradients() -> TensorMap {
+    let block = |samples: Vec<[i32; 1]>, gradient_samples: Vec<[i32; 2]>, nested_samples: Vec<[i32; 2]>, value: f64| {
+        let samples = example_labels(vec!["samples"], samples);
+        let properties = example_labels(vec!["properties"], vec![[0], [1]]);
+        let mut block = TensorBlock::new(
+            ArrayD::from_elem(vec![samples.count(), 2], value),
+            &samples,
+            &[],
+            &properties,
+        ).unwrap();
+
+        let gradient_samples = example_labels(vec!["sample", "parameter"], gradient_samples);
+        let mut gradient = TensorBlock::new(
+            ArrayD::from_elem(vec![gradient_samples.count(), 2], 10.0 + value),
+            &gradient_samples,
+            &[],
+            &properties,
+        ).unwrap();
+
+        let nested_samples = example_labels(vec!["sample", "other"], nested_samples);
+        let nested = TensorBlock::new(
+            ArrayD::from_elem(vec![nested_samples.count(), 2], 100.0 + value),
+            &nested_samples,
+            &[],
+            &properties,
+        ).unwrap();
+
+        gradient.add_gradient("other", nested).unwrap();
+        block.add_gradient("parameter", gradient).unwrap();
+        return block;
+    };
+
+    let block_1 = block(vec![[0], [2]], vec![[0, 1], [1, 2]], vec![[1, 5]], 1.0);
+    let block_2 = block(vec![[1], [2]], vec![[1, 3]], vec![[0, 4]], 2.0);
+
+    let keys = Labels::new(["key_1", "key_2"], &[[0, 0], [0, 1]]);
+    return TensorMap::new(keys, vec![block_1, block_2]).unwrap();
+}